@@ -103,6 +103,12 @@ pub struct Export {
     /// Whether or not this function should be flagged as the Wasm start
     /// function.
     pub start: bool,
+    /// The name of the lazily loaded Wasm module this function is split into,
+    /// if any.
+    pub split: Option<String>,
+    /// Whether this function is called through `WebAssembly.promising`, making
+    /// it return a promise to JS and allowing it to call `suspending` imports
+    pub promising: bool,
     /// Path to wasm_bindgen
     pub wasm_bindgen: Path,
     /// Path to wasm_bindgen_futures
@@ -183,6 +189,9 @@ pub struct ImportFunction {
     pub js_ret: Option<syn::Type>,
     /// Whether to catch JS exceptions
     pub catch: bool,
+    /// Whether the import returns a promise which suspends Wasm until it
+    /// settles, through JS Promise Integration
+    pub suspending: bool,
     /// Whether the function is variadic on the JS side
     pub variadic: bool,
     /// Whether the function should use structural type checking
@@ -434,6 +443,9 @@ pub struct Struct {
     pub comments: Vec<String>,
     /// Whether this struct is inspectable (provides toJSON/toString properties to JS)
     pub is_inspectable: bool,
+    /// Whether this struct is passed to and from JS as a plain JS value
+    /// through `serde` instead of being exported as a class
+    pub is_serde: bool,
     /// Whether to generate a typescript definition for this struct
     pub generate_typescript: bool,
     /// Path to wasm_bindgen
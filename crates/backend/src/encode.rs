@@ -6,6 +6,7 @@ use std::env;
 use std::fs;
 use std::path::PathBuf;
 use syn::ext::IdentExt;
+use syn::spanned::Spanned;
 
 use crate::ast;
 use crate::Diagnostic;
@@ -120,6 +121,30 @@ impl Interner {
         self.resolve_import_module(id, span, linked_module)
     }
 
+    /// Returns an expression evaluating to the `file:line:column` location of
+    /// `span` in Rust code, used to map generated bindings back to the Rust
+    /// items they were generated from.
+    fn source_location(&self, span: Span) -> LitOrExpr<'_> {
+        let expr: syn::Expr = syn::parse_quote_spanned! {span=>
+            concat!(file!(), ":", line!(), ":", column!())
+        };
+        LitOrExpr::Expr(self.bump.alloc(expr))
+    }
+
+    /// Returns the directory of the crate relative to the working directory
+    /// of the compiler, which the paths returned by `file!()` are relative to.
+    ///
+    /// Only this relative path is recorded, so that the output doesn't depend
+    /// on where the crate was built. It is empty if the crate directory is the
+    /// working directory, or isn't inside of it.
+    fn source_root(&self) -> LitOrExpr<'_> {
+        let root = env::current_dir()
+            .ok()
+            .and_then(|cwd| Some(self.root.strip_prefix(cwd).ok()?.to_str()?.to_string()))
+            .unwrap_or_default();
+        LitOrExpr::Lit(self.intern_str(&root))
+    }
+
     fn unique_crate_identifier(&self) -> String {
         format!("{}-{}", self.crate_name, ShortHash(0))
     }
@@ -148,6 +173,7 @@ fn shared_program<'a>(
         structs: prog
             .structs
             .iter()
+            .filter(|a| !a.is_serde)
             .map(|a| shared_struct(a, intern))
             .collect(),
         enums: prog.enums.iter().map(|a| shared_enum(a, intern)).collect(),
@@ -160,6 +186,12 @@ fn shared_program<'a>(
             .typescript_custom_sections
             .iter()
             .map(|x| shared_lit_or_expr(x, intern))
+            .chain(
+                prog.structs
+                    .iter()
+                    .filter(|a| a.is_serde && a.generate_typescript)
+                    .map(|a| LitOrExpr::Lit(intern.intern_str(&serde_typescript_alias(a)))),
+            )
             .collect(),
         linked_modules: prog
             .linked_modules
@@ -195,6 +227,7 @@ fn shared_program<'a>(
         } else {
             None
         },
+        source_root: intern.source_root(),
     })
 }
 
@@ -211,10 +244,12 @@ fn shared_export<'a>(
         function: shared_function(&export.function, intern),
         method_kind,
         start: export.start,
+        split: export.split.as_deref(),
+        promising: export.promising,
     })
 }
 
-fn shared_function<'a>(func: &'a ast::Function, _intern: &'a Interner) -> Function<'a> {
+fn shared_function<'a>(func: &'a ast::Function, intern: &'a Interner) -> Function<'a> {
     let args =
         func.arguments
             .iter()
@@ -243,6 +278,7 @@ fn shared_function<'a>(func: &'a ast::Function, _intern: &'a Interner) -> Functi
         variadic: func.variadic,
         ret_ty_override: func.ret.as_ref().and_then(|v| v.js_type.as_deref()),
         ret_desc: func.ret.as_ref().and_then(|v| v.desc.as_deref()),
+        location: intern.source_location(func.name_span),
     }
 }
 
@@ -257,6 +293,7 @@ fn shared_enum<'a>(e: &'a ast::Enum, intern: &'a Interner) -> Enum<'a> {
             .collect(),
         comments: e.comments.iter().map(|s| &**s).collect(),
         generate_typescript: e.generate_typescript,
+        location: intern.source_location(e.rust_name.span()),
     }
 }
 
@@ -287,6 +324,21 @@ fn shared_lit_or_expr<'a>(i: &'a ast::LitOrExpr, _intern: &'a Interner) -> LitOr
     }
 }
 
+/// Returns the TypeScript type alias of a `#[wasm_bindgen(serde)]` struct,
+/// which signatures refer to by the name of the struct.
+fn serde_typescript_alias(s: &ast::Struct) -> String {
+    let mut alias = String::new();
+    if !s.comments.is_empty() {
+        alias.push_str("/**\n");
+        for comment in &s.comments {
+            alias.push_str(&format!(" *{}\n", comment));
+        }
+        alias.push_str(" */\n");
+    }
+    alias.push_str(&format!("export type {} = any;", s.js_name));
+    alias
+}
+
 fn shared_linked_module<'a>(
     name: &str,
     i: &'a ast::ImportModule,
@@ -340,6 +392,7 @@ fn shared_import_function<'a>(
     Ok(ImportFunction {
         shim: intern.intern(&i.shim),
         catch: i.catch,
+        suspending: i.suspending,
         method,
         assert_no_shim: i.assert_no_shim,
         structural: i.structural,
@@ -370,12 +423,13 @@ fn shared_import_type<'a>(i: &'a ast::ImportType, intern: &'a Interner) -> Impor
     }
 }
 
-fn shared_import_enum<'a>(i: &'a ast::StringEnum, _intern: &'a Interner) -> StringEnum<'a> {
+fn shared_import_enum<'a>(i: &'a ast::StringEnum, intern: &'a Interner) -> StringEnum<'a> {
     StringEnum {
         name: &i.js_name,
         generate_typescript: i.generate_typescript,
         variant_values: i.variant_values.iter().map(|x| &**x).collect(),
         comments: i.comments.iter().map(|s| &**s).collect(),
+        location: intern.source_location(i.name.span()),
     }
 }
 
@@ -390,16 +444,18 @@ fn shared_struct<'a>(s: &'a ast::Struct, intern: &'a Interner) -> Struct<'a> {
         comments: s.comments.iter().map(|s| &**s).collect(),
         is_inspectable: s.is_inspectable,
         generate_typescript: s.generate_typescript,
+        location: intern.source_location(s.rust_name.span()),
     }
 }
 
-fn shared_struct_field<'a>(s: &'a ast::StructField, _intern: &'a Interner) -> StructField<'a> {
+fn shared_struct_field<'a>(s: &'a ast::StructField, intern: &'a Interner) -> StructField<'a> {
     StructField {
         name: &s.js_name,
         readonly: s.readonly,
         comments: s.comments.iter().map(|s| &**s).collect(),
         generate_typescript: s.generate_typescript,
         generate_jsdoc: s.generate_jsdoc,
+        location: intern.source_location(s.rust_name.span()),
     }
 }
 
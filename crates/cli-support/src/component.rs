@@ -0,0 +1,541 @@
+//! Wrapping of a module into a [component] implementing the WIT world
+//! generated by `wit_world`.
+//!
+//! The exports of the module keep the ABI of the JS bindings, so for each
+//! function of the world an export implementing the canonical ABI is
+//! generated from the instructions of its adapter, next to `cabi_realloc` and
+//! the `cabi_post_*` functions freeing returned strings and lists. Only free
+//! functions taking and returning numbers, `bool`s, strings and lists of
+//! numbers are supported, the world of a component leaves everything else out.
+//!
+//! [component]: https://component-model.bytecodealliance.org/
+
+use crate::descriptor::VectorKind;
+use crate::wit::{Adapter, AdapterKind, AdapterType, Instruction, NonstandardWitSection};
+use crate::wit_world::World;
+use anyhow::{anyhow, bail, Context, Error};
+use std::collections::HashMap;
+use walrus::ir::{BinaryOp, LoadKind, MemArg, StoreKind};
+use walrus::{
+    ExportItem, FunctionBuilder, FunctionId, GlobalId, ImportKind, InstrSeqBuilder, LocalId,
+    MemoryId, Module, ValType,
+};
+use wit_component::{ComponentEncoder, StringEncoding};
+use wit_parser::Resolve;
+
+/// The maximum number of flat parameters of a canonical ABI function, more
+/// are passed through memory.
+const MAX_FLAT_PARAMS: usize = 16;
+
+/// Returns why the adapter of an exported function can't be wrapped into a
+/// component, if it can't.
+pub fn check(adapter: &Adapter) -> Result<(), String> {
+    let instructions = match &adapter.kind {
+        AdapterKind::Local { instructions } => instructions,
+        AdapterKind::Import { .. } => return Err("imports can't be wrapped".to_string()),
+    };
+    if adapter
+        .params
+        .iter()
+        .chain(&adapter.results)
+        .any(|ty| flat_types(ty).is_none())
+    {
+        return Err(
+            "only numbers, `bool`s, strings and lists of numbers are supported in \
+             components yet"
+                .to_string(),
+        );
+    }
+    let flat_params = adapter
+        .params
+        .iter()
+        .filter_map(flat_types)
+        .map(|types| types.len())
+        .sum::<usize>();
+    if flat_params > MAX_FLAT_PARAMS {
+        return Err(format!(
+            "functions with more than {} flat parameters are not supported in components yet",
+            MAX_FLAT_PARAMS
+        ));
+    }
+    for instr in instructions {
+        match &instr.instr {
+            Instruction::ArgGet(_)
+            | Instruction::CallExport(_)
+            | Instruction::Retptr { .. }
+            | Instruction::LoadRetptr { .. }
+            | Instruction::Int32ToWasm
+            | Instruction::WasmToInt32 { .. }
+            | Instruction::Int64ToWasm
+            | Instruction::WasmToInt64 { .. }
+            | Instruction::I32FromBool
+            | Instruction::BoolFromI32
+            | Instruction::StringToMemory { .. }
+            | Instruction::DeferFree { .. }
+            | Instruction::MemoryToString(_) => {}
+            Instruction::VectorToMemory { kind, .. } | Instruction::VectorLoad { kind, .. }
+                if numeric(kind) => {}
+            _ => {
+                return Err(
+                    "its arguments or return value need JS to be converted, which is not \
+                     supported in components yet"
+                        .to_string(),
+                )
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the core Wasm bytes of a component wrapping `module` and
+/// implementing `world`.
+///
+/// `module` itself is left as is, the component is built from a copy parsed
+/// with `from_bytes`.
+pub fn wrap(
+    module: &mut Module,
+    world: &World,
+    adapters: &NonstandardWitSection,
+    from_bytes: impl Fn(&[u8]) -> Result<Module, Error>,
+) -> Result<Vec<u8>, Error> {
+    // The adapters refer to the exports of the original module, which are
+    // found by name in the copy.
+    let export_names = module
+        .exports
+        .iter()
+        .map(|e| (e.id(), e.name.clone()))
+        .collect::<HashMap<_, _>>();
+    let mut copy = from_bytes(&module.emit_wasm())?;
+
+    let memory = copy
+        .exports
+        .iter()
+        .find_map(|e| match e.item {
+            ExportItem::Memory(id) if e.name == "memory" => Some(id),
+            _ => None,
+        })
+        .ok_or_else(|| anyhow!("the Wasm module doesn't export its memory as `memory`"))?;
+    if copy.memories.get(memory).memory64 {
+        bail!("modules with a 64-bit memory can't be wrapped into a component yet");
+    }
+    let functions = copy
+        .exports
+        .iter()
+        .filter_map(|e| match e.item {
+            ExportItem::Function(id) => Some((e.name.clone(), id)),
+            _ => None,
+        })
+        .collect::<HashMap<_, _>>();
+    let function = |name: &str| {
+        functions
+            .get(name)
+            .copied()
+            .ok_or_else(|| anyhow!("failed to find `{}` in the Wasm module", name))
+    };
+    let mut cx = Wrapper {
+        memory,
+        malloc: function("__wbindgen_malloc")?,
+        free: function("__wbindgen_free")?,
+        stack_pointer: wasm_bindgen_wasm_conventions::get_stack_pointer(&copy),
+        module: &mut copy,
+    };
+
+    let package = world.name.trim_start_matches('%');
+    for (id, name) in world.functions.iter() {
+        let adapter = &adapters.adapters[id];
+        let name = format!("local:{}/exports#{}", package, name.trim_start_matches('%'));
+        cx.export(&name, adapter, &export_names, &functions)
+            .with_context(|| format!("failed to wrap `{}`", name))?;
+    }
+    cx.export_realloc(functions.get("__wbindgen_realloc").copied());
+    if cx.module.start.is_none() {
+        cx.module.start = functions.get("__wbindgen_start").copied();
+    }
+    replace_intrinsics(cx.module)?;
+
+    let mut bytes = copy.emit_wasm();
+    let mut resolve = Resolve::default();
+    let id = resolve.push_str(format!("{}.wit", package), &world.wit)?;
+    // The package only contains the world, which is named after it.
+    let world = resolve.packages[id].worlds[package];
+    wit_component::embed_component_metadata(&mut bytes, &resolve, world, StringEncoding::UTF8)?;
+    ComponentEncoder::default()
+        .validate(true)
+        .module(&bytes)?
+        .encode()
+}
+
+struct Wrapper<'a> {
+    module: &'a mut Module,
+    memory: MemoryId,
+    malloc: FunctionId,
+    free: FunctionId,
+    stack_pointer: Option<GlobalId>,
+}
+
+/// A value on the stack of an adapter, which is made up of several core
+/// values for strings and lists.
+struct Value {
+    locals: Vec<LocalId>,
+    /// The element size of a string or list which the caller has to free.
+    owned: Option<i32>,
+}
+
+impl Wrapper<'_> {
+    /// Exports a function named `name` implementing the canonical ABI on top
+    /// of the core function called by `adapter`.
+    fn export(
+        &mut self,
+        name: &str,
+        adapter: &Adapter,
+        export_names: &HashMap<walrus::ExportId, String>,
+        functions: &HashMap<String, FunctionId>,
+    ) -> Result<(), Error> {
+        let instructions = match &adapter.kind {
+            AdapterKind::Local { instructions } => instructions,
+            AdapterKind::Import { .. } => bail!("imports can't be wrapped"),
+        };
+        let mut params = Vec::new();
+        let mut args = Vec::new();
+        for ty in adapter.params.iter() {
+            let types = flat_types(ty).ok_or_else(|| anyhow!("unsupported type {:?}", ty))?;
+            args.push(
+                types
+                    .iter()
+                    .map(|ty| self.module.locals.add(*ty))
+                    .collect::<Vec<_>>(),
+            );
+            params.extend(types);
+        }
+        let results = match adapter.results.first() {
+            Some(ty) => flat_types(ty).ok_or_else(|| anyhow!("unsupported type {:?}", ty))?,
+            None => Vec::new(),
+        };
+        // More than one flat result is returned through memory.
+        let indirect = results.len() > 1;
+        let wrapper_results = if indirect {
+            vec![ValType::I32]
+        } else {
+            results.clone()
+        };
+
+        let mut builder = FunctionBuilder::new(&mut self.module.types, &params, &wrapper_results);
+        let mut body = builder.func_body();
+        let mut stack = Vec::<Value>::new();
+        let mut retptr = None;
+        let mut deferred_free = false;
+        for instr in instructions {
+            match &instr.instr {
+                Instruction::ArgGet(i) => stack.push(Value {
+                    locals: args[*i as usize].clone(),
+                    owned: None,
+                }),
+
+                // Numbers and `bool`s have the same representation in both
+                // ABIs, and so do strings and lists in memory.
+                Instruction::Int32ToWasm
+                | Instruction::WasmToInt32 { .. }
+                | Instruction::Int64ToWasm
+                | Instruction::WasmToInt64 { .. }
+                | Instruction::I32FromBool
+                | Instruction::BoolFromI32 => {}
+                Instruction::StringToMemory { .. } | Instruction::VectorToMemory { .. } => {
+                    let value = pop(&mut stack)?;
+                    for local in value.locals {
+                        stack.push(Value {
+                            locals: vec![local],
+                            owned: None,
+                        });
+                    }
+                }
+
+                Instruction::Retptr { size } => {
+                    let stack_pointer = self
+                        .stack_pointer
+                        .ok_or_else(|| anyhow!("failed to find the stack pointer"))?;
+                    let local = self.module.locals.add(ValType::I32);
+                    body.global_get(stack_pointer)
+                        .i32_const(*size as i32)
+                        .binop(BinaryOp::I32Sub)
+                        .local_tee(local)
+                        .global_set(stack_pointer);
+                    retptr = Some((local, *size));
+                    stack.push(Value {
+                        locals: vec![local],
+                        owned: None,
+                    });
+                }
+                Instruction::LoadRetptr { ty, offset, .. } => {
+                    let (local, _) = retptr.ok_or_else(|| anyhow!("no return pointer"))?;
+                    let (kind, ty, align) = match ty {
+                        AdapterType::I32 => (LoadKind::I32 { atomic: false }, ValType::I32, 4),
+                        AdapterType::I64 => (LoadKind::I64 { atomic: false }, ValType::I64, 8),
+                        AdapterType::F32 => (LoadKind::F32, ValType::F32, 4),
+                        AdapterType::F64 => (LoadKind::F64, ValType::F64, 8),
+                        other => bail!("invalid aggregate return type {:?}", other),
+                    };
+                    let value = self.module.locals.add(ty);
+                    // The offset is in multiples of 4 bytes.
+                    let arg = MemArg {
+                        align,
+                        offset: *offset as u32 * 4,
+                    };
+                    body.local_get(local)
+                        .load(self.memory, kind, arg)
+                        .local_set(value);
+                    stack.push(Value {
+                        locals: vec![value],
+                        owned: None,
+                    });
+                }
+
+                Instruction::CallExport(id) => {
+                    let func = export_names
+                        .get(id)
+                        .and_then(|name| functions.get(name))
+                        .copied()
+                        .ok_or_else(|| anyhow!("failed to find the wrapped export"))?;
+                    let ty = self.module.funcs.get(func).ty();
+                    let params = self.module.types.params(ty).len();
+                    let results = self.module.types.results(ty).to_vec();
+                    if stack.len() < params {
+                        bail!("not enough values on the stack for the call");
+                    }
+                    for value in stack.drain(stack.len() - params..) {
+                        for local in value.locals {
+                            body.local_get(local);
+                        }
+                    }
+                    body.call(func);
+                    let locals = results
+                        .iter()
+                        .map(|ty| self.module.locals.add(*ty))
+                        .collect::<Vec<_>>();
+                    for local in locals.iter().rev() {
+                        body.local_set(*local);
+                    }
+                    for local in locals {
+                        stack.push(Value {
+                            locals: vec![local],
+                            owned: None,
+                        });
+                    }
+                }
+
+                // The caller frees the returned string or list in the
+                // post-return function.
+                Instruction::DeferFree { .. } => deferred_free = true,
+                Instruction::MemoryToString(_) => {
+                    let len = pop(&mut stack)?;
+                    let ptr = pop(&mut stack)?;
+                    stack.push(Value {
+                        locals: ptr.locals.into_iter().chain(len.locals).collect(),
+                        owned: deferred_free.then_some(1),
+                    });
+                    deferred_free = false;
+                }
+                Instruction::VectorLoad { kind, .. } => {
+                    let len = pop(&mut stack)?;
+                    let ptr = pop(&mut stack)?;
+                    stack.push(Value {
+                        locals: ptr.locals.into_iter().chain(len.locals).collect(),
+                        owned: Some(kind.size() as i32),
+                    });
+                }
+
+                other => bail!("unsupported instruction {:?}", other),
+            }
+        }
+
+        let result = match stack.len() {
+            0 => None,
+            1 => stack.pop(),
+            _ => bail!("too many values left on the stack"),
+        };
+        if let Some((local, size)) = retptr {
+            let stack_pointer = self.stack_pointer.unwrap();
+            body.local_get(local)
+                .i32_const(size as i32)
+                .binop(BinaryOp::I32Add)
+                .global_set(stack_pointer);
+        }
+        let owned = result.as_ref().and_then(|value| value.owned);
+        match result {
+            Some(value) if indirect => {
+                // The pointer and length of a string or list.
+                let area = self.module.locals.add(ValType::I32);
+                body.i32_const(8)
+                    .i32_const(4)
+                    .call(self.malloc)
+                    .local_set(area);
+                for (i, local) in value.locals.iter().enumerate() {
+                    body.local_get(area).local_get(*local).store(
+                        self.memory,
+                        StoreKind::I32 { atomic: false },
+                        MemArg {
+                            align: 4,
+                            offset: i as u32 * 4,
+                        },
+                    );
+                }
+                body.local_get(area);
+            }
+            Some(value) => {
+                for local in value.locals {
+                    body.local_get(local);
+                }
+            }
+            None => {}
+        }
+        let id = builder.finish(args.into_iter().flatten().collect(), &mut self.module.funcs);
+        self.module.funcs.get_mut(id).name = Some(name.to_string());
+        self.module.exports.add(name, id);
+
+        if indirect {
+            self.export_post_return(name, owned);
+        }
+        Ok(())
+    }
+
+    /// Exports the post-return function of `name`, which frees the return
+    /// area and the string or list in it if it's `owned`.
+    fn export_post_return(&mut self, name: &str, owned: Option<i32>) {
+        let (memory, free) = (self.memory, self.free);
+        let area = self.module.locals.add(ValType::I32);
+        let mut builder = FunctionBuilder::new(&mut self.module.types, &[ValType::I32], &[]);
+        let mut body = builder.func_body();
+        let load = |body: &mut InstrSeqBuilder, offset| {
+            body.local_get(area).load(
+                memory,
+                LoadKind::I32 { atomic: false },
+                MemArg { align: 4, offset },
+            );
+        };
+        if let Some(size) = owned {
+            load(&mut body, 0);
+            load(&mut body, 4);
+            body.i32_const(size).binop(BinaryOp::I32Mul);
+            body.i32_const(size).call(free);
+        }
+        body.local_get(area).i32_const(8).i32_const(4).call(free);
+        let name = format!("cabi_post_{}", name);
+        let id = builder.finish(vec![area], &mut self.module.funcs);
+        self.module.funcs.get_mut(id).name = Some(name.clone());
+        self.module.exports.add(&name, id);
+    }
+
+    /// Exports `cabi_realloc`, which the host uses to allocate the strings
+    /// and lists it passes in.
+    fn export_realloc(&mut self, realloc: Option<FunctionId>) {
+        let ptr = self.module.locals.add(ValType::I32);
+        let old_size = self.module.locals.add(ValType::I32);
+        let align = self.module.locals.add(ValType::I32);
+        let new_size = self.module.locals.add(ValType::I32);
+        let mut builder =
+            FunctionBuilder::new(&mut self.module.types, &[ValType::I32; 4], &[ValType::I32]);
+        let malloc = self.malloc;
+        builder.func_body().local_get(old_size).if_else(
+            Some(ValType::I32),
+            |body| match realloc {
+                Some(realloc) => {
+                    body.local_get(ptr)
+                        .local_get(old_size)
+                        .local_get(new_size)
+                        .local_get(align)
+                        .call(realloc);
+                }
+                None => {
+                    body.unreachable();
+                }
+            },
+            |body| {
+                body.local_get(new_size).local_get(align).call(malloc);
+            },
+        );
+        let id = builder.finish(vec![ptr, old_size, align, new_size], &mut self.module.funcs);
+        self.module.funcs.get_mut(id).name = Some("cabi_realloc".to_string());
+        self.module.exports.add("cabi_realloc", id);
+    }
+}
+
+/// Replaces the imports of intrinsics which can be implemented without JS,
+/// failing on any other import.
+fn replace_intrinsics(module: &mut Module) -> Result<(), Error> {
+    let mut shims = HashMap::new();
+    for import in module.imports.iter() {
+        let func = match import.kind {
+            ImportKind::Function(func) => func,
+            _ => bail!(
+                "the Wasm module imports `{}::{}`, which a component can't provide",
+                import.module,
+                import.name
+            ),
+        };
+        let trap = match import.name.as_str() {
+            "__wbindgen_throw" | "__wbindgen_rethrow" => true,
+            // The slots reserved for JS values are never used.
+            "__wbindgen_init_externref_table" => false,
+            _ => bail!(
+                "the Wasm module imports `{}::{}`, which a component can't provide",
+                import.module,
+                import.name
+            ),
+        };
+        shims.insert(func, (import.name.clone(), trap));
+    }
+    let shims = shims
+        .into_iter()
+        .map(|(func, (name, trap))| {
+            let ty = module.funcs.get(func).ty();
+            let params = module.types.params(ty).to_vec();
+            let results = module.types.results(ty).to_vec();
+            let args = params.iter().map(|ty| module.locals.add(*ty)).collect();
+            let mut builder = FunctionBuilder::new(&mut module.types, &params, &results);
+            if trap {
+                builder.func_body().unreachable();
+            }
+            let id = builder.finish(args, &mut module.funcs);
+            module.funcs.get_mut(id).name = Some(name);
+            (func, id)
+        })
+        .collect::<HashMap<_, _>>();
+    crate::exceptions::rewrite_calls(module, &shims);
+    if let Some(start) = module.start {
+        module.start = Some(shims.get(&start).copied().unwrap_or(start));
+    }
+    walrus::passes::gc::run(module);
+    Ok(())
+}
+
+fn pop(stack: &mut Vec<Value>) -> Result<Value, Error> {
+    stack
+        .pop()
+        .ok_or_else(|| anyhow!("not enough values on the stack"))
+}
+
+/// Returns the flattened core types of `ty` in the canonical ABI, if it's
+/// supported.
+fn flat_types(ty: &AdapterType) -> Option<Vec<ValType>> {
+    Some(match ty {
+        AdapterType::S8
+        | AdapterType::S16
+        | AdapterType::S32
+        | AdapterType::U8
+        | AdapterType::U16
+        | AdapterType::U32
+        | AdapterType::Bool => vec![ValType::I32],
+        AdapterType::S64 | AdapterType::U64 => vec![ValType::I64],
+        AdapterType::F32 => vec![ValType::F32],
+        AdapterType::F64 => vec![ValType::F64],
+        AdapterType::String => vec![ValType::I32, ValType::I32],
+        AdapterType::Vector(kind) if numeric(kind) => vec![ValType::I32, ValType::I32],
+        _ => return None,
+    })
+}
+
+fn numeric(kind: &VectorKind) -> bool {
+    !matches!(
+        kind,
+        VectorKind::String | VectorKind::Externref | VectorKind::NamedExternref(_)
+    )
+}
@@ -0,0 +1,502 @@
+//! Decoding of programs encoded by older versions of wasm-bindgen.
+//!
+//! Whenever the schema changes, the previous definitions of all items which
+//! changed, along with the items containing them, are kept here in a module
+//! named after the previous schema version. Programs of that version are
+//! decoded with them and then upgraded into the items of the next version,
+//! and so on until the current schema is reached.
+//!
+//! Only the schemas of the last `SCHEMA_WINDOW` releases are kept, older ones
+//! are dropped from here. A schema version names the schema of a release: all
+//! changes made to the schema until a release share that release's version,
+//! so programs encoded by unreleased development versions of wasm-bindgen with
+//! a schema which was changed again before the release can't be decoded.
+
+use super::{get, Decode, LitOrExpr, Program};
+use anyhow::{Context, Error};
+
+/// The number of releases with an older schema whose programs can still be
+/// decoded.
+pub const SCHEMA_WINDOW: usize = 3;
+
+/// The older schema versions which can still be decoded, newest first. At most
+/// `SCHEMA_WINDOW` of them are listed.
+pub const SUPPORTED_SCHEMA_VERSIONS: &[&str] = &["0.2.100"];
+
+/// Decodes a program encoded with the older schema `version`, upgraded to the
+/// current schema, or returns `None` if `version` isn't supported.
+///
+/// Programs are upgraded one schema version at a time, so a program of an
+/// older version is decoded with its own items and then passed through the
+/// upgrades of all newer versions, e.g.
+/// `v0_2_99::Program::decode_all(data).upgrade().and_then(Upgrade::upgrade)`.
+///
+/// Besides the schema, the runtime ABI of some features changed between
+/// versions, which the CLI can't translate. `memory64` tells whether the
+/// module uses a 64-bit memory, whose ABI changed in 0.2.101.
+pub fn decode_program<'a>(
+    version: &str,
+    data: &'a [u8],
+    memory64: bool,
+) -> Option<Result<Program<'a>, Error>> {
+    let program = match version {
+        "0.2.100" => v0_2_100::check_abi(memory64)
+            .and_then(|()| v0_2_100::Program::decode_all(data).upgrade()),
+        _ => return None,
+    };
+    Some(program.with_context(|| {
+        format!(
+            "failed to upgrade a program encoded with wasm-bindgen schema version {}",
+            version
+        )
+    }))
+}
+
+/// Conversion of an item of an older schema into the corresponding item of the
+/// next schema version, failing if it uses a feature which can no longer be
+/// represented.
+trait Upgrade {
+    type Target;
+
+    fn upgrade(self) -> Result<Self::Target, Error>;
+}
+
+impl<T: Upgrade> Upgrade for Vec<T> {
+    type Target = Vec<T::Target>;
+
+    fn upgrade(self) -> Result<Self::Target, Error> {
+        self.into_iter().map(Upgrade::upgrade).collect()
+    }
+}
+
+impl<T: Upgrade> Upgrade for Option<T> {
+    type Target = Option<T::Target>;
+
+    fn upgrade(self) -> Result<Self::Target, Error> {
+        self.map(Upgrade::upgrade).transpose()
+    }
+}
+
+/// Items as of schema version 0.2.100, which didn't have the source locations
+/// of items, `#[wasm_bindgen(split)]` or JS Promise Integration yet.
+mod v0_2_100 {
+    use super::super::{
+        self as next, EnumVariant, FunctionArgumentData, ImportModule, ImportStatic, ImportString,
+        ImportType, LinkedModule, LocalModule, MethodData, MethodKind,
+    };
+    use super::{get, Decode, LitOrExpr, Upgrade};
+    use anyhow::{bail, Error};
+
+    // Source locations were not recorded yet.
+    const NO_LOCATION: LitOrExpr<'static> = LitOrExpr { str: "" };
+
+    /// Fails if the module relies on a runtime ABI which changed since.
+    pub fn check_abi(memory64: bool) -> Result<(), Error> {
+        // Pointers to exported structs were `u32`s and `usize`/`isize` were
+        // described as 32-bit integers even on 64-bit targets.
+        if memory64 {
+            bail!(
+                "64-bit memories (wasm64) need wasm-bindgen 0.2.101 or newer: older versions \
+                 passed pointers to structs as 32-bit integers and described `usize` and \
+                 `isize` as 32-bit integers"
+            );
+        }
+        Ok(())
+    }
+
+    decode_api! {
+        struct Program<'a> {
+            exports: Vec<Export<'a>>,
+            enums: Vec<Enum<'a>>,
+            imports: Vec<Import<'a>>,
+            structs: Vec<Struct<'a>>,
+            typescript_custom_sections: Vec<LitOrExpr<'a>>,
+            local_modules: Vec<LocalModule<'a>>,
+            inline_js: Vec<&'a str>,
+            unique_crate_identifier: &'a str,
+            package_json: Option<&'a str>,
+            linked_modules: Vec<LinkedModule<'a>>,
+        }
+
+        struct Import<'a> {
+            module: Option<ImportModule<'a>>,
+            js_namespace: Option<Vec<String>>,
+            kind: ImportKind<'a>,
+        }
+
+        enum ImportKind<'a> {
+            Function(ImportFunction<'a>),
+            Static(ImportStatic<'a>),
+            String(ImportString<'a>),
+            Type(ImportType<'a>),
+            Enum(StringEnum<'a>),
+        }
+
+        struct ImportFunction<'a> {
+            shim: &'a str,
+            catch: bool,
+            variadic: bool,
+            assert_no_shim: bool,
+            method: Option<MethodData<'a>>,
+            structural: bool,
+            function: Function<'a>,
+        }
+
+        struct StringEnum<'a> {
+            name: &'a str,
+            variant_values: Vec<&'a str>,
+            comments: Vec<&'a str>,
+            generate_typescript: bool,
+        }
+
+        struct Export<'a> {
+            class: Option<&'a str>,
+            comments: Vec<&'a str>,
+            consumed: bool,
+            function: Function<'a>,
+            method_kind: MethodKind<'a>,
+            start: bool,
+        }
+
+        struct Enum<'a> {
+            name: &'a str,
+            signed: bool,
+            variants: Vec<EnumVariant<'a>>,
+            comments: Vec<&'a str>,
+            generate_typescript: bool,
+        }
+
+        struct Function<'a> {
+            args: Vec<FunctionArgumentData<'a>>,
+            asyncness: bool,
+            name: &'a str,
+            generate_typescript: bool,
+            generate_jsdoc: bool,
+            variadic: bool,
+            ret_ty_override: Option<&'a str>,
+            ret_desc: Option<&'a str>,
+        }
+
+        struct Struct<'a> {
+            name: &'a str,
+            fields: Vec<StructField<'a>>,
+            comments: Vec<&'a str>,
+            is_inspectable: bool,
+            generate_typescript: bool,
+        }
+
+        struct StructField<'a> {
+            name: &'a str,
+            readonly: bool,
+            comments: Vec<&'a str>,
+            generate_typescript: bool,
+            generate_jsdoc: bool,
+        }
+    }
+
+    impl<'a> Upgrade for Program<'a> {
+        type Target = next::Program<'a>;
+
+        fn upgrade(self) -> Result<Self::Target, Error> {
+            Ok(next::Program {
+                exports: self.exports.upgrade()?,
+                enums: self.enums.upgrade()?,
+                imports: self.imports.upgrade()?,
+                structs: self.structs.upgrade()?,
+                typescript_custom_sections: self.typescript_custom_sections,
+                local_modules: self.local_modules,
+                inline_js: self.inline_js,
+                unique_crate_identifier: self.unique_crate_identifier,
+                package_json: self.package_json,
+                linked_modules: self.linked_modules,
+                source_root: NO_LOCATION,
+            })
+        }
+    }
+
+    impl<'a> Upgrade for Import<'a> {
+        type Target = next::Import<'a>;
+
+        fn upgrade(self) -> Result<Self::Target, Error> {
+            Ok(next::Import {
+                module: self.module,
+                js_namespace: self.js_namespace,
+                kind: match self.kind {
+                    ImportKind::Function(f) => next::ImportKind::Function(f.upgrade()?),
+                    ImportKind::Static(s) => next::ImportKind::Static(s),
+                    ImportKind::String(s) => next::ImportKind::String(s),
+                    ImportKind::Type(t) => next::ImportKind::Type(t),
+                    ImportKind::Enum(e) => next::ImportKind::Enum(e.upgrade()?),
+                },
+            })
+        }
+    }
+
+    impl<'a> Upgrade for ImportFunction<'a> {
+        type Target = next::ImportFunction<'a>;
+
+        fn upgrade(self) -> Result<Self::Target, Error> {
+            Ok(next::ImportFunction {
+                shim: self.shim,
+                catch: self.catch,
+                suspending: false,
+                variadic: self.variadic,
+                assert_no_shim: self.assert_no_shim,
+                method: self.method,
+                structural: self.structural,
+                function: self.function.upgrade()?,
+            })
+        }
+    }
+
+    impl<'a> Upgrade for StringEnum<'a> {
+        type Target = next::StringEnum<'a>;
+
+        fn upgrade(self) -> Result<Self::Target, Error> {
+            Ok(next::StringEnum {
+                name: self.name,
+                variant_values: self.variant_values,
+                comments: self.comments,
+                generate_typescript: self.generate_typescript,
+                location: NO_LOCATION,
+            })
+        }
+    }
+
+    impl<'a> Upgrade for Export<'a> {
+        type Target = next::Export<'a>;
+
+        fn upgrade(self) -> Result<Self::Target, Error> {
+            Ok(next::Export {
+                class: self.class,
+                comments: self.comments,
+                consumed: self.consumed,
+                function: self.function.upgrade()?,
+                method_kind: self.method_kind,
+                start: self.start,
+                split: None,
+                promising: false,
+            })
+        }
+    }
+
+    impl<'a> Upgrade for Enum<'a> {
+        type Target = next::Enum<'a>;
+
+        fn upgrade(self) -> Result<Self::Target, Error> {
+            Ok(next::Enum {
+                name: self.name,
+                signed: self.signed,
+                variants: self.variants,
+                comments: self.comments,
+                generate_typescript: self.generate_typescript,
+                location: NO_LOCATION,
+            })
+        }
+    }
+
+    impl<'a> Upgrade for Function<'a> {
+        type Target = next::Function<'a>;
+
+        fn upgrade(self) -> Result<Self::Target, Error> {
+            Ok(next::Function {
+                args: self.args,
+                asyncness: self.asyncness,
+                name: self.name,
+                generate_typescript: self.generate_typescript,
+                generate_jsdoc: self.generate_jsdoc,
+                variadic: self.variadic,
+                ret_ty_override: self.ret_ty_override,
+                ret_desc: self.ret_desc,
+                location: NO_LOCATION,
+            })
+        }
+    }
+
+    impl<'a> Upgrade for Struct<'a> {
+        type Target = next::Struct<'a>;
+
+        fn upgrade(self) -> Result<Self::Target, Error> {
+            Ok(next::Struct {
+                name: self.name,
+                fields: self.fields.upgrade()?,
+                comments: self.comments,
+                is_inspectable: self.is_inspectable,
+                generate_typescript: self.generate_typescript,
+                location: NO_LOCATION,
+            })
+        }
+    }
+
+    impl<'a> Upgrade for StructField<'a> {
+        type Target = next::StructField<'a>;
+
+        fn upgrade(self) -> Result<Self::Target, Error> {
+            Ok(next::StructField {
+                name: self.name,
+                readonly: self.readonly,
+                comments: self.comments,
+                generate_typescript: self.generate_typescript,
+                generate_jsdoc: self.generate_jsdoc,
+                location: NO_LOCATION,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_program, SCHEMA_WINDOW, SUPPORTED_SCHEMA_VERSIONS};
+    use crate::decode::{ImportKind, ImportModule};
+    use crate::wit::extract_programs;
+    use walrus::Module;
+
+    /// The custom section of a crate built with wasm-bindgen 0.2.100, with the
+    /// programs of `add_one`, `Color`, `Point` and the imports `bar` and `log`.
+    const SECTION_0_2_100: &[u8] = include_bytes!("v0_2_100.bin");
+
+    fn module_with_section(mut module: Module, data: &[u8]) -> Module {
+        module.customs.add(walrus::RawCustomSection {
+            name: "__wasm_bindgen_unstable".to_string(),
+            data: data.to_vec(),
+        });
+        module
+    }
+
+    fn str(data: &mut Vec<u8>, s: &str) {
+        data.push(s.len() as u8);
+        data.extend_from_slice(s.as_bytes());
+    }
+
+    #[test]
+    fn upgrades_0_2_100() {
+        let mut data = Vec::new();
+        // exports
+        data.push(1);
+        data.push(0); // class
+        data.push(1); // comments
+        str(&mut data, " Adds one.");
+        data.push(0); // consumed
+        data.push(1); // function args
+        str(&mut data, "x");
+        data.extend([0, 0]); // ty_override, desc
+        data.push(0); // asyncness
+        str(&mut data, "add_one");
+        data.extend([1, 1, 0, 0, 0]); // typescript, jsdoc, variadic, ret ty, ret desc
+        data.push(1); // method kind: operation
+        data.extend([1, 0]); // static, regular
+        data.push(0); // start
+
+        // enums
+        data.push(1);
+        str(&mut data, "Color");
+        data.push(0); // signed
+        data.push(1); // variants
+        str(&mut data, "Red");
+        data.extend([0, 0]); // value, comments
+        data.push(0); // comments
+        data.push(1); // typescript
+
+        // imports, structs, custom sections, local modules, inline js
+        data.extend([0, 0, 0, 0, 0]);
+        str(&mut data, "crate-1234");
+        data.push(0); // package json
+        data.push(0); // linked modules
+
+        let program = decode_program("0.2.100", &data, false).unwrap().unwrap();
+        assert_eq!(program.exports.len(), 1);
+        let export = &program.exports[0];
+        assert_eq!(export.function.name, "add_one");
+        assert_eq!(export.function.args[0].name, "x");
+        assert_eq!(&*export.function.location, "");
+        assert!(export.split.is_none());
+        assert_eq!(program.enums[0].name, "Color");
+        assert_eq!(program.unique_crate_identifier, "crate-1234");
+        assert_eq!(&*program.source_root, "");
+
+        assert!(decode_program("0.2.50", &data, false).is_none());
+    }
+
+    #[test]
+    fn supported_versions() {
+        assert!(SUPPORTED_SCHEMA_VERSIONS.len() <= SCHEMA_WINDOW);
+        assert!(!SUPPORTED_SCHEMA_VERSIONS.contains(&wasm_bindgen_shared::SCHEMA_VERSION));
+        // An empty program, with only empty lists and an empty crate
+        // identifier, is valid in all versions.
+        let data = [0; 10];
+        for version in SUPPORTED_SCHEMA_VERSIONS {
+            let program = decode_program(version, &data, false).unwrap().unwrap();
+            assert!(program.exports.is_empty());
+        }
+    }
+
+    #[test]
+    fn decodes_0_2_100_section() {
+        let mut module = module_with_section(Module::default(), SECTION_0_2_100);
+        let mut storage = Vec::new();
+        let programs = extract_programs(&mut module, &mut storage).unwrap();
+        assert_eq!(programs.len(), 5);
+
+        let export = &programs[0].exports[0];
+        assert_eq!(export.function.name, "add_one");
+        assert_eq!(export.comments, [" Adds one."]);
+        assert_eq!(export.function.args[0].name, "x");
+        assert!(!export.function.asyncness);
+        assert!(!export.promising);
+
+        let color = &programs[1].enums[0];
+        assert_eq!(color.name, "Color");
+        let variants = color
+            .variants
+            .iter()
+            .map(|v| (v.name, v.value))
+            .collect::<Vec<_>>();
+        assert_eq!(variants, [("Red", 0), ("Green", 5)]);
+
+        let point = &programs[2].structs[0];
+        assert_eq!(point.name, "Point");
+        let fields = point
+            .fields
+            .iter()
+            .map(|f| (f.name, f.readonly))
+            .collect::<Vec<_>>();
+        assert_eq!(fields, [("x", false), ("y", true)]);
+
+        let bar = &programs[3].imports[0];
+        assert!(matches!(bar.module, Some(ImportModule::Named("foo"))));
+        let log = &programs[4].imports[0];
+        assert!(log.module.is_none());
+        assert_eq!(
+            log.js_namespace.as_deref(),
+            Some(&["console".to_string()][..])
+        );
+        let imports = [bar, log]
+            .iter()
+            .map(|import| match &import.kind {
+                ImportKind::Function(f) => (f.function.name, f.catch, f.suspending),
+                _ => panic!("expected an imported function"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(imports, [("bar", false, false), ("log", true, false)]);
+
+        for program in programs.iter() {
+            assert_eq!(program.unique_crate_identifier, "old-f1f353b8391a1675");
+            assert_eq!(&*program.source_root, "");
+        }
+    }
+
+    #[test]
+    fn rejects_0_2_100_with_memory64() {
+        // A module with only a 64-bit memory.
+        let module = walrus::ModuleConfig::new()
+            .strict_validate(false)
+            .parse(b"\0asm\x01\0\0\0\x05\x03\x01\x04\x01")
+            .unwrap();
+        let mut module = module_with_section(module, SECTION_0_2_100);
+        let mut storage = Vec::new();
+        let err = extract_programs(&mut module, &mut storage).err().unwrap();
+        let err = format!("{:#}", err);
+        assert!(err.contains("schema version 0.2.100"), "{}", err);
+        assert!(err.contains("64-bit memories (wasm64) need wasm-bindgen 0.2.101"));
+    }
+}
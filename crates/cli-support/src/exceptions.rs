@@ -0,0 +1,405 @@
+//! Support for catching and throwing JS exceptions natively in Wasm.
+//!
+//! By default `#[wasm_bindgen(catch)]` imports are wrapped in a JS `try/catch`
+//! (`handleError`) which stores the exception through `__wbindgen_exn_store`,
+//! and `throw_val` calls out to JS to throw its value. If the module is
+//! compiled with the exception handling proposal this pass instead imports
+//! `WebAssembly.JSTag`, the tag of all exceptions thrown by JS, and:
+//!
+//! * wraps each catching import in a shim which calls it inside of a
+//!   `try_table`, storing any JS exception it catches in the externref table
+//!   and passing it to `__wbindgen_exn_store`.
+//! * replaces `__wbindgen_rethrow` with a function which throws its argument
+//!   with the `throw` instruction.
+//!
+//! Exceptions which aren't JS exceptions, like Rust panics compiled with
+//! `-Cpanic=unwind`, aren't caught by these shims and unwind through JS.
+
+use crate::intrinsic::Intrinsic;
+use crate::wit::{AuxImport, NonstandardWitSection, WasmBindgenAux};
+use crate::PLACEHOLDER_MODULE;
+use anyhow::{anyhow, Error};
+use std::collections::{HashMap, HashSet};
+use walrus::ir::*;
+use walrus::{FunctionBuilder, FunctionId, Module, RefType, TagId, ValType};
+
+/// The name of the import which is provided `WebAssembly.JSTag` in JS.
+pub const JS_TAG: &str = "__wbindgen_jstag";
+
+pub fn run(module: &mut Module) -> Result<(), Error> {
+    let mut aux = module
+        .customs
+        .delete_typed::<WasmBindgenAux>()
+        .expect("aux section should be present");
+    let section = module
+        .customs
+        .get_typed::<NonstandardWitSection>()
+        .expect("wit custom section should exist");
+
+    let mut catching = Vec::new();
+    let mut rethrow = None;
+    for (_, func, adapter) in section.implements.iter() {
+        if aux.imports_with_catch.contains(adapter) {
+            catching.push(*func);
+        }
+        if let Some(AuxImport::Intrinsic(Intrinsic::Rethrow)) = aux.import_map.get(adapter) {
+            rethrow = Some(*func);
+        }
+    }
+    if catching.is_empty() && rethrow.is_none() {
+        module.customs.add(*aux);
+        return Ok(());
+    }
+
+    let externref = ValType::Ref(RefType::Externref);
+    let ty = module.types.add(&[externref], &[]);
+    let (tag, _) = module.add_import_tag(PLACEHOLDER_MODULE, JS_TAG, ty);
+
+    let mut shims = HashMap::new();
+    if !catching.is_empty() {
+        let table = aux
+            .externref_table
+            .ok_or_else(|| anyhow!("failed to find the externref table"))?;
+        let alloc = aux
+            .externref_alloc
+            .ok_or_else(|| anyhow!("failed to find the `__externref_table_alloc` function"))?;
+        let exn_store = aux
+            .exn_store
+            .ok_or_else(|| anyhow!("failed to find the `__wbindgen_exn_store` function"))?;
+        for import in catching {
+            let shim = catch_shim(module, import, tag, table, alloc, exn_store);
+            shims.insert(import, shim);
+        }
+        // All catching is now done by the Wasm module, so the JS imports are
+        // free to throw.
+        aux.wasm_catches_exceptions = true;
+    }
+    if let Some(import) = rethrow {
+        shims.insert(import, throw_shim(module, import, tag));
+    }
+
+    rewrite_calls(module, &shims);
+    module.customs.add(*aux);
+    Ok(())
+}
+
+/// Generates a function with the same signature as `import` which calls it,
+/// catching any JS exception it throws and storing it for
+/// `take_last_exception`, in which case all results are zero.
+fn catch_shim(
+    module: &mut Module,
+    import: FunctionId,
+    tag: TagId,
+    table: walrus::TableId,
+    alloc: FunctionId,
+    exn_store: FunctionId,
+) -> FunctionId {
+    let ty = module.funcs.get(import).ty();
+    let params = module.types.params(ty).to_vec();
+    let results = module.types.results(ty).to_vec();
+    let try_ty = InstrSeqType::new(&mut module.types, &[], &results);
+    let args = params
+        .iter()
+        .map(|ty| module.locals.add(*ty))
+        .collect::<Vec<_>>();
+    let exn = module.locals.add(ValType::Ref(RefType::Externref));
+    let idx = module.locals.add(ValType::I32);
+
+    let mut builder = FunctionBuilder::new(&mut module.types, &params, &results);
+    let mut body = builder.func_body();
+    body.block(Some(ValType::Ref(RefType::Externref)), |caught| {
+        let label = caught.id();
+        let mut call = caught.dangling_instr_seq(try_ty);
+        for arg in args.iter() {
+            call.local_get(*arg);
+        }
+        call.call(import);
+        let seq = call.id();
+        caught.instr(TryTable {
+            seq,
+            catches: vec![TryTableCatch::Catch { tag, block: label }],
+        });
+        caught.return_();
+    });
+    body.local_set(exn)
+        .call(alloc)
+        .local_tee(idx)
+        .local_get(exn)
+        .table_set(table)
+        .local_get(idx)
+        .call(exn_store);
+    for ty in results.iter() {
+        match ty {
+            ValType::I32 => body.i32_const(0),
+            ValType::I64 => body.i64_const(0),
+            ValType::F32 => body.f32_const(0.0),
+            ValType::F64 => body.f64_const(0.0),
+            ValType::V128 => body.const_(Value::V128(0)),
+            ValType::Ref(ty) => body.ref_null(*ty),
+        };
+    }
+
+    let id = builder.finish(args, &mut module.funcs);
+    let name = module.funcs.get(import).name.clone().unwrap_or_default();
+    module.funcs.get_mut(id).name = Some(format!("{} catch shim", name));
+    id
+}
+
+/// Generates the replacement of `__wbindgen_rethrow`, which after the
+/// externref pass takes the `externref` to throw.
+fn throw_shim(module: &mut Module, import: FunctionId, tag: TagId) -> FunctionId {
+    let ty = module.funcs.get(import).ty();
+    let params = module.types.params(ty).to_vec();
+    let results = module.types.results(ty).to_vec();
+    let args = params
+        .iter()
+        .map(|ty| module.locals.add(*ty))
+        .collect::<Vec<_>>();
+
+    let mut builder = FunctionBuilder::new(&mut module.types, &params, &results);
+    let mut body = builder.func_body();
+    for arg in args.iter() {
+        body.local_get(*arg);
+    }
+    body.instr(Throw { tag });
+
+    let id = builder.finish(args, &mut module.funcs);
+    module.funcs.get_mut(id).name = Some("__wbindgen_rethrow".to_string());
+    id
+}
+
+/// Redirects all calls to the imports in `shims` to their shims, leaving the
+/// calls in the shims themselves alone.
+pub(crate) fn rewrite_calls(module: &mut Module, shims: &HashMap<FunctionId, FunctionId>) {
+    let skip = shims.values().copied().collect::<HashSet<_>>();
+    for (id, func) in module.funcs.iter_local_mut() {
+        if skip.contains(&id) {
+            continue;
+        }
+        let entry = func.entry_block();
+        dfs_pre_order_mut(&mut Rewrite { shims }, func, entry);
+    }
+
+    struct Rewrite<'a> {
+        shims: &'a HashMap<FunctionId, FunctionId>,
+    }
+
+    impl VisitorMut for Rewrite<'_> {
+        fn visit_call_mut(&mut self, call: &mut Call) {
+            if let Some(shim) = self.shims.get(&call.func) {
+                call.func = *shim;
+            }
+        }
+
+        fn visit_return_call_mut(&mut self, instr: &mut ReturnCall) {
+            if let Some(shim) = self.shims.get(&instr.func) {
+                instr.func = *shim;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wit::AdapterId;
+    use walrus::FunctionKind;
+
+    /// A module importing `catching` (a `catch` import), `rethrow` and
+    /// `plain`, with a local function `caller` calling all three.
+    struct Fixture {
+        module: Module,
+        catching: FunctionId,
+        rethrow: FunctionId,
+        plain: FunctionId,
+        caller: FunctionId,
+    }
+
+    fn fixture(catch: bool, throw: bool) -> Fixture {
+        let mut module = Module::default();
+        let externref = ValType::Ref(RefType::Externref);
+
+        let ty = module.types.add(&[ValType::I32], &[ValType::I32]);
+        let (catching, catching_import) =
+            module.add_import_func(PLACEHOLDER_MODULE, "catching", ty);
+        let ty = module.types.add(&[externref], &[]);
+        let (rethrow, rethrow_import) = module.add_import_func(PLACEHOLDER_MODULE, "rethrow", ty);
+        let ty = module.types.add(&[ValType::I32], &[]);
+        let (plain, plain_import) = module.add_import_func(PLACEHOLDER_MODULE, "plain", ty);
+
+        let table = module
+            .tables
+            .add_local(false, 128, None, RefType::Externref);
+        let mut builder = FunctionBuilder::new(&mut module.types, &[], &[ValType::I32]);
+        builder.func_body().i32_const(0);
+        let alloc = builder.finish(Vec::new(), &mut module.funcs);
+        let mut builder = FunctionBuilder::new(&mut module.types, &[ValType::I32], &[]);
+        let exn_store = builder.finish(vec![module.locals.add(ValType::I32)], &mut module.funcs);
+
+        let mut builder = FunctionBuilder::new(&mut module.types, &[], &[ValType::I32]);
+        builder
+            .func_body()
+            .i32_const(1)
+            .call(plain)
+            .ref_null(RefType::Externref)
+            .call(rethrow)
+            .i32_const(2)
+            .call(catching);
+        let caller = builder.finish(Vec::new(), &mut module.funcs);
+        module.exports.add("caller", caller);
+
+        let mut aux = WasmBindgenAux {
+            externref_table: Some(table),
+            externref_alloc: Some(alloc),
+            exn_store: Some(exn_store),
+            ..Default::default()
+        };
+        if catch {
+            aux.imports_with_catch.insert(AdapterId(0));
+        }
+        if throw {
+            aux.import_map
+                .insert(AdapterId(1), AuxImport::Intrinsic(Intrinsic::Rethrow));
+        }
+        module.customs.add(aux);
+
+        let mut section = NonstandardWitSection::default();
+        section
+            .implements
+            .push((catching_import, catching, AdapterId(0)));
+        section
+            .implements
+            .push((rethrow_import, rethrow, AdapterId(1)));
+        section.implements.push((plain_import, plain, AdapterId(2)));
+        module.customs.add(section);
+
+        Fixture {
+            module,
+            catching,
+            rethrow,
+            plain,
+            caller,
+        }
+    }
+
+    /// All instructions of a local function, including nested ones.
+    fn instrs(module: &Module, func: FunctionId) -> Vec<Instr> {
+        struct Collect(Vec<Instr>);
+
+        impl<'instr> Visitor<'instr> for Collect {
+            fn visit_instr(&mut self, instr: &'instr Instr, _: &'instr InstrLocId) {
+                self.0.push(instr.clone());
+            }
+        }
+
+        let local = match &module.funcs.get(func).kind {
+            FunctionKind::Local(local) => local,
+            _ => panic!("expected a local function"),
+        };
+        let mut collect = Collect(Vec::new());
+        dfs_in_order(&mut collect, local, local.entry_block());
+        collect.0
+    }
+
+    fn calls(module: &Module, func: FunctionId) -> Vec<FunctionId> {
+        instrs(module, func)
+            .into_iter()
+            .filter_map(|instr| match instr {
+                Instr::Call(Call { func }) => Some(func),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn find(module: &Module, name: &str) -> FunctionId {
+        module
+            .funcs
+            .iter()
+            .find(|f| f.name.as_deref() == Some(name))
+            .map(|f| f.id())
+            .unwrap_or_else(|| panic!("no function named `{}`", name))
+    }
+
+    #[test]
+    fn catching_import() {
+        let Fixture {
+            mut module,
+            catching,
+            rethrow,
+            plain,
+            caller,
+        } = fixture(true, false);
+        module.funcs.get_mut(catching).name = Some("catching".to_string());
+        run(&mut module).unwrap();
+
+        let import = module.imports.find(PLACEHOLDER_MODULE, JS_TAG).unwrap();
+        let tag = match module.imports.get(import).kind {
+            walrus::ImportKind::Tag(tag) => tag,
+            _ => panic!("`{}` should import a tag", JS_TAG),
+        };
+
+        let shim = find(&module, "catching catch shim");
+        let body = instrs(&module, shim);
+        assert!(body.iter().any(|instr| matches!(
+            instr,
+            Instr::TryTable(TryTable { catches, .. })
+                if matches!(catches[..], [TryTableCatch::Catch { tag: t, .. }] if t == tag)
+        )));
+        assert!(body.iter().any(|instr| matches!(instr, Instr::TableSet(_))));
+        let aux = module.customs.get_typed::<WasmBindgenAux>().unwrap();
+        assert_eq!(
+            calls(&module, shim),
+            [
+                catching,
+                aux.externref_alloc.unwrap(),
+                aux.exn_store.unwrap()
+            ]
+        );
+        assert!(aux.wasm_catches_exceptions);
+
+        // Only the catching import is redirected.
+        assert_eq!(calls(&module, caller), [plain, rethrow, shim]);
+        module.emit_wasm();
+    }
+
+    #[test]
+    fn rethrow_shim() {
+        let Fixture {
+            mut module,
+            catching,
+            plain,
+            caller,
+            ..
+        } = fixture(false, true);
+        run(&mut module).unwrap();
+
+        let shim = find(&module, "__wbindgen_rethrow");
+        let body = instrs(&module, shim);
+        assert!(matches!(body[..], [Instr::LocalGet(_), Instr::Throw(_)]));
+        assert_eq!(calls(&module, caller), [plain, shim, catching]);
+
+        // Throwing doesn't move catching out of JS.
+        let aux = module.customs.get_typed::<WasmBindgenAux>().unwrap();
+        assert!(!aux.wasm_catches_exceptions);
+        module.emit_wasm();
+    }
+
+    #[test]
+    fn nothing_to_do() {
+        let Fixture {
+            mut module,
+            catching,
+            rethrow,
+            plain,
+            caller,
+        } = fixture(false, false);
+        let funcs = module.funcs.iter().count();
+        run(&mut module).unwrap();
+
+        assert!(module.imports.find(PLACEHOLDER_MODULE, JS_TAG).is_none());
+        assert_eq!(module.funcs.iter().count(), funcs);
+        assert_eq!(calls(&module, caller), [plain, rethrow, catching]);
+        let aux = module.customs.get_typed::<WasmBindgenAux>().unwrap();
+        assert!(!aux.wasm_catches_exceptions);
+    }
+}
@@ -83,6 +83,10 @@ fn vector(contents: Descriptor) -> Descriptor {
     Descriptor::Vector(Box::new(contents))
 }
 
+fn opt_vector(contents: Descriptor) -> Descriptor {
+    Descriptor::Option(Box::new(vector(contents)))
+}
+
 intrinsics! {
     pub enum Intrinsic {
         #[symbol = "__wbindgen_jsval_eq"]
@@ -196,6 +200,9 @@ intrinsics! {
         #[symbol = "__wbindgen_cb_drop"]
         #[signature = fn(Externref) -> Boolean]
         CallbackDrop,
+        #[symbol = "__wbindgen_cb_drop_scoped"]
+        #[signature = fn(Externref) -> Boolean]
+        CallbackDropScoped,
         #[symbol = "__wbindgen_number_new"]
         #[signature = fn(F64) -> Externref]
         NumberNew,
@@ -232,6 +239,24 @@ intrinsics! {
         #[symbol = "__wbindgen_string_get"]
         #[signature = fn(ref_externref()) -> opt_string()]
         StringGet,
+        #[symbol = "__wbindgen_string_length"]
+        #[signature = fn(ref_externref()) -> U32]
+        StringLength,
+        #[symbol = "__wbindgen_string_char_code_at"]
+        #[signature = fn(ref_externref(), U32) -> F64]
+        StringCharCodeAt,
+        #[symbol = "__wbindgen_string_concat"]
+        #[signature = fn(ref_externref(), ref_externref()) -> Externref]
+        StringConcat,
+        #[symbol = "__wbindgen_string_substring"]
+        #[signature = fn(ref_externref(), U32, U32) -> Externref]
+        StringSubstring,
+        #[symbol = "__wbindgen_string_equals"]
+        #[signature = fn(ref_externref(), ref_externref()) -> Boolean]
+        StringEquals,
+        #[symbol = "__wbindgen_string_from_char_code_array"]
+        #[signature = fn(slice(U16)) -> Externref]
+        StringFromCharCodeArray,
         #[symbol = "__wbindgen_boolean_get"]
         #[signature = fn(ref_externref()) -> I32]
         BooleanGet,
@@ -244,9 +269,15 @@ intrinsics! {
         #[symbol = "__wbindgen_error_new"]
         #[signature = fn(ref_string()) -> Externref]
         ErrorNew,
+        #[symbol = "__wbindgen_panic_error_new"]
+        #[signature = fn(ref_string(), ref_string()) -> Externref]
+        PanicErrorNew,
         #[symbol = "__wbindgen_memory"]
         #[signature = fn() -> Externref]
         Memory,
+        #[symbol = "__wbindgen_memory_by_name"]
+        #[signature = fn(ref_string()) -> Externref]
+        MemoryByName,
         #[symbol = "__wbindgen_exports"]
         #[signature = fn() -> Externref]
         Exports,
@@ -256,6 +287,9 @@ intrinsics! {
         #[symbol = "__wbindgen_function_table"]
         #[signature = fn() -> Externref]
         FunctionTable,
+        #[symbol = "__wbindgen_spawn_thread"]
+        #[signature = fn(U32, U32) -> Externref]
+        SpawnThread,
         #[symbol = "__wbindgen_debug_string"]
         #[signature = fn(ref_externref()) -> String]
         DebugString,
@@ -307,6 +341,42 @@ intrinsics! {
         #[symbol = "__wbindgen_array_push"]
         #[signature = fn(ref_externref(), Externref) -> Unit]
         ArrayPush,
+        #[symbol = "__wbindgen_array_length"]
+        #[signature = fn(ref_externref()) -> U32]
+        ArrayLength,
+        #[symbol = "__wbindgen_array_get"]
+        #[signature = fn(ref_externref(), U32) -> Externref]
+        ArrayGet,
+        #[symbol = "__wbindgen_object_new"]
+        #[signature = fn() -> Externref]
+        ObjectNew,
+        #[symbol = "__wbindgen_object_set"]
+        #[signature = fn(ref_externref(), Externref, Externref) -> Unit]
+        ObjectSet,
+        #[symbol = "__wbindgen_object_entries"]
+        #[signature = fn(ref_externref()) -> Externref]
+        ObjectEntries,
+        #[symbol = "__wbindgen_map_new"]
+        #[signature = fn() -> Externref]
+        MapNew,
+        #[symbol = "__wbindgen_map_set"]
+        #[signature = fn(ref_externref(), Externref, Externref) -> Unit]
+        MapSet,
+        #[symbol = "__wbindgen_uint8_array_get"]
+        #[signature = fn(ref_externref()) -> opt_vector(U8)]
+        Uint8ArrayGet,
+        #[symbol = "__wbindgen_weak_ref_new"]
+        #[signature = fn(ref_externref()) -> Externref]
+        WeakRefNew,
+        #[symbol = "__wbindgen_weak_ref_deref"]
+        #[signature = fn(ref_externref()) -> Externref]
+        WeakRefDeref,
+        #[symbol = "__wbindgen_weak_ref_register"]
+        #[signature = fn(ref_externref(), ref_externref(), ref_externref()) -> Unit]
+        WeakRefRegister,
+        #[symbol = "__wbindgen_weak_ref_unregister"]
+        #[signature = fn(ref_externref()) -> Unit]
+        WeakRefUnregister,
         #[symbol = "__wbindgen_externref_heap_live_count"]
         #[signature = fn() -> I32]
         ExternrefHeapLiveCount,
@@ -30,6 +30,15 @@ pub struct Builder<'a, 'b> {
     catch: bool,
     /// Whether or not we're logging the error coming out of this intrinsic
     log_error: bool,
+    /// Whether or not the main function invocation may suspend Wasm through JS
+    /// Promise Integration, in which case it's awaited.
+    jspi: bool,
+    /// Whether or not this is a `promising` export, of which only one call may
+    /// be suspended at a time as they all share the Rust stack.
+    promising: bool,
+    /// The secondary Wasm module the function was split into, which is loaded
+    /// before the function runs.
+    split: Option<String>,
 }
 
 /// Helper struct used to create JS to process all instructions in an adapter
@@ -66,6 +75,10 @@ pub struct JsBuilder<'a, 'b> {
     /// pushed to the stack. We're not super principled about this though, so
     /// improvements will likely happen here over time.
     stack: Vec<String>,
+
+    /// Whether calls of exports go through `WebAssembly.promising` and calls of
+    /// imports are awaited.
+    jspi: bool,
 }
 
 pub struct JsFunction {
@@ -100,6 +113,9 @@ impl<'a, 'b> Builder<'a, 'b> {
             constructor: None,
             method: None,
             catch: false,
+            jspi: false,
+            promising: false,
+            split: None,
         }
     }
 
@@ -119,6 +135,19 @@ impl<'a, 'b> Builder<'a, 'b> {
         self.log_error = log;
     }
 
+    pub fn jspi(&mut self, jspi: bool) {
+        self.jspi = jspi;
+    }
+
+    pub fn promising(&mut self, promising: bool) {
+        self.jspi = promising;
+        self.promising = promising;
+    }
+
+    pub fn split(&mut self, split: Option<&str>) {
+        self.split = split.map(String::from);
+    }
+
     pub fn process(
         &mut self,
         adapter: &Adapter,
@@ -148,6 +177,7 @@ impl<'a, 'b> Builder<'a, 'b> {
         // method, so the leading parameter is the this pointer stored on
         // the JS object, so synthesize that here.
         let mut js = JsBuilder::new(self.cx, debug_name);
+        js.jspi = self.jspi;
         if let Some(consumes_self) = self.method {
             let _ = params.next();
             if js.cx.config.debug {
@@ -155,12 +185,14 @@ impl<'a, 'b> Builder<'a, 'b> {
                     "if (this.__wbg_ptr == 0) throw new Error('Attempt to use a moved value');",
                 );
             }
-            if consumes_self {
+            let ptr = if consumes_self {
                 js.prelude("const ptr = this.__destroy_into_raw();");
-                js.args.push("ptr".into());
+                "ptr"
             } else {
-                js.args.push("this.__wbg_ptr".into());
-            }
+                "this.__wbg_ptr"
+            };
+            let ptr = js.cx.ptr_to_wasm(ptr);
+            js.args.push(ptr);
         }
         for (i, param) in params.enumerate() {
             let arg = match args_data {
@@ -245,7 +277,7 @@ impl<'a, 'b> Builder<'a, 'b> {
         }
         code.push_str(") {\n");
 
-        let call = if !js.finally.is_empty() {
+        let mut call = if !js.finally.is_empty() {
             format!(
                 "{}try {{\n{}}} finally {{\n{}}}\n",
                 js.pre_try, js.prelude, js.finally
@@ -254,6 +286,27 @@ impl<'a, 'b> Builder<'a, 'b> {
             js.pre_try + &js.prelude
         };
 
+        // Calls which may suspend with JS Promise Integration are awaited, so
+        // run them in an async closure. A `promising` call is rejected before
+        // touching the Rust stack if another one is still suspended.
+        if self.promising {
+            js.cx.expose_jspi();
+            call = format!(
+                "enterPromising();\ntry {{\n{}}} finally {{\npromisingInFlight = false;\n}}\n",
+                call
+            );
+        }
+        if self.jspi {
+            call = format!("return (async () => {{\n{}}})();\n", call);
+        }
+
+        if let Some(split) = &self.split {
+            call = format!(
+                "return __wbg_load_split('{}').then(() => {{\n{}}});\n",
+                split, call
+            );
+        }
+
         if self.catch {
             js.cx.expose_handle_error()?;
         }
@@ -568,6 +621,7 @@ impl<'a, 'b> JsBuilder<'a, 'b> {
             finally: String::new(),
             prelude: String::new(),
             stack: Vec::new(),
+            jspi: false,
         }
     }
 
@@ -610,6 +664,18 @@ impl<'a, 'b> JsBuilder<'a, 'b> {
         self.stack.push(arg);
     }
 
+    /// Pops a pointer or length received from Wasm as a JS number.
+    fn pop_ptr(&mut self) -> String {
+        let val = self.pop();
+        self.cx.ptr_from_wasm(&val)
+    }
+
+    /// Pushes a pointer or length held as a JS number to pass to Wasm.
+    fn push_ptr(&mut self, val: String) {
+        let val = self.cx.ptr_to_wasm(&val);
+        self.push(val);
+    }
+
     fn assert_class(&mut self, arg: &str, class: &str) {
         self.cx.expose_assert_class();
         self.prelude(&format!("_assertClass({}, {});", arg, class));
@@ -716,8 +782,8 @@ impl<'a, 'b> JsBuilder<'a, 'b> {
             realloc = realloc,
         ));
         self.prelude(&format!("const len{} = WASM_VECTOR_LEN;", i));
-        self.push(format!("ptr{}", i));
-        self.push(format!("len{}", i));
+        self.push_ptr(format!("ptr{}", i));
+        self.push_ptr(format!("len{}", i));
         Ok(())
     }
 }
@@ -796,7 +862,7 @@ fn instruction(
                 }
                 if let Instruction::DeferFree { align, .. } = instr {
                     // add alignment
-                    args.push(align.to_string());
+                    args.push(js.cx.ptr_to_wasm(&align.to_string()));
                 }
             } else {
                 // Otherwise, pop off the number of parameters for the function we're calling.
@@ -807,7 +873,22 @@ fn instruction(
             }
 
             // Call the function through an export of the underlying module.
-            let call = invoc.invoke(js.cx, &args, &mut js.prelude, log_error)?;
+            let mut call = invoc.invoke(js.cx, &args, &mut js.prelude, log_error)?;
+
+            // With JS Promise Integration the exported function is called
+            // through `WebAssembly.promising`, while the JS import returns a
+            // promise which Wasm is suspended on, so both are awaited.
+            if js.jspi {
+                match (instr, &invoc) {
+                    (Instruction::CallExport(_), Invocation::Core { id, defer: false }) => {
+                        js.cx.expose_jspi();
+                        let name = js.cx.export_name_of(*id);
+                        call = format!("await makePromising(wasm.{})({})", name, args.join(", "));
+                    }
+                    (Instruction::CallAdapter(_), _) => call = format!("await {}", call),
+                    _ => {}
+                }
+            }
 
             // And then figure out how to actually handle where the call
             // happens. This is pretty conditional depending on the number of
@@ -930,8 +1011,8 @@ fn instruction(
         }
 
         Instruction::MemoryToString(mem) => {
-            let len = js.pop();
-            let ptr = js.pop();
+            let len = js.pop_ptr();
+            let ptr = js.pop_ptr();
             let get = js.cx.expose_get_string_from_wasm(*mem)?;
             js.push(format!("{}({}, {})", get, ptr, len));
         }
@@ -946,12 +1027,16 @@ fn instruction(
 
         Instruction::Retptr { size } => {
             js.cx.inject_stack_pointer_shim()?;
-            js.prelude(&format!(
-                "const retptr = wasm.__wbindgen_add_to_stack_pointer(-{});",
-                size
+            let retptr = js.cx.ptr_from_wasm(&format!(
+                "wasm.__wbindgen_add_to_stack_pointer({})",
+                js.cx.ptr_to_wasm(&format!("-{}", size))
             ));
-            js.finally(&format!("wasm.__wbindgen_add_to_stack_pointer({});", size));
-            js.stack.push("retptr".to_string());
+            js.prelude(&format!("const retptr = {};", retptr));
+            js.finally(&format!(
+                "wasm.__wbindgen_add_to_stack_pointer({});",
+                js.cx.ptr_to_wasm(&size.to_string())
+            ));
+            js.push_ptr("retptr".to_string());
         }
 
         Instruction::StoreRetptr { ty, offset, mem } => {
@@ -970,7 +1055,7 @@ fn instruction(
                 "{}().{}({} + {} * {}, {}, true);",
                 mem,
                 method,
-                js.arg(0),
+                js.cx.ptr_from_wasm(js.arg(0)),
                 size,
                 offset,
                 val,
@@ -1038,14 +1123,14 @@ fn instruction(
             js.assert_not_moved(&val);
             let i = js.tmp();
             js.prelude(&format!("var ptr{} = {}.__destroy_into_raw();", i, val));
-            js.push(format!("ptr{}", i));
+            js.push_ptr(format!("ptr{}", i));
         }
 
         Instruction::I32FromExternrefRustBorrow { class } => {
             let val = js.pop();
             js.assert_class(&val, class);
             js.assert_not_moved(&val);
-            js.push(format!("{}.__wbg_ptr", val));
+            js.push_ptr(format!("{}.__wbg_ptr", val));
         }
 
         Instruction::I32FromOptionRust { class } => {
@@ -1058,7 +1143,7 @@ fn instruction(
             js.assert_not_moved(&val);
             js.prelude(&format!("ptr{} = {}.__destroy_into_raw();", i, val));
             js.prelude("}");
-            js.push(format!("ptr{}", i));
+            js.push_ptr(format!("ptr{}", i));
         }
 
         Instruction::I32FromOptionExternref { table_and_alloc } => {
@@ -1185,8 +1270,8 @@ fn instruction(
                 malloc = malloc,
             ));
             js.prelude(&format!("const len{} = WASM_VECTOR_LEN;", i));
-            js.push(format!("ptr{}", i));
-            js.push(format!("len{}", i));
+            js.push_ptr(format!("ptr{}", i));
+            js.push_ptr(format!("len{}", i));
         }
 
         Instruction::UnwrapResult { table_and_drop } => {
@@ -1237,10 +1322,11 @@ fn instruction(
                 var ptr{i} = {ptr};
                 var len{i} = {len};
                 if ({is_err}) {{
-                    ptr{i} = 0; len{i} = 0;
+                    ptr{i} = {zero}; len{i} = {zero};
                     throw {take_object}({err});
                 }}
                 ",
+                zero = js.cx.ptr_to_wasm("0"),
                 take_object = take_object,
                 is_err = is_err,
                 err = err,
@@ -1275,8 +1361,8 @@ fn instruction(
                 realloc = realloc,
             ));
             js.prelude(&format!("var len{} = WASM_VECTOR_LEN;", i));
-            js.push(format!("ptr{}", i));
-            js.push(format!("len{}", i));
+            js.push_ptr(format!("ptr{}", i));
+            js.push_ptr(format!("len{}", i));
         }
 
         Instruction::OptionVector { kind, mem, malloc } => {
@@ -1293,8 +1379,8 @@ fn instruction(
                 malloc = malloc,
             ));
             js.prelude(&format!("var len{} = WASM_VECTOR_LEN;", i));
-            js.push(format!("ptr{}", i));
-            js.push(format!("len{}", i));
+            js.push_ptr(format!("ptr{}", i));
+            js.push_ptr(format!("len{}", i));
         }
 
         Instruction::MutableSliceToMemory { kind, malloc, mem } => {
@@ -1312,8 +1398,8 @@ fn instruction(
             ));
             js.prelude(&format!("var len{} = WASM_VECTOR_LEN;", i));
             // Then pass it the pointer and the length of where we copied it.
-            js.push(format!("ptr{}", i));
-            js.push(format!("len{}", i));
+            js.push_ptr(format!("ptr{}", i));
+            js.push_ptr(format!("len{}", i));
             // Then we give Wasm a reference to the original typed array, so that it can
             // update it with modifications made on the Wasm side before returning.
             js.push(val);
@@ -1343,12 +1429,17 @@ fn instruction(
         }
 
         Instruction::RustFromI32 { class } => {
-            let val = js.pop();
+            let val = js.pop_ptr();
             match constructor {
                 Some(name) if name == class => {
+                    let val = if js.cx.aux.memory64 {
+                        val
+                    } else {
+                        format!("{val} >>> 0")
+                    };
                     js.prelude(&format!(
                         "
-                        this.__wbg_ptr = {val} >>> 0;
+                        this.__wbg_ptr = {val};
                         {name}Finalization.register(this, this.__wbg_ptr, this);
                         "
                     ));
@@ -1363,7 +1454,7 @@ fn instruction(
 
         Instruction::OptionRustFromI32 { class } => {
             assert!(constructor.is_none());
-            let val = js.pop();
+            let val = js.pop_ptr();
             js.cx.require_class_wrap(class);
             js.push(format!(
                 "{0} === 0 ? undefined : {1}.__wrap({0})",
@@ -1383,13 +1474,21 @@ fn instruction(
 
             let get = js.cx.expose_get_cached_string_from_wasm(*mem, *table)?;
 
-            js.prelude(&format!("var v{} = {}({}, {});", tmp, get, ptr, len));
+            js.prelude(&format!(
+                "var v{} = {}({}, {});",
+                tmp,
+                get,
+                js.cx.ptr_from_wasm(&ptr),
+                js.cx.ptr_from_wasm(&len)
+            ));
 
             if *owned {
                 let free = js.cx.export_name_of(*free);
                 js.prelude(&format!(
-                    "if ({ptr} !== 0) {{ wasm.{}({ptr}, {len}, 1); }}",
+                    "if ({} !== 0) {{ wasm.{}({ptr}, {len}, {}); }}",
+                    js.cx.ptr_from_wasm(&ptr),
                     free,
+                    js.cx.ptr_to_wasm("1"),
                     ptr = ptr,
                     len = len,
                 ));
@@ -1412,6 +1511,7 @@ fn instruction(
             let i = js.tmp();
             let b = js.pop();
             let a = js.pop();
+            let null = js.cx.ptr_to_wasm("0");
             js.prelude(&format!("var state{} = {{a: {}, b: {}}};", i, a, b));
             let args = (0..*nargs)
                 .map(|i| format!("arg{}", i))
@@ -1425,7 +1525,7 @@ fn instruction(
                 js.prelude(&format!(
                     "var cb{i} = ({args}) => {{
                         const a = state{i}.a;
-                        state{i}.a = 0;
+                        state{i}.a = {null};
                         try {{
                             return {name}(a, state{i}.b, {args});
                         }} finally {{
@@ -1435,6 +1535,7 @@ fn instruction(
                     i = i,
                     args = args,
                     name = wrapper,
+                    null = null,
                 ));
             } else {
                 js.prelude(&format!(
@@ -1449,30 +1550,32 @@ fn instruction(
             // back to Rust to ensure that any lingering references to the
             // closure will fail immediately due to null pointers passed in
             // to Rust.
-            js.finally(&format!("state{}.a = state{0}.b = 0;", i));
+            js.finally(&format!("state{}.a = state{0}.b = {};", i, null));
             js.push(format!("cb{}", i));
         }
 
         Instruction::VectorLoad { kind, mem, free } => {
-            let len = js.pop();
-            let ptr = js.pop();
+            let len = js.pop_ptr();
+            let wasm_ptr = js.pop();
+            let ptr = js.cx.ptr_from_wasm(&wasm_ptr);
             let f = js.cx.expose_get_vector_from_wasm(kind.clone(), *mem)?;
             let i = js.tmp();
             let free = js.cx.export_name_of(*free);
             js.prelude(&format!("var v{} = {}({}, {}).slice();", i, f, ptr, len));
             js.prelude(&format!(
-                "wasm.{}({}, {} * {size}, {size});",
+                "wasm.{}({}, {}, {});",
                 free,
-                ptr,
-                len,
-                size = kind.size()
+                wasm_ptr,
+                js.cx.ptr_to_wasm(&format!("{} * {}", len, kind.size())),
+                js.cx.ptr_to_wasm(&kind.size().to_string()),
             ));
             js.push(format!("v{}", i))
         }
 
         Instruction::OptionVectorLoad { kind, mem, free } => {
-            let len = js.pop();
-            let ptr = js.pop();
+            let len = js.pop_ptr();
+            let wasm_ptr = js.pop();
+            let ptr = js.cx.ptr_from_wasm(&wasm_ptr);
             let f = js.cx.expose_get_vector_from_wasm(kind.clone(), *mem)?;
             let i = js.tmp();
             let free = js.cx.export_name_of(*free);
@@ -1480,26 +1583,26 @@ fn instruction(
             js.prelude(&format!("if ({} !== 0) {{", ptr));
             js.prelude(&format!("v{} = {}({}, {}).slice();", i, f, ptr, len));
             js.prelude(&format!(
-                "wasm.{}({}, {} * {size}, {size});",
+                "wasm.{}({}, {}, {});",
                 free,
-                ptr,
-                len,
-                size = kind.size()
+                wasm_ptr,
+                js.cx.ptr_to_wasm(&format!("{} * {}", len, kind.size())),
+                js.cx.ptr_to_wasm(&kind.size().to_string()),
             ));
             js.prelude("}");
             js.push(format!("v{}", i));
         }
 
         Instruction::View { kind, mem } => {
-            let len = js.pop();
-            let ptr = js.pop();
+            let len = js.pop_ptr();
+            let ptr = js.pop_ptr();
             let f = js.cx.expose_get_vector_from_wasm(kind.clone(), *mem)?;
             js.push(format!("{f}({ptr}, {len})", ptr = ptr, len = len, f = f));
         }
 
         Instruction::OptionView { kind, mem } => {
-            let len = js.pop();
-            let ptr = js.pop();
+            let len = js.pop_ptr();
+            let ptr = js.pop_ptr();
             let f = js.cx.expose_get_vector_from_wasm(kind.clone(), *mem)?;
             js.push(format!(
                 "{ptr} === 0 ? undefined : {f}({ptr}, {len})",
@@ -1558,19 +1661,32 @@ fn instruction(
         Instruction::I32FromNonNull => {
             let val = js.pop();
             js.assert_non_null(&val);
-            js.push(val);
+            js.push_ptr(val);
         }
 
         Instruction::I32FromOptionNonNull => {
             let val = js.pop();
             js.cx.expose_is_like_none();
             js.assert_optional_number(&val);
-            js.push(format!("isLikeNone({0}) ? 0 : {0}", val));
+            js.push_ptr(format!("isLikeNone({0}) ? 0 : {0}", val));
         }
 
         Instruction::OptionNonNullFromI32 => {
-            let val = js.pop();
-            js.push(format!("{0} === 0 ? undefined : {0} >>> 0", val));
+            let val = js.pop_ptr();
+            if js.cx.aux.memory64 {
+                js.push(format!("{0} === 0 ? undefined : {0}", val));
+            } else {
+                js.push(format!("{0} === 0 ? undefined : {0} >>> 0", val));
+            }
+        }
+
+        Instruction::NonNullFromI32 => {
+            let val = js.pop_ptr();
+            if js.cx.aux.memory64 {
+                js.push(val);
+            } else {
+                js.push(format!("{} >>> 0", val));
+            }
         }
     }
     Ok(())
@@ -1,5 +1,6 @@
 use crate::descriptor::VectorKind;
 use crate::intrinsic::Intrinsic;
+use crate::source_map::{SourceLocations, SourceMap};
 use crate::wit::{
     Adapter, AdapterId, AdapterJsImportKind, AuxExportedMethodKind, AuxReceiverKind, AuxStringEnum,
     AuxValue,
@@ -7,7 +8,10 @@ use crate::wit::{
 use crate::wit::{AdapterKind, Instruction, InstructionData};
 use crate::wit::{AuxEnum, AuxExport, AuxExportKind, AuxImport, AuxStruct};
 use crate::wit::{JsImport, JsImportName, NonstandardWitSection, WasmBindgenAux};
-use crate::{reset_indentation, Bindgen, EncodeInto, OutputMode, PLACEHOLDER_MODULE};
+use crate::{
+    reset_indentation, Bindgen, EncodeInto, InlineWasm, OutputMode, INLINE_WASM_PLACEHOLDER,
+    PLACEHOLDER_MODULE,
+};
 use anyhow::{anyhow, bail, Context as _, Error};
 use binding::TsReference;
 use std::borrow::Cow;
@@ -66,6 +70,17 @@ pub struct Context<'a> {
     /// they're defined in as well as their version specification.
     pub npm_dependencies: HashMap<String, (PathBuf, String)>,
 
+    /// The Rust locations referenced by markers in the generated JS and
+    /// TypeScript, used to generate source maps.
+    source_locations: SourceLocations,
+
+    /// The source map of the generated JS, available after `finalize`.
+    pub js_source_map: Option<SourceMap>,
+
+    /// The source map of the generated TypeScript, available after
+    /// `finalize`.
+    pub declaration_map: Option<SourceMap>,
+
     /// A mapping from the memory IDs as we see them to an index for that memory,
     /// used in function names, as well as all the kinds of views we've created
     /// of that memory.
@@ -94,6 +109,9 @@ struct ExportedClass {
     unwrap_needed: bool,
     /// Whether to generate helper methods for inspecting the class
     is_inspectable: bool,
+    /// Marker for the location of the struct in Rust code, if a declaration
+    /// map was requested.
+    ts_source_marker: String,
     /// All readable properties of the class
     readable_properties: Vec<String>,
     /// Map from field to information about those fields
@@ -119,6 +137,9 @@ struct FieldAccessor {
     ty: String,
     docs: String,
     is_optional: bool,
+    /// Marker for the location of the accessor in Rust code, if a declaration
+    /// map was requested.
+    source_marker: String,
 }
 
 /// Different JS constructs that can be exported.
@@ -160,6 +181,9 @@ impl<'a> Context<'a> {
             threads_enabled: wasm_bindgen_threads_xform::is_enabled(module),
             module,
             npm_dependencies: Default::default(),
+            source_locations: Default::default(),
+            js_source_map: None,
+            declaration_map: None,
             next_export_idx: 0,
             wit,
             aux,
@@ -169,6 +193,24 @@ impl<'a> Context<'a> {
         })
     }
 
+    /// Returns a marker to put on the line defining the JS of an item located
+    /// at `location` in Rust code, if a source map was requested.
+    fn js_source_marker(&mut self, location: Option<&str>) -> String {
+        match location {
+            Some(location) if self.config.source_map => self.source_locations.marker(location),
+            _ => String::new(),
+        }
+    }
+
+    /// Returns a marker to put on the line declaring the TypeScript of an item
+    /// located at `location` in Rust code, if a declaration map was requested.
+    fn ts_source_marker(&mut self, location: Option<&str>) -> String {
+        match location {
+            Some(location) if self.config.declaration_map => self.source_locations.marker(location),
+            _ => String::new(),
+        }
+    }
+
     fn should_write_global(&mut self, name: impl Into<Cow<'static, str>>) -> bool {
         self.exposed_globals.as_mut().unwrap().insert(name.into())
     }
@@ -209,7 +251,10 @@ impl<'a> Context<'a> {
             OutputMode::Bundler { .. }
             | OutputMode::Node { module: true }
             | OutputMode::Web
-            | OutputMode::Deno => match export {
+            | OutputMode::Deno
+            | OutputMode::Module
+            | OutputMode::Workerd
+            | OutputMode::Bun => match export {
                 ExportJs::Class(class) => {
                     assert_eq!(export_name, definition_name);
                     format!("export {}\n", class)
@@ -258,7 +303,7 @@ impl<'a> Context<'a> {
         self.finalize_js(module_name, needs_manual_start)
     }
 
-    fn generate_node_imports(&self) -> String {
+    fn generate_node_imports(&self, module_name: &str) -> String {
         let mut imports = BTreeSet::new();
         for import in self
             .module
@@ -285,12 +330,17 @@ impl<'a> Context<'a> {
                 }
             }
         } else {
+            // The CommonJS flavour of a dual package loads the Wasm module
+            // emitted for the ES module flavour, which imports our intrinsics
+            // from its `_bg.js` file, corresponding to this file.
+            let own_module = if self.config.dual {
+                format!("./{}_bg.js", module_name)
+            } else {
+                PLACEHOLDER_MODULE.to_string()
+            };
             for module in imports.iter() {
                 if module.as_str() == PLACEHOLDER_MODULE {
-                    shim.push_str(&format!(
-                        "imports['{0}'] = module.exports;\n",
-                        PLACEHOLDER_MODULE
-                    ));
+                    shim.push_str(&format!("imports['{}'] = module.exports;\n", own_module));
                 } else {
                     shim.push_str(&format!("imports['{0}'] = require('{0}');\n", module));
                 }
@@ -304,19 +354,26 @@ impl<'a> Context<'a> {
         let mut shim = String::new();
 
         let module_name = "wbg";
-        if let Some(mem) = self.module.memories.iter().next() {
+        if let Ok(mem) = wasm_bindgen_wasm_conventions::get_memory(self.module) {
+            let mem = self.module.memories.get(mem);
             if let Some(id) = mem.import {
                 self.module.imports.get_mut(id).module = module_name.to_string();
+                // Workers started by `wasm_bindgen::thread::spawn` share the
+                // memory of the thread which spawned them.
+                let worker_memory = if !mem.shared {
+                    ""
+                } else if self.config.mode.uses_es_modules() {
+                    shim.push_str(
+                        "import { workerData as __wbg_worker_data } from 'node:worker_threads';\n",
+                    );
+                    "(__wbg_worker_data || {}).__wbindgen_memory || "
+                } else {
+                    "(require('worker_threads').workerData || {}).__wbindgen_memory || "
+                };
                 shim.push_str(&format!(
-                    "imports.{module_name} = {{ memory: new WebAssembly.Memory({{"
+                    "imports.{module_name} = {{ memory: {worker_memory}new WebAssembly.Memory({{"
                 ));
-                shim.push_str(&format!("initial:{}", mem.initial));
-                if let Some(max) = mem.maximum {
-                    shim.push_str(&format!(",maximum:{}", max));
-                }
-                if mem.shared {
-                    shim.push_str(",shared:true");
-                }
+                shim.push_str(&memory_descriptor(mem));
                 shim.push_str("}) };");
             }
         }
@@ -411,6 +468,24 @@ impl<'a> Context<'a> {
         (imports, wasm_import_object)
     }
 
+    fn generate_wasm_module_instantiation(&self) -> String {
+        // `wasmModule` was already compiled, so it can be instantiated
+        // synchronously.
+        "const wasmInstance = new WebAssembly.Instance(wasmModule, imports);
+        const wasm = wasmInstance.exports;
+        export const __wasm = wasm;"
+            .to_string()
+    }
+
+    fn generate_bun_wasm_loading(&self, module_name: &str) -> String {
+        format!(
+            "const wasmCode = await Bun.file(new URL('{module_name}_bg.wasm', import.meta.url)).arrayBuffer();
+            const wasmModule = new WebAssembly.Module(wasmCode);
+            {}",
+            self.generate_wasm_module_instantiation()
+        )
+    }
+
     fn generate_deno_wasm_loading(&self, module_name: &str) -> String {
         // Deno removed support for .wasm imports in https://github.com/denoland/deno/pull/5135
         // the issue for bringing it back is https://github.com/denoland/deno/issues/5609.
@@ -482,7 +557,7 @@ impl<'a> Context<'a> {
             // With normal CommonJS node we need to defer requiring the wasm
             // until the end so most of our own exports are hooked up
             OutputMode::Node { module: false } => {
-                js.push_str(&self.generate_node_imports());
+                js.push_str(&self.generate_node_imports(module_name));
 
                 js.push_str("let wasm;\n");
 
@@ -521,6 +596,50 @@ impl<'a> Context<'a> {
                 }
             }
 
+            // With source-phase imports the host compiles the Wasm module for us,
+            // which we then instantiate with the same import object as Deno.
+            OutputMode::Module => {
+                let (js_imports, wasm_import_object) = self.generate_deno_imports();
+                imports.push_str(&format!(
+                    "import source wasmModule from \"./{module_name}_bg.wasm\";\n"
+                ));
+                imports.push_str(&js_imports);
+                footer.push_str(&wasm_import_object);
+
+                footer.push_str(&self.generate_wasm_module_instantiation());
+
+                footer.push_str("\n\n");
+
+                if needs_manual_start {
+                    footer.push_str("\nwasm.__wbindgen_start();\n");
+                }
+            }
+
+            // workerd compiles Wasm files imported by the worker to a
+            // `WebAssembly.Module`, while Bun can read them with `Bun.file`.
+            OutputMode::Workerd | OutputMode::Bun => {
+                let (js_imports, wasm_import_object) = self.generate_deno_imports();
+                if let OutputMode::Workerd = self.config.mode {
+                    imports.push_str(&format!(
+                        "import wasmModule from \"./{module_name}_bg.wasm\";\n"
+                    ));
+                }
+                imports.push_str(&js_imports);
+                footer.push_str(&wasm_import_object);
+
+                if let OutputMode::Workerd = self.config.mode {
+                    footer.push_str(&self.generate_wasm_module_instantiation());
+                } else {
+                    footer.push_str(&self.generate_bun_wasm_loading(module_name));
+                }
+
+                footer.push_str("\n\n");
+
+                if needs_manual_start {
+                    footer.push_str("\nwasm.__wbindgen_start();\n");
+                }
+            }
+
             // With Bundlers we can simply import the Wasm file as if it were an ES module
             // and let the bundler/runtime take care of it.
             // With Node we manually read the Wasm file from the filesystem and instantiate it.
@@ -573,7 +692,7 @@ __wbg_set_wasm(wasm);"
                         );
 
                         let start = start.get_or_insert_with(String::new);
-                        start.push_str(&self.generate_node_imports());
+                        start.push_str(&self.generate_node_imports(module_name));
                         start.push_str(&self.generate_node_wasm_loading(Path::new(&format!(
                             "./{}_bg.wasm",
                             module_name
@@ -652,6 +771,23 @@ __wbg_set_wasm(wasm);"
             js = js.replace("\n\n\n", "\n\n");
         }
 
+        if self.config.source_map {
+            let file = if self.config.mode.esm_integration() {
+                format!("{}_bg.js", module_name)
+            } else {
+                format!("{}.js", module_name)
+            };
+            let (stripped, map) = self.source_locations.strip_markers(&js, &file);
+            js = stripped;
+            self.js_source_map = Some(map);
+        }
+        if self.config.declaration_map {
+            let file = format!("{}.d.ts", module_name);
+            let (stripped, map) = self.source_locations.strip_markers(&ts, &file);
+            ts = stripped;
+            self.declaration_map = Some(map);
+        }
+
         Ok((js, ts, start))
     }
 
@@ -698,7 +834,10 @@ __wbg_set_wasm(wasm);"
             OutputMode::Bundler { .. }
             | OutputMode::Node { module: true }
             | OutputMode::Web
-            | OutputMode::Deno => {
+            | OutputMode::Deno
+            | OutputMode::Module
+            | OutputMode::Workerd
+            | OutputMode::Bun => {
                 for (module, items) in crate::sorted_iter(&self.js_imports) {
                     imports.push_str("import { ");
                     for (i, (item, rename)) in items.iter().enumerate() {
@@ -724,6 +863,7 @@ __wbg_set_wasm(wasm);"
         &self,
         has_memory: bool,
         has_module_or_path_optional: bool,
+        has_module_optional: bool,
     ) -> Result<String, Error> {
         let output = crate::wasm2es6js::interface(self.module)?;
 
@@ -741,6 +881,7 @@ __wbg_set_wasm(wasm);"
             ""
         };
         let arg_optional = if has_module_or_path_optional { "?" } else { "" };
+        let sync_arg_optional = if has_module_optional { "?" } else { "" };
         // With TypeScript 3.8.3, I'm seeing that any "export"s at the root level cause TypeScript to ignore all "declare" statements.
         // So using "declare" everywhere for at least the NoModules option.
         // Also in (at least) the NoModules, the `init()` method is renamed to `wasm_bindgen()`.
@@ -765,7 +906,7 @@ __wbg_set_wasm(wasm);"
                 *\n\
                 * @returns {{InitOutput}}\n\
                 */\n\
-                export function initSync(module: {{ module: SyncInitInput{memory_param}{stack_size} }} | SyncInitInput{memory_param}): InitOutput;\n\n\
+                export function initSync(module{sync_arg_optional}: {{ module: SyncInitInput{memory_param}{stack_size} }} | SyncInitInput{memory_param}): InitOutput;\n\n\
                 ",
                 memory_doc = memory_doc,
                 memory_param = memory_param
@@ -809,27 +950,28 @@ __wbg_set_wasm(wasm);"
         let mut init_memory_arg = "";
         let mut init_memory = String::new();
         let mut has_memory = false;
-        if let Some(mem) = self.module.memories.iter().next() {
+        if let Ok(mem) = wasm_bindgen_wasm_conventions::get_memory(self.module) {
+            let mem = self.module.memories.get(mem);
             if let Some(id) = mem.import {
                 self.module.imports.get_mut(id).module = module_name.to_string();
                 init_memory = format!(
                     "imports.{}.memory = memory || new WebAssembly.Memory({{",
                     module_name
                 );
-                init_memory.push_str(&format!("initial:{}", mem.initial));
-                if let Some(max) = mem.maximum {
-                    init_memory.push_str(&format!(",maximum:{}", max));
-                }
-                if mem.shared {
-                    init_memory.push_str(",shared:true");
-                }
+                init_memory.push_str(&memory_descriptor(mem));
                 init_memory.push_str("});");
                 init_memory_arg = ", memory";
                 has_memory = true;
             }
         }
 
-        let default_module_path = if !self.config.omit_default_module_path {
+        let default_module_path = if self.config.inline_wasm.is_some() {
+            "\
+            if (typeof module_or_path === 'undefined') {
+                module_or_path = __wbg_inline_wasm();
+            }"
+            .to_string()
+        } else if !self.config.omit_default_module_path {
             match self.config.mode {
                 OutputMode::Web => format!(
                     "\
@@ -849,9 +991,23 @@ __wbg_set_wasm(wasm);"
             String::from("")
         };
 
+        // Only base64-encoded Wasm can be instantiated synchronously, as
+        // decompression is asynchronous.
+        let sync_inline_wasm = matches!(self.config.inline_wasm, Some(InlineWasm::Base64));
+        let default_sync_module = if sync_inline_wasm {
+            "\
+            if (typeof module === 'undefined') {
+                module = __wbg_inline_wasm();
+            }"
+        } else {
+            ""
+        };
+
         let ts = self.ts_for_init_fn(
             has_memory,
-            !self.config.omit_default_module_path && !default_module_path.is_empty(),
+            self.config.inline_wasm.is_some()
+                || (!self.config.omit_default_module_path && !default_module_path.is_empty()),
+            sync_inline_wasm,
         )?;
 
         // Initialize the `imports` object for all import definitions that we're
@@ -874,6 +1030,26 @@ __wbg_set_wasm(wasm);"
             imports_init.push_str(";\n");
         }
 
+        // Engines supporting the JS string builtins provide them when compiling
+        // the module, everywhere else they're polyfilled.
+        let compile_options = if self.config.js_string_builtins {
+            ", { builtins: ['js-string'] }"
+        } else {
+            ""
+        };
+        let uses_string_builtins = self
+            .module
+            .imports
+            .iter()
+            .any(|i| i.module == crate::string_builtins::MODULE);
+        if uses_string_builtins {
+            imports_init.push_str(&format!(
+                "imports['{}'] = {};\n",
+                crate::string_builtins::MODULE,
+                crate::string_builtins::POLYFILL,
+            ));
+        }
+
         let extra_modules = self
             .module
             .imports
@@ -884,6 +1060,7 @@ __wbg_set_wasm(wasm);"
                 // consider this a candidate for importing from extra modules.
                 !(matches!(i.kind, walrus::ImportKind::Memory(_)))
             })
+            .filter(|i| i.module != crate::string_builtins::MODULE)
             .map(|i| &i.module)
             .collect::<BTreeSet<_>>();
         for (i, extra) in extra_modules.iter().enumerate() {
@@ -898,6 +1075,83 @@ __wbg_set_wasm(wasm);"
             imports_init.push_str(&format!("imports['{}'] = __wbg_star{};\n", extra, i));
         }
 
+        // The final Wasm module is only known once all transformations are
+        // done, so a placeholder is embedded for now, see
+        // `INLINE_WASM_PLACEHOLDER`.
+        let inline_wasm = match self.config.inline_wasm {
+            Some(ref mode) => {
+                let bytes = crate::wasm2es6js::base64_decoder(INLINE_WASM_PLACEHOLDER);
+                let module = match mode {
+                    InlineWasm::Base64 => "bytes",
+                    InlineWasm::Compressed => {
+                        "new Response(new Blob([bytes]).stream().pipeThrough(new DecompressionStream('gzip')), { headers: { 'Content-Type': 'application/wasm' } })"
+                    }
+                };
+                format!(
+                    "\
+                    function __wbg_inline_wasm() {{
+                        {bytes}
+                        return {module};
+                    }}
+                    "
+                )
+            }
+            None => String::new(),
+        };
+
+        let load_split = if self.aux.split_exports.is_empty() {
+            String::new()
+        } else {
+            let (path, check_path) = match self.config.mode {
+                OutputMode::Web => (
+                    format!(
+                        "new URL('{stem}_bg_' + name + '.wasm', import.meta.url)",
+                        stem = self.config.stem()?
+                    ),
+                    "",
+                ),
+                _ => (
+                    "script_src.replace(/\\.js$/, '_bg_' + name + '.wasm')".to_string(),
+                    "\
+                    if (script_src === undefined) {
+                        return Promise.reject(new Error('With `--target no-modules`, split exports can only be loaded from a web page\\'s main thread.'));
+                    }
+                    ",
+                ),
+            };
+            format!(
+                "\
+                const __wbg_splits = {{}};
+
+                function __wbg_load_split(name) {{
+                    if (wasm === undefined) {{
+                        return Promise.reject(new Error('the Wasm module must be initialized before calling its functions'));
+                    }}
+                    {check_path}\
+                    if (!(name in __wbg_splits)) {{
+                        __wbg_splits[name] = (async () => {{
+                            const imports = __wbg_get_imports();
+                            imports.{main} = wasm;
+                            const {{ instance }} = await __wbg_load(await fetch({path}), imports);
+                            Object.assign(wasm, instance.exports);
+                        }})();
+                    }}
+                    return __wbg_splits[name];
+                }}
+                ",
+                main = wasm_bindgen_split_xform::MAIN_MODULE,
+            )
+        };
+
+        // The exports of split modules are merged into `wasm` once they are
+        // loaded, which requires a mutable copy of the frozen exports of the
+        // main module. Merging in place keeps every reference to `wasm` valid.
+        let exports = if self.aux.split_exports.is_empty() {
+            "instance.exports"
+        } else {
+            "Object.assign({}, instance.exports)"
+        };
+
         let mut init_memviews = String::new();
         for &(num, ref views) in self.memories.values() {
             for kind in views {
@@ -916,11 +1170,12 @@ __wbg_set_wasm(wasm);"
 
         let js = format!(
             "\
+                {inline_wasm}\
                 async function __wbg_load(module, imports) {{
                     if (typeof Response === 'function' && module instanceof Response) {{
                         if (typeof WebAssembly.instantiateStreaming === 'function') {{
                             try {{
-                                return await WebAssembly.instantiateStreaming(module, imports);
+                                return await WebAssembly.instantiateStreaming(module, imports{compile_options});
 
                             }} catch (e) {{
                                 if (module.headers.get('Content-Type') != 'application/wasm') {{
@@ -937,10 +1192,10 @@ __wbg_set_wasm(wasm);"
                         }}
 
                         const bytes = await module.arrayBuffer();
-                        return await WebAssembly.instantiate(bytes, imports);
+                        return await WebAssembly.instantiate(bytes, imports{compile_options});
 
                     }} else {{
-                        const instance = await WebAssembly.instantiate(module, imports);
+                        const instance = await WebAssembly.instantiate(module, imports{compile_options});
 
                         if (instance instanceof WebAssembly.Instance) {{
                             return {{ instance, module }};
@@ -962,7 +1217,7 @@ __wbg_set_wasm(wasm);"
                 }}
 
                 function __wbg_finalize_init(instance, module{init_stack_size_arg}) {{
-                    wasm = instance.exports;
+                    wasm = {exports};
                     __wbg_init.__wbindgen_wasm_module = module;
                     {init_memviews}
                     {init_stack_size_check}
@@ -982,12 +1237,13 @@ __wbg_set_wasm(wasm);"
                         }}
                     }}
 
+                    {default_sync_module}
                     const imports = __wbg_get_imports();
 
                     __wbg_init_memory(imports{init_memory_arg});
 
                     if (!(module instanceof WebAssembly.Module)) {{
-                        module = new WebAssembly.Module(module);
+                        module = new WebAssembly.Module(module{compile_options});
                     }}
 
                     const instance = new WebAssembly.Instance(module, imports);
@@ -1020,9 +1276,13 @@ __wbg_set_wasm(wasm);"
 
                     return __wbg_finalize_init(instance, module{init_stack_size_arg});
                 }}
+
+                {load_split}\
             ",
+            inline_wasm = inline_wasm,
             init_memory_arg = init_memory_arg,
             default_module_path = default_module_path,
+            default_sync_module = default_sync_module,
             init_memory = init_memory,
             init_memviews = init_memviews,
             start = if needs_manual_start && self.threads_enabled {
@@ -1065,7 +1325,7 @@ __wbg_set_wasm(wasm);"
 
     fn write_class(&mut self, name: &str, class: &ExportedClass) -> Result<(), Error> {
         let mut dst = format!("class {} {{\n", name);
-        let mut ts_dst = format!("export {}", dst);
+        let mut ts_dst = format!("export {}{}", class.ts_source_marker, dst);
 
         if !class.has_constructor {
             // declare the constructor as private to prevent direct instantiation
@@ -1086,13 +1346,14 @@ __wbg_set_wasm(wasm);"
             dst.push_str(&format!(
                 "
                 static __wrap(ptr) {{
-                    ptr = ptr >>> 0;
+                    {coerce}
                     const obj = Object.create({name}.prototype);
                     obj.__wbg_ptr = ptr;
                     {name}Finalization.register(obj, obj.__wbg_ptr, obj);
                     return obj;
                 }}
-                "
+                ",
+                coerce = self.coerce_ptr(),
             ));
         }
 
@@ -1114,8 +1375,13 @@ __wbg_set_wasm(wasm);"
             "
             const {name}Finalization = (typeof FinalizationRegistry === 'undefined')
                 ? {{ register: () => {{}}, unregister: () => {{}} }}
-                : new FinalizationRegistry(ptr => wasm.{}(ptr >>> 0, 1));",
+                : new FinalizationRegistry(ptr => wasm.{}({}, 1));",
             wasm_bindgen_shared::free_function(name),
+            if self.aux.memory64 {
+                "BigInt(ptr)"
+            } else {
+                "ptr >>> 0"
+            },
         ));
 
         // If the class is inspectable, generate `toJSON` and `toString`
@@ -1187,10 +1453,11 @@ __wbg_set_wasm(wasm);"
 
             free() {{
                 const ptr = this.__destroy_into_raw();
-                wasm.{}(ptr, 0);
+                wasm.{}({}, 0);
             }}
             ",
             wasm_bindgen_shared::free_function(name),
+            self.ptr_to_wasm("ptr"),
         ));
         ts_dst.push_str("  free(): void;\n");
         if self.config.symbol_dispose {
@@ -1249,6 +1516,7 @@ __wbg_set_wasm(wasm);"
             let write_getter = |ts_dst: &mut String, getter: &FieldAccessor| {
                 write_docs(ts_dst, &getter.docs);
                 ts_dst.push_str("  ");
+                ts_dst.push_str(&getter.source_marker);
                 ts_dst.push_str(is_static);
                 ts_dst.push_str("get ");
                 ts_dst.push_str(name);
@@ -1259,6 +1527,7 @@ __wbg_set_wasm(wasm);"
             let write_setter = |ts_dst: &mut String, setter: &FieldAccessor| {
                 write_docs(ts_dst, &setter.docs);
                 ts_dst.push_str("  ");
+                ts_dst.push_str(&setter.source_marker);
                 ts_dst.push_str(is_static);
                 ts_dst.push_str("set ");
                 ts_dst.push_str(name);
@@ -1276,6 +1545,7 @@ __wbg_set_wasm(wasm);"
                     // readonly property
                     write_docs(ts_dst, &getter.docs);
                     ts_dst.push_str("  ");
+                    ts_dst.push_str(&getter.source_marker);
                     ts_dst.push_str(is_static);
                     ts_dst.push_str("readonly ");
                     ts_dst.push_str(name);
@@ -1324,6 +1594,7 @@ __wbg_set_wasm(wasm);"
                         };
                         write_docs(ts_dst, docs);
                         ts_dst.push_str("  ");
+                        ts_dst.push_str(&getter.source_marker);
                         ts_dst.push_str(is_static);
                         ts_dst.push_str(name);
                         ts_dst.push_str(if setter.is_optional { "?: " } else { ": " });
@@ -1538,14 +1809,14 @@ __wbg_set_wasm(wasm);"
             "\
                 if (realloc === undefined) {{
                     const buf = cachedTextEncoder.encode(arg);
-                    const ptr = malloc(buf.length, 1) >>> 0;
+                    const ptr = {malloc_buf};
                     {mem}().subarray(ptr, ptr + buf.length).set(buf);
                     WASM_VECTOR_LEN = buf.length;
                     return ptr;
                 }}
 
                 let len = arg.length;
-                let ptr = malloc(len, 1) >>> 0;
+                let ptr = {malloc_len};
 
                 const mem = {mem}();
 
@@ -1558,6 +1829,8 @@ __wbg_set_wasm(wasm);"
                 }}
             ",
             mem = mem,
+            malloc_buf = self.alloc_call("malloc", &["buf.length", "1"]),
+            malloc_len = self.alloc_call("malloc", &["len", "1"]),
         );
 
         self.global(&format!(
@@ -1568,12 +1841,12 @@ __wbg_set_wasm(wasm);"
                     if (offset !== 0) {{
                         arg = arg.slice(offset);
                     }}
-                    ptr = realloc(ptr, len, len = offset + arg.length * 3, 1) >>> 0;
+                    ptr = {realloc_grow};
                     const view = {mem}().subarray(ptr + offset, ptr + len);
                     const ret = encodeString(arg, view);
                     {debug_end}
                     offset += ret.written;
-                    ptr = realloc(ptr, len, offset, 1) >>> 0;
+                    ptr = {realloc_shrink};
                 }}
 
                 WASM_VECTOR_LEN = offset;
@@ -1583,6 +1856,11 @@ __wbg_set_wasm(wasm);"
             debug = debug,
             ascii = encode_as_ascii,
             mem = mem,
+            realloc_grow = self.alloc_call(
+                "realloc",
+                &["ptr", "len", "len = offset + arg.length * 3", "1"]
+            ),
+            realloc_shrink = self.alloc_call("realloc", &["ptr", "len", "offset", "1"]),
             debug_end = if self.config.debug {
                 "if (ret.read !== arg.length) throw new Error('failed to pass whole string');"
             } else {
@@ -1633,6 +1911,7 @@ __wbg_set_wasm(wasm);"
             return Ok(ret);
         }
         self.expose_wasm_vector_len();
+        let malloc = self.alloc_call("malloc", &["array.length * 4", "4"]);
         match (self.aux.externref_table, self.aux.externref_alloc) {
             (Some(table), Some(alloc)) => {
                 // TODO: using `addToExternrefTable` goes back and forth between wasm
@@ -1641,7 +1920,7 @@ __wbg_set_wasm(wasm);"
                 self.global(&format!(
                     "
                         function {ret}(array, malloc) {{
-                            const ptr = malloc(array.length * 4, 4) >>> 0;
+                            const ptr = {malloc};
                             for (let i = 0; i < array.length; i++) {{
                                 const add = {add}(array[i]);
                                 {mem}().setUint32(ptr + 4 * i, add, true);
@@ -1657,7 +1936,7 @@ __wbg_set_wasm(wasm);"
                 self.global(&format!(
                     "
                         function {ret}(array, malloc) {{
-                            const ptr = malloc(array.length * 4, 4) >>> 0;
+                            const ptr = {malloc};
                             const mem = {mem}();
                             for (let i = 0; i < array.length; i++) {{
                                 mem.setUint32(ptr + 4 * i, addHeapObject(array[i]), true);
@@ -1689,13 +1968,17 @@ __wbg_set_wasm(wasm);"
         self.global(&format!(
             "
             function {}(arg, malloc) {{
-                const ptr = malloc(arg.length * {size}, {size}) >>> 0;
+                const ptr = {};
                 {}().set(arg, ptr / {size});
                 WASM_VECTOR_LEN = arg.length;
                 return ptr;
             }}
             ",
             ret,
+            self.alloc_call(
+                "malloc",
+                &[&format!("arg.length * {size}"), &size.to_string()]
+            ),
             view,
             size = size
         ));
@@ -1710,6 +1993,40 @@ __wbg_set_wasm(wasm);"
         Ok(())
     }
 
+    /// Exports the `PanicError` class which Rust panics are thrown to JS as when
+    /// panics unwind.
+    fn expose_panic_error(&mut self) -> Result<(), Error> {
+        if !self.should_write_global("panic_error") {
+            return Ok(());
+        }
+        self.export(
+            "PanicError",
+            ExportJs::Class(
+                "\
+                class PanicError extends Error {
+                    constructor(message, location) {
+                        super(location === undefined ? message : `${message} at ${location}`);
+                        this.name = 'PanicError';
+                        this.location = location;
+                    }
+                }",
+            ),
+            Some("/**\n * An error thrown by a Rust panic in an exported function.\n */\n"),
+        )?;
+        self.typescript.push_str(
+            "/**\n \
+             * An error thrown by a Rust panic in an exported function.\n \
+             */\n\
+             export class PanicError extends Error {\n  \
+               /**\n   \
+                * The `file:line:column` location of the panic, if known.\n   \
+                */\n  \
+               location: string | undefined;\n\
+             }\n",
+        );
+        Ok(())
+    }
+
     fn expose_text_encoder(&mut self) -> Result<(), Error> {
         if !self.should_write_global("text_encoder") {
             return Ok(());
@@ -1770,6 +2087,9 @@ __wbg_set_wasm(wasm);"
             }
             OutputMode::Deno
             | OutputMode::Web
+            | OutputMode::Module
+            | OutputMode::Workerd
+            | OutputMode::Bun
             | OutputMode::NoModules { .. }
             | OutputMode::Bundler { browser_only: true } => {
                 self.global(&format!("const cached{0} = (typeof {0} !== 'undefined' ? new {0}{1} : {{ {2}: () => {{ throw Error('{0} not available') }} }} );", s, args, op))
@@ -1784,6 +2104,9 @@ __wbg_set_wasm(wasm);"
                 } => self.global(init),
                 OutputMode::Deno
                 | OutputMode::Web
+                | OutputMode::Module
+                | OutputMode::Workerd
+                | OutputMode::Bun
                 | OutputMode::NoModules { .. }
                 | OutputMode::Bundler { browser_only: true } => self.global(&format!(
                     "if (typeof {} !== 'undefined') {{ {} }};",
@@ -1821,11 +2144,14 @@ __wbg_set_wasm(wasm);"
         self.global(&format!(
             "
             function {}(ptr, len) {{
-                ptr = ptr >>> 0;
+                {}
                 return cachedTextDecoder.decode({}().{}(ptr, ptr + len));
             }}
             ",
-            ret, mem, method
+            ret,
+            self.coerce_ptr(),
+            mem,
+            method
         ));
         Ok(ret)
     }
@@ -1892,17 +2218,23 @@ __wbg_set_wasm(wasm);"
                 self.global(&format!(
                     "
                     function {}(ptr, len) {{
-                        ptr = ptr >>> 0;
+                        {}
                         const mem = {}();
                         const result = [];
                         for (let i = ptr; i < ptr + 4 * len; i += 4) {{
                             result.push(wasm.{}.get(mem.getUint32(i, true)));
                         }}
-                        wasm.{}(ptr, len);
+                        wasm.{}({}, {});
                         return result;
                     }}
                     ",
-                    ret, mem, table, drop,
+                    ret,
+                    self.coerce_ptr(),
+                    mem,
+                    table,
+                    drop,
+                    self.ptr_to_wasm("ptr"),
+                    self.ptr_to_wasm("len"),
                 ));
             }
             _ => {
@@ -1910,7 +2242,7 @@ __wbg_set_wasm(wasm);"
                 self.global(&format!(
                     "
                     function {}(ptr, len) {{
-                        ptr = ptr >>> 0;
+                        {}
                         const mem = {}();
                         const result = [];
                         for (let i = ptr; i < ptr + 4 * len; i += 4) {{
@@ -1919,7 +2251,9 @@ __wbg_set_wasm(wasm);"
                         return result;
                     }}
                     ",
-                    ret, mem,
+                    ret,
+                    self.coerce_ptr(),
+                    mem,
                 ));
             }
         }
@@ -1992,10 +2326,11 @@ __wbg_set_wasm(wasm);"
         self.global(&format!(
             "
             function {name}(ptr, len) {{
-                ptr = ptr >>> 0;
+                {coerce}
                 return {mem}().subarray(ptr / {size}, ptr / {size} + len);
             }}
             ",
+            coerce = self.coerce_ptr(),
             name = ret,
             mem = view,
             size = size,
@@ -2262,6 +2597,199 @@ __wbg_set_wasm(wasm);"
         Ok(())
     }
 
+    /// Like `handleError`, but for suspending imports which return a promise
+    /// and may reject after they've been called.
+    fn expose_handle_error_async(&mut self) -> Result<(), Error> {
+        if !self.should_write_global("handle_error_async") {
+            return Ok(());
+        }
+        self.expose_handle_error()?;
+        self.global(
+            "\
+            async function handleErrorAsync(f, args) {
+                try {
+                    return await f.apply(this, args);
+                } catch (e) {
+                    handleError(() => { throw e; }, []);
+                }
+            }
+            ",
+        );
+        Ok(())
+    }
+
+    fn expose_jspi(&mut self) {
+        if !self.should_write_global("jspi") {
+            return;
+        }
+        self.global(
+            "\
+            function checkJspi() {
+                if (typeof WebAssembly.Suspending !== 'function' || typeof WebAssembly.promising !== 'function') {
+                    throw new Error('JS Promise Integration (`WebAssembly.Suspending` and `WebAssembly.promising`) is not supported by this engine');
+                }
+            }
+
+            function makeSuspending(f) {
+                checkJspi();
+                return new WebAssembly.Suspending(f);
+            }
+
+            const promisingFunctions = new WeakMap();
+
+            function makePromising(f) {
+                let promising = promisingFunctions.get(f);
+                if (promising === undefined) {
+                    checkJspi();
+                    promising = WebAssembly.promising(f);
+                    promisingFunctions.set(f, promising);
+                }
+                return promising;
+            }
+
+            let promisingInFlight = false;
+
+            function enterPromising() {
+                if (promisingInFlight) {
+                    throw new Error('a `promising` function was called while another one is still suspended, which is not supported as they share the Rust stack');
+                }
+                promisingInFlight = true;
+            }
+            ",
+        );
+    }
+
+    fn expose_spawn_thread(&mut self) -> Result<(), Error> {
+        if !self.should_write_global("spawn_thread") {
+            return Ok(());
+        }
+        if !self.threads_enabled {
+            bail!("`wasm_bindgen::thread::spawn` requires a module with a shared memory");
+        }
+        let memory = wasm_bindgen_wasm_conventions::get_memory(self.module)?;
+        let memory = format!("wasm.{}", self.export_name_of(memory));
+
+        // Runs the closure passed to `spawn` in the new worker once its
+        // instance is created, and frees the stack and thread-local storage
+        // of the thread afterwards, even if the closure fails, in which case
+        // the thread waiting to join it is woken up first.
+        let run = "\
+            try {
+                wasm.__wbindgen_thread_entry(work);
+            } catch (e) {
+                wasm.__wbindgen_thread_failed(state);
+                throw e;
+            } finally {
+                wasm.__wbindgen_thread_destroy();
+            }";
+
+        let spawn = match &self.config.mode {
+            // The bootstrap script of the worker is the same for all threads,
+            // so only one object URL is created for it.
+            OutputMode::Web => format!(
+                "\
+                let threadBootstrapUrl;
+
+                function startThread(work, state) {{
+                    if (threadBootstrapUrl === undefined) {{
+                        const src = `import {{ initSync }} from ${{JSON.stringify(import.meta.url)}};
+                        self.onmessage = ({{ data: [module, memory, work, state] }}) => {{
+                            const wasm = initSync({{ module, memory }});
+                            try {{
+                                {run}
+                            }} finally {{
+                                close();
+                            }}
+                        }};`;
+                        threadBootstrapUrl = URL.createObjectURL(new Blob([src], {{ type: 'text/javascript' }}));
+                    }}
+                    const worker = new Worker(threadBootstrapUrl, {{ type: 'module' }});
+                    worker.postMessage([__wbg_init.__wbindgen_wasm_module, {memory}, work, state]);
+                }}
+                "
+            ),
+            OutputMode::NoModules { global } => format!(
+                "\
+                let threadBootstrapUrl;
+
+                function startThread(work, state) {{
+                    if (threadBootstrapUrl === undefined) {{
+                        if (script_src === undefined) {{
+                            throw new Error('With `--target no-modules`, threads can only be spawned from a web page\\'s main thread.');
+                        }}
+                        const src = `importScripts(${{JSON.stringify(script_src)}});
+                        self.onmessage = ({{ data: [module, memory, work, state] }}) => {{
+                            const wasm = {global}.initSync({{ module, memory }});
+                            try {{
+                                {run}
+                            }} finally {{
+                                close();
+                            }}
+                        }};`;
+                        threadBootstrapUrl = URL.createObjectURL(new Blob([src], {{ type: 'text/javascript' }}));
+                    }}
+                    const worker = new Worker(threadBootstrapUrl);
+                    worker.postMessage([__wbg_init.__wbindgen_wasm_module, {memory}, work, state]);
+                }}
+                "
+            ),
+            // Node.js workers load the JS glue again, which picks up the
+            // memory from `workerData` instead of creating a new one.
+            OutputMode::Node { module } => {
+                let module = *module;
+                let worker = self.import_name(&JsImport {
+                    name: JsImportName::Module {
+                        module: "worker_threads".to_string(),
+                        name: "Worker".to_string(),
+                    },
+                    fields: Vec::new(),
+                })?;
+                let (glue, load) = if module {
+                    (
+                        format!("new URL('./{}.js', import.meta.url).href", self.config.stem()?),
+                        format!("import(glue).then(({{ __wasm: wasm }}) => {{\n{run}\n}});"),
+                    )
+                } else {
+                    (
+                        "__filename".to_string(),
+                        format!("const wasm = require(glue).__wasm;\n{run}"),
+                    )
+                };
+                format!(
+                    "\
+                    function startThread(work, state) {{
+                        const src = `const {{ workerData: {{ glue, work, state }} }} = require('worker_threads');
+                        {load}`;
+                        const workerData = {{ __wbindgen_memory: {memory}, glue: {glue}, work, state }};
+                        new {worker}(src, {{ eval: true, workerData }});
+                    }}
+                    "
+                )
+            }
+            _ => bail!(
+                "`wasm_bindgen::thread::spawn` is currently only supported with \
+                 `--target web`, `--target no-modules`, `--target nodejs` and \
+                 `--target experimental-nodejs-module`"
+            ),
+        };
+        self.global(&spawn);
+
+        // Errors creating the worker are returned, so that `spawn` can free
+        // the closure before panicking.
+        self.global(
+            "\
+            function spawnThread(work, state) {
+                try {
+                    startThread(work, state);
+                } catch (e) {
+                    return e;
+                }
+            }
+            ",
+        );
+        Ok(())
+    }
+
     fn expose_log_error(&mut self) {
         if !self.should_write_global("log_error") {
             return;
@@ -2414,13 +2942,13 @@ __wbg_set_wasm(wasm);"
             "
             function makeMutClosure(arg0, arg1, dtor, f) {{
                 const state = {{ a: arg0, b: arg1, cnt: 1, dtor }};
-                const real = (...args) => {{
+                const real = (...args) => {{{scope_check}
                     // First up with a closure we increment the internal reference
                     // count. This ensures that the Rust closure environment won't
                     // be deallocated while we're invoking it.
                     state.cnt++;
                     const a = state.a;
-                    state.a = 0;
+                    state.a = {null};
                     try {{
                         return f(a, state.b, ...args);
                     }} finally {{
@@ -2437,6 +2965,8 @@ __wbg_set_wasm(wasm);"
                 return real;
             }}
             ",
+            null = self.ptr_to_wasm("0"),
+            scope_check = self.scoped_closure_check(),
         ));
 
         Ok(())
@@ -2460,7 +2990,7 @@ __wbg_set_wasm(wasm);"
             "
             function makeClosure(arg0, arg1, dtor, f) {{
                 const state = {{ a: arg0, b: arg1, cnt: 1, dtor }};
-                const real = (...args) => {{
+                const real = (...args) => {{{scope_check}
                     // First up with a closure we increment the internal reference
                     // count. This ensures that the Rust closure environment won't
                     // be deallocated while we're invoking it.
@@ -2470,7 +3000,7 @@ __wbg_set_wasm(wasm);"
                     }} finally {{
                         if (--state.cnt === 0) {{
                             wasm.{table}.get(state.dtor)(state.a, state.b);
-                            state.a = 0;
+                            state.a = {null};
                             CLOSURE_DTORS.unregister(state);
                         }}
                     }}
@@ -2480,11 +3010,30 @@ __wbg_set_wasm(wasm);"
                 return real;
             }}
             ",
+            null = self.ptr_to_wasm("0"),
+            scope_check = self.scoped_closure_check(),
         ));
 
         Ok(())
     }
 
+    /// Returns the check throwing a descriptive error when a closure created
+    /// with `Closure::scoped` is called after its scope ended, if the module
+    /// creates any.
+    fn scoped_closure_check(&self) -> &'static str {
+        let scoped =
+            self.aux.import_map.values().any(|import| {
+                matches!(import, AuxImport::Intrinsic(Intrinsic::CallbackDropScoped))
+            });
+        if !scoped {
+            return "";
+        }
+        "
+                    if (state.scopeEnded) {
+                        throw new Error('closure invoked after the end of its `Closure::scoped` scope');
+                    }"
+    }
+
     fn expose_closure_finalization(&mut self) -> Result<(), Error> {
         if !self.should_write_global("closure_finalization") {
             return Ok(());
@@ -2502,6 +3051,19 @@ __wbg_set_wasm(wasm);"
 
         Ok(())
     }
+    fn expose_weak_ref_finalization(&mut self) {
+        if !self.should_write_global("weak_ref_finalization") {
+            return;
+        }
+        self.global(
+            "
+            const WEAK_REF_FINALIZATION = (typeof FinalizationRegistry === 'undefined')
+                ? { register: () => {}, unregister: () => {} }
+                : new FinalizationRegistry(finalizer => finalizer());
+            ",
+        );
+    }
+
     fn global(&mut self, s: &str) {
         let s = s.trim();
 
@@ -2728,6 +3290,18 @@ __wbg_set_wasm(wasm);"
             self.expose_symbol_dispose()?;
         }
 
+        // Modules catching and throwing JS exceptions natively import the tag
+        // of JS exceptions.
+        if let Some(import) = self
+            .module
+            .imports
+            .iter()
+            .find(|i| i.module == PLACEHOLDER_MODULE && i.name == crate::exceptions::JS_TAG)
+        {
+            self.wasm_import_definitions
+                .insert(import.id(), "WebAssembly.JSTag".to_string());
+        }
+
         for (id, adapter, kind) in iter_adapeter(self.aux, self.wit, self.module) {
             let instrs = match &adapter.kind {
                 AdapterKind::Import { .. } => continue,
@@ -2813,9 +3387,10 @@ __wbg_set_wasm(wasm);"
         instrs: &[InstructionData],
         kind: ContextAdapterKind,
     ) -> Result<(), Error> {
-        let catch = self.aux.imports_with_catch.contains(&id);
+        let catch = !self.aux.wasm_catches_exceptions && self.aux.imports_with_catch.contains(&id);
+        let suspending = self.aux.imports_with_suspending.contains(&id);
         if let ContextAdapterKind::Import(core) = kind {
-            if !catch && self.attempt_direct_import(core, instrs)? {
+            if !catch && !suspending && self.attempt_direct_import(core, instrs)? {
                 return Ok(());
             }
         }
@@ -2837,7 +3412,11 @@ __wbg_set_wasm(wasm);"
         match kind {
             ContextAdapterKind::Export(export) => {
                 args = &export.args;
-                asyncness = export.asyncness;
+                // Calling a split function first loads its module, and a
+                // promising function may suspend, so both return a promise.
+                asyncness = export.asyncness || export.split.is_some() || export.promising;
+                builder.promising(export.promising);
+                builder.split(export.split.as_deref());
                 variadic = export.variadic;
                 generate_jsdoc = export.generate_jsdoc;
                 ret_ty_override = &export.fn_ret_ty_override;
@@ -2852,7 +3431,7 @@ __wbg_set_wasm(wasm);"
                     },
                 }
             }
-            ContextAdapterKind::Import(_) => {}
+            ContextAdapterKind::Import(_) => builder.jspi(suspending),
             ContextAdapterKind::Adapter => {}
         }
 
@@ -2894,6 +3473,14 @@ __wbg_set_wasm(wasm);"
 
         self.typescript_refs.extend(ts_refs);
 
+        // Mark the start of the definitions with their location in Rust code so
+        // they can be mapped back to it.
+        let aux = self.aux;
+        let location = aux.source_locations.get(&id).map(|s| s.as_str());
+        let code = self.js_source_marker(location) + &code;
+        let ts_marker = self.ts_source_marker(location);
+        let ts_sig = ts_marker.clone() + &ts_sig;
+
         // Once we've got all the JS then put it in the right location depending
         // on what's being exported.
         match kind {
@@ -2969,6 +3556,7 @@ __wbg_set_wasm(wasm);"
                                         ty: ts_ret_ty.expect("missing return type for getter"),
                                         docs: ts_docs.clone(),
                                         is_optional: false,
+                                        source_marker: ts_marker.clone(),
                                     };
 
                                     exported.push_accessor_ts(location, accessor, false);
@@ -2989,6 +3577,7 @@ __wbg_set_wasm(wasm);"
                                         ty: ts_arg_tys[0].clone(),
                                         docs: ts_docs.clone(),
                                         is_optional: might_be_optional_field,
+                                        source_marker: ts_marker.clone(),
                                     };
 
                                     exported.push_accessor_ts(location, accessor, true);
@@ -3002,7 +3591,13 @@ __wbg_set_wasm(wasm);"
                 }
             }
             ContextAdapterKind::Import(core) => {
-                let code = if catch {
+                let code = if catch && suspending {
+                    self.expose_handle_error_async()?;
+                    format!(
+                        "function() {{ return handleErrorAsync(function {}, arguments) }}",
+                        code
+                    )
+                } else if catch {
                     format!(
                         "function() {{ return handleError(function {}, arguments) }}",
                         code
@@ -3015,6 +3610,12 @@ __wbg_set_wasm(wasm);"
                 } else {
                     format!("function{}", code)
                 };
+                let code = if suspending {
+                    self.expose_jspi();
+                    format!("makeSuspending({})", code)
+                } else {
+                    code
+                };
 
                 self.wasm_import_definitions.insert(core, code);
             }
@@ -3504,7 +4105,11 @@ __wbg_set_wasm(wasm);"
                 assert!(!variadic);
                 assert_eq!(args.len(), 1);
                 self.require_class_wrap(class);
-                Ok(format!("{}.__wrap({})", class, args[0]))
+                Ok(format!(
+                    "{}.__wrap({})",
+                    class,
+                    self.ptr_from_wasm(&args[0])
+                ))
             }
 
             AuxImport::Intrinsic(intrinsic) => {
@@ -3522,6 +4127,9 @@ __wbg_set_wasm(wasm);"
                         OutputMode::Web
                         | OutputMode::Bundler { .. }
                         | OutputMode::Deno
+                        | OutputMode::Module
+                        | OutputMode::Workerd
+                        | OutputMode::Bun
                         | OutputMode::Node { module: true } => "import.meta.url",
                         OutputMode::Node { module: false } => {
                             "require('url').pathToFileURL(__filename)"
@@ -3563,7 +4171,7 @@ __wbg_set_wasm(wasm);"
                 assert!(!variadic);
                 assert_eq!(args.len(), 1);
                 self.require_class_unwrap(class);
-                Ok(format!("{}.__unwrap({})", class, args[0]))
+                Ok(self.ptr_to_wasm(&format!("{}.__unwrap({})", class, args[0])))
             }
         }
     }
@@ -3780,6 +4388,17 @@ __wbg_set_wasm(wasm);"
                 args[0].clone()
             }
 
+            Intrinsic::CallbackDropScoped => {
+                assert_eq!(args.len(), 1);
+                prelude.push_str(&format!("const obj = {}.original;\n", args[0]));
+                prelude.push_str("obj.scopeEnded = true;\n");
+                prelude.push_str("if (obj.cnt-- == 1) {\n");
+                prelude.push_str("obj.a = 0;\n");
+                prelude.push_str("return true;\n");
+                prelude.push_str("}\n");
+                "false".to_string()
+            }
+
             Intrinsic::CallbackDrop => {
                 assert_eq!(args.len(), 1);
                 prelude.push_str(&format!("const obj = {}.original;\n", args[0]));
@@ -3837,6 +4456,36 @@ __wbg_set_wasm(wasm);"
                 "typeof(obj) === 'string' ? obj : undefined".to_string()
             }
 
+            Intrinsic::StringLength => {
+                assert_eq!(args.len(), 1);
+                format!("{}.length", args[0])
+            }
+
+            Intrinsic::StringCharCodeAt => {
+                assert_eq!(args.len(), 2);
+                format!("{}.charCodeAt({})", args[0], args[1])
+            }
+
+            Intrinsic::StringConcat => {
+                assert_eq!(args.len(), 2);
+                format!("{}.concat({})", args[0], args[1])
+            }
+
+            Intrinsic::StringSubstring => {
+                assert_eq!(args.len(), 3);
+                format!("{}.substring({}, {})", args[0], args[1], args[2])
+            }
+
+            Intrinsic::StringEquals => {
+                assert_eq!(args.len(), 2);
+                format!("{} === {}", args[0], args[1])
+            }
+
+            Intrinsic::StringFromCharCodeArray => {
+                assert_eq!(args.len(), 1);
+                format!("String.fromCharCode.apply(null, {})", args[0])
+            }
+
             Intrinsic::BooleanGet => {
                 assert_eq!(args.len(), 1);
                 prelude.push_str(&format!("const v = {};\n", args[0]));
@@ -3864,6 +4513,12 @@ __wbg_set_wasm(wasm);"
                 format!("new Error({})", args[0])
             }
 
+            Intrinsic::PanicErrorNew => {
+                assert_eq!(args.len(), 2);
+                self.expose_panic_error()?;
+                format!("new PanicError({}, {} || undefined)", args[0], args[1])
+            }
+
             Intrinsic::Module => {
                 assert_eq!(args.len(), 0);
 
@@ -3871,10 +4526,14 @@ __wbg_set_wasm(wasm);"
                     OutputMode::Web | OutputMode::NoModules { .. } => {
                         "__wbg_init.__wbindgen_wasm_module"
                     }
-                    OutputMode::Node { .. } => "wasmModule",
+                    OutputMode::Node { .. }
+                    | OutputMode::Module
+                    | OutputMode::Workerd
+                    | OutputMode::Bun => "wasmModule",
                     _ => bail!(
                         "`wasm_bindgen::module` is currently only supported with \
-                         `--target no-modules`, `--target web` and `--target nodejs`"
+                         `--target no-modules`, `--target web`, `--target nodejs`, \
+                         `--target module`, `--target workerd` and `--target bun`"
                     ),
                 }
                 .to_string()
@@ -3887,27 +4546,29 @@ __wbg_set_wasm(wasm);"
 
             Intrinsic::Memory => {
                 assert_eq!(args.len(), 0);
-                let mut memories = self.module.memories.iter();
-                let memory = memories
-                    .next()
-                    .ok_or_else(|| anyhow!("no memory found to return in memory intrinsic"))?
-                    .id();
-                if memories.next().is_some() {
-                    bail!(
-                        "multiple memories found, unsure which to return \
-                         from memory intrinsic"
-                    );
-                }
-                drop(memories);
+                let memory = wasm_bindgen_wasm_conventions::get_memory(self.module)
+                    .context("no memory found to return in memory intrinsic")?;
                 format!("wasm.{}", self.export_name_of(memory))
             }
 
+            Intrinsic::MemoryByName => {
+                assert_eq!(args.len(), 1);
+                prelude.push_str(&format!("const memory = wasm[{}];\n", args[0]));
+                "memory instanceof WebAssembly.Memory ? memory : undefined".to_string()
+            }
+
             Intrinsic::FunctionTable => {
                 assert_eq!(args.len(), 0);
                 let name = self.export_function_table()?;
                 format!("wasm.{}", name)
             }
 
+            Intrinsic::SpawnThread => {
+                assert_eq!(args.len(), 2);
+                self.expose_spawn_thread()?;
+                format!("spawnThread({}, {})", args[0], args[1])
+            }
+
             Intrinsic::DebugString => {
                 assert_eq!(args.len(), 1);
                 self.expose_debug_string();
@@ -3962,6 +4623,88 @@ __wbg_set_wasm(wasm);"
                 format!("{}.push({})", args[0], args[1])
             }
 
+            Intrinsic::ArrayLength => {
+                assert_eq!(args.len(), 1);
+                format!("{}.length", args[0])
+            }
+
+            Intrinsic::ArrayGet => {
+                assert_eq!(args.len(), 2);
+                format!("{}[{}]", args[0], args[1])
+            }
+
+            Intrinsic::ObjectNew => {
+                assert_eq!(args.len(), 0);
+                "({})".to_string()
+            }
+
+            Intrinsic::ObjectSet => {
+                assert_eq!(args.len(), 3);
+                format!("{}[{}] = {}", args[0], args[1], args[2])
+            }
+
+            Intrinsic::ObjectEntries => {
+                assert_eq!(args.len(), 1);
+                prelude.push_str(&format!("const obj = {};\n", args[0]));
+                "obj instanceof Map ? Array.from(obj) : Object.entries(obj)".to_string()
+            }
+
+            Intrinsic::MapNew => {
+                assert_eq!(args.len(), 0);
+                "new Map()".to_string()
+            }
+
+            Intrinsic::MapSet => {
+                assert_eq!(args.len(), 3);
+                format!("{}.set({}, {})", args[0], args[1], args[2])
+            }
+
+            Intrinsic::Uint8ArrayGet => {
+                assert_eq!(args.len(), 1);
+                prelude.push_str(&format!("const obj = {};\n", args[0]));
+                "obj instanceof Uint8Array ? obj : undefined".to_string()
+            }
+
+            Intrinsic::WeakRefNew => {
+                assert_eq!(args.len(), 1);
+                // Primitives can't be the target of a `WeakRef`, but they also
+                // can't be collected, so they're simply held strongly.
+                prelude.push_str(&format!("const obj = {};\n", args[0]));
+                prelude.push_str(
+                    "const isObject = (typeof(obj) === 'object' && obj !== null) || typeof(obj) === 'function';\n",
+                );
+                "isObject && typeof WeakRef !== 'undefined' ? new WeakRef(obj) : { deref: () => obj }"
+                    .to_string()
+            }
+
+            Intrinsic::WeakRefDeref => {
+                assert_eq!(args.len(), 1);
+                format!("{}.deref()", args[0])
+            }
+
+            Intrinsic::WeakRefRegister => {
+                assert_eq!(args.len(), 3);
+                self.expose_weak_ref_finalization();
+                // Only objects can be registered, primitives are never collected.
+                prelude.push_str(&format!("const obj = {};\n", args[0]));
+                prelude.push_str(
+                    "const isObject = (typeof(obj) === 'object' && obj !== null) || typeof(obj) === 'function';\n",
+                );
+                prelude.push_str("if (!isObject) {\n");
+                prelude.push_str("return;\n");
+                prelude.push_str("}\n");
+                format!(
+                    "WEAK_REF_FINALIZATION.register(obj, {}, {})",
+                    args[1], args[2]
+                )
+            }
+
+            Intrinsic::WeakRefUnregister => {
+                assert_eq!(args.len(), 1);
+                self.expose_weak_ref_finalization();
+                format!("WEAK_REF_FINALIZATION.unregister({})", args[0])
+            }
+
             Intrinsic::ExternrefHeapLiveCount => {
                 assert_eq!(args.len(), 0);
                 self.expose_global_heap();
@@ -4014,10 +4757,11 @@ __wbg_set_wasm(wasm);"
         let mut variants = String::new();
 
         if enum_.generate_typescript {
+            let marker = self.ts_source_marker(Some(enum_.location.as_str()));
             self.typescript
                 .push_str(&format_doc_comments(&enum_.comments, None));
             self.typescript
-                .push_str(&format!("export enum {} {{", enum_.name));
+                .push_str(&format!("export {}enum {} {{", marker, enum_.name));
         }
         for (name, value, comments) in enum_.variants.iter() {
             let variant_docs = if comments.is_empty() {
@@ -4083,7 +4827,9 @@ __wbg_set_wasm(wasm);"
                 variants.join(" | ")
             };
 
+            let marker = self.ts_source_marker(Some(string_enum.location.as_str()));
             self.typescript.push_str(&docs);
+            self.typescript.push_str(&marker);
             self.typescript.push_str("type ");
             self.typescript.push_str(&string_enum.name);
             self.typescript.push_str(" = ");
@@ -4108,7 +4854,9 @@ __wbg_set_wasm(wasm);"
     }
 
     fn generate_struct(&mut self, struct_: &AuxStruct) -> Result<(), Error> {
+        let marker = self.ts_source_marker(Some(struct_.location.as_str()));
         let class = require_class(&mut self.exported_classes, &struct_.name);
+        class.ts_source_marker = marker;
         class.comments = format_doc_comments(&struct_.comments, None);
         class.is_inspectable = struct_.is_inspectable;
         class.generate_typescript = struct_.generate_typescript;
@@ -4281,7 +5029,10 @@ __wbg_set_wasm(wasm);"
         let default_name = format!("__wbindgen_export_{}", self.next_export_idx);
         self.next_export_idx += 1;
         let name = match id {
-            walrus::ExportItem::Memory(_) if self.module.memories.iter().count() == 1 => {
+            walrus::ExportItem::Memory(m)
+                if wasm_bindgen_wasm_conventions::get_memory(self.module).ok() == Some(m)
+                    && self.module.exports.iter().all(|e| e.name != "memory") =>
+            {
                 "memory".to_owned()
             }
             walrus::ExportItem::Function(f) => match &self.module.funcs.get(f).name {
@@ -4336,6 +5087,51 @@ __wbg_set_wasm(wasm);"
         }
     }
 
+    /// Converts `val`, a pointer or length received from Wasm, to a JS number.
+    /// With 64-bit memories these are received as `BigInt`s.
+    fn ptr_from_wasm(&self, val: &str) -> String {
+        if self.aux.memory64 {
+            format!("Number({val})")
+        } else {
+            val.to_string()
+        }
+    }
+
+    /// The inverse of `ptr_from_wasm`, converting a JS number to a pointer or
+    /// length to pass to Wasm.
+    fn ptr_to_wasm(&self, val: &str) -> String {
+        if self.aux.memory64 {
+            format!("BigInt({val})")
+        } else {
+            val.to_string()
+        }
+    }
+
+    /// A statement coercing a `ptr` argument, a JS number, to a `u32`. Pointers
+    /// into 64-bit memories are left as is.
+    fn coerce_ptr(&self) -> &'static str {
+        if self.aux.memory64 {
+            ""
+        } else {
+            "ptr = ptr >>> 0;"
+        }
+    }
+
+    /// Generates a call to the allocation function `func`, such as `malloc`,
+    /// with the given JS number arguments, evaluating to the returned pointer
+    /// as a JS number.
+    fn alloc_call(&self, func: &str, args: &[&str]) -> String {
+        if self.aux.memory64 {
+            let args = args
+                .iter()
+                .map(|arg| format!("BigInt({arg})"))
+                .collect::<Vec<_>>();
+            format!("Number({func}({}))", args.join(", "))
+        } else {
+            format!("{func}({}) >>> 0", args.join(", "))
+        }
+    }
+
     fn inject_stack_pointer_shim(&mut self) -> Result<(), Error> {
         if self.stack_pointer_shim_injected {
             return Ok(());
@@ -4351,18 +5147,23 @@ __wbg_set_wasm(wasm);"
 
         use walrus::ir::*;
 
-        let mut builder =
-            walrus::FunctionBuilder::new(&mut self.module.types, &[ValType::I32], &[ValType::I32]);
+        // The stack pointer is an `i64` with 64-bit memories.
+        let ty = self.module.globals.get(stack_pointer).ty;
+        let add = match ty {
+            ValType::I64 => BinaryOp::I64Add,
+            _ => BinaryOp::I32Add,
+        };
+        let mut builder = walrus::FunctionBuilder::new(&mut self.module.types, &[ty], &[ty]);
         builder.name("__wbindgen_add_to_stack_pointer".to_string());
 
         let mut body = builder.func_body();
-        let arg = self.module.locals.add(ValType::I32);
+        let arg = self.module.locals.add(ty);
 
         // Create a shim function that mutate the stack pointer
         // to avoid exporting a mutable global.
         body.local_get(arg)
             .global_get(stack_pointer)
-            .binop(BinaryOp::I32Add)
+            .binop(add)
             .global_set(stack_pointer)
             .global_get(stack_pointer);
 
@@ -4599,6 +5400,27 @@ fn property_accessor(name: &str) -> String {
     }
 }
 
+/// Returns the properties of the `WebAssembly.Memory` descriptor to create an
+/// imported `mem` with. The limits of 64-bit memories are `BigInt`s.
+fn memory_descriptor(mem: &walrus::Memory) -> String {
+    let mut ret = if mem.memory64 {
+        format!("initial:BigInt({}),address:'i64'", mem.initial)
+    } else {
+        format!("initial:{}", mem.initial)
+    };
+    if let Some(max) = mem.maximum {
+        if mem.memory64 {
+            ret.push_str(&format!(",maximum:BigInt({})", max));
+        } else {
+            ret.push_str(&format!(",maximum:{}", max));
+        }
+    }
+    if mem.shared {
+        ret.push_str(",shared:true");
+    }
+    ret
+}
+
 impl ExportedClass {
     fn push(
         &mut self,
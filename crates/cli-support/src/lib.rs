@@ -1,7 +1,8 @@
 #![doc(html_root_url = "https://docs.rs/wasm-bindgen-cli-support/0.2")]
 
+use crate::source_map::SourceMap;
 use anyhow::{bail, Context, Error};
-use std::collections::{BTreeMap, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::env;
 use std::fs;
 use std::mem;
@@ -9,17 +10,29 @@ use std::path::{Path, PathBuf};
 use std::str;
 use walrus::Module;
 
+pub use wasm_bindgen_threads_xform::MemoryLayout;
+
 pub(crate) const PLACEHOLDER_MODULE: &str = "__wbindgen_placeholder__";
 
+/// Stands in for the base64 encoded module in the generated JS with
+/// `--inline-wasm` until the final module is known.
+pub(crate) const INLINE_WASM_PLACEHOLDER: &str = "__wbindgen_inline_wasm__";
+
+mod component;
 mod decode;
 mod descriptor;
 mod descriptors;
+mod exceptions;
 mod externref;
 mod intrinsic;
 mod js;
+pub mod manifest;
 mod multivalue;
+mod source_map;
+mod string_builtins;
 pub mod wasm2es6js;
 mod wit;
+mod wit_world;
 
 pub struct Bindgen {
     input: Input,
@@ -40,11 +53,24 @@ pub struct Bindgen {
     encode_into: EncodeInto,
     split_linked_modules: bool,
     symbol_dispose: bool,
+    source_map: bool,
+    declaration_map: bool,
+    inline_wasm: Option<InlineWasm>,
+    dual: bool,
+    wit: bool,
+    wit_component: bool,
+    js_string_builtins: bool,
+    memory: Option<String>,
+    thread_stack_size: Option<u32>,
+    snapshot_start: bool,
 }
 
 pub struct Output {
     module: walrus::Module,
+    thread_layout: Option<MemoryLayout>,
     stem: String,
+    splits: Vec<wasm_bindgen_split_xform::Split>,
+    manifest: manifest::Manifest,
     generated: Generated,
 }
 
@@ -52,11 +78,17 @@ struct Generated {
     mode: OutputMode,
     js: String,
     ts: String,
+    js_source_map: Option<SourceMap>,
+    declaration_map: Option<SourceMap>,
     start: Option<String>,
     snippets: HashMap<String, Vec<String>>,
     local_modules: HashMap<String, String>,
     npm_dependencies: HashMap<String, (PathBuf, String)>,
     typescript: bool,
+    inline_wasm: bool,
+    cjs: Option<String>,
+    wit: Option<String>,
+    component: Option<Vec<u8>>,
 }
 
 #[derive(Clone)]
@@ -66,6 +98,9 @@ enum OutputMode {
     NoModules { global: String },
     Node { module: bool },
     Deno,
+    Module,
+    Workerd,
+    Bun,
 }
 
 enum Input {
@@ -81,6 +116,16 @@ pub enum EncodeInto {
     Never,
 }
 
+/// How the Wasm module is embedded into the generated JS by
+/// `Bindgen::inline_wasm`.
+pub enum InlineWasm {
+    /// The Wasm module is embedded as a base64 string.
+    Base64,
+    /// The Wasm module is gzip-compressed and embedded as a base64 string,
+    /// then decompressed with a `DecompressionStream` when loaded.
+    Compressed,
+}
+
 impl Bindgen {
     pub fn new() -> Bindgen {
         let externref =
@@ -108,6 +153,16 @@ impl Bindgen {
             omit_default_module_path: true,
             split_linked_modules: false,
             symbol_dispose,
+            source_map: false,
+            declaration_map: false,
+            inline_wasm: None,
+            dual: false,
+            wit: false,
+            wit_component: false,
+            js_string_builtins: false,
+            memory: None,
+            thread_stack_size: None,
+            snapshot_start: false,
         }
     }
 
@@ -218,6 +273,45 @@ impl Bindgen {
         Ok(self)
     }
 
+    pub fn workerd(&mut self, workerd: bool) -> Result<&mut Bindgen, Error> {
+        if workerd {
+            self.switch_mode(OutputMode::Workerd, "--target workerd")?;
+            self.encode_into(EncodeInto::Always);
+        }
+        Ok(self)
+    }
+
+    pub fn bun(&mut self, bun: bool) -> Result<&mut Bindgen, Error> {
+        if bun {
+            self.switch_mode(OutputMode::Bun, "--target bun")?;
+            self.encode_into(EncodeInto::Always);
+        }
+        Ok(self)
+    }
+
+    pub fn module(&mut self, module: bool) -> Result<&mut Bindgen, Error> {
+        if module {
+            self.switch_mode(OutputMode::Module, "--target module")?;
+        }
+        Ok(self)
+    }
+
+    /// Generates both an ES module for bundlers and a CommonJS module for
+    /// Node.js sharing a single Wasm file, along with a `package.json` whose
+    /// conditional exports select between them.
+    pub fn dual(&mut self, dual: bool) -> Result<&mut Bindgen, Error> {
+        if dual {
+            self.switch_mode(
+                OutputMode::Bundler {
+                    browser_only: false,
+                },
+                "--target dual",
+            )?;
+            self.dual = true;
+        }
+        Ok(self)
+    }
+
     pub fn no_modules_global(&mut self, name: &str) -> Result<&mut Bindgen, Error> {
         match &mut self.mode {
             OutputMode::NoModules { global } => *global = name.to_string(),
@@ -271,6 +365,14 @@ impl Bindgen {
         self
     }
 
+    /// Run the start function at build time and store the resulting memory
+    /// and globals in the module, instead of running it when the module is
+    /// instantiated. Fails if the start function calls any import.
+    pub fn snapshot_start(&mut self, snapshot: bool) -> &mut Bindgen {
+        self.snapshot_start = snapshot;
+        self
+    }
+
     pub fn encode_into(&mut self, mode: EncodeInto) -> &mut Bindgen {
         self.encode_into = mode;
         self
@@ -286,6 +388,76 @@ impl Bindgen {
         self
     }
 
+    /// Emit a source map alongside the generated JS, mapping each generated
+    /// export, class method and import shim back to its Rust definition.
+    pub fn source_map(&mut self, source_map: bool) -> &mut Bindgen {
+        self.source_map = source_map;
+        self
+    }
+
+    /// Emit a declaration map alongside the generated TypeScript definitions,
+    /// mapping each exported function, class, field and enum back to its Rust
+    /// definition.
+    pub fn declaration_map(&mut self, declaration_map: bool) -> &mut Bindgen {
+        self.declaration_map = declaration_map;
+        self
+    }
+
+    /// Emit a WIT world describing the exports which can be expressed in the
+    /// component model. The module itself keeps the ABI of the JS bindings,
+    /// see `wit_component` for a component implementing the world.
+    pub fn wit(&mut self, wit: bool) -> &mut Bindgen {
+        self.wit = wit;
+        self
+    }
+
+    /// Also wrap the module into a component implementing the WIT world,
+    /// which then only contains the free functions taking and returning
+    /// numbers, `bool`s, strings and lists of numbers. Implies `wit`.
+    pub fn wit_component(&mut self, wit_component: bool) -> &mut Bindgen {
+        self.wit_component = wit_component;
+        self
+    }
+
+    /// Import the string operations of `js_sys::JsString` from the
+    /// `wasm:js-string` builtins, falling back to JS implementations in
+    /// engines not supporting them. Requires reference types and is only
+    /// supported by `--target web` and `--target no-modules`.
+    pub fn js_string_builtins(&mut self, js_string_builtins: bool) -> &mut Bindgen {
+        self.js_string_builtins = js_string_builtins;
+        self
+    }
+
+    /// Bind the memory exported as `name`, or named `name` in the name section,
+    /// instead of the only memory of the module. Required for modules with
+    /// multiple memories, unless the memory is named by the
+    /// `__wasm_bindgen_memory` custom section.
+    pub fn memory(&mut self, name: &str) -> &mut Bindgen {
+        self.memory = Some(name.to_string());
+        self
+    }
+
+    /// Set the default stack size in bytes of threads other than the main
+    /// thread, overriding the `__wasm_bindgen_thread_stack_size` custom
+    /// section. Must be a multiple of the page size. Only used when the
+    /// module uses threads.
+    pub fn thread_stack_size(&mut self, size: u32) -> &mut Bindgen {
+        self.thread_stack_size = Some(size);
+        self
+    }
+
+    /// Embed the Wasm module into the generated JS instead of emitting a
+    /// separate `.wasm` file, so that the default initialization function
+    /// doesn't need to fetch anything. Only supported by `--target web` and
+    /// `--target no-modules`, and not with `#[wasm_bindgen(split)]`.
+    ///
+    /// The module is embedded as it is at the end of `generate`, so later
+    /// changes through `Output::wasm_mut` aren't reflected in the JS.
+    pub fn inline_wasm(&mut self, inline_wasm: Option<InlineWasm>) -> &mut Bindgen {
+        self.inline_wasm = inline_wasm;
+        self
+    }
+
     pub fn generate<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
         self.generate_output()?.emit(path.as_ref())
     }
@@ -320,6 +492,28 @@ impl Bindgen {
                 .context("failed getting Wasm module")?,
         };
 
+        if !self.dual {
+            return self.generate_output_from(module);
+        }
+
+        // Generating JS modifies the module, so each flavour of a dual
+        // package is generated from its own copy of the input module. The
+        // CommonJS flavour then loads the module emitted for the ES module
+        // flavour, which must have the imports and exports it expects.
+        let bytes = module.emit_wasm();
+        let mut output = self.generate_output_from(self.module_from_bytes(&bytes)?)?;
+        let mode = mem::replace(&mut self.mode, OutputMode::Node { module: false });
+        let cjs = self
+            .module_from_bytes(&bytes)
+            .and_then(|module| self.generate_output_from(module));
+        self.mode = mode;
+        let cjs = cjs?;
+        check_dual_modules(&output.module, &cjs.module, &output.stem)?;
+        output.generated.cjs = Some(cjs.generated.js);
+        Ok(output)
+    }
+
+    fn generate_output_from(&mut self, mut module: Module) -> Result<Output, Error> {
         // Enable reference type transformations if the module is already using it.
         if let Ok(true) = wasm_bindgen_wasm_conventions::target_feature(&module, "reference-types")
         {
@@ -331,6 +525,27 @@ impl Bindgen {
             self.multi_value = true;
         }
 
+        if self.inline_wasm.is_some()
+            && !matches!(self.mode, OutputMode::Web | OutputMode::NoModules { .. })
+        {
+            bail!("`--inline-wasm` is only supported with `--target web` and `--target no-modules`")
+        }
+
+        if self.js_string_builtins {
+            if !matches!(self.mode, OutputMode::Web | OutputMode::NoModules { .. }) {
+                bail!(
+                    "`--js-string-builtins` is only supported with `--target web` and \
+                     `--target no-modules`"
+                )
+            }
+            if !self.externref {
+                bail!(
+                    "`--js-string-builtins` requires reference types, enable them with \
+                     `-Ctarget-feature=+reference-types`"
+                )
+            }
+        }
+
         // Check that no exported symbol is called "default" if we target web.
         if matches!(self.mode, OutputMode::Web)
             && module.exports.iter().any(|export| export.name == "default")
@@ -338,7 +553,14 @@ impl Bindgen {
             bail!("exported symbol \"default\" not allowed for --target web")
         }
 
-        let thread_count = wasm_bindgen_threads_xform::run(&mut module)
+        if let Some(name) = &self.memory {
+            wasm_bindgen_wasm_conventions::set_memory(&mut module, name)?;
+        }
+        if module.memories.iter().count() > 1 {
+            export_secondary_memories(&mut module)?;
+        }
+
+        let thread_count = wasm_bindgen_threads_xform::run(&mut module, self.thread_stack_size)
             .with_context(|| "failed to prepare module for threading")?;
 
         // If requested, turn all mangled symbols into prettier unmangled
@@ -377,6 +599,7 @@ impl Bindgen {
         // auxiliary section for all sorts of miscellaneous information and
         // features #[wasm_bindgen] supports that aren't covered by wasm
         // interface types.
+        let thread_layout = thread_count.map(|count| count.layout());
         wit::process(self, &mut module, programs, thread_count)?;
 
         // Now that we've got type information from the webidl processing pass,
@@ -390,6 +613,20 @@ impl Bindgen {
         // GC pass before JS generation.
         if self.externref {
             externref::process(&mut module)?;
+
+            // Modules compiled with support for exception handling can catch
+            // and throw JS exceptions themselves, which relies on the externref
+            // table to store caught exceptions in.
+            if let Ok(true) =
+                wasm_bindgen_wasm_conventions::target_feature(&module, "exception-handling")
+            {
+                exceptions::run(&mut module)
+                    .context("failed to use native exception handling for JS exceptions")?;
+            }
+
+            if self.js_string_builtins {
+                string_builtins::run(&mut module).context("failed to import JS string builtins")?;
+            }
         } else {
             let ids = module
                 .exports
@@ -419,34 +656,98 @@ impl Bindgen {
         // unnecessary things here.
         gc_module_and_adapters(&mut module);
 
-        let stem = self.stem()?;
+        let stem = self.stem()?.to_string();
 
         // Now we execute the JS generation passes to actually emit JS/TypeScript/etc.
         let aux = module
             .customs
             .delete_typed::<wit::WasmBindgenAux>()
             .expect("aux section should be present");
+        if !aux.split_exports.is_empty() {
+            if !matches!(self.mode, OutputMode::Web | OutputMode::NoModules { .. }) {
+                bail!(
+                    "`#[wasm_bindgen(split)]` is only supported with `--target web` and \
+                     `--target no-modules`"
+                );
+            }
+            if module.memories.iter().any(|m| m.shared) {
+                bail!("`#[wasm_bindgen(split)]` is not supported with shared memory");
+            }
+            if let Ok(true) =
+                wasm_bindgen_wasm_conventions::target_feature(&module, "exception-handling")
+            {
+                bail!("`#[wasm_bindgen(split)]` is not supported with exception handling yet");
+            }
+            if self.inline_wasm.is_some() {
+                bail!("`#[wasm_bindgen(split)]` is not supported with `--inline-wasm`");
+            }
+            if self.wit_component {
+                bail!("`#[wasm_bindgen(split)]` is not supported with `--wit-component`");
+            }
+        }
         let adapters = module
             .customs
             .delete_typed::<wit::NonstandardWitSection>()
             .unwrap();
+        let manifest = manifest::Manifest::new(self, &aux, &adapters);
+        let world = (self.wit || self.wit_component)
+            .then(|| wit_world::generate(&stem, &aux, &adapters, self.wit_component));
         let mut cx = js::Context::new(&mut module, self, &adapters, &aux)?;
         cx.generate()?;
-        let (js, ts, start) = cx.finalize(stem)?;
-        let generated = Generated {
+        let (js, ts, start) = cx.finalize(&stem)?;
+        let mut generated = Generated {
             snippets: aux.snippets.clone(),
             local_modules: aux.local_modules.clone(),
             mode: self.mode.clone(),
             typescript: self.typescript,
+            inline_wasm: self.inline_wasm.is_some(),
+            cjs: None,
+            wit: None,
+            component: None,
             npm_dependencies: cx.npm_dependencies.clone(),
+            js_source_map: cx.js_source_map.take(),
+            declaration_map: cx.declaration_map.take(),
             js,
             ts,
             start,
         };
 
+        // The choice of memory was only needed to generate the bindings.
+        module
+            .customs
+            .remove_raw(wasm_bindgen_wasm_conventions::MEMORY_SECTION);
+
+        if let Some(world) = world {
+            if self.wit_component {
+                let component = component::wrap(&mut module, &world, &adapters, |bytes| {
+                    self.module_from_bytes(bytes)
+                })
+                .context("failed to wrap the Wasm module into a component")?;
+                generated.component = Some(component);
+            }
+            generated.wit = Some(world.wit);
+        }
+
+        // Move the exports which are only loaded on demand, and everything
+        // only they use, into their own modules.
+        let splits = wasm_bindgen_split_xform::run(&mut module, &aux.split_exports, |bytes| {
+            self.module_from_bytes(bytes)
+        })
+        .context("failed to split Wasm module")?;
+
+        // Only now the module is final and can be embedded.
+        if let Some(mode) = &self.inline_wasm {
+            let base64 =
+                wasm2es6js::base64(&module.emit_wasm(), matches!(mode, InlineWasm::Compressed));
+            generated.js = generated.js.replacen(INLINE_WASM_PLACEHOLDER, &base64, 1);
+        }
+
         Ok(Output {
             module,
-            stem: stem.to_string(),
+            thread_layout,
+            stem,
+            splits,
+            manifest,
             generated,
         })
     }
@@ -549,6 +850,9 @@ impl OutputMode {
                 | OutputMode::Web
                 | OutputMode::Node { module: true }
                 | OutputMode::Deno
+                | OutputMode::Module
+                | OutputMode::Workerd
+                | OutputMode::Bun
         )
     }
 
@@ -586,6 +890,88 @@ fn unexported_unused_lld_things(module: &mut Module) {
     }
 }
 
+/// Checks that the final modules of the ES module and CommonJS flavours of a
+/// dual package have the same imports and exports, as only the former is
+/// emitted and loaded by both. Memory imports are named by the JS loading the
+/// module and are left out.
+fn check_dual_modules(esm: &Module, cjs: &Module, stem: &str) -> Result<(), Error> {
+    // The intrinsics imported from the generated JS are named after the
+    // `_bg.js` file of the ES module flavour, see `js::Context::generate_node_imports`.
+    let own_module = format!("./{}_bg.js", stem);
+    let imports = |module: &Module| {
+        module
+            .imports
+            .iter()
+            .filter(|i| !matches!(i.kind, walrus::ImportKind::Memory(_)))
+            .map(|i| {
+                let module = if i.module == PLACEHOLDER_MODULE {
+                    own_module.clone()
+                } else {
+                    i.module.clone()
+                };
+                format!("{}::{}", module, i.name)
+            })
+            .collect::<BTreeSet<_>>()
+    };
+    let exports = |module: &Module| {
+        module
+            .exports
+            .iter()
+            .map(|e| e.name.clone())
+            .collect::<BTreeSet<_>>()
+    };
+    for (kind, esm, cjs) in [
+        ("import", imports(esm), imports(cjs)),
+        ("export", exports(esm), exports(cjs)),
+    ] {
+        if let Some(name) = esm.symmetric_difference(&cjs).next() {
+            bail!(
+                "the Wasm modules of the ES module and CommonJS flavours of the dual package \
+                 differ in the {} `{}`, so they can't share a single Wasm file",
+                kind,
+                name
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Exports all memories besides the one used for the bindings under their
+/// name, so they're accessible with `wasm_bindgen::memory_by_name`. Memories
+/// without a name in the name section are named `memory{index}` after their
+/// index in the module.
+fn export_secondary_memories(module: &mut Module) -> Result<(), Error> {
+    let memory = wasm_bindgen_wasm_conventions::get_memory(module)?;
+    let unexported = module
+        .memories
+        .iter()
+        .enumerate()
+        .filter(|(_, m)| m.id() != memory)
+        .filter(|(_, m)| {
+            !module
+                .exports
+                .iter()
+                .any(|e| matches!(e.item, walrus::ExportItem::Memory(id) if id == m.id()))
+        })
+        .map(|(i, m)| {
+            (
+                m.id(),
+                m.name.clone().unwrap_or_else(|| format!("memory{}", i)),
+            )
+        })
+        .collect::<Vec<_>>();
+    for (id, name) in unexported {
+        if module.exports.iter().any(|e| e.name == name) {
+            bail!(
+                "cannot export memory `{}`, its name is already exported",
+                name
+            );
+        }
+        module.exports.add(&name, id);
+    }
+    Ok(())
+}
+
 impl Output {
     pub fn js(&self) -> &str {
         &self.generated.js
@@ -599,6 +985,32 @@ impl Output {
         }
     }
 
+    /// The JS of the CommonJS flavour of a dual package, if `Bindgen::dual`
+    /// was enabled.
+    pub fn cjs(&self) -> Option<&str> {
+        self.generated.cjs.as_deref()
+    }
+
+    /// The source map of the generated JS, if `Bindgen::source_map` was
+    /// enabled. Its sources are made relative to `map_dir`, the directory the
+    /// source map will be written to.
+    pub fn js_source_map(&self, map_dir: &Path) -> Option<String> {
+        let map = self.generated.js_source_map.as_ref()?;
+        Some(map.to_json(map_dir))
+    }
+
+    /// The source map of the generated TypeScript definitions, if
+    /// `Bindgen::declaration_map` was enabled. Its sources are made relative
+    /// to `map_dir`, the directory the source map will be written to.
+    pub fn declaration_map(&self, map_dir: &Path) -> Option<String> {
+        if self.generated.typescript {
+            let map = self.generated.declaration_map.as_ref()?;
+            Some(map.to_json(map_dir))
+        } else {
+            None
+        }
+    }
+
     pub fn start(&self) -> Option<&String> {
         self.generated.start.as_ref()
     }
@@ -615,6 +1027,28 @@ impl Output {
         &self.generated.npm_dependencies
     }
 
+    /// The WIT world describing the exports, if `Bindgen::wit` was enabled.
+    pub fn wit(&self) -> Option<&str> {
+        self.generated.wit.as_deref()
+    }
+
+    /// The component wrapping the Wasm module, if `Bindgen::wit_component`
+    /// was enabled.
+    pub fn component(&self) -> Option<&[u8]> {
+        self.generated.component.as_deref()
+    }
+
+    /// A machine-readable description of the generated bindings.
+    pub fn manifest(&self) -> &manifest::Manifest {
+        &self.manifest
+    }
+
+    /// The layout of the memory shared between threads, if the module uses
+    /// threads.
+    pub fn thread_layout(&self) -> Option<&MemoryLayout> {
+        self.thread_layout.as_ref()
+    }
+
     pub fn wasm(&self) -> &walrus::Module {
         &self.module
     }
@@ -623,6 +1057,13 @@ impl Output {
         &mut self.module
     }
 
+    /// The secondary Wasm modules exports marked with
+    /// `#[wasm_bindgen(split)]` were moved into, which are emitted as
+    /// `{stem}_bg_{name}.wasm`.
+    pub fn splits(&self) -> &[wasm_bindgen_split_xform::Split] {
+        &self.splits
+    }
+
     pub fn emit(&mut self, out_dir: impl AsRef<Path>) -> Result<(), Error> {
         self._emit(out_dir.as_ref())
     }
@@ -631,12 +1072,22 @@ impl Output {
         let wasm_name = format!("{}_bg", self.stem);
         let wasm_path = out_dir.join(&wasm_name).with_extension("wasm");
         fs::create_dir_all(out_dir)?;
-        let wasm_bytes = self.module.emit_wasm();
-        fs::write(&wasm_path, wasm_bytes)
-            .with_context(|| format!("failed to write `{}`", wasm_path.display()))?;
 
         let gen = &self.generated;
 
+        // An inlined Wasm module is already part of the generated JS.
+        if !gen.inline_wasm {
+            let wasm_bytes = self.module.emit_wasm();
+            fs::write(&wasm_path, wasm_bytes)
+                .with_context(|| format!("failed to write `{}`", wasm_path.display()))?;
+        }
+
+        for split in self.splits.iter_mut() {
+            let path = out_dir.join(format!("{}_{}.wasm", wasm_name, split.name));
+            fs::write(&path, split.module.emit_wasm())
+                .with_context(|| format!("failed to write `{}`", path.display()))?;
+        }
+
         // Write out all local JS snippets to the final destination now that
         // we've collected them from all the programs.
         for (identifier, list) in gen.snippets.iter() {
@@ -657,15 +1108,55 @@ impl Output {
         }
 
         let is_genmode_nodemodule = matches!(gen.mode, OutputMode::Node { module: true });
-        if !gen.npm_dependencies.is_empty() || is_genmode_nodemodule {
+        let is_dual = gen.cjs.is_some();
+        if !gen.npm_dependencies.is_empty() || is_genmode_nodemodule || is_dual {
             #[derive(serde::Serialize)]
             struct PackageJson<'a> {
                 #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
                 ty: Option<&'static str>,
+                #[serde(skip_serializing_if = "Option::is_none")]
+                main: Option<String>,
+                #[serde(skip_serializing_if = "Option::is_none")]
+                module: Option<String>,
+                #[serde(skip_serializing_if = "Option::is_none")]
+                types: Option<String>,
+                #[serde(skip_serializing_if = "Option::is_none")]
+                exports: Option<Exports>,
                 dependencies: BTreeMap<&'a str, &'a str>,
             }
+            #[derive(serde::Serialize)]
+            struct Exports {
+                #[serde(rename = ".")]
+                root: Conditions,
+            }
+            // The order of the conditions matters, the first matching one is
+            // used.
+            #[derive(serde::Serialize)]
+            struct Conditions {
+                #[serde(skip_serializing_if = "Option::is_none")]
+                types: Option<String>,
+                browser: String,
+                node: String,
+                import: String,
+                require: String,
+            }
+            let esm = format!("./{}.js", self.stem);
+            let cjs = format!("./{}.cjs", self.stem);
+            let types = gen.typescript.then(|| format!("./{}.d.ts", self.stem));
             let pj = PackageJson {
-                ty: is_genmode_nodemodule.then_some("module"),
+                ty: (is_genmode_nodemodule || is_dual).then_some("module"),
+                main: is_dual.then(|| cjs.clone()),
+                module: is_dual.then(|| esm.clone()),
+                types: types.clone().filter(|_| is_dual),
+                exports: is_dual.then(|| Exports {
+                    root: Conditions {
+                        types,
+                        browser: esm.clone(),
+                        node: cjs.clone(),
+                        import: esm,
+                        require: cjs,
+                    },
+                }),
                 dependencies: gen
                     .npm_dependencies
                     .iter()
@@ -689,8 +1180,25 @@ impl Output {
                 .with_context(|| format!("failed to write `{}`", path.as_ref().display()))
         }
 
+        fn write_js(path: &Path, js: &str, source_map: Option<&SourceMap>) -> Result<(), Error> {
+            let mut js = reset_indentation(js);
+            if let Some(source_map) = source_map {
+                let mut map_path = path.as_os_str().to_owned();
+                map_path.push(".map");
+                let map_path = PathBuf::from(map_path);
+                let map_name = map_path.file_name().unwrap().to_str().unwrap();
+                js.push_str(&format!("//# sourceMappingURL={}\n", map_name));
+                write(&map_path, source_map.to_json(path.parent().unwrap()))?;
+            }
+            write(path, js)
+        }
+
         let js_path = out_dir.join(&self.stem).with_extension(extension);
 
+        if let Some(cjs) = &gen.cjs {
+            write_js(&js_path.with_extension("cjs"), cjs, None)?;
+        }
+
         if gen.mode.esm_integration() {
             let js_name = format!("{}_bg.{}", self.stem, extension);
 
@@ -716,18 +1224,40 @@ export * from \"./{js_name}\";
                     ),
                 )?;
             }
-            write(out_dir.join(&js_name), reset_indentation(&gen.js))?;
+            write_js(&out_dir.join(&js_name), &gen.js, gen.js_source_map.as_ref())?;
         } else {
-            write(&js_path, reset_indentation(&gen.js))?;
+            write_js(&js_path, &gen.js, gen.js_source_map.as_ref())?;
         }
 
         if gen.typescript {
             let ts_path = js_path.with_extension("d.ts");
-            fs::write(&ts_path, &gen.ts)
+            let mut ts = gen.ts.clone();
+            if let Some(declaration_map) = &gen.declaration_map {
+                let map_path = js_path.with_extension("d.ts.map");
+                ts.push_str(&format!(
+                    "//# sourceMappingURL={}\n",
+                    map_path.file_name().unwrap().to_str().unwrap()
+                ));
+                write(&map_path, declaration_map.to_json(out_dir))?;
+            }
+            fs::write(&ts_path, ts)
                 .with_context(|| format!("failed to write `{}`", ts_path.display()))?;
         }
 
-        if gen.typescript {
+        if let Some(wit) = &gen.wit {
+            let wit_path = js_path.with_extension("wit");
+            fs::write(&wit_path, wit)
+                .with_context(|| format!("failed to write `{}`", wit_path.display()))?;
+        }
+
+        if let Some(component) = &gen.component {
+            write(
+                out_dir.join(format!("{}_component.wasm", self.stem)),
+                component,
+            )?;
+        }
+
+        if gen.typescript && !gen.inline_wasm {
             let ts_path = wasm_path.with_extension("wasm.d.ts");
             let ts = wasm2es6js::typescript(&self.module)?;
             fs::write(&ts_path, ts)
@@ -0,0 +1,512 @@
+//! A machine-readable description of the bindings generated for a Wasm
+//! module, for tools like documentation generators, API diff checks or
+//! wrappers in other languages which need the exact binding surface.
+
+use crate::descriptor::VectorKind;
+use crate::wit::{
+    AdapterId, AdapterJsImportKind, AdapterKind, AdapterType, AuxExportKind, AuxExportedMethodKind,
+    AuxImport, AuxReceiverKind, AuxValue, Instruction, JsImport, JsImportName,
+    NonstandardWitSection, WasmBindgenAux,
+};
+use crate::Bindgen;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// The bindings of a Wasm module.
+#[derive(Debug, Clone, Serialize)]
+pub struct Manifest {
+    /// Everything exported to JS.
+    pub exports: Vec<Export>,
+    /// Everything imported from JS.
+    pub imports: Vec<Import>,
+    /// Exported numeric enums.
+    pub enums: Vec<Enum>,
+    /// String enums, which only exist in the TypeScript definitions.
+    pub string_enums: Vec<StringEnum>,
+    /// Exported structs, whose fields, constructors and methods are listed in
+    /// `exports`.
+    pub structs: Vec<Struct>,
+}
+
+/// A function exported to JS.
+#[derive(Debug, Clone, Serialize)]
+pub struct Export {
+    /// The name of the function in JS. For getters and setters this is the
+    /// name of the field.
+    pub name: String,
+    pub kind: ExportKind,
+    /// The class the function belongs to, if any.
+    pub class: Option<String>,
+    /// How the method receives `self`, or `None` if it's a free function,
+    /// constructor or static method.
+    pub receiver: Option<Receiver>,
+    /// The arguments of the function, not including the receiver.
+    pub args: Vec<Argument>,
+    /// The return type, or `None` if nothing is returned. For `async`
+    /// functions this is the type the returned `Promise` resolves to.
+    pub ret: Option<String>,
+    #[serde(rename = "async")]
+    pub asyncness: bool,
+    pub variadic: bool,
+    /// The doc comments of the Rust function.
+    pub comments: String,
+    /// The `file:line:column` location of the Rust function.
+    pub location: Option<String>,
+    /// The name of the lazily loaded module the function was split into with
+    /// `#[wasm_bindgen(split)]`, if any.
+    pub split: Option<String>,
+    /// Whether the function is called through `WebAssembly.promising` with
+    /// `#[wasm_bindgen(promising)]`, returning a `Promise`.
+    pub promising: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportKind {
+    Function,
+    Constructor,
+    Method,
+    Getter,
+    Setter,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Receiver {
+    /// `&self` or `&mut self`.
+    Borrowed,
+    /// `self`.
+    Owned,
+}
+
+/// An argument of an exported function.
+#[derive(Debug, Clone, Serialize)]
+pub struct Argument {
+    pub name: String,
+    /// The type of the argument.
+    pub ty: String,
+}
+
+/// A JS value imported by the Wasm module.
+#[derive(Debug, Clone, Serialize)]
+pub struct Import {
+    /// The JS module the value is imported from, or `None` if it's a global.
+    pub module: Option<String>,
+    /// The path of the object the value is accessed on, e.g. `["console"]`
+    /// for `console.log`. For methods, getters and setters this is the path of
+    /// the class. Empty for structural operations on the receiver.
+    pub namespace: Vec<String>,
+    /// The name of the value, or `None` for indexing operations.
+    pub name: Option<String>,
+    pub kind: ImportKind,
+    /// The types of the arguments, not including the receiver.
+    pub args: Vec<String>,
+    /// The return type, or `None` if nothing is returned.
+    pub ret: Option<String>,
+    /// Whether JS exceptions are caught and returned as `Err`.
+    pub catch: bool,
+    /// Whether the returned `Promise` suspends Wasm until it settles, with
+    /// `#[wasm_bindgen(suspending)]`.
+    pub suspending: bool,
+    pub variadic: bool,
+    /// The `file:line:column` location of the Rust declaration.
+    pub location: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportKind {
+    Function,
+    Constructor,
+    Method,
+    StaticMethod,
+    Getter,
+    StaticGetter,
+    Setter,
+    StaticSetter,
+    Static,
+    Instanceof,
+    IndexingGetter,
+    IndexingSetter,
+    IndexingDeleter,
+}
+
+/// An exported numeric enum.
+#[derive(Debug, Clone, Serialize)]
+pub struct Enum {
+    pub name: String,
+    pub comments: String,
+    pub variants: Vec<EnumVariant>,
+    pub location: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EnumVariant {
+    pub name: String,
+    pub value: i64,
+    pub comments: String,
+}
+
+/// An imported string enum.
+#[derive(Debug, Clone, Serialize)]
+pub struct StringEnum {
+    pub name: String,
+    pub comments: String,
+    pub values: Vec<String>,
+    pub location: String,
+}
+
+/// An exported struct.
+#[derive(Debug, Clone, Serialize)]
+pub struct Struct {
+    pub name: String,
+    pub comments: String,
+    pub location: String,
+}
+
+impl Manifest {
+    pub(crate) fn new(
+        bindgen: &Bindgen,
+        aux: &WasmBindgenAux,
+        adapters: &NonstandardWitSection,
+    ) -> Manifest {
+        let location = |id: &AdapterId| aux.source_locations.get(id).cloned();
+
+        let mut exports = Vec::new();
+        for (id, export) in crate::sorted_iter(&aux.export_map) {
+            let adapter = match adapters.adapters.get(id) {
+                Some(adapter) => adapter,
+                None => continue,
+            };
+            let (name, kind, class, receiver) = match &export.kind {
+                AuxExportKind::Function(name) => (name.clone(), ExportKind::Function, None, None),
+                AuxExportKind::Constructor(class) => (
+                    "constructor".to_string(),
+                    ExportKind::Constructor,
+                    Some(class.clone()),
+                    None,
+                ),
+                AuxExportKind::Method {
+                    class,
+                    name,
+                    receiver,
+                    kind,
+                } => {
+                    let kind = match kind {
+                        AuxExportedMethodKind::Method => ExportKind::Method,
+                        AuxExportedMethodKind::Getter => ExportKind::Getter,
+                        AuxExportedMethodKind::Setter => ExportKind::Setter,
+                    };
+                    let receiver = match receiver {
+                        AuxReceiverKind::None => None,
+                        AuxReceiverKind::Borrowed => Some(Receiver::Borrowed),
+                        AuxReceiverKind::Owned => Some(Receiver::Owned),
+                    };
+                    (name.clone(), kind, Some(class.clone()), receiver)
+                }
+            };
+            // The receiver is passed as the first argument.
+            let params = &adapter.params[receiver.is_some() as usize..];
+            let args = params
+                .iter()
+                .enumerate()
+                .map(|(i, ty)| Argument {
+                    name: export
+                        .args
+                        .as_ref()
+                        .and_then(|args| args.get(i))
+                        .map(|arg| arg.name.clone())
+                        .unwrap_or_else(|| format!("arg{}", i)),
+                    ty: type_name(ty),
+                })
+                .collect();
+            exports.push(Export {
+                name,
+                kind,
+                class,
+                receiver,
+                args,
+                ret: adapter.inner_results.first().map(type_name),
+                asyncness: export.asyncness,
+                variadic: export.variadic,
+                comments: export.comments.clone(),
+                location: location(id),
+                split: export.split.clone(),
+                promising: export.promising,
+            });
+        }
+
+        // Attributes like `catch` are tracked on the adapters implementing the
+        // core Wasm imports, which call the adapters of the JS imports.
+        let mut implementing = HashMap::new();
+        for (_, _, id) in adapters.implements.iter() {
+            if let Some(AdapterKind::Local { instructions }) =
+                adapters.adapters.get(id).map(|a| &a.kind)
+            {
+                for instr in instructions {
+                    if let Instruction::CallAdapter(import) = instr.instr {
+                        implementing.insert(import, *id);
+                    }
+                }
+            }
+        }
+
+        let mut imports = Vec::new();
+        for (id, import) in crate::sorted_iter(&aux.import_map) {
+            let adapter = match adapters.adapters.get(id) {
+                Some(adapter) => adapter,
+                None => continue,
+            };
+            let js_kind = match &adapter.kind {
+                AdapterKind::Import { kind, .. } => kind,
+                AdapterKind::Local { .. } => continue,
+            };
+            let (js, namespace, name, kind) = match import {
+                AuxImport::Value(AuxValue::Bare(js)) => {
+                    let mut path = js_path(js);
+                    let name = path.pop();
+                    let kind = match js_kind {
+                        AdapterJsImportKind::Constructor => ImportKind::Constructor,
+                        AdapterJsImportKind::Method => {
+                            path.pop(); // `prototype`
+                            ImportKind::Method
+                        }
+                        AdapterJsImportKind::Normal => ImportKind::Function,
+                    };
+                    (Some(js), path, name, kind)
+                }
+                AuxImport::Value(AuxValue::Getter(js, field)) => (
+                    Some(js),
+                    js_path(js),
+                    Some(field.clone()),
+                    ImportKind::Getter,
+                ),
+                AuxImport::Value(AuxValue::ClassGetter(js, field))
+                | AuxImport::StructuralClassGetter(js, field) => (
+                    Some(js),
+                    js_path(js),
+                    Some(field.clone()),
+                    ImportKind::StaticGetter,
+                ),
+                AuxImport::Value(AuxValue::Setter(js, field)) => (
+                    Some(js),
+                    js_path(js),
+                    Some(field.clone()),
+                    ImportKind::Setter,
+                ),
+                AuxImport::Value(AuxValue::ClassSetter(js, field))
+                | AuxImport::StructuralClassSetter(js, field) => (
+                    Some(js),
+                    js_path(js),
+                    Some(field.clone()),
+                    ImportKind::StaticSetter,
+                ),
+                AuxImport::ValueWithThis(js, name) => (
+                    Some(js),
+                    js_path(js),
+                    Some(name.clone()),
+                    ImportKind::StaticMethod,
+                ),
+                AuxImport::Static { js, .. } => {
+                    let mut path = js_path(js);
+                    let name = path.pop();
+                    (Some(js), path, name, ImportKind::Static)
+                }
+                AuxImport::Instanceof(js) => {
+                    let mut path = js_path(js);
+                    let name = path.pop();
+                    (Some(js), path, name, ImportKind::Instanceof)
+                }
+                AuxImport::StructuralMethod(name) => {
+                    (None, Vec::new(), Some(name.clone()), ImportKind::Method)
+                }
+                AuxImport::StructuralGetter(field) => {
+                    (None, Vec::new(), Some(field.clone()), ImportKind::Getter)
+                }
+                AuxImport::StructuralSetter(field) => {
+                    (None, Vec::new(), Some(field.clone()), ImportKind::Setter)
+                }
+                AuxImport::IndexingGetterOfClass(js) => {
+                    (Some(js), js_path(js), None, ImportKind::IndexingGetter)
+                }
+                AuxImport::IndexingSetterOfClass(js) => {
+                    (Some(js), js_path(js), None, ImportKind::IndexingSetter)
+                }
+                AuxImport::IndexingDeleterOfClass(js) => {
+                    (Some(js), js_path(js), None, ImportKind::IndexingDeleter)
+                }
+                AuxImport::IndexingGetterOfObject => {
+                    (None, Vec::new(), None, ImportKind::IndexingGetter)
+                }
+                AuxImport::IndexingSetterOfObject => {
+                    (None, Vec::new(), None, ImportKind::IndexingSetter)
+                }
+                AuxImport::IndexingDeleterOfObject => {
+                    (None, Vec::new(), None, ImportKind::IndexingDeleter)
+                }
+
+                // Everything else is generated by wasm-bindgen itself rather
+                // than imported from JS.
+                AuxImport::String(_)
+                | AuxImport::Closure { .. }
+                | AuxImport::WrapInExportedClass(_)
+                | AuxImport::Intrinsic(_)
+                | AuxImport::LinkTo(..)
+                | AuxImport::UnwrapExportedClass(_) => continue,
+            };
+
+            // The receiver of instance operations is passed as the first
+            // argument.
+            let has_receiver = matches!(
+                import,
+                AuxImport::Value(AuxValue::Getter(..) | AuxValue::Setter(..))
+                    | AuxImport::StructuralMethod(_)
+                    | AuxImport::StructuralGetter(_)
+                    | AuxImport::StructuralSetter(_)
+                    | AuxImport::IndexingGetterOfObject
+                    | AuxImport::IndexingSetterOfObject
+                    | AuxImport::IndexingDeleterOfObject
+            ) || matches!(js_kind, AdapterJsImportKind::Method);
+            let params = &adapter.params[has_receiver as usize..];
+
+            let shim = implementing.get(id);
+            imports.push(Import {
+                module: js.and_then(|js| js_module(bindgen, &js.name)),
+                namespace,
+                name,
+                kind,
+                args: params.iter().map(type_name).collect(),
+                ret: adapter.results.first().map(type_name),
+                catch: shim.is_some_and(|shim| aux.imports_with_catch.contains(shim)),
+                suspending: shim.is_some_and(|shim| aux.imports_with_suspending.contains(shim)),
+                variadic: aux.imports_with_variadic.contains(id),
+                location: shim.and_then(location),
+            });
+        }
+
+        let mut enums = aux
+            .enums
+            .values()
+            .map(|e| Enum {
+                name: e.name.clone(),
+                comments: e.comments.clone(),
+                variants: e
+                    .variants
+                    .iter()
+                    .map(|(name, value, comments)| EnumVariant {
+                        name: name.clone(),
+                        value: *value,
+                        comments: comments.clone(),
+                    })
+                    .collect(),
+                location: e.location.clone(),
+            })
+            .collect::<Vec<_>>();
+        enums.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut string_enums = aux
+            .string_enums
+            .values()
+            .map(|e| StringEnum {
+                name: e.name.clone(),
+                comments: e.comments.clone(),
+                values: e.variant_values.clone(),
+                location: e.location.clone(),
+            })
+            .collect::<Vec<_>>();
+        string_enums.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let structs = aux
+            .structs
+            .iter()
+            .map(|s| Struct {
+                name: s.name.clone(),
+                comments: s.comments.clone(),
+                location: s.location.clone(),
+            })
+            .collect();
+
+        Manifest {
+            exports,
+            imports,
+            enums,
+            string_enums,
+            structs,
+        }
+    }
+}
+
+/// The full path of the value `js` accesses, e.g. `["console", "log"]`.
+fn js_path(js: &JsImport) -> Vec<String> {
+    let name = match &js.name {
+        JsImportName::Global { name }
+        | JsImportName::Module { name, .. }
+        | JsImportName::LocalModule { name, .. }
+        | JsImportName::InlineJs { name, .. }
+        | JsImportName::VendorPrefixed { name, .. } => name,
+    };
+    let mut path = vec![name.clone()];
+    path.extend(js.fields.iter().cloned());
+    path
+}
+
+/// The module `name` is imported from, as it's imported by the generated JS.
+fn js_module(bindgen: &Bindgen, name: &JsImportName) -> Option<String> {
+    match name {
+        JsImportName::Module { module, .. } => Some(module.clone()),
+        JsImportName::LocalModule { module, .. } => Some(bindgen.local_module_name(module)),
+        JsImportName::InlineJs {
+            unique_crate_identifier,
+            snippet_idx_in_crate,
+            ..
+        } => Some(bindgen.inline_js_module_name(unique_crate_identifier, *snippet_idx_in_crate)),
+        JsImportName::Global { .. } | JsImportName::VendorPrefixed { .. } => None,
+    }
+}
+
+/// A Rust-like name for `ty`.
+fn type_name(ty: &AdapterType) -> String {
+    match ty {
+        AdapterType::S8 => "i8".to_string(),
+        AdapterType::S16 => "i16".to_string(),
+        AdapterType::S32 | AdapterType::I32 => "i32".to_string(),
+        AdapterType::S64 | AdapterType::I64 => "i64".to_string(),
+        AdapterType::S128 => "i128".to_string(),
+        AdapterType::U8 => "u8".to_string(),
+        AdapterType::U16 => "u16".to_string(),
+        AdapterType::U32 => "u32".to_string(),
+        AdapterType::U64 => "u64".to_string(),
+        AdapterType::U128 => "u128".to_string(),
+        AdapterType::F32 => "f32".to_string(),
+        AdapterType::F64 => "f64".to_string(),
+        AdapterType::String => "String".to_string(),
+        AdapterType::Externref => "JsValue".to_string(),
+        AdapterType::Bool => "bool".to_string(),
+        AdapterType::Vector(kind) => {
+            let element = match kind {
+                VectorKind::I8 => "i8",
+                VectorKind::U8 => "u8",
+                VectorKind::ClampedU8 => return "Clamped<Vec<u8>>".to_string(),
+                VectorKind::I16 => "i16",
+                VectorKind::U16 => "u16",
+                VectorKind::I32 => "i32",
+                VectorKind::U32 => "u32",
+                VectorKind::I64 => "i64",
+                VectorKind::U64 => "u64",
+                VectorKind::F32 => "f32",
+                VectorKind::F64 => "f64",
+                VectorKind::String => "String",
+                VectorKind::Externref => "JsValue",
+                VectorKind::NamedExternref(name) => name.as_str(),
+            };
+            format!("Vec<{}>", element)
+        }
+        AdapterType::Option(ty) => format!("Option<{}>", type_name(ty)),
+        AdapterType::Struct(name)
+        | AdapterType::Enum(name)
+        | AdapterType::StringEnum(name)
+        | AdapterType::NamedExternref(name) => name.clone(),
+        AdapterType::Function => "Function".to_string(),
+        AdapterType::NonNull => "NonNull".to_string(),
+    }
+}
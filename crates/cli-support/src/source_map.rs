@@ -0,0 +1,259 @@
+//! Support for generating [source maps] which link generated JS back to the
+//! Rust definitions it was generated from.
+//!
+//! While JS is being generated, the code emitted for an item with a known Rust
+//! location is prefixed with a marker comment referencing that location. Once
+//! the final JS has been assembled the markers are stripped out again and the
+//! line each of them was found on is recorded in the source map.
+//!
+//! Items are mapped at line granularity: every mapping points at the first
+//! column of the generated line, which keeps the mappings valid when the
+//! output is re-indented later on.
+//!
+//! [source maps]: https://tc39.es/source-map/
+
+use std::collections::HashMap;
+use std::path::{Component, Path};
+
+const MARKER_PREFIX: &str = "/*__wbindgen_source_location:";
+const MARKER_SUFFIX: &str = "*/";
+
+/// Collects Rust source locations and produces the markers referencing them.
+#[derive(Default)]
+pub struct SourceLocations {
+    locations: Vec<String>,
+}
+
+impl SourceLocations {
+    /// Returns a marker to be inserted into generated code at the start of the
+    /// definition of an item located at `location` (`file:line:column`).
+    pub fn marker(&mut self, location: &str) -> String {
+        self.locations.push(location.to_string());
+        format!(
+            "{}{}{}",
+            MARKER_PREFIX,
+            self.locations.len() - 1,
+            MARKER_SUFFIX
+        )
+    }
+
+    /// Removes all markers from `code` and returns the resulting code along
+    /// with a source map for it, named `file`.
+    pub fn strip_markers(&self, code: &str, file: &str) -> (String, SourceMap) {
+        let mut map = SourceMap {
+            file: file.to_string(),
+            ..Default::default()
+        };
+        let mut stripped = String::with_capacity(code.len());
+        for (line, mut rest) in code.split_inclusive('\n').enumerate() {
+            while let Some(start) = rest.find(MARKER_PREFIX) {
+                stripped.push_str(&rest[..start]);
+                rest = &rest[start + MARKER_PREFIX.len()..];
+                let end = rest.find(MARKER_SUFFIX).unwrap();
+                let idx: usize = rest[..end].parse().unwrap();
+                rest = &rest[end + MARKER_SUFFIX.len()..];
+                map.add(line, &self.locations[idx]);
+            }
+            stripped.push_str(rest);
+        }
+        (stripped, map)
+    }
+}
+
+/// A source map of generated code, see [`SourceLocations::strip_markers`].
+#[derive(Default)]
+pub struct SourceMap {
+    file: String,
+    sources: Vec<String>,
+    source_ids: HashMap<String, usize>,
+    /// Generated line, source index, source line and source column, all
+    /// zero-based.
+    mappings: Vec<(usize, usize, u32, u32)>,
+}
+
+impl SourceMap {
+    fn add(&mut self, generated_line: usize, location: &str) {
+        // Only the first item defined on a line is mapped.
+        if self.mappings.last().map(|m| m.0) == Some(generated_line) {
+            return;
+        }
+        // The file name may itself contain colons, so split from the end.
+        let mut parts = location.rsplitn(3, ':');
+        let column = parts.next().and_then(|c| c.parse::<u32>().ok());
+        let line = parts.next().and_then(|l| l.parse::<u32>().ok());
+        let (file, line, column) = match (parts.next(), line, column) {
+            (Some(file), Some(line), Some(column)) => (file, line, column),
+            _ => return,
+        };
+        let next_id = self.sources.len();
+        let source = *self
+            .source_ids
+            .entry(file.to_string())
+            .or_insert_with(|| next_id);
+        if source == next_id {
+            self.sources.push(file.to_string());
+        }
+        self.mappings.push((
+            generated_line,
+            source,
+            line.saturating_sub(1),
+            column.saturating_sub(1),
+        ));
+    }
+
+    /// Serializes the source map, with the paths of its sources made relative
+    /// to `map_dir`, the directory the source map is written to. Relative
+    /// paths of sources are relative to the directory of their crate, which is
+    /// assumed to be the current directory.
+    pub fn to_json(&self, map_dir: &Path) -> String {
+        #[derive(serde::Serialize)]
+        struct Json<'a> {
+            version: u32,
+            file: &'a str,
+            sources: Vec<String>,
+            names: &'a [String],
+            mappings: String,
+        }
+
+        let mut mappings = String::new();
+        let mut current_line = 0;
+        let (mut prev_source, mut prev_line, mut prev_column) = (0, 0, 0);
+        for &(generated_line, source, line, column) in self.mappings.iter() {
+            while current_line < generated_line {
+                mappings.push(';');
+                current_line += 1;
+            }
+            // Each segment starts at the first column of its line, followed by
+            // the source index, line and column relative to the previous
+            // segment.
+            vlq(&mut mappings, 0);
+            vlq(&mut mappings, source as i64 - prev_source as i64);
+            vlq(&mut mappings, i64::from(line) - i64::from(prev_line));
+            vlq(&mut mappings, i64::from(column) - i64::from(prev_column));
+            prev_source = source;
+            prev_line = line;
+            prev_column = column;
+        }
+
+        let cwd = std::env::current_dir().unwrap_or_default();
+        let map_dir = cwd.join(map_dir);
+        serde_json::to_string(&Json {
+            version: 3,
+            file: &self.file,
+            sources: self
+                .sources
+                .iter()
+                .map(|source| relative_path(&map_dir, &cwd.join(source)))
+                .collect(),
+            names: &[],
+            mappings,
+        })
+        .unwrap()
+    }
+}
+
+/// Returns the path of `path` relative to the directory `from`, using `/` as
+/// the separator as source maps contain URLs. Relative paths, and paths which
+/// can't be reached from `from` such as ones on a different drive, are
+/// returned as they are.
+fn relative_path(from: &Path, path: &Path) -> String {
+    let unchanged = || path.to_string_lossy().into_owned();
+    if !path.is_absolute() {
+        return unchanged();
+    }
+    let mut from = from.components().filter(|c| *c != Component::CurDir);
+    let mut path = path.components().peekable();
+    let mut parts = Vec::new();
+    let mut first = true;
+    loop {
+        match (from.next(), path.peek()) {
+            (Some(a), Some(b)) if a == *b => {
+                path.next();
+            }
+            // Paths not sharing a root can't be made relative.
+            (_, _) if first => return unchanged(),
+            (Some(_), _) => {
+                parts.push("..".to_string());
+                parts.extend(from.map(|_| "..".to_string()));
+                break;
+            }
+            (None, _) => break,
+        }
+        first = false;
+    }
+    parts.extend(path.map(|c| c.as_os_str().to_string_lossy().into_owned()));
+    parts.join("/")
+}
+
+/// Appends `value` to `dst` as a base64 VLQ.
+fn vlq(dst: &mut String, value: i64) {
+    const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut vlq = if value < 0 {
+        ((-value as u64) << 1) | 1
+    } else {
+        (value as u64) << 1
+    };
+    loop {
+        let mut digit = (vlq & 0b11111) as usize;
+        vlq >>= 5;
+        if vlq > 0 {
+            digit |= 0b100000;
+        }
+        dst.push(BASE64[digit] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+
+#[test]
+fn test_vlq() {
+    let encode = |value| {
+        let mut dst = String::new();
+        vlq(&mut dst, value);
+        dst
+    };
+    assert_eq!(encode(0), "A");
+    assert_eq!(encode(1), "C");
+    assert_eq!(encode(-1), "D");
+    assert_eq!(encode(15), "e");
+    assert_eq!(encode(16), "gB");
+    assert_eq!(encode(-17), "jB");
+    assert_eq!(encode(1000), "w+B");
+}
+
+#[test]
+fn test_strip_markers() {
+    let mut locations = SourceLocations::default();
+    let code = format!(
+        "let wasm;\nexport function foo{}(a) {{\n}}\n\nexport function bar{}() {{\n}}\n",
+        locations.marker("src/lib.rs:3:8"),
+        locations.marker("src/other.rs:10:12"),
+    );
+    let (code, map) = locations.strip_markers(&code, "out.js");
+    let map = map.to_json(Path::new("pkg"));
+    assert_eq!(
+        code,
+        "let wasm;\nexport function foo(a) {\n}\n\nexport function bar() {\n}\n"
+    );
+    assert_eq!(
+        map,
+        r#"{"version":3,"file":"out.js","sources":["../src/lib.rs","../src/other.rs"],"names":[],"mappings":";AAEO;;;ACOI"}"#
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn test_relative_path() {
+    let dir = Path::new("/project/pkg");
+    assert_eq!(
+        relative_path(dir, Path::new("/project/src/lib.rs")),
+        "../src/lib.rs"
+    );
+    assert_eq!(relative_path(dir, Path::new("/project/pkg/a.rs")), "a.rs");
+    assert_eq!(
+        relative_path(Path::new("/project/./pkg"), Path::new("/other/lib.rs")),
+        "../../other/lib.rs"
+    );
+    assert_eq!(relative_path(dir, Path::new("src/lib.rs")), "src/lib.rs");
+}
@@ -0,0 +1,324 @@
+//! Support for the JS String Builtins proposal.
+//!
+//! The string operations `js_sys::JsString` and `JsValue::is_string` are built
+//! on are intrinsics which are normally implemented by small JS shims. With
+//! `--js-string-builtins` this pass instead uses the builtins imported from the
+//! `wasm:js-string` module, which engines supporting the proposal provide when
+//! the module is compiled with `{ builtins: ['js-string'] }`. This allows them
+//! to be inlined into Wasm code rather than calling out to JS.
+//!
+//! The builtins trap for values other than primitive strings, like `String`
+//! objects cast with `unchecked_into`, and some of them differ from their
+//! `String.prototype` counterparts in edge cases. So each intrinsic is replaced
+//! with a function which checks its arguments with the `test` builtin and only
+//! uses the builtins for strings, falling back to the JS shim otherwise.
+//!
+//! The JS glue provides the same functions in its imports object as a
+//! polyfill, which is only used by engines not supporting the builtins.
+
+use crate::intrinsic::Intrinsic;
+use crate::wit::{AuxImport, NonstandardWitSection, WasmBindgenAux};
+use anyhow::Error;
+use std::collections::HashMap;
+use walrus::ir::{BinaryOp, ExtendedLoad, LoadKind, MemArg, UnaryOp};
+use walrus::{FunctionBuilder, FunctionId, InstrSeqBuilder, LocalId, Module, RefType, ValType};
+use wasm_bindgen_wasm_conventions as wasm_conventions;
+
+/// The module all builtins are imported from.
+pub const MODULE: &str = "wasm:js-string";
+
+/// Implementations of the builtins in JS, used by engines which don't support
+/// them.
+pub const POLYFILL: &str = "\
+{
+    test: x => typeof x === 'string',
+    length: s => s.length,
+    charCodeAt: (s, i) => s.charCodeAt(i),
+    concat: (a, b) => a + b,
+    substring: (s, start, end) => start > end ? '' : s.substring(start, end),
+    equals: (a, b) => a === b,
+    fromCharCode: c => String.fromCharCode(c),
+}";
+
+const EXTERNREF: ValType = ValType::Ref(RefType::Externref);
+
+/// Replaces all intrinsics with an equivalent builtin in `wasm:js-string` by
+/// functions using the builtin.
+///
+/// This must run after the externref pass, as the builtins take and return
+/// `externref`s rather than indices into the heap of JS values.
+pub fn run(module: &mut Module) -> Result<(), Error> {
+    let mut section = module
+        .customs
+        .delete_typed::<NonstandardWitSection>()
+        .expect("wit custom section should exist");
+    let aux = module
+        .customs
+        .delete_typed::<WasmBindgenAux>()
+        .expect("aux section should be present");
+
+    let mut builtins = Builtins::default();
+    let mut intrinsics = Vec::new();
+    section.implements.retain(|(import, func, adapter)| {
+        let intrinsic = match aux.import_map.get(adapter) {
+            Some(AuxImport::Intrinsic(intrinsic)) => intrinsic,
+            _ => return true,
+        };
+        match intrinsic {
+            // `test` behaves exactly like the intrinsic, so it's imported in
+            // its place. The adapter is no longer referenced once the import
+            // isn't implemented by it, so no JS is generated for it.
+            Intrinsic::IsString => {
+                let import = module.imports.get_mut(*import);
+                import.module = MODULE.to_string();
+                import.name = "test".to_string();
+                builtins.0.insert("test", *func);
+                false
+            }
+            Intrinsic::StringLength
+            | Intrinsic::StringCharCodeAt
+            | Intrinsic::StringConcat
+            | Intrinsic::StringSubstring
+            | Intrinsic::StringEquals
+            | Intrinsic::StringFromCharCodeArray => {
+                intrinsics.push((*func, intrinsic));
+                true
+            }
+            _ => true,
+        }
+    });
+    module.customs.add(*section);
+
+    let mut shims = HashMap::new();
+    for (import, intrinsic) in intrinsics {
+        let shim = match intrinsic {
+            Intrinsic::StringLength => {
+                let length = builtins.get(module, "length");
+                wrap(module, &mut builtins, import, &[0], |body, args| {
+                    body.local_get(args[0]).call(length);
+                })
+            }
+            Intrinsic::StringCharCodeAt => {
+                let length = builtins.get(module, "length");
+                let char_code_at = builtins.get(module, "charCodeAt");
+                wrap(module, &mut builtins, import, &[0], |body, args| {
+                    // The builtin traps for indexes out of bounds, for which
+                    // `charCodeAt()` returns `NaN`.
+                    body.local_get(args[1])
+                        .local_get(args[0])
+                        .call(length)
+                        .binop(BinaryOp::I32LtU)
+                        .if_else(
+                            Some(ValType::F64),
+                            |body| {
+                                body.local_get(args[0])
+                                    .local_get(args[1])
+                                    .call(char_code_at)
+                                    .unop(UnaryOp::F64ConvertUI32);
+                            },
+                            |body| {
+                                body.f64_const(f64::NAN);
+                            },
+                        );
+                })
+            }
+            Intrinsic::StringConcat => {
+                let concat = builtins.get(module, "concat");
+                wrap(module, &mut builtins, import, &[0, 1], |body, args| {
+                    body.local_get(args[0]).local_get(args[1]).call(concat);
+                })
+            }
+            Intrinsic::StringSubstring => {
+                let substring = builtins.get(module, "substring");
+                wrap(module, &mut builtins, import, &[0], |body, args| {
+                    // Unlike `substring()`, the builtin doesn't swap the
+                    // indexes if the start is after the end.
+                    let (s, start, end) = (args[0], args[1], args[2]);
+                    body.local_get(s)
+                        .local_get(start)
+                        .local_get(end)
+                        .local_get(start)
+                        .local_get(end)
+                        .binop(BinaryOp::I32LtU)
+                        .select(None)
+                        .local_get(end)
+                        .local_get(start)
+                        .local_get(start)
+                        .local_get(end)
+                        .binop(BinaryOp::I32LtU)
+                        .select(None)
+                        .call(substring);
+                })
+            }
+            Intrinsic::StringEquals => {
+                let equals = builtins.get(module, "equals");
+                wrap(module, &mut builtins, import, &[0, 1], |body, args| {
+                    body.local_get(args[0]).local_get(args[1]).call(equals);
+                })
+            }
+            Intrinsic::StringFromCharCodeArray => {
+                match from_char_code_array(module, &mut builtins, import)? {
+                    Some(shim) => shim,
+                    None => continue,
+                }
+            }
+            _ => unreachable!(),
+        };
+        shims.insert(import, shim);
+    }
+
+    crate::exceptions::rewrite_calls(module, &shims);
+    module.customs.add(*aux);
+    Ok(())
+}
+
+/// The builtins imported so far.
+#[derive(Default)]
+struct Builtins(HashMap<&'static str, FunctionId>);
+
+impl Builtins {
+    /// Returns the import of the builtin `name`, adding it if necessary.
+    fn get(&mut self, module: &mut Module, name: &'static str) -> FunctionId {
+        *self.0.entry(name).or_insert_with(|| {
+            let (params, results): (&[ValType], &[ValType]) = match name {
+                "test" | "length" => (&[EXTERNREF], &[ValType::I32]),
+                "charCodeAt" => (&[EXTERNREF, ValType::I32], &[ValType::I32]),
+                "concat" => (&[EXTERNREF, EXTERNREF], &[EXTERNREF]),
+                "substring" => (&[EXTERNREF, ValType::I32, ValType::I32], &[EXTERNREF]),
+                "equals" => (&[EXTERNREF, EXTERNREF], &[ValType::I32]),
+                "fromCharCode" => (&[ValType::I32], &[EXTERNREF]),
+                _ => unreachable!("unknown builtin `{}`", name),
+            };
+            let ty = module.types.add(params, results);
+            module.add_import_func(MODULE, name, ty).0
+        })
+    }
+}
+
+/// Generates a function with the same signature as `import` which runs the
+/// instructions of `builtin` if all arguments at the indexes `strings` are
+/// primitive strings, and calls `import` otherwise.
+fn wrap(
+    module: &mut Module,
+    builtins: &mut Builtins,
+    import: FunctionId,
+    strings: &[usize],
+    builtin: impl FnOnce(&mut InstrSeqBuilder<'_>, &[LocalId]),
+) -> FunctionId {
+    let test = builtins.get(module, "test");
+    let ty = module.funcs.get(import).ty();
+    let params = module.types.params(ty).to_vec();
+    let results = module.types.results(ty).to_vec();
+    let args = params
+        .iter()
+        .map(|ty| module.locals.add(*ty))
+        .collect::<Vec<_>>();
+
+    let mut builder = FunctionBuilder::new(&mut module.types, &params, &results);
+    let mut body = builder.func_body();
+    for (i, arg) in strings.iter().enumerate() {
+        body.local_get(args[*arg]).call(test);
+        if i > 0 {
+            body.binop(BinaryOp::I32And);
+        }
+    }
+    body.if_else(
+        results.first().copied(),
+        |body| builtin(body, &args),
+        |body| {
+            for arg in args.iter() {
+                body.local_get(*arg);
+            }
+            body.call(import);
+        },
+    );
+
+    let id = builder.finish(args, &mut module.funcs);
+    let name = module.funcs.get(import).name.clone().unwrap_or_default();
+    module.funcs.get_mut(id).name = Some(format!("{} string builtins", name));
+    id
+}
+
+/// Generates a function which creates a string from the UTF-16 code units in
+/// linear memory with `fromCharCode` and `concat`.
+///
+/// `fromCharCodeArray` takes an array of the Wasm GC proposal, which can't be
+/// created here, so the string is built one code unit at a time instead.
+/// Returns `None` for 64-bit memories, where the JS shim is used instead.
+fn from_char_code_array(
+    module: &mut Module,
+    builtins: &mut Builtins,
+    import: FunctionId,
+) -> Result<Option<FunctionId>, Error> {
+    let ty = module.funcs.get(import).ty();
+    if module.types.params(ty) != [ValType::I32, ValType::I32] {
+        return Ok(None);
+    }
+    let memory = wasm_conventions::get_memory(module)?;
+    let from_char_code = builtins.get(module, "fromCharCode");
+    let concat = builtins.get(module, "concat");
+    let ptr = module.locals.add(ValType::I32);
+    let len = module.locals.add(ValType::I32);
+    let end = module.locals.add(ValType::I32);
+    let s = module.locals.add(EXTERNREF);
+
+    let load = |body: &mut InstrSeqBuilder<'_>| {
+        body.local_get(ptr)
+            .load(
+                memory,
+                LoadKind::I32_16 {
+                    kind: ExtendedLoad::ZeroExtend,
+                },
+                MemArg {
+                    align: 2,
+                    offset: 0,
+                },
+            )
+            .call(from_char_code);
+    };
+
+    let mut builder = FunctionBuilder::new(&mut module.types, &[ValType::I32; 2], &[EXTERNREF]);
+    let mut body = builder.func_body();
+    body.local_get(len).unop(UnaryOp::I32Eqz).if_else(
+        Some(EXTERNREF),
+        // There's no builtin returning an empty string.
+        |body| {
+            body.local_get(ptr).local_get(len).call(import);
+        },
+        |body| {
+            // end = ptr + 2 * len
+            body.local_get(ptr)
+                .local_get(len)
+                .i32_const(1)
+                .binop(BinaryOp::I32Shl)
+                .binop(BinaryOp::I32Add)
+                .local_set(end);
+            load(body);
+            body.local_set(s);
+            body.loop_(None, |loop_| {
+                let loop_id = loop_.id();
+                loop_
+                    .local_get(ptr)
+                    .i32_const(2)
+                    .binop(BinaryOp::I32Add)
+                    .local_tee(ptr)
+                    .local_get(end)
+                    .binop(BinaryOp::I32LtU)
+                    .if_else(
+                        None,
+                        |body| {
+                            body.local_get(s);
+                            load(body);
+                            body.call(concat).local_set(s).br(loop_id);
+                        },
+                        |_| {},
+                    );
+            });
+            body.local_get(s);
+        },
+    );
+
+    let id = builder.finish(vec![ptr, len], &mut module.funcs);
+    module.funcs.get_mut(id).name = Some("__wbindgen_string_from_char_code_array".to_string());
+    Ok(Some(id))
+}
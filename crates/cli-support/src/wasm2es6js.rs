@@ -1,5 +1,6 @@
 use anyhow::{bail, Error};
 use base64::{prelude::BASE64_STANDARD, Engine as _};
+use flate2::{write::GzEncoder, Compression};
 use std::collections::HashSet;
 use std::fmt::Write;
 use walrus::Module;
@@ -46,6 +47,46 @@ impl Config {
     }
 }
 
+/// Generates JS declaring `bytes` as a `Uint8Array`-like view of `wasm`, which
+/// is embedded as a base64 string.
+pub(crate) fn base64_bytes(wasm: &[u8]) -> String {
+    base64_decoder(&BASE64_STANDARD.encode(wasm))
+}
+
+/// Like `base64_bytes`, but for an already encoded module.
+pub(crate) fn base64_decoder(base64: &str) -> String {
+    format!(
+        "
+        let bytes;
+        const base64 = \"{base64}\";
+        if (typeof Buffer === 'undefined') {{
+            bytes = Uint8Array.from(atob(base64), c => c.charCodeAt(0));
+        }} else {{
+            bytes = Buffer.from(base64, 'base64');
+        }}
+        "
+    )
+}
+
+/// Returns `wasm`, gzip-compressed if requested, as a base64 string.
+pub(crate) fn base64(wasm: &[u8], compress: bool) -> String {
+    if compress {
+        BASE64_STANDARD.encode(gzip(wasm))
+    } else {
+        BASE64_STANDARD.encode(wasm)
+    }
+}
+
+/// Compresses `wasm` with gzip, suitable for decompression with a
+/// `DecompressionStream('gzip')`.
+pub(crate) fn gzip(wasm: &[u8]) -> Vec<u8> {
+    use std::io::Write as _;
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(wasm).unwrap();
+    encoder.finish().unwrap()
+}
+
 // Function to ensure we always append a valid typescript parameter name based
 // on parameter index
 fn push_index_identifier(i: usize, s: &mut String) {
@@ -89,6 +130,7 @@ fn module_export_types(module: &Module, mut export: impl FnMut(&str, &str)) {
             }
             walrus::ExportItem::Memory(_) => export(&entry.name, "WebAssembly.Memory"),
             walrus::ExportItem::Table(_) => export(&entry.name, "WebAssembly.Table"),
+            walrus::ExportItem::Tag(_) => export(&entry.name, "WebAssembly.Tag"),
             walrus::ExportItem::Global(_) => continue,
         };
     }
@@ -228,21 +270,7 @@ impl Output {
         );
         let wasm = self.module.emit_wasm();
         let (bytes, booted) = if self.base64 {
-            (
-                format!(
-                    "
-                    let bytes;
-                    const base64 = \"{base64}\";
-                    if (typeof Buffer === 'undefined') {{
-                        bytes = Uint8Array.from(atob(base64), c => c.charCodeAt(0));
-                    }} else {{
-                        bytes = Buffer.from(base64, 'base64');
-                    }}
-                    ",
-                    base64 = BASE64_STANDARD.encode(&wasm)
-                ),
-                inst,
-            )
+            (base64_bytes(&wasm), inst)
         } else if let Some(ref path) = self.fetch_path {
             (
                 String::new(),
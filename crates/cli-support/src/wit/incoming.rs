@@ -78,7 +78,7 @@ impl InstructionBuilder<'_, '_> {
                     Instruction::I32FromExternrefRustOwned {
                         class: class.clone(),
                     },
-                    &[AdapterType::I32],
+                    &[self.cx.ptr_ty()],
                 );
             }
             Descriptor::I8 => self.number_i32(AdapterType::S8),
@@ -140,7 +140,7 @@ impl InstructionBuilder<'_, '_> {
                         realloc: self.cx.realloc(),
                         mem: self.cx.memory()?,
                     },
-                    &[AdapterType::I32, AdapterType::I32],
+                    &[self.cx.ptr_ty(), self.cx.ptr_ty()],
                 );
             }
 
@@ -155,7 +155,7 @@ impl InstructionBuilder<'_, '_> {
                         malloc: self.cx.malloc()?,
                         mem: self.cx.memory()?,
                     },
-                    &[AdapterType::I32, AdapterType::I32],
+                    &[self.cx.ptr_ty(), self.cx.ptr_ty()],
                 );
             }
 
@@ -179,7 +179,7 @@ impl InstructionBuilder<'_, '_> {
             Descriptor::NonNull => self.instruction(
                 &[AdapterType::NonNull],
                 Instruction::I32FromNonNull,
-                &[AdapterType::I32],
+                &[self.cx.ptr_ty()],
             ),
         }
         Ok(())
@@ -193,7 +193,7 @@ impl InstructionBuilder<'_, '_> {
                     Instruction::I32FromExternrefRustBorrow {
                         class: class.clone(),
                     },
-                    &[AdapterType::I32],
+                    &[self.cx.ptr_ty()],
                 );
             }
             Descriptor::Externref => {
@@ -219,7 +219,7 @@ impl InstructionBuilder<'_, '_> {
                         realloc: self.cx.realloc(),
                         mem: self.cx.memory()?,
                     },
-                    &[AdapterType::I32, AdapterType::I32],
+                    &[self.cx.ptr_ty(), self.cx.ptr_ty()],
                 );
             }
             Descriptor::Slice(_) => {
@@ -239,7 +239,7 @@ impl InstructionBuilder<'_, '_> {
                             malloc: self.cx.malloc()?,
                             mem: self.cx.memory()?,
                         },
-                        &[AdapterType::I32, AdapterType::I32, AdapterType::Externref],
+                        &[self.cx.ptr_ty(), self.cx.ptr_ty(), AdapterType::Externref],
                     );
                     self.late_instruction(
                         &[AdapterType::Externref],
@@ -254,7 +254,7 @@ impl InstructionBuilder<'_, '_> {
                             malloc: self.cx.malloc()?,
                             mem: self.cx.memory()?,
                         },
-                        &[AdapterType::I32, AdapterType::I32],
+                        &[self.cx.ptr_ty(), self.cx.ptr_ty()],
                     );
                 }
             }
@@ -351,7 +351,7 @@ impl InstructionBuilder<'_, '_> {
                     Instruction::I32FromOptionRust {
                         class: name.to_string(),
                     },
-                    &[AdapterType::I32],
+                    &[self.cx.ptr_ty()],
                 );
             }
 
@@ -366,7 +366,7 @@ impl InstructionBuilder<'_, '_> {
                         mem,
                         realloc,
                     },
-                    &[AdapterType::I32, AdapterType::I32],
+                    &[self.cx.ptr_ty(), self.cx.ptr_ty()],
                 );
             }
 
@@ -382,14 +382,14 @@ impl InstructionBuilder<'_, '_> {
                 self.instruction(
                     &[AdapterType::Vector(kind.clone()).option()],
                     Instruction::OptionVector { kind, malloc, mem },
-                    &[AdapterType::I32, AdapterType::I32],
+                    &[self.cx.ptr_ty(), self.cx.ptr_ty()],
                 );
             }
 
             Descriptor::NonNull => self.instruction(
                 &[AdapterType::NonNull.option()],
                 Instruction::I32FromOptionNonNull,
-                &[AdapterType::I32],
+                &[self.cx.ptr_ty()],
             ),
 
             _ => bail!(
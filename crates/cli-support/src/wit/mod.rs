@@ -3,8 +3,9 @@ use crate::descriptor::{Descriptor, Function};
 use crate::descriptors::WasmBindgenDescriptorsSection;
 use crate::intrinsic::Intrinsic;
 use crate::{decode, Bindgen, PLACEHOLDER_MODULE};
-use anyhow::{anyhow, bail, Error};
+use anyhow::{anyhow, bail, Context as _, Error};
 use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
 use std::str;
 use walrus::MemoryId;
 use walrus::{ExportId, FunctionId, ImportId, Module};
@@ -32,10 +33,14 @@ struct Context<'a> {
     memory: Option<MemoryId>,
     vendor_prefixes: HashMap<String, Vec<String>>,
     unique_crate_identifier: &'a str,
+    /// The directory of the crate of the current program, relative to the
+    /// directory the paths of its source locations are relative to.
+    source_root: String,
     descriptors: HashMap<String, Descriptor>,
     externref_enabled: bool,
     thread_count: Option<ThreadCount>,
     support_start: bool,
+    snapshot_start: bool,
     linked_modules: bool,
 }
 
@@ -62,12 +67,19 @@ pub fn process(
         vendor_prefixes: Default::default(),
         descriptors: Default::default(),
         unique_crate_identifier: "",
-        memory: wasm_bindgen_wasm_conventions::get_memory(module).ok(),
+        source_root: String::new(),
+        // With multiple memories one of them must have been chosen.
+        memory: if module.memories.iter().count() > 1 {
+            Some(wasm_bindgen_wasm_conventions::get_memory(module)?)
+        } else {
+            wasm_bindgen_wasm_conventions::get_memory(module).ok()
+        },
         module,
         start_found: false,
         externref_enabled: bindgen.externref,
         thread_count,
         support_start: bindgen.emit_start,
+        snapshot_start: bindgen.snapshot_start,
         linked_modules: bindgen.split_linked_modules,
     };
     cx.init()?;
@@ -92,6 +104,9 @@ pub fn process(
 impl<'a> Context<'a> {
     fn init(&mut self) -> Result<(), Error> {
         self.aux.stack_pointer = wasm_bindgen_wasm_conventions::get_stack_pointer(self.module);
+        self.aux.memory64 = self
+            .memory
+            .is_some_and(|id| self.module.memories.get(id).memory64);
 
         // Make a map from string name to ids of all exports
         for export in self.module.exports.iter() {
@@ -188,7 +203,11 @@ impl<'a> Context<'a> {
             for (id, descriptor) in crate::sorted_iter(&closure_imports) {
                 let signature = Function {
                     shim_idx: 0,
-                    arguments: vec![Descriptor::I32; 3],
+                    arguments: vec![
+                        self.ptr_descriptor(),
+                        self.ptr_descriptor(),
+                        Descriptor::I32,
+                    ],
                     ret: Descriptor::Externref,
                     inner_ret: None,
                 };
@@ -197,8 +216,8 @@ impl<'a> Context<'a> {
                 // aren't present in the signature but are present in the wasm
                 // signature.
                 let mut function = descriptor.function.clone();
-                function.arguments.insert(0, Descriptor::I32);
-                function.arguments.insert(0, Descriptor::I32);
+                function.arguments.insert(0, self.ptr_descriptor());
+                function.arguments.insert(0, self.ptr_descriptor());
                 let adapter = self.table_element_adapter(descriptor.shim_idx, function)?;
                 self.aux.import_map.insert(
                     id,
@@ -250,6 +269,13 @@ impl<'a> Context<'a> {
 
     // Discover a function `main(i32, i32) -> i32` and, if it exists, make that function run at module start.
     fn discover_main(&mut self) -> Result<(), Error> {
+        // `argv` is an `i64` with 64-bit memories.
+        let (argv_ty, argv_null) = if self.aux.memory64 {
+            (walrus::ValType::I64, walrus::ir::Value::I64(0))
+        } else {
+            (walrus::ValType::I32, walrus::ir::Value::I32(0))
+        };
+
         // find a `main(i32, i32) -> i32`
         let main_id = self
             .module
@@ -266,7 +292,7 @@ impl<'a> Context<'a> {
                 let name_matches = export.name == "main";
                 // type has to be `(i32, i32) -> i32`
                 let ty = self.module.types.get(func.ty());
-                let type_matches = ty.params() == [I32, I32] && ty.results() == [I32];
+                let type_matches = ty.params() == [I32, argv_ty] && ty.results() == [I32];
                 // Having the correct name and signature doesn't necessarily mean that it's
                 // actually a `main` function. Unfortunately, there doesn't seem to be any 100%
                 // reliable way to make sure that it is, but we can at least rule out any
@@ -286,7 +312,7 @@ impl<'a> Context<'a> {
         wrapper
             .func_body()
             .i32_const(0)
-            .i32_const(0)
+            .const_(argv_null)
             .call(main_id)
             .drop()
             .return_();
@@ -339,6 +365,27 @@ impl<'a> Context<'a> {
         Ok(())
     }
 
+    /// Makes the file of a `file:line:column` source location of the current
+    /// program relative to the directory of its crate, so that it no longer
+    /// depends on the directory the Rust code was compiled from.
+    fn source_location(&self, location: &str) -> String {
+        if location.is_empty() || self.source_root.is_empty() || Path::new(location).is_absolute() {
+            return location.to_string();
+        }
+        let location = location.replace('\\', "/");
+        let root = self.source_root.replace('\\', "/");
+        let root = root.trim_end_matches('/');
+        match location.strip_prefix(&format!("{}/", root)) {
+            Some(location) => location.to_string(),
+            // Files outside of the crate directory are reached from it through
+            // the compiler's working directory.
+            None => {
+                let depth = root.split('/').filter(|c| !c.is_empty()).count();
+                format!("{}{}", "../".repeat(depth), location)
+            }
+        }
+    }
+
     fn link_module(
         &mut self,
         id: ImportId,
@@ -380,6 +427,7 @@ impl<'a> Context<'a> {
 
     fn program(&mut self, program: decode::Program<'a>) -> Result<(), Error> {
         self.unique_crate_identifier = program.unique_crate_identifier;
+        self.source_root = program.source_root.to_string();
         let decode::Program {
             exports,
             enums,
@@ -391,6 +439,7 @@ impl<'a> Context<'a> {
             unique_crate_identifier,
             package_json,
             linked_modules,
+            source_root: _,
         } = program;
 
         for module in local_modules
@@ -494,7 +543,7 @@ impl<'a> Context<'a> {
                     decode::MethodKind::Operation(op) => {
                         if !op.is_static {
                             // Make the first argument be the index of the receiver.
-                            descriptor.arguments.insert(0, Descriptor::I32);
+                            descriptor.arguments.insert(0, self.ptr_descriptor());
                         }
 
                         let (name, kind) = match op.kind {
@@ -534,6 +583,16 @@ impl<'a> Context<'a> {
                 .collect::<Vec<_>>(),
         );
         let id = self.export_adapter(export_id, descriptor)?;
+        self.aux
+            .source_locations
+            .insert(id, self.source_location(&export.function.location));
+        if let Some(split) = export.split {
+            self.aux
+                .split_exports
+                .entry(split.to_string())
+                .or_default()
+                .push(wasm_name.clone());
+        }
         self.aux.export_map.insert(
             id,
             AuxExport {
@@ -547,6 +606,8 @@ impl<'a> Context<'a> {
                 variadic: export.function.variadic,
                 fn_ret_ty_override: export.function.ret_ty_override.map(String::from),
                 fn_ret_desc: export.function.ret_desc.map(String::from),
+                split: export.split.map(String::from),
+                promising: export.promising,
             },
         );
         Ok(())
@@ -563,6 +624,14 @@ impl<'a> Context<'a> {
             return Ok(());
         }
 
+        if self.snapshot_start {
+            if self.thread_count.is_some() {
+                bail!("snapshotting the start function isn't supported with threads");
+            }
+            return wasm_bindgen_wasm_interpreter::snapshot(self.module, id)
+                .context("failed to snapshot the start function");
+        }
+
         if let Some(thread_count) = self.thread_count {
             let builder = wasm_bindgen_wasm_conventions::get_or_insert_start_builder(self.module);
             thread_count.wrap_start(builder, id);
@@ -599,6 +668,7 @@ impl<'a> Context<'a> {
         let decode::ImportFunction {
             shim,
             catch,
+            suspending,
             variadic,
             method,
             structural,
@@ -676,9 +746,15 @@ impl<'a> Context<'a> {
                 self.find_exn_store();
             }
         }
+        if *suspending {
+            self.aux.imports_with_suspending.insert(adapter);
+        }
         if *assert_no_shim {
             self.aux.imports_with_assert_no_shim.insert(adapter);
         }
+        self.aux
+            .source_locations
+            .insert(adapter, self.source_location(&function.location));
 
         self.aux.import_map.insert(id, import);
         Ok(())
@@ -892,6 +968,7 @@ impl<'a> Context<'a> {
                 .map(|v| v.to_string())
                 .collect(),
             generate_typescript: string_enum.generate_typescript,
+            location: self.source_location(&string_enum.location),
         };
         let mut result = Ok(());
         self.aux
@@ -926,6 +1003,7 @@ impl<'a> Context<'a> {
                 })
                 .collect(),
             generate_typescript: enum_.generate_typescript,
+            location: self.source_location(&enum_.location),
         };
         let mut result = Ok(());
         self.aux
@@ -950,12 +1028,15 @@ impl<'a> Context<'a> {
             // Register a webidl transformation for the getter
             let (getter_id, _) = self.function_exports[&getter];
             let getter_descriptor = Function {
-                arguments: vec![Descriptor::I32],
+                arguments: vec![self.ptr_descriptor()],
                 shim_idx: 0,
                 ret: descriptor.clone(),
                 inner_ret: Some(descriptor.clone()),
             };
             let getter_id = self.export_adapter(getter_id, getter_descriptor)?;
+            self.aux
+                .source_locations
+                .insert(getter_id, self.source_location(&field.location));
             self.aux.export_map.insert(
                 getter_id,
                 AuxExport {
@@ -974,6 +1055,8 @@ impl<'a> Context<'a> {
                     variadic: false,
                     fn_ret_ty_override: None,
                     fn_ret_desc: None,
+                    split: None,
+                    promising: false,
                 },
             );
 
@@ -984,12 +1067,15 @@ impl<'a> Context<'a> {
 
             let (setter_id, _) = self.function_exports[&setter];
             let setter_descriptor = Function {
-                arguments: vec![Descriptor::I32, descriptor],
+                arguments: vec![self.ptr_descriptor(), descriptor],
                 shim_idx: 0,
                 ret: Descriptor::Unit,
                 inner_ret: None,
             };
             let setter_id = self.export_adapter(setter_id, setter_descriptor)?;
+            self.aux
+                .source_locations
+                .insert(setter_id, self.source_location(&field.location));
             self.aux.export_map.insert(
                 setter_id,
                 AuxExport {
@@ -1008,6 +1094,8 @@ impl<'a> Context<'a> {
                     variadic: false,
                     fn_ret_ty_override: None,
                     fn_ret_desc: None,
+                    split: None,
+                    promising: false,
                 },
             );
         }
@@ -1016,13 +1104,14 @@ impl<'a> Context<'a> {
             comments: concatenate_comments(&struct_.comments),
             is_inspectable: struct_.is_inspectable,
             generate_typescript: struct_.generate_typescript,
+            location: self.source_location(&struct_.location),
         };
         self.aux.structs.push(aux);
 
         let wrap_constructor = wasm_bindgen_shared::new_function(struct_.name);
         self.add_aux_import_to_import_map(
             &wrap_constructor,
-            vec![Descriptor::I32],
+            vec![self.ptr_descriptor()],
             Descriptor::Externref,
             AuxImport::WrapInExportedClass(struct_.name.to_string()),
         )?;
@@ -1031,7 +1120,7 @@ impl<'a> Context<'a> {
         self.add_aux_import_to_import_map(
             &unwrap_fn,
             vec![Descriptor::Externref],
-            Descriptor::I32,
+            self.ptr_descriptor(),
             AuxImport::UnwrapExportedClass(struct_.name.to_string()),
         )?;
 
@@ -1242,7 +1331,8 @@ impl<'a> Context<'a> {
         // usage of closures going out to the import.
         let mut args = ret.cx.instruction_builder(false);
         if uses_retptr {
-            args.input.push(AdapterType::I32);
+            let ptr_ty = args.cx.ptr_ty();
+            args.input.push(ptr_ty);
         }
         for arg in signature.arguments.iter() {
             args.outgoing(arg)?;
@@ -1495,6 +1585,26 @@ impl<'a> Context<'a> {
             .ok_or_else(|| anyhow!("failed to find memory declaration in module"))
     }
 
+    /// The adapter type of pointers and lengths, which are `i64` with a 64-bit
+    /// memory.
+    fn ptr_ty(&self) -> AdapterType {
+        if self.aux.memory64 {
+            AdapterType::I64
+        } else {
+            AdapterType::I32
+        }
+    }
+
+    /// The descriptor of pointers which JS merely passes back to Wasm, like the
+    /// data of closures and the receivers of methods.
+    fn ptr_descriptor(&self) -> Descriptor {
+        if self.aux.memory64 {
+            Descriptor::I64
+        } else {
+            Descriptor::I32
+        }
+    }
+
     /// Removes the export item for all `__wbindgen` intrinsics which are
     /// generally only purely internal helpers.
     ///
@@ -1584,6 +1694,7 @@ pub fn extract_programs<'a>(
         program_storage.push(raw.data);
     }
 
+    let memory64 = module.memories.iter().any(|m| m.memory64);
     let mut ret = Vec::new();
     for program in program_storage.iter() {
         let mut payload = &program[..];
@@ -1603,9 +1714,29 @@ pub fn extract_programs<'a>(
             // can just delete this entirely. The `wasm-pack` project already
             // manages versions for us, so we in theory should need this check
             // less and less over time.
-            if let Some(their_version) = verify_schema_matches(data)? {
-                bail!(
-                    "
+            let mismatch = verify_schema_matches(data)?;
+            let next = get_remaining(&mut payload).unwrap();
+            log::debug!("found a program of length {}", next.len());
+            let (their_schema_version, their_version) = match mismatch {
+                Some(mismatch) => mismatch,
+                None => {
+                    ret.push(<decode::Program as decode::Decode>::decode_all(next));
+                    continue;
+                }
+            };
+            if let Some(program) =
+                decode::compat::decode_program(their_schema_version, next, memory64)
+            {
+                log::debug!(
+                    "upgrading a program from schema version {}",
+                    their_schema_version
+                );
+                ret.push(program?);
+                continue;
+            }
+            let supported = decode::compat::SUPPORTED_SCHEMA_VERSIONS.join(", ");
+            bail!(
+                "
 
 it looks like the Rust project used to create this Wasm file was linked against
 version of wasm-bindgen that uses a different bindgen format than this binary:
@@ -1614,8 +1745,9 @@ version of wasm-bindgen that uses a different bindgen format than this binary:
      this binary schema version: {my_version}
 
 Currently the bindgen format is unstable enough that these two schema versions
-must exactly match. You can accomplish this by either updating this binary or
-the wasm-bindgen dependency in the Rust project.
+must either exactly match or the Rust Wasm file must use one of the older schema
+versions this binary can still read ({supported}). You can accomplish this by
+either updating this binary or the wasm-bindgen dependency in the Rust project.
 
 You should be able to update the wasm-bindgen dependency with:
 
@@ -1629,11 +1761,7 @@ binary with:
 if this warning fails to go away though and you're not sure what to do feel free
 to open an issue at https://github.com/rustwasm/wasm-bindgen/issues!
 "
-                );
-            }
-            let next = get_remaining(&mut payload).unwrap();
-            log::debug!("found a program of length {}", next.len());
-            ret.push(<decode::Program as decode::Decode>::decode_all(next));
+            );
         }
     }
     Ok(ret)
@@ -1649,7 +1777,9 @@ fn get_remaining<'a>(data: &mut &'a [u8]) -> Option<&'a [u8]> {
     Some(a)
 }
 
-fn verify_schema_matches(data: &[u8]) -> Result<Option<&str>, Error> {
+/// Returns the schema version and the wasm-bindgen version of the program if
+/// its schema version differs from ours.
+fn verify_schema_matches(data: &[u8]) -> Result<Option<(&str, &str)>, Error> {
     macro_rules! bad {
         () => {
             bail!("failed to decode what looked like wasm-bindgen data")
@@ -1684,7 +1814,7 @@ fn verify_schema_matches(data: &[u8]) -> Result<Option<&str>, Error> {
         Some(i) => &rest[..i],
         None => bad!(),
     };
-    Ok(Some(their_version))
+    Ok(Some((their_schema_version, their_version)))
 }
 
 fn concatenate_comments(comments: &[&str]) -> String {
@@ -1733,3 +1863,32 @@ fn test_struct_packer() {
     assert_eq!(read_ty(i32___), 4); // u32, already aligned
     assert_eq!(read_ty(double), 6); // f64, NOT already aligned, skips up to offset 6
 }
+
+#[test]
+fn test_unsupported_schema_version() {
+    let mut data = Vec::new();
+    let version = br#"{"schema_version":"0.2.50","version":"0.2.50"}"#;
+    data.extend((version.len() as u32).to_le_bytes());
+    data.extend(version);
+    data.extend(0u32.to_le_bytes());
+    let mut module = Module::default();
+    module.customs.add(walrus::RawCustomSection {
+        name: "__wasm_bindgen_unstable".to_string(),
+        data,
+    });
+
+    let mut storage = Vec::new();
+    let err = extract_programs(&mut module, &mut storage)
+        .err()
+        .unwrap()
+        .to_string();
+    assert!(err.contains("rust Wasm file schema version: 0.2.50"));
+    assert!(err.contains(&format!(
+        "this binary schema version: {}",
+        wasm_bindgen_shared::version()
+    )));
+    assert!(err.contains(&format!(
+        "versions this binary can still read ({})",
+        decode::compat::SUPPORTED_SCHEMA_VERSIONS.join(", ")
+    )));
+}
@@ -1,7 +1,7 @@
 use crate::intrinsic::Intrinsic;
 use crate::wit::AdapterId;
 use std::borrow::Cow;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::PathBuf;
 use walrus::TypedCustomSectionId;
 
@@ -36,9 +36,24 @@ pub struct WasmBindgenAux {
 
     /// Small bits of metadata about imports.
     pub imports_with_catch: HashSet<AdapterId>,
+    /// Whether the Wasm module catches the JS exceptions of the
+    /// `imports_with_catch` itself, in which case their JS shims don't.
+    pub wasm_catches_exceptions: bool,
     pub imports_with_variadic: HashSet<AdapterId>,
+    /// Imports wrapped in `WebAssembly.Suspending`, which suspend Wasm until
+    /// the promise they return settles.
+    pub imports_with_suspending: HashSet<AdapterId>,
     pub imports_with_assert_no_shim: HashSet<AdapterId>,
 
+    /// The `file:line:column` location in Rust code of exported functions,
+    /// exported struct fields and the shims of imported functions, used to
+    /// generate source maps.
+    pub source_locations: HashMap<AdapterId, String>,
+
+    /// A map from the name of each lazily loaded Wasm module to the names of
+    /// the Wasm exports split into it.
+    pub split_exports: BTreeMap<String, Vec<String>>,
+
     /// Auxiliary information to go into JS/TypeScript bindings describing the
     /// exported enums from Rust.
     pub enums: HashMap<String, AuxEnum>,
@@ -62,6 +77,10 @@ pub struct WasmBindgenAux {
     pub exn_store: Option<walrus::FunctionId>,
     pub stack_pointer: Option<walrus::GlobalId>,
     pub thread_destroy: Option<walrus::FunctionId>,
+
+    /// Whether the main memory is a 64-bit memory, in which case pointers and
+    /// lengths are passed to and from Wasm as `i64`s (`BigInt`s in JS).
+    pub memory64: bool,
 }
 
 pub type WasmBindgenAuxId = TypedCustomSectionId<WasmBindgenAux>;
@@ -90,6 +109,10 @@ pub struct AuxExport {
     pub fn_ret_ty_override: Option<String>,
     /// Function's return description
     pub fn_ret_desc: Option<String>,
+    /// The name of the lazily loaded Wasm module this function was split into.
+    pub split: Option<String>,
+    /// Whether this function is called through `WebAssembly.promising`.
+    pub promising: bool,
 }
 
 /// Information about a functions' argument
@@ -188,6 +211,8 @@ pub struct AuxEnum {
     pub variants: Vec<(String, i64, String)>,
     /// Whether typescript bindings should be generated for this enum.
     pub generate_typescript: bool,
+    /// The `file:line:column` location of this enum in Rust code
+    pub location: String,
 }
 
 #[derive(Debug)]
@@ -200,6 +225,8 @@ pub struct AuxStringEnum {
     pub variant_values: Vec<String>,
     /// Whether typescript bindings should be generated for this enum.
     pub generate_typescript: bool,
+    /// The `file:line:column` location of this enum in Rust code
+    pub location: String,
 }
 
 #[derive(Debug)]
@@ -212,6 +239,8 @@ pub struct AuxStruct {
     pub is_inspectable: bool,
     /// Whether typescript bindings should be generated for this struct.
     pub generate_typescript: bool,
+    /// The `file:line:column` location of this struct in Rust code
+    pub location: String,
 }
 
 /// All possible types of imports that can be imported by a Wasm module.
@@ -100,7 +100,7 @@ impl InstructionBuilder<'_, '_> {
 
             Descriptor::RustStruct(class) => {
                 self.instruction(
-                    &[AdapterType::I32],
+                    &[self.cx.ptr_ty()],
                     Instruction::RustFromI32 {
                         class: class.to_string(),
                     },
@@ -114,8 +114,8 @@ impl InstructionBuilder<'_, '_> {
 
             Descriptor::String => {
                 // fetch the ptr/length ...
-                self.get(AdapterType::I32);
-                self.get(AdapterType::I32);
+                self.get(self.cx.ptr_ty());
+                self.get(self.cx.ptr_ty());
 
                 // ... then defer a call to `free` to happen later
                 let free = self.cx.free()?;
@@ -148,7 +148,7 @@ impl InstructionBuilder<'_, '_> {
                 let mem = self.cx.memory()?;
                 let free = self.cx.free()?;
                 self.instruction(
-                    &[AdapterType::I32, AdapterType::I32],
+                    &[self.cx.ptr_ty(), self.cx.ptr_ty()],
                     Instruction::VectorLoad {
                         kind: kind.clone(),
                         mem,
@@ -172,7 +172,11 @@ impl InstructionBuilder<'_, '_> {
             // Largely synthetic and can't show up
             Descriptor::ClampedU8 => unreachable!(),
 
-            Descriptor::NonNull => self.outgoing_i32(AdapterType::NonNull),
+            Descriptor::NonNull => self.instruction(
+                &[self.cx.ptr_ty()],
+                Instruction::NonNullFromI32,
+                &[AdapterType::NonNull],
+            ),
         }
         Ok(())
     }
@@ -197,7 +201,7 @@ impl InstructionBuilder<'_, '_> {
 
             Descriptor::String => {
                 self.instruction(
-                    &[AdapterType::I32, AdapterType::I32],
+                    &[self.cx.ptr_ty(), self.cx.ptr_ty()],
                     Instruction::MemoryToString(self.cx.memory()?),
                     &[AdapterType::String],
                 );
@@ -211,7 +215,7 @@ impl InstructionBuilder<'_, '_> {
                 })?;
                 let mem = self.cx.memory()?;
                 self.instruction(
-                    &[AdapterType::I32, AdapterType::I32],
+                    &[self.cx.ptr_ty(), self.cx.ptr_ty()],
                     Instruction::View {
                         kind: kind.clone(),
                         mem,
@@ -225,13 +229,13 @@ impl InstructionBuilder<'_, '_> {
                 // signature from wasm-bindgen but are present in the Wasm file.
                 let mut descriptor = (**descriptor).clone();
                 let nargs = descriptor.arguments.len();
-                descriptor.arguments.insert(0, Descriptor::I32);
-                descriptor.arguments.insert(0, Descriptor::I32);
+                descriptor.arguments.insert(0, self.cx.ptr_descriptor());
+                descriptor.arguments.insert(0, self.cx.ptr_descriptor());
                 let adapter = self
                     .cx
                     .table_element_adapter(descriptor.shim_idx, descriptor)?;
                 self.instruction(
-                    &[AdapterType::I32, AdapterType::I32],
+                    &[self.cx.ptr_ty(), self.cx.ptr_ty()],
                     Instruction::StackClosure {
                         adapter,
                         nargs,
@@ -325,7 +329,7 @@ impl InstructionBuilder<'_, '_> {
             }
             Descriptor::RustStruct(name) => {
                 self.instruction(
-                    &[AdapterType::I32],
+                    &[self.cx.ptr_ty()],
                     Instruction::OptionRustFromI32 {
                         class: name.to_string(),
                     },
@@ -347,7 +351,7 @@ impl InstructionBuilder<'_, '_> {
                 let mem = self.cx.memory()?;
                 let free = self.cx.free()?;
                 self.instruction(
-                    &[AdapterType::I32, AdapterType::I32],
+                    &[self.cx.ptr_ty(), self.cx.ptr_ty()],
                     Instruction::OptionVectorLoad {
                         kind: kind.clone(),
                         mem,
@@ -358,7 +362,7 @@ impl InstructionBuilder<'_, '_> {
             }
 
             Descriptor::NonNull => self.instruction(
-                &[AdapterType::I32],
+                &[self.cx.ptr_ty()],
                 Instruction::OptionNonNullFromI32,
                 &[AdapterType::NonNull.option()],
             ),
@@ -451,8 +455,8 @@ impl InstructionBuilder<'_, '_> {
             }
             Descriptor::String => {
                 // fetch the ptr/length ...
-                self.get(AdapterType::I32);
-                self.get(AdapterType::I32);
+                self.get(self.cx.ptr_ty());
+                self.get(self.cx.ptr_ty());
                 // fetch the err/is_err
                 self.get(AdapterType::I32);
                 self.get(AdapterType::I32);
@@ -535,7 +539,7 @@ impl InstructionBuilder<'_, '_> {
                 })?;
                 let mem = self.cx.memory()?;
                 self.instruction(
-                    &[AdapterType::I32, AdapterType::I32],
+                    &[self.cx.ptr_ty(), self.cx.ptr_ty()],
                     Instruction::OptionView {
                         kind: kind.clone(),
                         mem,
@@ -563,7 +567,7 @@ impl InstructionBuilder<'_, '_> {
 
     fn outgoing_i32(&mut self, output: AdapterType) {
         let instr = Instruction::WasmToInt32 {
-            unsigned_32: output == AdapterType::U32 || output == AdapterType::NonNull,
+            unsigned_32: output == AdapterType::U32,
         };
         self.instruction(&[AdapterType::I32], instr, &[output]);
     }
@@ -578,7 +582,7 @@ impl InstructionBuilder<'_, '_> {
         let mem = self.cx.memory()?;
         let free = self.cx.free()?;
         self.instruction(
-            &[AdapterType::I32, AdapterType::I32],
+            &[self.cx.ptr_ty(), self.cx.ptr_ty()],
             Instruction::CachedStringLoad {
                 owned,
                 mem,
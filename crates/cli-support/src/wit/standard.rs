@@ -364,6 +364,7 @@ pub enum Instruction {
     I32FromOptionNonNull,
     OptionNonNullFromI32,
     I32FromNonNull,
+    NonNullFromI32,
 }
 
 impl AdapterType {
@@ -0,0 +1,459 @@
+//! Generation of a [WIT] world describing the exports of a module, for
+//! consumers based on the component model.
+//!
+//! Only exports which can be expressed without JS are translated: free
+//! functions, plain structs (which become records), structs with constructors
+//! or methods (which become resources) and enums. Everything else, like
+//! functions taking `JsValue`s or `async` functions, is listed in a comment
+//! instead.
+//!
+//! The module itself keeps the ABI of the generated JS bindings rather than
+//! the canonical ABI of the component model. For a world which `component`
+//! wraps the module into, only the free functions it supports are translated.
+//!
+//! [WIT]: https://component-model.bytecodealliance.org/design/wit.html
+
+use crate::descriptor::VectorKind;
+use crate::wit::{
+    AdapterId, AdapterType, AuxExport, AuxExportKind, AuxExportedMethodKind, AuxReceiverKind,
+    NonstandardWitSection, WasmBindgenAux,
+};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+
+/// A WIT document with a single world.
+pub struct World {
+    /// The name of the world and its package.
+    pub name: String,
+    pub wit: String,
+    /// The adapters of the free functions in the world, with their WIT names.
+    pub functions: Vec<(AdapterId, String)>,
+}
+
+/// Returns the WIT document describing the exports in `aux`, with a world and
+/// package named after `stem`.
+///
+/// If `component` is set, the world only contains the functions which
+/// `component::wrap` supports.
+pub fn generate(
+    stem: &str,
+    aux: &WasmBindgenAux,
+    adapters: &NonstandardWitSection,
+    component: bool,
+) -> World {
+    // Structs with only fields are records, all others are resources.
+    let mut with_methods = HashSet::new();
+    let mut with_fields = HashSet::new();
+    for export in aux.export_map.values() {
+        match &export.kind {
+            AuxExportKind::Constructor(class)
+            | AuxExportKind::Method {
+                class,
+                kind: AuxExportedMethodKind::Method,
+                ..
+            } => {
+                with_methods.insert(class.as_str());
+            }
+            AuxExportKind::Method {
+                class,
+                kind: AuxExportedMethodKind::Getter,
+                ..
+            } => {
+                with_fields.insert(class.as_str());
+            }
+            _ => {}
+        }
+    }
+    let (records, resources): (HashSet<_>, HashSet<_>) = aux
+        .structs
+        .iter()
+        .map(|s| s.name.as_str())
+        .partition(|name| with_fields.contains(name) && !with_methods.contains(name));
+    let mut skipped = Vec::new();
+    let types = if component {
+        let structs = aux.structs.iter().map(|s| (&s.name, "structs"));
+        let enums = aux.enums.keys().chain(aux.string_enums.keys());
+        let mut unsupported = structs
+            .chain(enums.map(|name| (name, "enums")))
+            .collect::<Vec<_>>();
+        unsupported.sort();
+        for (name, kind) in unsupported {
+            skipped.push(format!(
+                "`{}`: {} are not supported in components yet",
+                name, kind
+            ));
+        }
+        Types {
+            component,
+            ..Types::default()
+        }
+    } else {
+        Types {
+            component,
+            resources,
+            records,
+            enums: aux
+                .enums
+                .keys()
+                .chain(aux.string_enums.keys())
+                .map(|s| s.as_str())
+                .collect(),
+        }
+    };
+
+    let mut functions = String::new();
+    let mut function_adapters = Vec::new();
+    let mut resource_items = BTreeMap::<&str, String>::new();
+    let mut record_fields = BTreeMap::<&str, String>::new();
+    for (id, export) in crate::sorted_iter(&aux.export_map) {
+        let adapter = match adapters.adapters.get(id) {
+            Some(adapter) => adapter,
+            None => continue,
+        };
+        // The structs themselves are already listed as unsupported.
+        if component && !matches!(export.kind, AuxExportKind::Function(_)) {
+            continue;
+        }
+        // Fields are only exposed on records.
+        if let AuxExportKind::Method { class, kind, .. } = &export.kind {
+            match kind {
+                AuxExportedMethodKind::Getter if types.records.contains(class.as_str()) => {}
+                AuxExportedMethodKind::Getter | AuxExportedMethodKind::Setter => continue,
+                AuxExportedMethodKind::Method => {}
+            }
+        }
+        let receiver = matches!(
+            export.kind,
+            AuxExportKind::Method {
+                receiver: AuxReceiverKind::Borrowed | AuxReceiverKind::Owned,
+                ..
+            }
+        );
+        let params = &adapter.params[receiver as usize..];
+        let results = &adapter.inner_results;
+
+        let signature = signature(&types, export, params, results).and_then(|signature| {
+            if component {
+                crate::component::check(adapter)?;
+            }
+            Ok(signature)
+        });
+        let signature = match signature {
+            Ok(signature) => signature,
+            Err(reason) => {
+                skipped.push(format!("`{}`: {}", export.debug_name, reason));
+                continue;
+            }
+        };
+        match &export.kind {
+            AuxExportKind::Function(name) => {
+                writeln!(functions, "  {}: func{};", ident(name), signature).unwrap();
+                function_adapters.push((*id, ident(name)));
+            }
+            AuxExportKind::Constructor(class) => {
+                let items = resource_items.entry(class.as_str()).or_default();
+                // Constructors implicitly return the resource.
+                let params = signature.split(" -> ").next().unwrap();
+                writeln!(items, "    constructor{};", params).unwrap();
+            }
+            AuxExportKind::Method {
+                class,
+                name,
+                receiver,
+                kind,
+            } => match kind {
+                // Only getters of records are left at this point.
+                AuxExportedMethodKind::Getter | AuxExportedMethodKind::Setter => {
+                    let ty = signature.split(" -> ").nth(1).unwrap_or_default();
+                    let fields = record_fields.entry(class.as_str()).or_default();
+                    writeln!(fields, "    {}: {},", ident(name), ty).unwrap();
+                }
+                AuxExportedMethodKind::Method => {
+                    let kind = match receiver {
+                        AuxReceiverKind::None => "static func",
+                        AuxReceiverKind::Borrowed => "func",
+                        AuxReceiverKind::Owned => {
+                            skipped.push(format!(
+                                "`{}`: methods taking `self` by value are not supported",
+                                export.debug_name
+                            ));
+                            continue;
+                        }
+                    };
+                    let items = resource_items.entry(class.as_str()).or_default();
+                    writeln!(items, "    {}: {}{};", ident(name), kind, signature).unwrap();
+                }
+            },
+        }
+    }
+
+    let mut interface = String::new();
+    for (name, fields) in record_fields.iter() {
+        writeln!(interface, "  record {} {{\n{}  }}\n", ident(name), fields).unwrap();
+    }
+    for e in crate::sorted_iter(&aux.enums).map(|(_, e)| e) {
+        interface.push_str(&wit_enum(
+            &e.name,
+            e.variants.iter().map(|(name, _, _)| name.as_str()),
+        ));
+    }
+    for e in crate::sorted_iter(&aux.string_enums).map(|(_, e)| e) {
+        interface.push_str(&wit_enum(
+            &e.name,
+            e.variant_values.iter().map(|value| value.as_str()),
+        ));
+    }
+    let mut sorted_resources = types.resources.iter().collect::<Vec<_>>();
+    sorted_resources.sort();
+    for name in sorted_resources {
+        let items = resource_items.get(name).map(|s| s.as_str()).unwrap_or("");
+        writeln!(interface, "  resource {} {{\n{}  }}\n", ident(name), items).unwrap();
+    }
+    interface.push_str(&functions);
+
+    let name = ident(stem);
+    let header = if component {
+        format!("// The world implemented by the component `{stem}_component.wasm`.")
+    } else {
+        format!(
+            "\
+// Describes the exports of `{stem}_bg.wasm`, which keeps the ABI of the JS
+// bindings. `--wit-component` wraps the module into a component instead."
+        )
+    };
+    let mut wit = format!("{header}\n\npackage local:{name};\n\n");
+    if !skipped.is_empty() {
+        wit.push_str(if component {
+            "// The following exports are not part of the component:\n"
+        } else {
+            "// The following exports can't be expressed in WIT:\n"
+        });
+        for skipped in skipped {
+            writeln!(wit, "// - {}", skipped).unwrap();
+        }
+        wit.push('\n');
+    }
+    writeln!(
+        wit,
+        "interface exports {{\n{}}}\n\nworld {} {{\n  export exports;\n}}",
+        interface, name
+    )
+    .unwrap();
+    World {
+        name,
+        wit,
+        functions: function_adapters,
+    }
+}
+
+/// The type names of the module which can be used in WIT.
+#[derive(Default)]
+struct Types<'a> {
+    component: bool,
+    resources: HashSet<&'a str>,
+    records: HashSet<&'a str>,
+    enums: HashSet<&'a str>,
+}
+
+/// Returns the WIT signature of `export`, like `(a: u32) -> string`, or why
+/// it can't be expressed in WIT.
+fn signature(
+    types: &Types<'_>,
+    export: &AuxExport,
+    params: &[AdapterType],
+    results: &[AdapterType],
+) -> Result<String, String> {
+    if export.asyncness {
+        return Err("`async` functions are not supported".to_string());
+    }
+    if export.variadic {
+        return Err("variadic functions are not supported".to_string());
+    }
+    let mut signature = "(".to_string();
+    for (i, ty) in params.iter().enumerate() {
+        if i > 0 {
+            signature.push_str(", ");
+        }
+        let name = export
+            .args
+            .as_ref()
+            .and_then(|args| args.get(i))
+            .map(|arg| arg.name.clone())
+            .unwrap_or_else(|| format!("arg{}", i));
+        write!(signature, "{}: {}", ident(&name), ty_name(types, ty)?).unwrap();
+    }
+    signature.push(')');
+    if let Some(ty) = results.first() {
+        write!(signature, " -> {}", ty_name(types, ty)?).unwrap();
+    }
+    Ok(signature)
+}
+
+/// Returns the name of `ty` in WIT, or why it can't be expressed in WIT.
+fn ty_name(types: &Types<'_>, ty: &AdapterType) -> Result<String, String> {
+    Ok(match ty {
+        AdapterType::S8 => "s8".to_string(),
+        AdapterType::S16 => "s16".to_string(),
+        AdapterType::S32 => "s32".to_string(),
+        AdapterType::S64 => "s64".to_string(),
+        AdapterType::U8 => "u8".to_string(),
+        AdapterType::U16 => "u16".to_string(),
+        AdapterType::U32 => "u32".to_string(),
+        AdapterType::U64 => "u64".to_string(),
+        AdapterType::F32 => "f32".to_string(),
+        AdapterType::F64 => "f64".to_string(),
+        AdapterType::Bool => "bool".to_string(),
+        AdapterType::String => "string".to_string(),
+        AdapterType::Vector(kind) => {
+            let element = match kind {
+                VectorKind::I8 => "s8",
+                VectorKind::U8 | VectorKind::ClampedU8 => "u8",
+                VectorKind::I16 => "s16",
+                VectorKind::U16 => "u16",
+                VectorKind::I32 => "s32",
+                VectorKind::U32 => "u32",
+                VectorKind::I64 => "s64",
+                VectorKind::U64 => "u64",
+                VectorKind::F32 => "f32",
+                VectorKind::F64 => "f64",
+                VectorKind::String => "string",
+                VectorKind::Externref | VectorKind::NamedExternref(_) => {
+                    return Err("JS values are not supported".to_string())
+                }
+            };
+            format!("list<{}>", element)
+        }
+        AdapterType::Option(ty) => format!("option<{}>", ty_name(types, ty)?),
+        AdapterType::Struct(name)
+            if types.records.contains(name.as_str()) || types.resources.contains(name.as_str()) =>
+        {
+            ident(name)
+        }
+        AdapterType::Enum(name) | AdapterType::StringEnum(name)
+            if types.enums.contains(name.as_str()) =>
+        {
+            ident(name)
+        }
+        AdapterType::S128 | AdapterType::U128 => {
+            return Err("128-bit integers are not supported".to_string())
+        }
+        AdapterType::Externref | AdapterType::NamedExternref(_) | AdapterType::Function => {
+            return Err("JS values are not supported".to_string())
+        }
+        AdapterType::Struct(name) | AdapterType::Enum(name) | AdapterType::StringEnum(name)
+            if types.component =>
+        {
+            return Err(format!("`{}` is not supported in components yet", name))
+        }
+        AdapterType::Struct(name) | AdapterType::Enum(name) | AdapterType::StringEnum(name) => {
+            return Err(format!("`{}` is not exported", name))
+        }
+        AdapterType::I32 | AdapterType::I64 | AdapterType::NonNull => {
+            return Err("raw pointers are not supported".to_string())
+        }
+    })
+}
+
+fn wit_enum<'a>(name: &str, cases: impl Iterator<Item = &'a str>) -> String {
+    let mut wit = format!("  enum {} {{\n", ident(name));
+    for case in cases {
+        writeln!(wit, "    {},", ident(case)).unwrap();
+    }
+    wit.push_str("  }\n\n");
+    wit
+}
+
+/// Converts a Rust or JS identifier into a WIT identifier, which is
+/// kebab-case and escaped with `%` if it's a keyword.
+fn ident(name: &str) -> String {
+    let mut kebab = String::new();
+    let mut prev: Option<char> = None;
+    for c in name.chars() {
+        if !c.is_ascii_alphanumeric() {
+            if !kebab.is_empty() && !kebab.ends_with('-') {
+                kebab.push('-');
+            }
+        } else if c.is_ascii_uppercase() {
+            // An uppercase letter after a lowercase one starts a new word,
+            // while runs of uppercase letters like in `HTMLElement` don't.
+            if prev.is_some_and(|p| p.is_ascii_lowercase() || p.is_ascii_digit())
+                && !kebab.ends_with('-')
+            {
+                kebab.push('-');
+            }
+            kebab.push(c.to_ascii_lowercase());
+        } else {
+            // Words in WIT can't start with a digit.
+            if c.is_ascii_digit() && (kebab.is_empty() || kebab.ends_with('-')) {
+                kebab.push('x');
+            }
+            kebab.push(c);
+        }
+        prev = Some(c);
+    }
+    let kebab = kebab.trim_end_matches('-').to_string();
+    if KEYWORDS.contains(&kebab.as_str()) {
+        format!("%{}", kebab)
+    } else {
+        kebab
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "as",
+    "bool",
+    "borrow",
+    "char",
+    "constructor",
+    "enum",
+    "export",
+    "f32",
+    "f64",
+    "flags",
+    "func",
+    "future",
+    "import",
+    "include",
+    "interface",
+    "list",
+    "option",
+    "own",
+    "package",
+    "record",
+    "resource",
+    "result",
+    "s16",
+    "s32",
+    "s64",
+    "s8",
+    "static",
+    "stream",
+    "string",
+    "tuple",
+    "type",
+    "u16",
+    "u32",
+    "u64",
+    "u8",
+    "use",
+    "variant",
+    "with",
+    "world",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::ident;
+
+    #[test]
+    fn identifiers() {
+        assert_eq!(ident("add_one"), "add-one");
+        assert_eq!(ident("addOne"), "add-one");
+        assert_eq!(ident("HTMLElement"), "htmlelement");
+        assert_eq!(ident("MyStruct"), "my-struct");
+        assert_eq!(ident("vec2_len"), "vec2-len");
+        assert_eq!(ident("_private"), "private");
+        assert_eq!(ident("type"), "%type");
+        assert_eq!(ident("3d"), "x3d");
+    }
+}
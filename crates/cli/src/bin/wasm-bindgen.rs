@@ -1,8 +1,9 @@
-use anyhow::{bail, Error};
+use anyhow::{bail, Context, Error};
 use clap::Parser;
+use std::fs;
 use std::path::PathBuf;
 use std::process;
-use wasm_bindgen_cli_support::{Bindgen, EncodeInto};
+use wasm_bindgen_cli_support::{Bindgen, EncodeInto, InlineWasm};
 
 #[derive(Debug, Parser)]
 #[command(
@@ -68,7 +69,8 @@ struct Args {
         long,
         value_name = "TARGET",
         help = "What type of output to generate, valid\n\
-                values are [web, bundler, nodejs, no-modules, deno, experimental-nodejs-module],\n\
+                values are [web, bundler, nodejs, no-modules, deno, experimental-nodejs-module,\n\
+                module, dual, workerd, bun],\n\
                 and the default is [bundler]"
     )]
     target: Option<String>,
@@ -83,6 +85,78 @@ struct Args {
                 If a bundler is used, it needs to be set up accordingly."
     )]
     split_linked_modules: bool,
+    #[arg(
+        long,
+        help = "Emit a source map mapping the generated JavaScript to Rust code"
+    )]
+    source_map: bool,
+    #[arg(
+        long,
+        help = "Emit a declaration map mapping the TypeScript definitions to Rust code"
+    )]
+    declaration_map: bool,
+    #[arg(
+        long,
+        value_name = "MODE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "base64",
+        help = "Embed the WebAssembly module into the generated JavaScript, valid values\n\
+                are [base64, compressed], and the default is [base64].\n\
+                Only supported with `--target web` and `--target no-modules`."
+    )]
+    inline_wasm: Option<String>,
+    #[arg(
+        long,
+        help = "Import the string operations of `js_sys::JsString` from the `wasm:js-string`\n\
+                builtins, with a fallback for engines not supporting them.\n\
+                Only supported with `--target web` and `--target no-modules`."
+    )]
+    js_string_builtins: bool,
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "Name of the memory to use in the bindings if the module has multiple memories"
+    )]
+    memory: Option<String>,
+    #[arg(
+        long,
+        value_name = "BYTES",
+        help = "Default stack size of threads other than the main thread, a multiple of\n\
+                64KiB. Only used if the module uses threads, defaults to 2MiB."
+    )]
+    thread_stack_size: Option<u32>,
+    #[arg(
+        long,
+        help = "Print the layout of the memory of modules using threads: the stacks,\n\
+                the static data used to set up threads and the thread-local storage."
+    )]
+    print_memory_layout: bool,
+    #[arg(
+        long,
+        help = "Run the `#[wasm_bindgen(start)]` function at build time and store its effects\n\
+                in the Wasm module. Fails if the start function calls any import."
+    )]
+    snapshot_start: bool,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Write a JSON description of all exports and imports to FILE"
+    )]
+    emit_manifest: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Emit a WIT world describing the exports which can be expressed in the\n\
+                component model"
+    )]
+    wit: bool,
+    #[arg(
+        long,
+        help = "Also wrap the Wasm module into a component implementing the WIT world,\n\
+                which then only contains functions taking and returning numbers,\n\
+                `bool`s, strings and lists of numbers"
+    )]
+    wit_component: bool,
     input: PathBuf,
 }
 
@@ -110,6 +184,10 @@ fn rmain(args: &Args) -> Result<(), Error> {
             "nodejs" => b.nodejs(true)?,
             "deno" => b.deno(true)?,
             "experimental-nodejs-module" => b.nodejs_module(true)?,
+            "module" => b.module(true)?,
+            "dual" => b.dual(true)?,
+            "workerd" => b.workerd(true)?,
+            "bun" => b.bun(true)?,
             s => bail!("invalid encode-into mode: `{}`", s),
         };
     }
@@ -127,7 +205,13 @@ fn rmain(args: &Args) -> Result<(), Error> {
         .typescript(typescript)
         .omit_imports(args.omit_imports)
         .omit_default_module_path(args.omit_default_module_path)
-        .split_linked_modules(args.split_linked_modules);
+        .split_linked_modules(args.split_linked_modules)
+        .source_map(args.source_map)
+        .declaration_map(args.declaration_map)
+        .js_string_builtins(args.js_string_builtins)
+        .snapshot_start(args.snapshot_start)
+        .wit(args.wit)
+        .wit_component(args.wit_component);
     if args.reference_types {
         #[allow(deprecated)]
         b.reference_types(true);
@@ -138,6 +222,12 @@ fn rmain(args: &Args) -> Result<(), Error> {
     if let Some(ref name) = args.out_name {
         b.out_name(name);
     }
+    if let Some(ref name) = args.memory {
+        b.memory(name);
+    }
+    if let Some(size) = args.thread_stack_size {
+        b.thread_stack_size(size);
+    }
     if let Some(mode) = &args.encode_into {
         match mode.as_str() {
             "test" => b.encode_into(EncodeInto::Test),
@@ -146,11 +236,29 @@ fn rmain(args: &Args) -> Result<(), Error> {
             s => bail!("invalid encode-into mode: `{}`", s),
         };
     }
+    if let Some(mode) = &args.inline_wasm {
+        match mode.as_str() {
+            "base64" => b.inline_wasm(Some(InlineWasm::Base64)),
+            "compressed" => b.inline_wasm(Some(InlineWasm::Compressed)),
+            s => bail!("invalid inline-wasm mode: `{}`", s),
+        };
+    }
 
     let out_dir = match args.out_dir {
         Some(ref p) => p,
         None => bail!("the `--out-dir` argument is now required"),
     };
 
-    b.generate(out_dir)
+    let mut output = b.generate_output()?;
+    if args.print_memory_layout {
+        if let Some(layout) = output.thread_layout() {
+            println!("{}", layout);
+        }
+    }
+    if let Some(path) = &args.emit_manifest {
+        let manifest = serde_json::to_string_pretty(output.manifest())?;
+        fs::write(path, manifest)
+            .with_context(|| format!("failed to write `{}`", path.display()))?;
+    }
+    output.emit(out_dir)
 }
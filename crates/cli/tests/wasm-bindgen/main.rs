@@ -12,6 +12,7 @@
 //! large!
 
 use assert_cmd::prelude::*;
+use base64::{prelude::BASE64_STANDARD, Engine as _};
 use predicates::str;
 use std::env;
 use std::fs;
@@ -70,6 +71,12 @@ impl Project {
     }
 
     fn build(&mut self) -> PathBuf {
+        self.build_with(Command::new("cargo"), "wasm32-unknown-unknown")
+    }
+
+    /// Builds the project for `target` with `cargo`, which may already have
+    /// arguments such as a toolchain.
+    fn build_with(&mut self, mut cargo: Command, target: &str) -> PathBuf {
         if !self.root.join("Cargo.toml").is_file() {
             self.file(
                 "Cargo.toml",
@@ -96,17 +103,17 @@ impl Project {
         }
 
         let target_dir = target_dir();
-        Command::new("cargo")
+        cargo
             .current_dir(&self.root)
             .arg("build")
             .arg("--target")
-            .arg("wasm32-unknown-unknown")
+            .arg(target)
             .env("CARGO_TARGET_DIR", &target_dir)
             .assert()
             .success();
 
         target_dir
-            .join("wasm32-unknown-unknown")
+            .join(target)
             .join("debug")
             .join(self.name)
             .with_extension("wasm")
@@ -451,43 +458,1174 @@ fn omit_default_module_path_target_no_modules() {
 }
 
 #[test]
-fn function_table_preserved() {
-    let (mut cmd, _out_dir) = Project::new("function_table_preserved")
+fn source_map() {
+    let (mut cmd, out_dir) = Project::new("source_map")
         .file(
             "src/lib.rs",
             r#"
                 use wasm_bindgen::prelude::*;
+                #[wasm_bindgen]
+                pub fn foo() {}
+            "#,
+        )
+        .wasm_bindgen("--target web --source-map");
+    // Source map paths are resolved against the crate directory.
+    cmd.current_dir(out_dir.parent().unwrap());
+    cmd.assert().success();
+    let js = fs::read_to_string(out_dir.join("source_map.js")).unwrap();
+    assert!(js.ends_with("//# sourceMappingURL=source_map.js.map\n"));
+    assert!(!js.contains("__wbindgen_source_location"));
+    let map = fs::read_to_string(out_dir.join("source_map.js.map")).unwrap();
+    assert!(map.starts_with(r#"{"version":3,"file":"source_map.js","#));
+    assert!(map.contains(r#""sources":["../src/lib.rs"]"#));
+}
 
+#[test]
+fn declaration_map() {
+    let (mut cmd, out_dir) = Project::new("declaration_map")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
                 #[wasm_bindgen]
-                pub fn bar() {
-                    Closure::wrap(Box::new(|| {}) as Box<dyn Fn()>);
+                pub struct Foo {
+                    pub x: u32,
+                }
+                #[wasm_bindgen]
+                pub enum Bar {
+                    A,
                 }
+                #[wasm_bindgen]
+                pub fn foo() {}
             "#,
         )
-        .wasm_bindgen("");
+        .wasm_bindgen("--target web --declaration-map");
+    // Source map paths are resolved against the crate directory.
+    cmd.current_dir(out_dir.parent().unwrap());
     cmd.assert().success();
+    let ts = fs::read_to_string(out_dir.join("declaration_map.d.ts")).unwrap();
+    assert!(ts.ends_with("//# sourceMappingURL=declaration_map.d.ts.map\n"));
+    assert!(!ts.contains("__wbindgen_source_location"));
+    let map = fs::read_to_string(out_dir.join("declaration_map.d.ts.map")).unwrap();
+    assert!(map.starts_with(r#"{"version":3,"file":"declaration_map.d.ts","#));
+    assert!(map.contains(r#""sources":["../src/lib.rs"]"#));
 }
 
 #[test]
-fn constructor_cannot_return_option_struct() {
-    let (mut cmd, _out_dir) = Project::new("constructor_cannot_return_option_struct")
+fn inline_wasm() {
+    let (mut cmd, out_dir) = Project::new("inline_wasm")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+                #[wasm_bindgen]
+                pub fn foo() {}
+            "#,
+        )
+        .wasm_bindgen("--target web --inline-wasm");
+    cmd.assert().success();
+    assert!(!out_dir.join("inline_wasm_bg.wasm").exists());
+    let js = fs::read_to_string(out_dir.join("inline_wasm.js")).unwrap();
+    assert!(js.contains("function __wbg_inline_wasm()"));
+    assert!(!js.contains("inline_wasm_bg.wasm"));
+    let ts = fs::read_to_string(out_dir.join("inline_wasm.d.ts")).unwrap();
+    assert!(ts.contains("export function initSync(module?:"));
+}
+
+#[test]
+fn inline_wasm_matches_emitted_wasm() {
+    let mut project = Project::new("inline_wasm_matches_emitted_wasm");
+    project.file(
+        "src/lib.rs",
+        r#"
+            use wasm_bindgen::prelude::*;
+            #[wasm_bindgen]
+            pub fn foo(s: &str) -> String { s.to_string() }
+        "#,
+    );
+    let (mut cmd, out_dir) = project.wasm_bindgen("--target web");
+    cmd.assert().success();
+    let wasm = fs::read(out_dir.join("inline_wasm_matches_emitted_wasm_bg.wasm")).unwrap();
+
+    let (mut cmd, out_dir) = project.wasm_bindgen("--target web --inline-wasm");
+    cmd.assert().success();
+    let js = fs::read_to_string(out_dir.join("inline_wasm_matches_emitted_wasm.js")).unwrap();
+    let start = js.find("const base64 = \"").unwrap() + "const base64 = \"".len();
+    let end = start + js[start..].find('"').unwrap();
+    assert_eq!(BASE64_STANDARD.decode(&js[start..end]).unwrap(), wasm);
+}
+
+#[test]
+fn inline_wasm_rejects_split_exports() {
+    let (mut cmd, _out_dir) = Project::new("inline_wasm_rejects_split_exports")
         .file(
             "src/lib.rs",
             r#"
                 use wasm_bindgen::prelude::*;
+                #[wasm_bindgen(split = "editor")]
+                pub fn foo() {}
+            "#,
+        )
+        .wasm_bindgen("--target web --inline-wasm");
+    cmd.assert().failure().stderr(str::contains(
+        "`#[wasm_bindgen(split)]` is not supported with `--inline-wasm`",
+    ));
+}
 
+#[test]
+fn inline_wasm_requires_web_target() {
+    let (mut cmd, _out_dir) = Project::new("inline_wasm_requires_web_target")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
                 #[wasm_bindgen]
-                pub struct Foo(());
-                
+                pub fn foo() {}
+            "#,
+        )
+        .wasm_bindgen("--target bundler --inline-wasm=compressed");
+    cmd.assert().failure().stderr(str::contains(
+        "`--inline-wasm` is only supported with `--target web` and `--target no-modules`",
+    ));
+}
+
+#[test]
+fn module_target() {
+    let (mut cmd, out_dir) = Project::new("module_target")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
                 #[wasm_bindgen]
-                impl Foo {
-                    #[wasm_bindgen(constructor)]
-                    pub fn new() -> Option<Foo> {
-                        Some(Foo(()))
-                    }
-                }
+                pub fn foo() {}
+            "#,
+        )
+        .wasm_bindgen("--target module");
+    cmd.assert().success();
+    assert!(out_dir.join("module_target_bg.wasm").exists());
+    assert!(!out_dir.join("module_target_bg.js").exists());
+    let js = fs::read_to_string(out_dir.join("module_target.js")).unwrap();
+    assert!(js.contains("import source wasmModule from \"./module_target_bg.wasm\";"));
+    assert!(js.contains("new WebAssembly.Instance(wasmModule, imports)"));
+    assert!(js.contains("export function foo()"));
+}
+
+#[test]
+fn workerd_target() {
+    let (mut cmd, out_dir) = Project::new("workerd_target")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+                #[wasm_bindgen]
+                pub fn foo() {}
+            "#,
+        )
+        .wasm_bindgen("--target workerd");
+    cmd.assert().success();
+    let js = fs::read_to_string(out_dir.join("workerd_target.js")).unwrap();
+    assert!(js.contains("import wasmModule from \"./workerd_target_bg.wasm\";"));
+    assert!(js.contains("new WebAssembly.Instance(wasmModule, imports)"));
+    assert!(out_dir.join("workerd_target.d.ts").exists());
+}
+
+#[test]
+fn bun_target() {
+    let (mut cmd, out_dir) = Project::new("bun_target")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+                #[wasm_bindgen]
+                pub fn foo() {}
+            "#,
+        )
+        .wasm_bindgen("--target bun");
+    cmd.assert().success();
+    let js = fs::read_to_string(out_dir.join("bun_target.js")).unwrap();
+    assert!(js.contains("await Bun.file(new URL('bun_target_bg.wasm', import.meta.url))"));
+    assert!(out_dir.join("bun_target.d.ts").exists());
+}
+
+#[test]
+fn split_exports() {
+    let (mut cmd, out_dir) = Project::new("split_exports")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+                #[wasm_bindgen]
+                pub fn foo() -> u32 { 1 }
+                #[wasm_bindgen(split = "editor")]
+                pub fn bar(x: u32) -> u32 { x * 2 }
             "#,
         )
         .wasm_bindgen("--target web");
-    cmd.assert().failure();
+    cmd.assert().success();
+    assert!(out_dir.join("split_exports_bg_editor.wasm").exists());
+    let js = fs::read_to_string(out_dir.join("split_exports.js")).unwrap();
+    assert!(js.contains("function __wbg_load_split(name)"));
+    assert!(js.contains("__wbg_load_split('editor')"));
+    let ts = fs::read_to_string(out_dir.join("split_exports.d.ts")).unwrap();
+    assert!(ts.contains("export function bar(x: number): Promise<number>;"));
+    assert!(ts.contains("export function foo(): number;"));
+}
+
+#[test]
+fn split_exports_load_concurrently() {
+    let (mut cmd, out_dir) = Project::new("split_exports_load_concurrently")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+                #[wasm_bindgen]
+                pub fn foo() -> u32 { 1 }
+                #[wasm_bindgen(split = "double")]
+                pub fn double(x: u32) -> u32 { x * 2 }
+                #[wasm_bindgen(split = "add")]
+                pub fn add(x: u32) -> u32 { x + 3 }
+            "#,
+        )
+        .wasm_bindgen("--target web");
+    cmd.assert().success();
+    fs::write(out_dir.join("package.json"), r#"{ "type": "module" }"#).unwrap();
+    fs::write(
+        out_dir.join("test.js"),
+        r#"
+            import { readFileSync } from 'fs';
+            import { initSync, foo, double, add } from './split_exports_load_concurrently.js';
+
+            // Node.js can't fetch `file:` URLs.
+            globalThis.fetch = async url => new Response(readFileSync(url), {
+                headers: { 'Content-Type': 'application/wasm' },
+            });
+
+            const wasm = initSync({
+                module: readFileSync(new URL('split_exports_load_concurrently_bg.wasm', import.meta.url)),
+            });
+            const [a, b] = await Promise.all([double(2), add(2)]);
+            // Both splits are merged into the exports returned by `initSync`.
+            console.log(foo(), a, b, 'double' in wasm && 'add' in wasm, await double(a));
+        "#,
+    )
+    .unwrap();
+    Command::new("node")
+        .arg("test.js")
+        .current_dir(&out_dir)
+        .assert()
+        .success()
+        .stdout("1 4 5 true 8\n");
+}
+
+#[test]
+fn split_exports_requires_web_target() {
+    let (mut cmd, _out_dir) = Project::new("split_exports_requires_web_target")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+                #[wasm_bindgen(split = "editor")]
+                pub fn foo() {}
+            "#,
+        )
+        .wasm_bindgen("--target bundler");
+    cmd.assert().failure().stderr(str::contains(
+        "`#[wasm_bindgen(split)]` is only supported with `--target web` and `--target no-modules`",
+    ));
+}
+
+#[test]
+fn js_string_builtins() {
+    let (mut cmd, out_dir) = Project::new("js_string_builtins")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+                #[wasm_bindgen]
+                pub fn is_string(x: &JsValue) -> bool { x.is_string() }
+            "#,
+        )
+        .wasm_bindgen("--target web --js-string-builtins");
+    cmd.assert().success();
+    let js = fs::read_to_string(out_dir.join("js_string_builtins.js")).unwrap();
+    assert!(js.contains("imports['wasm:js-string'] = {"));
+    assert!(js.contains("WebAssembly.instantiate(bytes, imports, { builtins: ['js-string'] })"));
+    assert!(!js.contains("typeof(arg0) === 'string'"));
+}
+
+#[test]
+fn js_string_builtins_fall_back_for_string_objects() {
+    let mut project = Project::new("js_string_builtins_fall_back_for_string_objects");
+    project
+        .file(
+            "Cargo.toml",
+            &format!(
+                "
+                    [package]
+                    name = \"js_string_builtins_fall_back_for_string_objects\"
+                    authors = []
+                    version = \"1.0.0\"
+                    edition = '2021'
+
+                    [dependencies]
+                    js-sys = {{ path = '{0}/crates/js-sys' }}
+                    wasm-bindgen = {{ path = '{0}' }}
+
+                    [lib]
+                    crate-type = ['cdylib']
+
+                    [workspace]
+                ",
+                repo_root().display(),
+            ),
+        )
+        .file(
+            "src/lib.rs",
+            r#"
+                use js_sys::JsString;
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen]
+                pub fn describe(s: JsString) -> String {
+                    format!(
+                        "{} {} {} {} {}",
+                        s.length(),
+                        s.char_code_at(0),
+                        s.char_code_at(99),
+                        String::from(s.substring(3, 1)),
+                        s == JsString::from("abc"),
+                    )
+                }
+
+                #[wasm_bindgen]
+                pub fn hi() -> JsString {
+                    JsString::from_char_code(&[104, 105]).concat(&1.into())
+                }
+            "#,
+        );
+    let (mut cmd, out_dir) = project.wasm_bindgen("--target web --js-string-builtins");
+    cmd.assert().success();
+
+    // Primitive strings use the builtins, while `String` objects, which the
+    // builtins trap for, behave like they do without `--js-string-builtins`.
+    Command::new("node")
+        .arg("--input-type=module")
+        .arg("-e")
+        .arg(
+            "import { readFileSync } from 'node:fs';
+             import { initSync, describe, hi } from './js_string_builtins_fall_back_for_string_objects.js';
+             initSync({ module: readFileSync('./js_string_builtins_fall_back_for_string_objects_bg.wasm') });
+             console.log(describe('abc'));
+             console.log(describe(new String('abc')));
+             console.log(hi());",
+        )
+        .current_dir(&out_dir)
+        .assert()
+        .success()
+        .stdout("3 97 NaN bc true\n3 97 NaN bc false\nhi1\n");
+}
+
+#[test]
+fn js_string_builtins_requires_web_target() {
+    let (mut cmd, _out_dir) = Project::new("js_string_builtins_requires_web_target")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+                #[wasm_bindgen]
+                pub fn foo() {}
+            "#,
+        )
+        .wasm_bindgen("--target bundler --js-string-builtins");
+    cmd.assert().failure().stderr(str::contains(
+        "`--js-string-builtins` is only supported with `--target web` and `--target no-modules`",
+    ));
+}
+
+#[test]
+fn memory_by_name() {
+    let (mut cmd, out_dir) = Project::new("memory_by_name")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+                #[wasm_bindgen]
+                pub fn has_memory(name: &str) -> bool {
+                    wasm_bindgen::memory_by_name(name).is_some()
+                }
+            "#,
+        )
+        .wasm_bindgen("--target web --memory memory");
+    cmd.assert().success();
+    let js = fs::read_to_string(out_dir.join("memory_by_name.js")).unwrap();
+    assert!(js.contains("memory instanceof WebAssembly.Memory ? memory : undefined"));
+}
+
+#[test]
+fn bind_second_memory() {
+    let project = Project::new("bind_second_memory");
+    let wasm = project.root.join("bind_second_memory.wasm");
+    // `__wbindgen_throw` reads its message from the memory used for the
+    // bindings, which holds different strings at the same address in each
+    // memory. The third memory has no name.
+    let bytes = wat::parse_str(
+        r#"
+            (module
+                (import "__wbindgen_placeholder__" "__wbindgen_throw"
+                    (func $throw (param i32 i32)))
+                (memory $first 1)
+                (memory $second 1)
+                (memory 1)
+                (data (memory $first) (i32.const 8) "from first")
+                (data (memory $second) (i32.const 8) "from second")
+                (func (export "run")
+                    i32.const 8
+                    i32.const 11
+                    call $throw)
+            )
+        "#,
+    )
+    .unwrap();
+    fs::write(&wasm, bytes).unwrap();
+
+    let out_dir = project.root.join("pkg");
+    Command::cargo_bin("wasm-bindgen")
+        .unwrap()
+        .arg("--out-dir")
+        .arg(&out_dir)
+        .arg(&wasm)
+        .arg("--target")
+        .arg("web")
+        .arg("--memory")
+        .arg("second")
+        .assert()
+        .success();
+
+    Command::new("node")
+        .arg("--input-type=module")
+        .arg("-e")
+        .arg(
+            "import { readFileSync } from 'node:fs';
+             import { initSync } from './bind_second_memory.js';
+             const wasm = initSync({ module: readFileSync('./bind_second_memory_bg.wasm') });
+             try {
+                 wasm.run();
+             } catch (e) {
+                 console.log(e.message);
+             }
+             console.log(wasm.first instanceof WebAssembly.Memory);
+             console.log(wasm.memory2 instanceof WebAssembly.Memory);",
+        )
+        .current_dir(&out_dir)
+        .assert()
+        .success()
+        .stdout("from second\ntrue\ntrue\n");
+}
+
+#[test]
+fn print_memory_layout() {
+    let project = Project::new("print_memory_layout");
+    let wasm = project.root.join("print_memory_layout.wasm");
+    let bytes = wat::parse_str(
+        r#"
+            (module
+                (import "env" "memory" (memory 6 1024 shared))
+                (func (export "__wasm_init_tls") (param i32))
+                (func (export "__wbindgen_malloc") (param i32 i32) (result i32)
+                    i32.const 0)
+                (func (export "__wbindgen_free") (param i32 i32 i32))
+                (global $__stack_pointer (mut i32) (i32.const 65536))
+                (global (export "__heap_base") i32 (i32.const 327680))
+                (global (export "__tls_size") i32 (i32.const 128))
+                (global (export "__tls_align") i32 (i32.const 8))
+                (global (export "__tls_base") (mut i32) (i32.const 0))
+            )
+        "#,
+    )
+    .unwrap();
+    fs::write(&wasm, bytes).unwrap();
+
+    let wasm_bindgen = |args: &str| {
+        let mut cmd = Command::cargo_bin("wasm-bindgen").unwrap();
+        cmd.arg("--out-dir")
+            .arg(project.root.join("pkg"))
+            .arg(&wasm)
+            .args("--target web".split_whitespace())
+            .args(args.split_whitespace());
+        cmd
+    };
+
+    wasm_bindgen("").assert().success().stdout("");
+    wasm_bindgen("--print-memory-layout --thread-stack-size 262144")
+        .assert()
+        .success()
+        .stdout(
+            "main thread stack:    below 0x10000\n\
+             thread static data:   0x50000..0x60000\n\
+             thread stacks:        262144 bytes each, allocated on the heap\n\
+             thread-local storage: 128 bytes aligned to 8, allocated on the heap, \
+             base address in `__tls_base`\n",
+        );
+    wasm_bindgen("--thread-stack-size 1000")
+        .assert()
+        .failure()
+        .stderr(str::contains(
+            "the stack size of threads must be a nonzero multiple of the page size \
+             (65536 bytes), found 1000",
+        ));
+}
+
+#[test]
+fn missing_memory() {
+    let (mut cmd, _out_dir) = Project::new("missing_memory")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+                #[wasm_bindgen]
+                pub fn foo() {}
+            "#,
+        )
+        .wasm_bindgen("--target web --memory media");
+    cmd.assert()
+        .failure()
+        .stderr(str::contains("module does not have a memory named `media`"));
+}
+
+#[test]
+fn jspi() {
+    let (mut cmd, out_dir) = Project::new("jspi")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+                #[wasm_bindgen]
+                extern "C" {
+                    #[wasm_bindgen(suspending, js_namespace = cache)]
+                    fn load(key: &str) -> u32;
+                }
+                #[wasm_bindgen(promising)]
+                pub fn lookup(key: &str) -> u32 { load(key) }
+            "#,
+        )
+        .wasm_bindgen("--target web");
+    cmd.assert().success();
+    let js = fs::read_to_string(out_dir.join("jspi.js")).unwrap();
+    assert!(js.contains("function makeSuspending(f)"));
+    assert!(js.contains("= makeSuspending(function("));
+    assert!(js.contains("await makePromising(wasm.lookup)("));
+    assert!(js.contains("enterPromising();"));
+    assert!(js.contains("await cache.load("));
+    let ts = fs::read_to_string(out_dir.join("jspi.d.ts")).unwrap();
+    assert!(ts.contains("export function lookup(key: string): Promise<number>;"));
+}
+
+#[test]
+fn jspi_overlapping_calls() {
+    let (mut cmd, out_dir) = Project::new("jspi_overlapping_calls")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+                #[wasm_bindgen]
+                extern "C" {
+                    #[wasm_bindgen(suspending, js_namespace = cache)]
+                    fn load(key: &str) -> u32;
+                }
+                #[wasm_bindgen(promising)]
+                pub fn lookup(key: &str) -> u32 { load(key) }
+                #[wasm_bindgen]
+                pub fn add(a: u32, b: u32) -> u32 { a + b }
+            "#,
+        )
+        .wasm_bindgen("--target nodejs");
+    cmd.assert().success();
+
+    // A second `promising` call while the first one is suspended is rejected,
+    // while synchronous exports keep working and the first call completes.
+    Command::new("node")
+        .arg("--experimental-wasm-jspi")
+        .arg("-e")
+        .arg(
+            "globalThis.cache = {
+                 load: key => new Promise(resolve => setTimeout(() => resolve(key.length), 10)),
+             };
+             const { lookup, add } = require('./jspi_overlapping_calls.js');
+             (async () => {
+                 const first = lookup('abc');
+                 try {
+                     await lookup('de');
+                 } catch (e) {
+                     console.log(e.message.includes('another one is still suspended'));
+                 }
+                 console.log(add(1, 2));
+                 console.log(await first);
+                 console.log(await lookup('de'));
+             })();",
+        )
+        .current_dir(&out_dir)
+        .assert()
+        .success()
+        .stdout("true\n3\n3\n2\n");
+}
+
+#[test]
+fn emit_manifest() {
+    let mut project = Project::new("emit_manifest");
+    let (mut cmd, out_dir) = project
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen]
+                extern "C" {
+                    #[wasm_bindgen(js_namespace = console)]
+                    fn log(s: &str);
+                    #[wasm_bindgen(catch, js_namespace = JSON)]
+                    fn parse(s: &str) -> Result<JsValue, JsValue>;
+                }
+
+                /// Adds one.
+                #[wasm_bindgen]
+                pub fn add_one(x: u32) -> u32 {
+                    log("add_one");
+                    let _ = parse("1");
+                    x + 1
+                }
+
+                #[wasm_bindgen]
+                pub struct Counter {
+                    pub count: u32,
+                }
+
+                #[wasm_bindgen]
+                impl Counter {
+                    #[wasm_bindgen(constructor)]
+                    pub fn new() -> Counter {
+                        Counter { count: 0 }
+                    }
+
+                    pub fn increment(&mut self, by: u32) {
+                        self.count += by;
+                    }
+                }
+
+                #[wasm_bindgen]
+                pub enum Color {
+                    Red = 1,
+                    Green = 2,
+                }
+            "#,
+        )
+        .wasm_bindgen("");
+    let manifest_path = project.root.join("manifest.json");
+    cmd.arg("--emit-manifest").arg(&manifest_path);
+    cmd.assert().success();
+    assert!(!out_dir.join("manifest.json").exists());
+
+    let manifest: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&manifest_path).unwrap()).unwrap();
+    let exports = manifest["exports"].as_array().unwrap();
+    let add_one = exports.iter().find(|e| e["name"] == "add_one").unwrap();
+    assert_eq!(add_one["kind"], "function");
+    assert_eq!(add_one["args"][0]["name"], "x");
+    assert_eq!(add_one["args"][0]["ty"], "u32");
+    assert_eq!(add_one["ret"], "u32");
+    assert_eq!(add_one["async"], false);
+    assert!(add_one["comments"].as_str().unwrap().contains("Adds one."));
+    let increment = exports.iter().find(|e| e["name"] == "increment").unwrap();
+    assert_eq!(increment["kind"], "method");
+    assert_eq!(increment["class"], "Counter");
+    assert_eq!(increment["receiver"], "borrowed");
+    assert_eq!(increment["args"].as_array().unwrap().len(), 1);
+    let count = exports
+        .iter()
+        .find(|e| e["name"] == "count" && e["kind"] == "getter");
+    assert_eq!(count.unwrap()["ret"], "u32");
+
+    let imports = manifest["imports"].as_array().unwrap();
+    let log = imports.iter().find(|i| i["name"] == "log").unwrap();
+    assert_eq!(log["namespace"], serde_json::json!(["console"]));
+    assert_eq!(log["kind"], "function");
+    assert_eq!(log["args"], serde_json::json!(["String"]));
+    assert_eq!(log["catch"], false);
+    let parse = imports.iter().find(|i| i["name"] == "parse").unwrap();
+    assert_eq!(parse["catch"], true);
+
+    assert_eq!(manifest["enums"][0]["name"], "Color");
+    assert_eq!(manifest["enums"][0]["variants"][1]["value"], 2);
+    assert_eq!(manifest["structs"][0]["name"], "Counter");
+}
+
+#[test]
+fn wit_world() {
+    let (mut cmd, out_dir) = Project::new("wit_world")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen]
+                pub fn add_one(x: u32) -> u32 { x + 1 }
+
+                #[wasm_bindgen]
+                pub fn greet(name: &str) -> String { format!("Hello, {}!", name) }
+
+                #[wasm_bindgen]
+                pub fn log_value(_value: JsValue) {}
+
+                #[wasm_bindgen]
+                pub struct Point {
+                    pub x: f64,
+                    pub y: f64,
+                }
+
+                #[wasm_bindgen]
+                pub struct Counter {
+                    count: u32,
+                }
+
+                #[wasm_bindgen]
+                impl Counter {
+                    #[wasm_bindgen(constructor)]
+                    pub fn new() -> Counter {
+                        Counter { count: 0 }
+                    }
+
+                    pub fn increment(&mut self, by: u32) -> u32 {
+                        self.count += by;
+                        self.count
+                    }
+                }
+
+                #[wasm_bindgen]
+                pub enum Color {
+                    Red,
+                    DarkGreen,
+                }
+            "#,
+        )
+        .wasm_bindgen("--wit");
+    cmd.assert().success();
+    let wit = fs::read_to_string(out_dir.join("wit_world.wit")).unwrap();
+    assert!(wit.starts_with("// Describes the exports of `wit_world_bg.wasm`,"));
+    assert!(wit.contains("package local:wit-world;"));
+    assert!(wit.contains("world wit-world {\n  export exports;\n}"));
+    assert!(wit.contains("  add-one: func(x: u32) -> u32;"));
+    assert!(wit.contains("  greet: func(name: string) -> string;"));
+    assert!(wit.contains("  record point {\n    x: f64,\n    y: f64,\n  }"));
+    assert!(wit.contains("  enum color {\n    red,\n    dark-green,\n  }"));
+    assert!(wit.contains(
+        "  resource counter {\n    constructor();\n    increment: func(by: u32) -> u32;\n  }"
+    ));
+    assert!(wit.contains("// - `log_value`: JS values are not supported"));
+}
+
+#[test]
+fn wit_component() {
+    let (mut cmd, out_dir) = Project::new("wit_component")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen]
+                pub fn add_one(x: u32) -> u32 { x + 1 }
+
+                #[wasm_bindgen]
+                pub fn greet(name: &str) -> String { format!("Hello, {}!", name) }
+
+                #[wasm_bindgen]
+                pub fn sum(values: Vec<u32>) -> u32 { values.iter().sum() }
+
+                #[wasm_bindgen]
+                pub fn log_value(_value: JsValue) {}
+
+                #[wasm_bindgen]
+                pub fn maybe(x: Option<u32>) -> u32 { x.unwrap_or(0) }
+
+                #[wasm_bindgen]
+                pub struct Point {
+                    pub x: f64,
+                    pub y: f64,
+                }
+            "#,
+        )
+        .wasm_bindgen("--wit-component");
+    cmd.assert().success();
+    let wit = fs::read_to_string(out_dir.join("wit_component.wit")).unwrap();
+    assert!(wit
+        .starts_with("// The world implemented by the component `wit_component_component.wasm`."));
+    assert!(wit.contains("  add-one: func(x: u32) -> u32;"));
+    assert!(wit.contains("  greet: func(name: string) -> string;"));
+    assert!(wit.contains("  sum: func(values: list<u32>) -> u32;"));
+    assert!(wit.contains("// - `log_value`: JS values are not supported"));
+    assert!(wit.contains("// - `maybe`: only numbers, `bool`s, strings and lists of numbers"));
+    assert!(wit.contains("// - `Point`: structs are not supported in components yet"));
+    assert!(!wit.contains("record point"));
+
+    // The module used by the JS bindings is left alone.
+    let wasm = fs::read(out_dir.join("wit_component_bg.wasm")).unwrap();
+    assert!(!wasm
+        .windows(b"cabi_realloc".len())
+        .any(|w| w == b"cabi_realloc"));
+
+    let component = fs::read(out_dir.join("wit_component_component.wasm")).unwrap();
+    wasmparser::Validator::new_with_features(wasmparser::WasmFeatures::all())
+        .validate_all(&component)
+        .unwrap();
+    let mut exports = Vec::new();
+    for payload in wasmparser::Parser::new(0).parse_all(&component) {
+        if let Payload::ComponentExportSection(section) = payload.unwrap() {
+            for export in section {
+                exports.push(export.unwrap().name.0.to_string());
+            }
+        }
+    }
+    for name in ["local:wit-component/exports", "add-one", "greet", "sum"] {
+        assert!(exports.iter().any(|export| export == name), "{}", name);
+    }
+    assert!(!exports.iter().any(|export| export == "log-value"));
+}
+
+#[test]
+fn snapshot_start() {
+    let (mut cmd, out_dir) = Project::new("snapshot_start")
+        .file(
+            "src/lib.rs",
+            r#"
+                use std::sync::atomic::{AtomicU32, Ordering};
+                use wasm_bindgen::prelude::*;
+
+                static VALUE: AtomicU32 = AtomicU32::new(0);
+
+                #[wasm_bindgen(start)]
+                fn start() { VALUE.store(0x5ca1ab1e, Ordering::Relaxed); }
+
+                #[wasm_bindgen]
+                pub fn value() -> u32 { VALUE.load(Ordering::Relaxed) }
+            "#,
+        )
+        .wasm_bindgen("--target web --snapshot-start");
+    cmd.assert().success();
+    // The value stored by the start function is now part of the data segments.
+    let wasm = fs::read(out_dir.join("snapshot_start_bg.wasm")).unwrap();
+    let value = 0x5ca1ab1e_u32.to_le_bytes();
+    assert!(wasm.windows(4).any(|w| w == value));
+}
+
+#[test]
+fn snapshot_start_calls_import() {
+    let (mut cmd, _out_dir) = Project::new("snapshot_start_calls_import")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen]
+                extern "C" {
+                    fn ping();
+                }
+
+                #[wasm_bindgen(start)]
+                fn start() { ping(); }
+            "#,
+        )
+        .wasm_bindgen("--target web --snapshot-start");
+    cmd.assert()
+        .failure()
+        .stderr(str::contains("failed to snapshot the start function"))
+        .stderr(str::contains("calls the import `__wbg_ping_"));
+}
+
+#[test]
+fn function_table_preserved() {
+    let (mut cmd, _out_dir) = Project::new("function_table_preserved")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen]
+                pub fn bar() {
+                    Closure::wrap(Box::new(|| {}) as Box<dyn Fn()>);
+                }
+            "#,
+        )
+        .wasm_bindgen("");
+    cmd.assert().success();
+}
+
+#[test]
+fn constructor_cannot_return_option_struct() {
+    let (mut cmd, _out_dir) = Project::new("constructor_cannot_return_option_struct")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen]
+                pub struct Foo(());
+                
+                #[wasm_bindgen]
+                impl Foo {
+                    #[wasm_bindgen(constructor)]
+                    pub fn new() -> Option<Foo> {
+                        Some(Foo(()))
+                    }
+                }
+            "#,
+        )
+        .wasm_bindgen("--target web");
+    cmd.assert().failure();
+}
+
+#[test]
+fn memory64() {
+    let mut project = Project::new("memory64");
+    project.file(
+        "src/lib.rs",
+        r#"
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub fn greet(name: &str) -> String {
+                format!("hello, {name}")
+            }
+
+            #[wasm_bindgen]
+            pub fn sum(values: &[f64]) -> f64 {
+                values.iter().sum()
+            }
+
+            #[wasm_bindgen]
+            pub fn bytes(len: u8) -> Vec<u8> {
+                (0..len).collect()
+            }
+
+            #[wasm_bindgen]
+            pub fn double(x: usize) -> usize {
+                x * 2
+            }
+        "#,
+    );
+    // The `wasm64-unknown-unknown` target is tier 3.
+    let mut cargo = Command::new("cargo");
+    cargo.arg("+nightly").arg("-Zbuild-std=std,panic_abort");
+    let wasm = project.build_with(cargo, "wasm64-unknown-unknown");
+
+    let out_dir = project.root.join("pkg");
+    fs::create_dir_all(&out_dir).unwrap();
+    Command::cargo_bin("wasm-bindgen")
+        .unwrap()
+        .arg("--out-dir")
+        .arg(&out_dir)
+        .arg(&wasm)
+        .arg("--target")
+        .arg("nodejs")
+        .assert()
+        .success();
+
+    // Pointers and lengths are `BigInt`s on the Wasm side, including the ones
+    // returned through the return pointer.
+    let js = fs::read_to_string(out_dir.join("memory64.js")).unwrap();
+    assert!(js.contains("Number(malloc(BigInt(len), BigInt(1)))"));
+    assert!(js.contains(".getBigInt64(retptr + 8 * 0, true)"));
+    let ts = fs::read_to_string(out_dir.join("memory64.d.ts")).unwrap();
+    assert!(ts.contains("export function double(x: bigint): bigint;"));
+
+    Command::new("node")
+        .arg("-e")
+        .arg(
+            "const m = require('./memory64.js');
+             console.log(m.greet('memory64'));
+             console.log(m.sum(new Float64Array([1, 2, 3.5])));
+             console.log(Array.from(m.bytes(4)).join(','));
+             console.log(typeof m.double(5n), m.double(5n));",
+        )
+        .current_dir(&out_dir)
+        .assert()
+        .success()
+        .stdout("hello, memory64\n6.5\n0,1,2,3\nbigint 10n\n");
+}
+
+#[test]
+fn panic_unwind() {
+    let mut project = Project::new("panic_unwind");
+    project.file(
+        "src/lib.rs",
+        r#"
+            use wasm_bindgen::prelude::*;
+
+            #[wasm_bindgen]
+            pub struct Flaky;
+
+            impl Clone for Flaky {
+                fn clone(&self) -> Self {
+                    panic!("flaky")
+                }
+            }
+
+            #[wasm_bindgen(getter_with_clone)]
+            pub struct Counter {
+                pub flaky: Flaky,
+                items: Vec<u32>,
+            }
+
+            #[wasm_bindgen]
+            impl Counter {
+                #[wasm_bindgen(constructor)]
+                pub fn new() -> Counter {
+                    Counter {
+                        flaky: Flaky,
+                        items: Vec::new(),
+                    }
+                }
+
+                pub fn push(&mut self, item: u32) {
+                    assert_ne!(item, 0, "zero");
+                    self.items.push(item);
+                }
+
+                pub fn len(&self) -> usize {
+                    self.items.len()
+                }
+            }
+        "#,
+    );
+    let mut cargo = Command::new("cargo");
+    cargo
+        .arg("+nightly")
+        .arg("-Zbuild-std=std,panic_unwind")
+        .env(
+            "RUSTFLAGS",
+            "-Cpanic=unwind -Ctarget-feature=+exception-handling",
+        );
+    let wasm = project.build_with(cargo, "wasm32-unknown-unknown");
+
+    let out_dir = project.root.join("pkg");
+    fs::create_dir_all(&out_dir).unwrap();
+    Command::cargo_bin("wasm-bindgen")
+        .unwrap()
+        .arg("--out-dir")
+        .arg(&out_dir)
+        .arg(&wasm)
+        .arg("--target")
+        .arg("nodejs")
+        .assert()
+        .success();
+
+    // Panics in methods and field getters are thrown as `PanicError`s, after
+    // which the instance and the object the panic happened in stay usable.
+    Command::new("node")
+        .arg("--experimental-wasm-exnref")
+        .arg("-e")
+        .arg(
+            "const { Counter, PanicError } = require('./panic_unwind.js');
+             const counter = new Counter();
+             counter.push(1);
+             try {
+                 counter.push(0);
+             } catch (e) {
+                 console.log(e instanceof PanicError, e.name);
+                 console.log(e.location.startsWith('src/lib.rs:'));
+                 console.log(e.message.includes('zero'));
+             }
+             try {
+                 counter.flaky;
+             } catch (e) {
+                 console.log(e instanceof PanicError, e.message.includes('flaky'));
+             }
+             counter.push(2);
+             console.log(counter.len());",
+        )
+        .current_dir(&out_dir)
+        .assert()
+        .success()
+        .stdout("true PanicError\ntrue\ntrue\ntrue true\n2\n");
+}
+
+#[test]
+fn thread_spawn() {
+    let mut project = Project::new("thread_spawn");
+    project.file(
+        "src/lib.rs",
+        r#"
+            use wasm_bindgen::prelude::*;
+            use wasm_bindgen::thread::{self, JoinHandle};
+
+            #[wasm_bindgen]
+            pub struct Handle(Option<JoinHandle<u32>>);
+
+            #[wasm_bindgen]
+            impl Handle {
+                pub fn is_finished(&self) -> bool {
+                    self.0.as_ref().unwrap().is_finished()
+                }
+
+                pub fn join(&mut self) -> Option<u32> {
+                    self.0.take().unwrap().join()
+                }
+            }
+
+            #[wasm_bindgen]
+            pub fn sum(n: u32) -> Handle {
+                let numbers = (1..=n).collect::<Vec<u32>>();
+                Handle(Some(thread::spawn(move || numbers.iter().sum())))
+            }
+        "#,
+    );
+    let mut cargo = Command::new("cargo");
+    cargo
+        .arg("+nightly")
+        .arg("-Zbuild-std=std,panic_abort")
+        .env(
+            "RUSTFLAGS",
+            "-Ctarget-feature=+atomics,+bulk-memory,+mutable-globals",
+        );
+    let wasm = project.build_with(cargo, "wasm32-unknown-unknown");
+
+    let out_dir = project.root.join("pkg");
+    fs::create_dir_all(&out_dir).unwrap();
+    Command::cargo_bin("wasm-bindgen")
+        .unwrap()
+        .arg("--out-dir")
+        .arg(&out_dir)
+        .arg(&wasm)
+        .arg("--target")
+        .arg("nodejs")
+        .assert()
+        .success();
+
+    // Errors creating the worker are returned to `spawn`, and the stack of
+    // the thread is freed whether or not its closure fails.
+    let js = fs::read_to_string(out_dir.join("thread_spawn.js")).unwrap();
+    let js = js.split_whitespace().collect::<String>();
+    assert!(js.contains(
+        "functionspawnThread(work,state){try{startThread(work,state);}catch(e){returne;}}"
+    ));
+    assert!(js.contains(
+        "catch(e){wasm.__wbindgen_thread_failed(state);throwe;}\
+         finally{wasm.__wbindgen_thread_destroy();}"
+    ));
+
+    Command::new("node")
+        .arg("-e")
+        .arg(
+            "const { sum } = require('./thread_spawn.js');
+             const handles = [sum(10), sum(100), sum(1000)];
+             const poll = () => {
+                 if (handles.every(h => h.is_finished())) {
+                     console.log(handles.map(h => h.join()).join(' '));
+                 } else {
+                     setTimeout(poll, 10);
+                 }
+             };
+             poll();",
+        )
+        .current_dir(&out_dir)
+        .assert()
+        .success()
+        .stdout("55 5050 500500\n");
 }
@@ -156,3 +156,106 @@ fn npm_conflict_rejected() {
         .stderr(str::is_match("dependency on NPM package `bar` specified in two").unwrap())
         .failure();
 }
+
+#[test]
+fn dual_package_json() {
+    let (mut cmd, out_dir) = Project::new("dual_package_json")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen(module = "foo")]
+                extern {
+                    fn foo();
+                }
+
+                #[wasm_bindgen(start)]
+                fn main() {
+                    foo();
+                }
+            "#,
+        )
+        .file(
+            "package.json",
+            r#"
+                {
+                    "dependencies": {
+                        "foo": "^1.0.0"
+                    }
+                }
+            "#,
+        )
+        .wasm_bindgen("--target dual");
+    cmd.assert().success();
+    assert!(out_dir.join("dual_package_json.js").exists());
+    assert!(out_dir.join("dual_package_json_bg.wasm").exists());
+    let cjs = fs::read_to_string(out_dir.join("dual_package_json.cjs")).unwrap();
+    assert!(cjs.contains("imports['./dual_package_json_bg.js'] = module.exports;"));
+    assert!(cjs.contains("wasm.__wbindgen_start();"));
+    let package_json = fs::read_to_string(out_dir.join("package.json")).unwrap();
+    assert!(package_json.contains(r#""type": "module""#));
+    assert!(package_json.contains(r#""require": "./dual_package_json.cjs""#));
+    assert!(package_json.contains(r#""import": "./dual_package_json.js""#));
+    assert!(package_json.contains(r#""foo": "^1.0.0""#));
+}
+
+#[test]
+fn dual_flavours_load_same_wasm() {
+    let (mut cmd, out_dir) = Project::new("dual_flavours_load_same_wasm")
+        .file(
+            "src/lib.rs",
+            r#"
+                use wasm_bindgen::prelude::*;
+
+                #[wasm_bindgen]
+                pub struct Counter(u32);
+
+                #[wasm_bindgen]
+                impl Counter {
+                    #[wasm_bindgen(constructor)]
+                    pub fn new() -> Counter {
+                        Counter(0)
+                    }
+
+                    pub fn bump(&mut self) -> u32 {
+                        self.0 += 1;
+                        self.0
+                    }
+                }
+
+                #[wasm_bindgen]
+                pub fn greet(name: &str) -> String {
+                    format!("hello, {name}")
+                }
+            "#,
+        )
+        .wasm_bindgen("--target dual");
+    cmd.assert().success();
+    Command::new("node")
+        .arg("-e")
+        .arg(
+            "const m = require('./dual_flavours_load_same_wasm.cjs');
+             const c = new m.Counter();
+             c.bump();
+             console.log(m.greet('cjs'), c.bump());",
+        )
+        .current_dir(&out_dir)
+        .assert()
+        .success()
+        .stdout("hello, cjs 2\n");
+    Command::new("node")
+        .arg("--experimental-wasm-modules")
+        .arg("--input-type=module")
+        .arg("-e")
+        .arg(
+            "import { Counter, greet } from './dual_flavours_load_same_wasm.js';
+             const c = new Counter();
+             c.bump();
+             console.log(greet('esm'), c.bump());",
+        )
+        .current_dir(&out_dir)
+        .assert()
+        .success()
+        .stdout("hello, esm 2\n");
+}
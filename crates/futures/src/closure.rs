@@ -0,0 +1,151 @@
+//! Passing `async` Rust closures to JavaScript.
+//!
+//! This module extends [`Closure`] with [`AsyncClosureExt::new_async`], which
+//! wraps a closure returning a `Future` into a JavaScript function returning a
+//! `Promise`. Every call of the JavaScript function invokes the Rust closure
+//! and runs the resulting future through [`future_to_promise`].
+//!
+//! ```rust,no_run
+//! use js_sys::Promise;
+//! use wasm_bindgen::prelude::*;
+//! use wasm_bindgen_futures::closure::AsyncClosureExt;
+//!
+//! #[wasm_bindgen]
+//! extern "C" {
+//!     fn register_interceptor(f: &Closure<dyn FnMut(JsValue) -> Promise>);
+//! }
+//!
+//! let interceptor = Closure::new_async(|request: JsValue| async move {
+//!     // ...
+//!     Ok::<_, JsValue>(request)
+//! });
+//! register_interceptor(&interceptor);
+//! interceptor.forget();
+//! ```
+
+use crate::future_to_promise;
+use alloc::boxed::Box;
+use core::future::Future;
+use js_sys::Promise;
+use wasm_bindgen::__rt::IntoJsResult;
+use wasm_bindgen::__wbindgen_closure_arg_ty as closure_arg_ty;
+use wasm_bindgen::closure::WasmClosure;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+extern "C" {
+    /// A JavaScript function returning a `Promise`, as created by
+    /// [`AsyncClosureExt::new_async`].
+    ///
+    /// This is typed as `(...args: any[]) => Promise<any>` in TypeScript
+    /// declarations.
+    #[wasm_bindgen(
+        extends = js_sys::Function,
+        extends = js_sys::Object,
+        typescript_type = "(...args: any[]) => Promise<any>"
+    )]
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub type AsyncFunction;
+}
+
+/// Extension trait adding support for `async` closures to [`Closure`].
+pub trait AsyncClosureExt<T: ?Sized>: Sized {
+    /// Creates a new `Closure` from a Rust closure returning a `Future`.
+    ///
+    /// The resulting JavaScript function returns a `Promise` which settles
+    /// with the output of the future. Like `async` exports the future may
+    /// output `()`, any type convertible into a `JsValue`, or a `Result` of
+    /// those, where `Err` rejects the `Promise`.
+    ///
+    /// The closure can be an `async` closure or a regular closure returning an
+    /// `async` block. Like with `Closure::new`, the first four arguments may
+    /// be taken by reference, up to sixteen arguments in total. References
+    /// are only valid until the closure returns the future though, so the
+    /// future itself can't borrow from them.
+    fn new_async<F>(f: F) -> Self
+    where
+        F: IntoAsyncClosure<T> + 'static;
+
+    /// Converts this `Closure` into an [`AsyncFunction`], with the same
+    /// semantics as [`Closure::into_js_value`].
+    fn into_async_function(self) -> AsyncFunction
+    where
+        T: AsyncWasmClosure;
+}
+
+impl<T> AsyncClosureExt<T> for Closure<T>
+where
+    T: ?Sized + WasmClosure,
+{
+    fn new_async<F>(f: F) -> Self
+    where
+        F: IntoAsyncClosure<T> + 'static,
+    {
+        Closure::wrap(f.into_async_closure())
+    }
+
+    fn into_async_function(self) -> AsyncFunction
+    where
+        T: AsyncWasmClosure,
+    {
+        self.into_js_value().unchecked_into()
+    }
+}
+
+/// An internal trait for [`AsyncClosureExt::new_async`], implemented for Rust
+/// closures returning a `Future`.
+///
+/// This trait is not stable and it's not recommended to use this in bounds or
+/// implement yourself.
+#[doc(hidden)]
+pub trait IntoAsyncClosure<T: ?Sized> {
+    fn into_async_closure(self) -> Box<T>;
+}
+
+/// An internal marker trait for closure types returning a `Promise`.
+///
+/// This trait is not stable and it's not recommended to use this in bounds or
+/// implement yourself.
+#[doc(hidden)]
+pub trait AsyncWasmClosure {}
+
+macro_rules! doit {
+    ($( ($var:ident $kind:ident [$($bound:tt)*] $($prim:ident)*) )*) => {
+        #[allow(coherence_leak_check)]
+        impl<$($var: $($bound)* + 'static,)*> AsyncWasmClosure for dyn Fn($(closure_arg_ty!($kind $var)),*) -> Promise {}
+
+        #[allow(coherence_leak_check)]
+        impl<$($var: $($bound)* + 'static,)*> AsyncWasmClosure for dyn FnMut($(closure_arg_ty!($kind $var)),*) -> Promise {}
+
+        #[allow(coherence_leak_check, non_snake_case)]
+        impl<T, Fut, $($var: $($bound)* + 'static,)*> IntoAsyncClosure<dyn Fn($(closure_arg_ty!($kind $var)),*) -> Promise> for T
+            where T: Fn($(closure_arg_ty!($kind $var)),*) -> Fut + 'static,
+                  Fut: Future + 'static,
+                  Fut::Output: IntoJsResult,
+        {
+            fn into_async_closure(self) -> Box<dyn Fn($(closure_arg_ty!($kind $var)),*) -> Promise> {
+                Box::new(move |$($var: closure_arg_ty!($kind $var)),*| {
+                    let future = self($($var),*);
+                    future_to_promise(async move { future.await.into_js_result() })
+                })
+            }
+        }
+
+        #[allow(coherence_leak_check, non_snake_case)]
+        impl<T, Fut, $($var: $($bound)* + 'static,)*> IntoAsyncClosure<dyn FnMut($(closure_arg_ty!($kind $var)),*) -> Promise> for T
+            where T: FnMut($(closure_arg_ty!($kind $var)),*) -> Fut + 'static,
+                  Fut: Future + 'static,
+                  Fut::Output: IntoJsResult,
+        {
+            fn into_async_closure(mut self) -> Box<dyn FnMut($(closure_arg_ty!($kind $var)),*) -> Promise> {
+                Box::new(move |$($var: closure_arg_ty!($kind $var)),*| {
+                    let future = self($($var),*);
+                    future_to_promise(async move { future.await.into_js_result() })
+                })
+            }
+        }
+    };
+}
+
+// The signatures are the ones `Closure` supports, generated by the same macro.
+wasm_bindgen::__wbindgen_closure_signatures!(doit);
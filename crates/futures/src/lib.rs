@@ -29,6 +29,9 @@
 //! These three items should provide enough of a bridge to interoperate the two
 //! systems and make sure that Rust/JavaScript can work together with
 //! asynchronous and I/O work.
+//!
+//! Additionally the [`closure`] module allows passing `async` Rust closures to
+//! JavaScript as functions returning a `Promise`.
 
 #![cfg_attr(not(feature = "std"), no_std)]
 #![cfg_attr(
@@ -50,6 +53,7 @@ use core::task::{Context, Poll, Waker};
 use js_sys::Promise;
 use wasm_bindgen::prelude::*;
 
+pub mod closure;
 mod queue;
 #[cfg_attr(docsrs, doc(cfg(feature = "futures-core-03-stream")))]
 #[cfg(feature = "futures-core-03-stream")]
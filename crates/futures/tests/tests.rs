@@ -156,6 +156,100 @@ async fn can_use_an_async_iterable_as_stream() {
     assert_eq!(stream.next().await, None);
 }
 
+#[wasm_bindgen_test]
+async fn async_closure_returns_promise() {
+    use wasm_bindgen_futures::closure::AsyncClosureExt;
+
+    let closure: Closure<dyn FnMut(u32) -> Promise> = Closure::new_async(|x: u32| async move {
+        let x = JsFuture::from(Promise::resolve(&JsValue::from(x))).await?;
+        Ok::<_, JsValue>(x.as_f64().unwrap() as u32 + 1)
+    });
+    let f = closure.into_async_function();
+
+    let p = f
+        .call1(&JsValue::undefined(), &JsValue::from(41))
+        .unwrap()
+        .unchecked_into::<Promise>();
+    assert_eq!(JsFuture::from(p).await.unwrap(), 42);
+}
+
+#[wasm_bindgen_test]
+async fn async_closure_error_rejects_promise() {
+    use wasm_bindgen_futures::closure::AsyncClosureExt;
+
+    let closure: Closure<dyn Fn() -> Promise> =
+        Closure::new_async(|| async { Err::<(), _>(JsError::new("async closure error")) });
+    let f: &js_sys::Function = closure.as_ref().unchecked_ref();
+
+    let p = f
+        .call0(&JsValue::undefined())
+        .unwrap()
+        .unchecked_into::<Promise>();
+    let e = JsFuture::from(p).await.unwrap_err();
+    let e = e.unchecked_into::<js_sys::Error>();
+    assert_eq!(e.message(), "async closure error");
+}
+
+#[wasm_bindgen_test]
+async fn async_closure_borrowed_args() {
+    use wasm_bindgen_futures::closure::AsyncClosureExt;
+
+    let closure: Closure<dyn Fn(&str, u32) -> Promise> =
+        Closure::new_async(|prefix: &str, x: u32| {
+            let prefix = prefix.to_owned();
+            async move { Ok::<_, JsValue>(format!("{}{}", prefix, x)) }
+        });
+    let f: &js_sys::Function = closure.as_ref().unchecked_ref();
+
+    let p = f
+        .call2(&JsValue::undefined(), &"n=".into(), &JsValue::from(7))
+        .unwrap()
+        .unchecked_into::<Promise>();
+    assert_eq!(JsFuture::from(p).await.unwrap(), "n=7");
+}
+
+#[wasm_bindgen_test]
+async fn async_closure_sixteen_args() {
+    use wasm_bindgen_futures::closure::AsyncClosureExt;
+
+    #[allow(clippy::type_complexity)]
+    let closure: Closure<
+        dyn Fn(u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8, u8) -> Promise,
+    > = Closure::new_async(
+        |a: u8,
+         b: u8,
+         c: u8,
+         d: u8,
+         e: u8,
+         f: u8,
+         g: u8,
+         h: u8,
+         i: u8,
+         j: u8,
+         k: u8,
+         l: u8,
+         m: u8,
+         n: u8,
+         o: u8,
+         p: u8| async move {
+            Ok::<_, JsValue>(
+                [a, b, c, d, e, f, g, h, i, j, k, l, m, n, o, p]
+                    .iter()
+                    .map(|x| u32::from(*x))
+                    .sum::<u32>(),
+            )
+        },
+    );
+    let f: &js_sys::Function = closure.as_ref().unchecked_ref();
+
+    let args = (1..=16).map(JsValue::from).collect::<js_sys::Array>();
+    let p = f
+        .apply(&JsValue::undefined(), &args)
+        .unwrap()
+        .unchecked_into::<Promise>();
+    assert_eq!(JsFuture::from(p).await.unwrap(), 136);
+}
+
 #[wasm_bindgen_test]
 #[should_panic]
 async fn should_panic() {
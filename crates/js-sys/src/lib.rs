@@ -4818,16 +4818,9 @@ pub mod JSON {
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(js_name = String, extends = Object, is_type_of = JsValue::is_string, typescript_type = "string")]
-    #[derive(Clone, PartialEq, Eq)]
+    #[derive(Clone, Eq)]
     pub type JsString;
 
-    /// The length property of a String object indicates the length of a string,
-    /// in UTF-16 code units.
-    ///
-    /// [MDN documentation](https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/String/length)
-    #[wasm_bindgen(method, getter, structural)]
-    pub fn length(this: &JsString) -> u32;
-
     /// The 'at()' method returns a new string consisting of the single UTF-16
     /// code unit located at the specified offset into the string, counting from
     /// the end if it's negative.
@@ -4844,20 +4837,6 @@ extern "C" {
     #[wasm_bindgen(method, js_class = "String", js_name = charAt)]
     pub fn char_at(this: &JsString, index: u32) -> JsString;
 
-    /// The `charCodeAt()` method returns an integer between 0 and 65535
-    /// representing the UTF-16 code unit at the given index (the UTF-16 code
-    /// unit matches the Unicode code point for code points representable in a
-    /// single UTF-16 code unit, but might also be the first code unit of a
-    /// surrogate pair for code points not representable in a single UTF-16 code
-    /// unit, e.g. Unicode code points > 0x10000).  If you want the entire code
-    /// point value, use `codePointAt()`.
-    ///
-    /// Returns `NaN` if index is out of range.
-    ///
-    /// [MDN documentation](https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/String/charCodeAt)
-    #[wasm_bindgen(method, js_class = "String", js_name = charCodeAt)]
-    pub fn char_code_at(this: &JsString, index: u32) -> f64;
-
     /// The `codePointAt()` method returns a non-negative integer that is the
     /// Unicode code point value.
     ///
@@ -4865,13 +4844,6 @@ extern "C" {
     #[wasm_bindgen(method, js_class = "String", js_name = codePointAt)]
     pub fn code_point_at(this: &JsString, pos: u32) -> JsValue;
 
-    /// The `concat()` method concatenates the string arguments to the calling
-    /// string and returns a new string.
-    ///
-    /// [MDN documentation](https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/String/concat)
-    #[wasm_bindgen(method, js_class = "String")]
-    pub fn concat(this: &JsString, string_2: &JsValue) -> JsString;
-
     /// The `endsWith()` method determines whether a string ends with the characters of a
     /// specified string, returning true or false as appropriate.
     ///
@@ -4879,22 +4851,6 @@ extern "C" {
     #[wasm_bindgen(method, js_class = "String", js_name = endsWith)]
     pub fn ends_with(this: &JsString, search_string: &str, length: i32) -> bool;
 
-    /// The static `String.fromCharCode()` method returns a string created from
-    /// the specified sequence of UTF-16 code units.
-    ///
-    /// [MDN documentation](https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/String/fromCharCode)
-    ///
-    /// # Notes
-    ///
-    /// There are a few bindings to `from_char_code` in `js-sys`: `from_char_code1`, `from_char_code2`, etc...
-    /// with different arities.
-    ///
-    /// Additionally, this function accepts `u16` for character codes, but
-    /// fixing others requires a breaking change release
-    /// (see https://github.com/rustwasm/wasm-bindgen/issues/1460 for details).
-    #[wasm_bindgen(static_method_of = JsString, js_class = "String", js_name = fromCharCode, variadic)]
-    pub fn from_char_code(char_codes: &[u16]) -> JsString;
-
     /// [MDN documentation](https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/String/fromCharCode)
     #[wasm_bindgen(static_method_of = JsString, js_class = "String", js_name = fromCharCode)]
     pub fn from_char_code1(a: u32) -> JsString;
@@ -5131,13 +5087,6 @@ extern "C" {
     #[wasm_bindgen(method, js_class = "String", js_name = startsWith)]
     pub fn starts_with(this: &JsString, search_string: &str, position: u32) -> bool;
 
-    /// The `substring()` method returns the part of the string between the
-    /// start and end indexes, or to the end of the string.
-    ///
-    /// [MDN documentation](https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/String/substring)
-    #[wasm_bindgen(method, js_class = "String")]
-    pub fn substring(this: &JsString, index_start: u32, index_end: u32) -> JsString;
-
     /// The `substr()` method returns the part of a string between
     /// the start index and a number of characters after it.
     ///
@@ -5326,6 +5275,78 @@ extern "C" {
     ) -> Result<JsString, JsValue>;
 }
 
+// The operations below are built on intrinsics which `--js-string-builtins`
+// lowers to the `wasm:js-string` builtins where possible.
+impl JsString {
+    /// The length property of a String object indicates the length of a string,
+    /// in UTF-16 code units.
+    ///
+    /// [MDN documentation](https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/String/length)
+    #[inline]
+    pub fn length(&self) -> u32 {
+        wasm_bindgen::__rt::js_string::length(self)
+    }
+
+    /// The static `String.fromCharCode()` method returns a string created from
+    /// the specified sequence of UTF-16 code units.
+    ///
+    /// [MDN documentation](https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/String/fromCharCode)
+    ///
+    /// # Notes
+    ///
+    /// There are a few bindings to `from_char_code` in `js-sys`: `from_char_code1`, `from_char_code2`, etc...
+    /// with different arities.
+    ///
+    /// Additionally, this function accepts `u16` for character codes, but
+    /// fixing others requires a breaking change release
+    /// (see https://github.com/rustwasm/wasm-bindgen/issues/1460 for details).
+    #[inline]
+    pub fn from_char_code(char_codes: &[u16]) -> JsString {
+        wasm_bindgen::__rt::js_string::from_char_code_array(char_codes).unchecked_into()
+    }
+
+    /// The `charCodeAt()` method returns an integer between 0 and 65535
+    /// representing the UTF-16 code unit at the given index (the UTF-16 code
+    /// unit matches the Unicode code point for code points representable in a
+    /// single UTF-16 code unit, but might also be the first code unit of a
+    /// surrogate pair for code points not representable in a single UTF-16 code
+    /// unit, e.g. Unicode code points > 0x10000).  If you want the entire code
+    /// point value, use `codePointAt()`.
+    ///
+    /// Returns `NaN` if index is out of range.
+    ///
+    /// [MDN documentation](https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/String/charCodeAt)
+    #[inline]
+    pub fn char_code_at(&self, index: u32) -> f64 {
+        wasm_bindgen::__rt::js_string::char_code_at(self, index)
+    }
+
+    /// The `concat()` method concatenates the string arguments to the calling
+    /// string and returns a new string.
+    ///
+    /// [MDN documentation](https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/String/concat)
+    #[inline]
+    pub fn concat(&self, string_2: &JsValue) -> JsString {
+        wasm_bindgen::__rt::js_string::concat(self, string_2).unchecked_into()
+    }
+
+    /// The `substring()` method returns the part of the string between the
+    /// start and end indexes, or to the end of the string.
+    ///
+    /// [MDN documentation](https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/String/substring)
+    #[inline]
+    pub fn substring(&self, index_start: u32, index_end: u32) -> JsString {
+        wasm_bindgen::__rt::js_string::substring(self, index_start, index_end).unchecked_into()
+    }
+}
+
+impl PartialEq for JsString {
+    #[inline]
+    fn eq(&self, other: &JsString) -> bool {
+        wasm_bindgen::__rt::js_string::equals(self, other)
+    }
+}
+
 impl JsString {
     /// Returns the `JsString` value of this JS value if it's an instance of a
     /// string.
@@ -65,6 +65,13 @@ fn concat() {
     assert_eq!(JsValue::from(foo.concat(&1234.into())), "foo1234");
 }
 
+#[wasm_bindgen_test]
+fn equals() {
+    assert_eq!(JsString::from("foo"), JsString::from("foo"));
+    assert_ne!(JsString::from("foo"), JsString::from("bar"));
+    assert_ne!(JsString::from("foo"), JsString::from(""));
+}
+
 #[wasm_bindgen_test]
 fn ends_with() {
     let s = "To be, or not to be, that is the question.";
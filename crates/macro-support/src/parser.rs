@@ -156,6 +156,8 @@ macro_rules! attrgen {
     ($mac:ident) => {
         $mac! {
             (catch, false, Catch(Span)),
+            (suspending, false, Suspending(Span)),
+            (promising, false, Promising(Span)),
             (constructor, false, Constructor(Span)),
             (method, false, Method(Span)),
             (static_method_of, false, StaticMethodOf(Span, Ident)),
@@ -174,6 +176,7 @@ macro_rules! attrgen {
             (js_name, false, JsName(Span, String, Span)),
             (js_class, false, JsClass(Span, String, Span)),
             (inspectable, false, Inspectable(Span)),
+            (serde, false, Serde(Span)),
             (is_type_of, false, IsTypeOf(Span, syn::Expr)),
             (extends, false, Extends(Span, syn::Path)),
             (no_deref, false, NoDeref(Span)),
@@ -184,6 +187,7 @@ macro_rules! attrgen {
             (skip_jsdoc, false, SkipJsDoc(Span)),
             (main, false, Main(Span)),
             (start, false, Start(Span)),
+            (split, false, Split(Span, String, Span)),
             (wasm_bindgen, false, WasmBindgen(Span, syn::Path)),
             (js_sys, false, JsSys(Span, syn::Path)),
             (wasm_bindgen_futures, false, WasmBindgenFutures(Span, syn::Path)),
@@ -563,10 +567,11 @@ impl ConvertToAst<&ast::Program> for &mut syn::ItemStruct {
         }
 
         let is_inspectable = attrs.inspectable().is_some();
+        let is_serde = attrs.serde().is_some();
         let getter_with_clone = attrs.getter_with_clone();
         for (i, field) in self.fields.iter_mut().enumerate() {
             match field.vis {
-                syn::Visibility::Public(..) => {}
+                syn::Visibility::Public(..) if !is_serde => {}
                 _ => continue,
             }
             let (js_field_name, member) = match &field.ident {
@@ -614,6 +619,7 @@ impl ConvertToAst<&ast::Program> for &mut syn::ItemStruct {
             fields,
             comments,
             is_inspectable,
+            is_serde,
             generate_typescript,
             wasm_bindgen: program.wasm_bindgen.clone(),
         })
@@ -655,6 +661,7 @@ impl<'a> ConvertToAst<(&ast::Program, BindgenAttrs, &'a Option<ast::ImportModule
             None,
         )?;
         let catch = opts.catch().is_some();
+        let suspending = opts.suspending().is_some();
         let variadic = opts.variadic().is_some();
         let js_ret = if catch {
             // TODO: this assumes a whole bunch:
@@ -817,6 +824,7 @@ impl<'a> ConvertToAst<(&ast::Program, BindgenAttrs, &'a Option<ast::ImportModule
             kind,
             js_ret,
             catch,
+            suspending,
             variadic,
             structural: opts.structural().is_some() || opts.r#final().is_none(),
             rust_name: self.sig.ident,
@@ -1351,6 +1359,29 @@ impl<'a> MacroParse<(Option<BindgenAttrs>, &'a mut TokenStream)> for syn::Item {
                 });
                 let rust_name = f.sig.ident.clone();
                 let start = opts.start().is_some();
+                let split = match opts.split() {
+                    Some((_, span)) if start => {
+                        return Err(Diagnostic::span_error(
+                            span,
+                            "the start function cannot be split into a separate module",
+                        ));
+                    }
+                    // The name ends up in file names and JS string literals.
+                    Some((name, span))
+                        if name.is_empty()
+                            || !name
+                                .chars()
+                                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') =>
+                    {
+                        return Err(Diagnostic::span_error(
+                            span,
+                            "split names may only contain ASCII letters, digits, `_` and `-`",
+                        ));
+                    }
+                    Some((name, _)) => Some(name.to_string()),
+                    None => None,
+                };
+                let promising = check_promising(&opts, &f.sig, start)?;
 
                 program.exports.push(ast::Export {
                     comments,
@@ -1361,6 +1392,8 @@ impl<'a> MacroParse<(Option<BindgenAttrs>, &'a mut TokenStream)> for syn::Item {
                     rust_class: None,
                     rust_name,
                     start,
+                    split,
+                    promising,
                     wasm_bindgen: program.wasm_bindgen.clone(),
                     wasm_bindgen_futures: program.wasm_bindgen_futures.clone(),
                 });
@@ -1555,6 +1588,7 @@ impl MacroParse<&ClassMarker> for &mut syn::ImplItemFn {
             let kind = operation_kind(&opts);
             ast::MethodKind::Operation(ast::Operation { is_static, kind })
         };
+        let promising = check_promising(&opts, &self.sig, opts.constructor().is_some())?;
         program.exports.push(ast::Export {
             comments,
             function,
@@ -1564,6 +1598,8 @@ impl MacroParse<&ClassMarker> for &mut syn::ImplItemFn {
             rust_class: Some(class.clone()),
             rust_name: self.sig.ident.clone(),
             start: false,
+            split: None,
+            promising,
             wasm_bindgen: program.wasm_bindgen.clone(),
             wasm_bindgen_futures: program.wasm_bindgen_futures.clone(),
         });
@@ -2217,6 +2253,31 @@ pub fn check_unused_attrs(tokens: &mut TokenStream) {
     })
 }
 
+/// Returns whether an exported function is `promising`. Such functions return a
+/// promise by themselves, so they can't be `async`, the start function or a
+/// constructor.
+fn check_promising(
+    opts: &BindgenAttrs,
+    sig: &syn::Signature,
+    start_or_constructor: bool,
+) -> Result<bool, Diagnostic> {
+    let span = match opts.promising() {
+        Some(span) => span,
+        None => return Ok(false),
+    };
+    if sig.asyncness.is_some() {
+        bail_span!(
+            sig.asyncness,
+            "`promising` functions already return a promise and cannot be `async`",
+        );
+    }
+    if start_or_constructor {
+        let msg = "the start function and constructors cannot be `promising`";
+        return Err(Diagnostic::span_error(*span, msg));
+    }
+    Ok(true)
+}
+
 fn operation_kind(opts: &BindgenAttrs) -> ast::OperationKind {
     let mut operation_kind = ast::OperationKind::Regular;
     if let Some(g) = opts.getter() {
@@ -0,0 +1,18 @@
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(split = "editor")]
+pub fn foo() {}
+
+#[wasm_bindgen(split = "")]
+pub fn foo2() {}
+
+#[wasm_bindgen(split = "a');alert('x")]
+pub fn foo3() {}
+
+#[wasm_bindgen(split = "../editor")]
+pub fn foo4() {}
+
+#[wasm_bindgen(start, split = "editor")]
+pub fn foo5() {}
+
+fn main() {}
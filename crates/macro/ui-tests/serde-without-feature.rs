@@ -0,0 +1,9 @@
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen(serde)]
+pub struct Point {
+    x: f64,
+    y: f64,
+}
+
+fn main() {}
@@ -150,9 +150,14 @@ fn xform_one(
     return_pointer_index: usize,
     results: &[walrus::ValType],
 ) -> Result<walrus::FunctionId, anyhow::Error> {
-    if module.globals.get(stack_pointer).ty != walrus::ValType::I32 {
-        anyhow::bail!("stack pointer global does not have type `i32`");
-    }
+    // The stack pointer, and so the return pointer, is an `i64` with 64-bit
+    // memories.
+    let ptr_ty = module.globals.get(stack_pointer).ty;
+    let (sub, add) = match ptr_ty {
+        walrus::ValType::I32 => (walrus::ir::BinaryOp::I32Sub, walrus::ir::BinaryOp::I32Add),
+        walrus::ValType::I64 => (walrus::ir::BinaryOp::I64Sub, walrus::ir::BinaryOp::I64Add),
+        _ => anyhow::bail!("stack pointer global does not have type `i32` or `i64`"),
+    };
 
     // Compute the total size of all results, potentially with padding to ensure
     // that each result is aligned.
@@ -176,6 +181,10 @@ fn xform_one(
     // Round up to 16-byte alignment, since that's what LLVM's emitted Wasm code
     // seems to expect.
     let results_size = round_up_to_alignment(results_size, 16);
+    let results_size_const = match ptr_ty {
+        walrus::ValType::I64 => walrus::ir::Value::I64(results_size.into()),
+        _ => walrus::ir::Value::I32(results_size as i32),
+    };
 
     let ty = module.funcs.get(func).ty();
     let (ty_params, ty_results) = module.types.params_results(ty);
@@ -188,9 +197,11 @@ fn xform_one(
     }
 
     match ty_params.get(return_pointer_index) {
-        Some(walrus::ValType::I32) => {}
+        Some(ty) if *ty == ptr_ty => {}
         None => anyhow::bail!("the return pointer parameter doesn't exist"),
-        Some(_) => anyhow::bail!("the return pointer parameter is not `i32`"),
+        Some(_) => {
+            anyhow::bail!("the return pointer parameter doesn't have the type of the stack pointer")
+        }
     }
 
     let new_params: Vec<_> = ty_params
@@ -210,15 +221,15 @@ fn xform_one(
     let params: Vec<_> = new_params.iter().map(|ty| module.locals.add(*ty)).collect();
 
     // A local to hold our stack-allocated return pointer.
-    let return_pointer = module.locals.add(walrus::ValType::I32);
+    let return_pointer = module.locals.add(ptr_ty);
 
     let mut wrapper = walrus::FunctionBuilder::new(&mut module.types, &new_params, results);
     let mut body = wrapper.func_body();
 
     // Allocate space in the stack for the call.
     body.global_get(stack_pointer)
-        .i32_const(results_size as i32)
-        .binop(walrus::ir::BinaryOp::I32Sub)
+        .const_(results_size_const)
+        .binop(sub)
         .local_tee(return_pointer)
         .global_set(stack_pointer);
 
@@ -294,8 +305,8 @@ fn xform_one(
 
     // Finally, restore the stack pointer.
     body.local_get(return_pointer)
-        .i32_const(results_size as i32)
-        .binop(walrus::ir::BinaryOp::I32Add)
+        .const_(results_size_const)
+        .binop(add)
         .global_set(stack_pointer);
 
     let wrapper = wrapper.finish(params, &mut module.funcs);
@@ -0,0 +1,38 @@
+;; @xform export "pair" (i32 i64)
+
+(module
+  (global (mut i64) (i64.const 0))
+  (memory i64 1)
+
+  (func $pair (export "pair") (param i64))
+)
+
+(; CHECK-ALL:
+(module
+  (type (;0;) (func (result i32 i64)))
+  (type (;1;) (func (param i64)))
+  (func $"pair multivalue shim" (;0;) (type 0) (result i32 i64)
+    (local i64)
+    global.get 0
+    i64.const 16
+    i64.sub
+    local.tee 0
+    global.set 0
+    local.get 0
+    call $pair
+    local.get 0
+    i32.load
+    local.get 0
+    i64.load offset=8
+    local.get 0
+    i64.const 16
+    i64.add
+    global.set 0
+  )
+  (func $pair (;1;) (type 1) (param i64))
+  (memory (;0;) i64 1)
+  (global (;0;) (mut i64) i64.const 0)
+  (export "pair" (func $"pair multivalue shim"))
+  (@custom "target_features" (after code) "\01+\0amultivalue")
+)
+;)
@@ -7,7 +7,7 @@ mod schema_hash_approval;
 // This gets changed whenever our schema changes.
 // At this time versions of wasm-bindgen and wasm-bindgen-cli are required to have the exact same
 // SCHEMA_VERSION in order to work together.
-pub const SCHEMA_VERSION: &str = "0.2.100";
+pub const SCHEMA_VERSION: &str = "0.2.101";
 
 #[macro_export]
 macro_rules! shared_api {
@@ -28,6 +28,9 @@ macro_rules! shared_api {
             unique_crate_identifier: &'a str,
             package_json: Option<&'a str>,
             linked_modules: Vec<LinkedModule<'a>>,
+            // The directory of the crate relative to the directory the paths
+            // in source locations are relative to, empty if they are the same.
+            source_root: LitOrExpr<'a>,
         }
 
         struct Import<'a> {
@@ -58,6 +61,7 @@ macro_rules! shared_api {
         struct ImportFunction<'a> {
             shim: &'a str,
             catch: bool,
+            suspending: bool,
             variadic: bool,
             assert_no_shim: bool,
             method: Option<MethodData<'a>>,
@@ -110,6 +114,7 @@ macro_rules! shared_api {
             variant_values: Vec<&'a str>,
             comments: Vec<&'a str>,
             generate_typescript: bool,
+            location: LitOrExpr<'a>,
         }
 
         struct Export<'a> {
@@ -119,6 +124,8 @@ macro_rules! shared_api {
             function: Function<'a>,
             method_kind: MethodKind<'a>,
             start: bool,
+            split: Option<&'a str>,
+            promising: bool,
         }
 
         struct Enum<'a> {
@@ -127,6 +134,7 @@ macro_rules! shared_api {
             variants: Vec<EnumVariant<'a>>,
             comments: Vec<&'a str>,
             generate_typescript: bool,
+            location: LitOrExpr<'a>,
         }
 
         struct EnumVariant<'a> {
@@ -144,6 +152,8 @@ macro_rules! shared_api {
             variadic: bool,
             ret_ty_override: Option<&'a str>,
             ret_desc: Option<&'a str>,
+            // The `file:line:column` location of the function in Rust code.
+            location: LitOrExpr<'a>,
         }
 
         struct FunctionArgumentData<'a> {
@@ -158,6 +168,7 @@ macro_rules! shared_api {
             comments: Vec<&'a str>,
             is_inspectable: bool,
             generate_typescript: bool,
+            location: LitOrExpr<'a>,
         }
 
         struct StructField<'a> {
@@ -166,6 +177,7 @@ macro_rules! shared_api {
             comments: Vec<&'a str>,
             generate_typescript: bool,
             generate_jsdoc: bool,
+            location: LitOrExpr<'a>,
         }
 
         struct LocalModule<'a> {
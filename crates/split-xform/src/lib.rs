@@ -0,0 +1,292 @@
+//! Splitting of rarely used exports out of a Wasm module into secondary
+//! modules which are only loaded once one of them is called.
+//!
+//! Each split is given a set of exports of the main module. Every function
+//! only reachable from those exports is moved into the secondary module, while
+//! functions also used by the rest of the main module stay where they are and
+//! are imported by the secondary module instead. The secondary module also
+//! imports all memories, tables and globals of the main module, so that once
+//! instantiated it shares all of its state.
+//!
+//! All items of the main module needed by secondary modules are exported from
+//! the main module, and imported by secondary modules from the
+//! [`MAIN_MODULE`] module under the same name. Instantiating a secondary
+//! module therefore only requires passing the exports of the main module
+//! under that name, along with the imports the main module was instantiated
+//! with.
+
+use anyhow::{anyhow, Error};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::mem;
+use walrus::ir::Visitor;
+use walrus::{
+    ConstExpr, DataKind, ElementItems, ElementKind, ExportItem, FunctionId, FunctionKind,
+    GlobalKind, ImportKind, ImportedFunction, Module,
+};
+
+/// The module secondary modules import the items they share with the main
+/// module from.
+pub const MAIN_MODULE: &str = "__wbindgen_main";
+
+/// A secondary module split off from the main module.
+pub struct Split {
+    /// The name of the split.
+    pub name: String,
+    /// The secondary module.
+    pub module: Module,
+}
+
+/// Splits the exports listed in `splits`, keyed by the name of their split,
+/// out of `module`.
+///
+/// As items are identified by their position in the module, `module` is
+/// re-parsed with `parse` first, which is also used to create the secondary
+/// modules.
+pub fn run(
+    module: &mut Module,
+    splits: &BTreeMap<String, Vec<String>>,
+    parse: impl Fn(&[u8]) -> Result<Module, Error>,
+) -> Result<Vec<Split>, Error> {
+    if splits.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let bytes = module.emit_wasm();
+    *module = parse(&bytes)?;
+
+    let split_exports = splits
+        .values()
+        .flatten()
+        .map(|name| name.as_str())
+        .collect::<HashSet<_>>();
+    let positions = module
+        .funcs
+        .iter()
+        .enumerate()
+        .map(|(i, f)| (f.id(), i))
+        .collect::<HashMap<_, _>>();
+
+    // Everything the main module still uses on its own must stay there.
+    let mut roots = Vec::new();
+    for export in module.exports.iter() {
+        if let ExportItem::Function(f) = export.item {
+            if !split_exports.contains(export.name.as_str()) {
+                roots.push(f);
+            }
+        }
+    }
+    roots.extend(module.start);
+    for segment in module.elements.iter() {
+        match &segment.items {
+            ElementItems::Functions(items) => roots.extend(items),
+            ElementItems::Expressions(_, items) => {
+                roots.extend(items.iter().filter_map(|item| match item {
+                    ConstExpr::RefFunc(f) => Some(*f),
+                    _ => None,
+                }))
+            }
+        }
+    }
+    for global in module.globals.iter() {
+        if let GlobalKind::Local(ConstExpr::RefFunc(f)) = global.kind {
+            roots.push(f);
+        }
+    }
+    let main = reachable(module, roots);
+
+    let mut shared = HashSet::new();
+    let mut secondaries = Vec::new();
+    for (name, exports) in splits {
+        let roots = exports
+            .iter()
+            .map(|export| {
+                module
+                    .exports
+                    .get_func(export)
+                    .map_err(|_| anyhow!("failed to find export `{}` of split `{}`", export, name))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let split = reachable(module, roots);
+
+        // Functions of the split which are also used by the main module are
+        // imported from it, everything else stays local.
+        let imported = split
+            .iter()
+            .filter(|f| main.contains(*f))
+            .filter(|f| matches!(module.funcs.get(**f).kind, FunctionKind::Local(_)))
+            .map(|f| positions[f])
+            .collect::<HashSet<_>>();
+        shared.extend(imported.iter().copied());
+
+        let mut secondary = parse(&bytes)?;
+        make_secondary(&mut secondary, exports, &imported);
+        secondaries.push(Split {
+            name: name.clone(),
+            module: secondary,
+        });
+    }
+
+    // Export everything secondary modules import from the main module.
+    let funcs = module.funcs.iter().map(|f| f.id()).collect::<Vec<_>>();
+    for (i, id) in funcs.into_iter().enumerate() {
+        if shared.contains(&i) {
+            module.exports.add(&func_name(i), id);
+        }
+    }
+    let memories = module.memories.iter().map(|m| m.id()).collect::<Vec<_>>();
+    for (i, id) in memories.into_iter().enumerate() {
+        module.exports.add(&memory_name(i), id);
+    }
+    let tables = module.tables.iter().map(|t| t.id()).collect::<Vec<_>>();
+    for (i, id) in tables.into_iter().enumerate() {
+        module.exports.add(&table_name(i), id);
+    }
+    let globals = module.globals.iter().map(|g| g.id()).collect::<Vec<_>>();
+    for (i, id) in globals.into_iter().enumerate() {
+        module.exports.add(&global_name(i), id);
+    }
+
+    // And finally remove the split exports, which takes everything only they
+    // used with them.
+    let split_export_ids = module
+        .exports
+        .iter()
+        .filter(|e| split_exports.contains(e.name.as_str()))
+        .map(|e| e.id())
+        .collect::<Vec<_>>();
+    for id in split_export_ids {
+        module.exports.delete(id);
+    }
+    walrus::passes::gc::run(module);
+
+    Ok(secondaries)
+}
+
+/// Turns `module`, a copy of the main module, into the secondary module for
+/// `exports`. Functions at the `imported` positions are imported from the main
+/// module.
+fn make_secondary(module: &mut Module, exports: &[String], imported: &HashSet<usize>) {
+    let funcs = module.funcs.iter().map(|f| f.id()).collect::<Vec<_>>();
+    for (i, id) in funcs.into_iter().enumerate() {
+        if !imported.contains(&i) {
+            continue;
+        }
+        let ty = module.funcs.get(id).ty();
+        let import = module
+            .imports
+            .add(MAIN_MODULE, &func_name(i), ImportKind::Function(id));
+        module.funcs.get_mut(id).kind = FunctionKind::Import(ImportedFunction { import, ty });
+    }
+
+    // Memories and tables are initialized by the main module, so all active
+    // segments are removed.
+    let data = module
+        .data
+        .iter()
+        .filter(|d| matches!(d.kind, DataKind::Active { .. }))
+        .map(|d| d.id())
+        .collect::<Vec<_>>();
+    for id in data {
+        module.data.delete(id);
+    }
+    let memories = module.memories.iter().map(|m| m.id()).collect::<Vec<_>>();
+    for (i, id) in memories.into_iter().enumerate() {
+        let import = module
+            .imports
+            .add(MAIN_MODULE, &memory_name(i), ImportKind::Memory(id));
+        let memory = module.memories.get_mut(id);
+        memory.data_segments.clear();
+        if let Some(old) = memory.import.replace(import) {
+            module.imports.delete(old);
+        }
+    }
+
+    let elements = module
+        .elements
+        .iter()
+        .filter(|e| matches!(e.kind, ElementKind::Active { .. }))
+        .map(|e| e.id())
+        .collect::<Vec<_>>();
+    for id in elements {
+        module.elements.delete(id);
+    }
+    let tables = module.tables.iter().map(|t| t.id()).collect::<Vec<_>>();
+    for (i, id) in tables.into_iter().enumerate() {
+        let import = module
+            .imports
+            .add(MAIN_MODULE, &table_name(i), ImportKind::Table(id));
+        let table = module.tables.get_mut(id);
+        table.elem_segments.clear();
+        if let Some(old) = table.import.replace(import) {
+            module.imports.delete(old);
+        }
+    }
+
+    let globals = module.globals.iter().map(|g| g.id()).collect::<Vec<_>>();
+    for (i, id) in globals.into_iter().enumerate() {
+        let import = module
+            .imports
+            .add(MAIN_MODULE, &global_name(i), ImportKind::Global(id));
+        let global = module.globals.get_mut(id);
+        if let GlobalKind::Import(old) = mem::replace(&mut global.kind, GlobalKind::Import(import))
+        {
+            module.imports.delete(old);
+        }
+    }
+
+    let other_exports = module
+        .exports
+        .iter()
+        .filter(|e| !exports.contains(&e.name))
+        .map(|e| e.id())
+        .collect::<Vec<_>>();
+    for id in other_exports {
+        module.exports.delete(id);
+    }
+    module.start = None;
+
+    walrus::passes::gc::run(module);
+}
+
+/// Returns all functions reachable from `roots` through calls or function
+/// references.
+fn reachable(module: &Module, roots: Vec<FunctionId>) -> HashSet<FunctionId> {
+    struct Find<'a> {
+        stack: &'a mut Vec<FunctionId>,
+    }
+
+    impl Visitor<'_> for Find<'_> {
+        fn visit_function_id(&mut self, function: &FunctionId) {
+            self.stack.push(*function);
+        }
+    }
+
+    let mut found = HashSet::new();
+    let mut stack = roots;
+    while let Some(id) = stack.pop() {
+        if !found.insert(id) {
+            continue;
+        }
+        if let FunctionKind::Local(local) = &module.funcs.get(id).kind {
+            let mut find = Find { stack: &mut stack };
+            walrus::ir::dfs_in_order(&mut find, local, local.entry_block());
+        }
+    }
+    found
+}
+
+fn func_name(i: usize) -> String {
+    format!("__wbindgen_split_func{}", i)
+}
+
+fn memory_name(i: usize) -> String {
+    format!("__wbindgen_split_memory{}", i)
+}
+
+fn table_name(i: usize) -> String {
+    format!("__wbindgen_split_table{}", i)
+}
+
+fn global_name(i: usize) -> String {
+    format!("__wbindgen_split_global{}", i)
+}
@@ -0,0 +1,54 @@
+use std::collections::BTreeMap;
+use walrus::{Module, ModuleConfig};
+
+fn parse(wasm: &[u8]) -> anyhow::Result<Module> {
+    ModuleConfig::new()
+        .generate_producers_section(false)
+        .parse(wasm)
+}
+
+#[test]
+fn splits_exports_into_secondary_module() {
+    let wasm = wat::parse_str(
+        r#"
+            (module
+                (memory 1)
+                (global (mut i32) (i32.const 1024))
+                (func $shared (result i32)
+                    i32.const 1)
+                (func $only_editor (result i32)
+                    call $shared
+                    i32.const 2
+                    i32.add)
+                (func $main (export "main") (result i32)
+                    call $shared)
+                (func $editor (export "editor") (result i32)
+                    call $only_editor)
+                (export "memory" (memory 0))
+                (data (i32.const 8) "hello"))
+        "#,
+    )
+    .unwrap();
+    let mut module = parse(&wasm).unwrap();
+
+    let mut splits = BTreeMap::new();
+    splits.insert("editor".to_string(), vec!["editor".to_string()]);
+    let secondaries = wasm_bindgen_split_xform::run(&mut module, &splits, parse).unwrap();
+    assert_eq!(secondaries.len(), 1);
+    assert_eq!(secondaries[0].name, "editor");
+
+    let main = wasmprinter::print_bytes(module.emit_wasm()).unwrap();
+    assert!(main.contains(r#"(export "main""#));
+    assert!(!main.contains(r#"(export "editor""#));
+    assert!(main.contains(r#"(export "__wbindgen_split_func0""#));
+    assert!(main.contains(r#"(export "__wbindgen_split_memory0""#));
+    assert!(main.contains(r#"(export "__wbindgen_split_global0""#));
+    assert!(main.contains("(data "));
+
+    let mut secondary = secondaries.into_iter().next().unwrap().module;
+    let secondary = wasmprinter::print_bytes(secondary.emit_wasm()).unwrap();
+    assert!(secondary.contains(r#"(import "__wbindgen_main" "__wbindgen_split_func0""#));
+    assert!(secondary.contains(r#"(export "editor""#));
+    assert!(!secondary.contains(r#"(export "main""#));
+    assert!(!secondary.contains("(data "));
+}
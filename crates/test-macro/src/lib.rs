@@ -98,7 +98,7 @@ pub fn wasm_bindgen_test(
             const _: () = {
                 #wasm_bindgen_path::__rt::wasm_bindgen::__wbindgen_coverage! {
                 #[export_name = ::core::concat!("__wbgt_", #ignore_name, "_", ::core::module_path!(), "::", ::core::stringify!(#ident))]
-                #[cfg(all(target_arch = "wasm32", any(target_os = "unknown", target_os = "none")))]
+                #[cfg(all(target_family = "wasm", any(target_os = "unknown", target_os = "none")))]
                 extern "C" fn __wbgt_test(cx: &#wasm_bindgen_path::__rt::Context) {
                     let test_name = ::core::concat!(::core::module_path!(), "::", ::core::stringify!(#ident));
                     #test_body
@@ -110,7 +110,7 @@ pub fn wasm_bindgen_test(
 
     if let Some(path) = attributes.unsupported {
         tokens.extend(
-            quote! { #[cfg_attr(not(all(target_arch = "wasm32", any(target_os = "unknown", target_os = "none"))), #path)] },
+            quote! { #[cfg_attr(not(all(target_family = "wasm", any(target_os = "unknown", target_os = "none"))), #path)] },
         );
 
         if let Some(should_panic) = should_panic {
@@ -121,7 +121,7 @@ pub fn wasm_bindgen_test(
             };
 
             tokens.extend(
-                quote! { #[cfg_attr(not(all(target_arch = "wasm32", any(target_os = "unknown", target_os = "none"))), #should_panic)] }
+                quote! { #[cfg_attr(not(all(target_family = "wasm", any(target_os = "unknown", target_os = "none"))), #should_panic)] }
             )
         }
 
@@ -133,7 +133,7 @@ pub fn wasm_bindgen_test(
             };
 
             tokens.extend(
-                quote! { #[cfg_attr(not(all(target_arch = "wasm32", any(target_os = "unknown", target_os = "none"))), #ignore)] }
+                quote! { #[cfg_attr(not(all(target_family = "wasm", any(target_os = "unknown", target_os = "none"))), #ignore)] }
             )
         }
     }
@@ -61,7 +61,7 @@ macro_rules! wasm_bindgen_test_configure {
     (run_in_browser $($others:tt)*) => (
         const _: () = {
             #[link_section = "__wasm_bindgen_test_unstable"]
-            #[cfg(target_arch = "wasm32")]
+            #[cfg(target_family = "wasm")]
             pub static __WBG_TEST_RUN_IN_BROWSER: [u8; 1] = [0x01];
             $crate::wasm_bindgen_test_configure!($($others)*);
         };
@@ -69,7 +69,7 @@ macro_rules! wasm_bindgen_test_configure {
     (run_in_worker $($others:tt)*) => (
         const _: () = {
             #[link_section = "__wasm_bindgen_test_unstable"]
-            #[cfg(target_arch = "wasm32")]
+            #[cfg(target_family = "wasm")]
             pub static __WBG_TEST_RUN_IN_DEDICATED_WORKER: [u8; 1] = [0x02];
             $crate::wasm_bindgen_test_configure!($($others)*);
         };
@@ -77,7 +77,7 @@ macro_rules! wasm_bindgen_test_configure {
     (run_in_dedicated_worker $($others:tt)*) => (
         const _: () = {
             #[link_section = "__wasm_bindgen_test_unstable"]
-            #[cfg(target_arch = "wasm32")]
+            #[cfg(target_family = "wasm")]
             pub static __WBG_TEST_RUN_IN_DEDICATED_WORKER: [u8; 1] = [0x02];
             $crate::wasm_bindgen_test_configure!($($others)*);
         };
@@ -85,7 +85,7 @@ macro_rules! wasm_bindgen_test_configure {
     (run_in_shared_worker $($others:tt)*) => (
         const _: () = {
             #[link_section = "__wasm_bindgen_test_unstable"]
-            #[cfg(target_arch = "wasm32")]
+            #[cfg(target_family = "wasm")]
             pub static __WBG_TEST_RUN_IN_SHARED_WORKER: [u8; 1] = [0x03];
             $crate::wasm_bindgen_test_configure!($($others)*);
         };
@@ -93,7 +93,7 @@ macro_rules! wasm_bindgen_test_configure {
     (run_in_service_worker $($others:tt)*) => (
         const _: () = {
             #[link_section = "__wasm_bindgen_test_unstable"]
-            #[cfg(target_arch = "wasm32")]
+            #[cfg(target_family = "wasm")]
             pub static __WBG_TEST_RUN_IN_SERVICE_WORKER: [u8; 1] = [0x04];
             $crate::wasm_bindgen_test_configure!($($others)*);
         };
@@ -101,7 +101,7 @@ macro_rules! wasm_bindgen_test_configure {
     (run_in_node_experimental $($others:tt)*) => (
         const _: () = {
             #[link_section = "__wasm_bindgen_test_unstable"]
-            #[cfg(target_arch = "wasm32")]
+            #[cfg(target_family = "wasm")]
             pub static __WBG_TEST_run_in_node_experimental: [u8; 1] = [0x05];
             $crate::wasm_bindgen_test_configure!($($others)*);
         };
@@ -115,5 +115,5 @@ pub mod __rt;
 // Make this only available to wasm32 so that we don't
 // import minicov on other archs.
 // That way you can use normal cargo test without minicov
-#[cfg(target_arch = "wasm32")]
+#[cfg(target_family = "wasm")]
 mod coverage;
@@ -1,5 +1,6 @@
 use anyhow::{anyhow, bail, Error};
 use std::cmp;
+use std::fmt;
 use walrus::ir::Value;
 use walrus::FunctionBuilder;
 use walrus::{
@@ -15,8 +16,57 @@ const ATOMIC_MEM_ARG: MemArg = MemArg {
     offset: 0,
 };
 
+/// The custom section containing the default stack size of threads other
+/// than the main thread, as a little-endian `u32`.
+pub const STACK_SIZE_SECTION: &str = "__wasm_bindgen_thread_stack_size";
+
 #[derive(Clone, Copy)]
-pub struct ThreadCount(walrus::LocalId);
+pub struct ThreadCount {
+    local: walrus::LocalId,
+    layout: MemoryLayout,
+}
+
+/// The layout of the memory of a module prepared for threads.
+#[derive(Clone, Copy, Debug)]
+pub struct MemoryLayout {
+    /// The address the stack of the main thread grows down from.
+    pub main_stack_top: u32,
+    /// The address range of the extra static data: the thread ID counter,
+    /// a lock and the temporary stack used to allocate other threads' stacks.
+    pub static_data: (u32, u32),
+    /// The default size of the stack allocated for threads other than the
+    /// main thread.
+    pub thread_stack_size: u32,
+    /// The size of the thread-local storage allocated for every thread.
+    pub tls_size: u32,
+    /// The alignment of the thread-local storage.
+    pub tls_align: u32,
+    /// The global holding the base address of the thread-local storage of
+    /// the current thread, exported as `__tls_base`.
+    pub tls_base: GlobalId,
+}
+
+impl fmt::Display for MemoryLayout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "main thread stack:    below {:#x}", self.main_stack_top)?;
+        writeln!(
+            f,
+            "thread static data:   {:#x}..{:#x}",
+            self.static_data.0, self.static_data.1
+        )?;
+        writeln!(
+            f,
+            "thread stacks:        {} bytes each, allocated on the heap",
+            self.thread_stack_size
+        )?;
+        write!(
+            f,
+            "thread-local storage: {} bytes aligned to {}, allocated on the heap, \
+             base address in `__tls_base`",
+            self.tls_size, self.tls_align
+        )
+    }
+}
 
 /// Is threaded Wasm enabled?
 pub fn is_enabled(module: &Module) -> bool {
@@ -51,13 +101,42 @@ pub fn is_enabled(module: &Module) -> bool {
 ///   thread ids for all threads.
 /// * Some stack space is prepared for each thread after the first one.
 ///
+/// The default stack size of threads after the first one is `stack_size` if
+/// given, otherwise the size stored in the [`STACK_SIZE_SECTION`] custom
+/// section, otherwise 2MB. Individual threads can still override it when
+/// they're initialized.
+///
 /// More and/or less may happen here over time, stay tuned!
-pub fn run(module: &mut Module) -> Result<Option<ThreadCount>, Error> {
+pub fn run(module: &mut Module, stack_size: Option<u32>) -> Result<Option<ThreadCount>, Error> {
+    let section = module.customs.remove_raw(STACK_SIZE_SECTION);
     if !is_enabled(module) {
         return Ok(None);
     }
 
     let memory = wasm_conventions::get_memory(module)?;
+    if module.memories.get(memory).memory64 {
+        bail!("threads are not supported with 64-bit memories yet");
+    }
+
+    let stack_size = match (stack_size, section) {
+        (Some(size), _) => size,
+        (None, Some(section)) => match <[u8; 4]>::try_from(section.data.as_slice()) {
+            Ok(bytes) => u32::from_le_bytes(bytes),
+            Err(_) => bail!(
+                "the `{}` custom section must contain a little-endian `u32`",
+                STACK_SIZE_SECTION
+            ),
+        },
+        (None, None) => DEFAULT_THREAD_STACK_SIZE,
+    };
+    if stack_size == 0 || stack_size % PAGE_SIZE != 0 {
+        bail!(
+            "the stack size of threads must be a nonzero multiple of the page size ({} bytes), \
+             found {}",
+            PAGE_SIZE,
+            stack_size
+        );
+    }
 
     // Now we need to allocate extra static memory for:
     // - A thread id counter.
@@ -104,13 +183,27 @@ pub fn run(module: &mut Module) -> Result<Option<ThreadCount>, Error> {
             ValType::I32,
             true,
             false,
-            ConstExpr::Value(Value::I32(DEFAULT_THREAD_STACK_SIZE as i32)),
+            ConstExpr::Value(Value::I32(stack_size as i32)),
         ),
+        default_size: stack_size,
+    };
+
+    let main_stack_top = match module.globals.get(stack.pointer).kind {
+        GlobalKind::Local(ConstExpr::Value(Value::I32(n))) => n as u32,
+        _ => bail!("the stack pointer must be initialized with an `i32` constant"),
+    };
+    let layout = MemoryLayout {
+        main_stack_top,
+        static_data: (base, base + static_data_pages * PAGE_SIZE),
+        thread_stack_size: stack_size,
+        tls_size: tls.size,
+        tls_align: tls.align,
+        tls_base: tls.base,
     };
 
     let _ = module.exports.add("__stack_alloc", stack.alloc);
 
-    let thread_count = inject_start(module, &tls, &stack, thread_counter_addr, memory)?;
+    let local = inject_start(module, &tls, &stack, thread_counter_addr, memory)?;
 
     // we expose a `__wbindgen_thread_destroy()` helper function that deallocates stack space.
     //
@@ -130,14 +223,19 @@ pub fn run(module: &mut Module) -> Result<Option<ThreadCount>, Error> {
     //   call while the leader is destroying its stack! You should make sure that this cannot happen.
     inject_destroy(module, &tls, &stack, memory)?;
 
-    Ok(Some(thread_count))
+    Ok(Some(ThreadCount { local, layout }))
 }
 
 impl ThreadCount {
+    /// Returns the layout of the memory of the transformed module.
+    pub fn layout(&self) -> MemoryLayout {
+        self.layout
+    }
+
     pub fn wrap_start(self, builder: &mut FunctionBuilder, start: FunctionId) {
         // We only want to call the start function if we are in the first thread.
         // The thread counter should be 0 for the first thread.
-        builder.func_body().local_get(self.0).if_else(
+        builder.func_body().local_get(self.local).if_else(
             None,
             |_| {},
             |body| {
@@ -257,6 +355,8 @@ struct Stack {
     alloc: GlobalId,
     /// The size of the stack
     size: GlobalId,
+    /// The default size of the stacks of threads after the first one
+    default_size: u32,
 }
 
 fn inject_start(
@@ -265,7 +365,7 @@ fn inject_start(
     stack: &Stack,
     thread_counter_addr: i32,
     memory: MemoryId,
-) -> Result<ThreadCount, Error> {
+) -> Result<walrus::LocalId, Error> {
     use walrus::ir::*;
 
     let local = module.locals.add(ValType::I32);
@@ -337,7 +437,7 @@ fn inject_start(
     let id = builder.finish(vec![stack_size], &mut module.funcs);
     module.start = Some(id);
 
-    Ok(ThreadCount(thread_count))
+    Ok(thread_count)
 }
 
 fn inject_destroy(
@@ -394,7 +494,7 @@ fn inject_destroy(
             // we're destroying somebody else's stack, so we can use our own
             body.local_get(stack_alloc)
                 .local_get(stack_size)
-                .i32_const(DEFAULT_THREAD_STACK_SIZE as i32)
+                .i32_const(stack.default_size as i32)
                 .local_get(stack_size)
                 .select(None)
                 .i32_const(16)
@@ -476,3 +576,108 @@ fn with_temp_stack(
         .atomic_notify(memory, ATOMIC_MEM_ARG)
         .drop();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn module(memory: &str, section: Option<&[u8]>) -> Module {
+        let wat = format!(
+            r#"
+            (module
+              (import "env" "memory" (memory {}))
+              (func (export "__wasm_init_tls") (param i32))
+              (func (export "__wbindgen_malloc") (param i32 i32) (result i32)
+                i32.const 0)
+              (func (export "__wbindgen_free") (param i32 i32 i32))
+              (global (export "__heap_base") i32 (i32.const 327680))
+              (global (export "__tls_size") i32 (i32.const 128))
+              (global (export "__tls_align") i32 (i32.const 8))
+              (global (export "__tls_base") (mut i32) (i32.const 0))
+              (global (mut i32) (i32.const 65536)))
+            "#,
+            memory
+        );
+        let wasm = wat::parse_str(wat).unwrap();
+        let mut module = Module::from_buffer(&wasm).unwrap();
+        if let Some(data) = section {
+            module.customs.add(walrus::RawCustomSection {
+                name: STACK_SIZE_SECTION.to_string(),
+                data: data.to_vec(),
+            });
+        }
+        module
+    }
+
+    fn layout(section: Option<&[u8]>, stack_size: Option<u32>) -> Result<MemoryLayout, Error> {
+        let mut module = module("6 1024 shared", section);
+        let count = run(&mut module, stack_size)?.unwrap();
+        assert!(module.customs.remove_raw(STACK_SIZE_SECTION).is_none());
+        Ok(count.layout())
+    }
+
+    #[test]
+    fn memory_layout() {
+        let layout = layout(None, None).unwrap();
+        assert_eq!(layout.main_stack_top, 65536);
+        assert_eq!(layout.static_data, (327680, 393216));
+        assert_eq!(layout.thread_stack_size, DEFAULT_THREAD_STACK_SIZE);
+        assert_eq!(layout.tls_size, 128);
+        assert_eq!(layout.tls_align, 8);
+        assert_eq!(
+            layout.to_string(),
+            "main thread stack:    below 0x10000\n\
+             thread static data:   0x50000..0x60000\n\
+             thread stacks:        2097152 bytes each, allocated on the heap\n\
+             thread-local storage: 128 bytes aligned to 8, allocated on the heap, \
+             base address in `__tls_base`"
+        );
+    }
+
+    #[test]
+    fn stack_size_section() {
+        let layout = layout(Some(&(256 * 1024u32).to_le_bytes()), None).unwrap();
+        assert_eq!(layout.thread_stack_size, 256 * 1024);
+    }
+
+    #[test]
+    fn stack_size_overrides_section() {
+        let layout = layout(Some(&(256 * 1024u32).to_le_bytes()), Some(128 * 1024)).unwrap();
+        assert_eq!(layout.thread_stack_size, 128 * 1024);
+    }
+
+    #[test]
+    fn invalid_stack_size_section() {
+        let err = layout(Some(&[0, 0, 4]), None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the `__wasm_bindgen_thread_stack_size` custom section must contain a \
+             little-endian `u32`"
+        );
+    }
+
+    #[test]
+    fn invalid_stack_size() {
+        for (section, size) in [
+            (None, Some(0)),
+            (None, Some(PAGE_SIZE + 1)),
+            (Some(&(PAGE_SIZE / 2).to_le_bytes()[..]), None),
+        ] {
+            let err = layout(section, size).unwrap_err();
+            assert!(
+                err.to_string().starts_with(
+                    "the stack size of threads must be a nonzero multiple of the page size"
+                ),
+                "{}",
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn section_removed_without_threads() {
+        let mut module = module("6 1024", Some(&(256 * 1024u32).to_le_bytes()));
+        assert!(run(&mut module, None).unwrap().is_none());
+        assert!(module.customs.remove_raw(STACK_SIZE_SECTION).is_none());
+    }
+}
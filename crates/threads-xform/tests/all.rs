@@ -23,7 +23,7 @@ fn runtest(test: &Test) -> Result<String> {
         .generate_producers_section(false)
         .parse(&wasm)?;
 
-    wasm_bindgen_threads_xform::run(&mut module)?;
+    wasm_bindgen_threads_xform::run(&mut module, None)?;
     walrus::passes::gc::run(&mut module);
 
     let features = wasmparser::WasmFeatures::default() | wasmparser::WasmFeatures::THREADS;
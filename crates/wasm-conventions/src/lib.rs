@@ -7,22 +7,44 @@
 //! * The canonical linear memory that contains the stack
 
 use std::io::Cursor;
+use std::str;
 
 use anyhow::{anyhow, bail, Context, Result};
 use walrus::{
     ir::Value, ConstExpr, ElementId, ElementItems, FunctionBuilder, FunctionId, FunctionKind,
     GlobalId, GlobalKind, MemoryId, Module, RawCustomSection, ValType,
 };
-use wasmparser::{BinaryReader, WasmFeatures};
+use wasmparser::BinaryReader;
+
+/// The custom section containing the name of the canonical linear memory of a
+/// module with multiple memories.
+pub const MEMORY_SECTION: &str = "__wasm_bindgen_memory";
 
 /// Get a Wasm module's canonical linear memory.
+///
+/// If the module has multiple memories, this is the memory named by the
+/// [`MEMORY_SECTION`] custom section.
 pub fn get_memory(module: &Module) -> Result<MemoryId> {
+    let section = module
+        .customs
+        .iter()
+        .find(|(_, custom)| custom.name() == MEMORY_SECTION);
+    if let Some((_, section)) = section {
+        let section: &RawCustomSection = section
+            .as_any()
+            .downcast_ref()
+            .context("failed to read section")?;
+        let name =
+            str::from_utf8(&section.data).context("the name of the memory isn't valid UTF-8")?;
+        return get_memory_by_name(module, name);
+    }
+
     let mut memories = module.memories.iter().map(|m| m.id());
     let memory = memories.next();
     if memories.next().is_some() {
         bail!(
-            "expected a single memory, found multiple; multiple memories \
-             currently not supported"
+            "expected a single memory, found multiple; choose the memory to use \
+             with `--memory NAME`"
         );
     }
     memory.ok_or_else(|| {
@@ -33,6 +55,35 @@ pub fn get_memory(module: &Module) -> Result<MemoryId> {
     })
 }
 
+/// Get the memory exported as `name`, or named `name` in the name section.
+pub fn get_memory_by_name(module: &Module, name: &str) -> Result<MemoryId> {
+    let exported = module.exports.iter().find_map(|e| match e.item {
+        walrus::ExportItem::Memory(id) if e.name == name => Some(id),
+        _ => None,
+    });
+    exported
+        .or_else(|| {
+            module
+                .memories
+                .iter()
+                .find(|m| m.name.as_deref() == Some(name))
+                .map(|m| m.id())
+        })
+        .ok_or_else(|| anyhow!("module does not have a memory named `{}`", name))
+}
+
+/// Use the memory exported as `name`, or named `name` in the name section, as
+/// the canonical linear memory of the module.
+pub fn set_memory(module: &mut Module, name: &str) -> Result<()> {
+    get_memory_by_name(module, name)?;
+    module.customs.remove_raw(MEMORY_SECTION);
+    module.customs.add(RawCustomSection {
+        name: MEMORY_SECTION.to_string(),
+        data: name.as_bytes().to_vec(),
+    });
+    Ok(())
+}
+
 /// Get the `__stack_pointer`.
 pub fn get_stack_pointer(module: &Module) -> Option<GlobalId> {
     if let Some(g) = module
@@ -46,13 +97,15 @@ pub fn get_stack_pointer(module: &Module) -> Option<GlobalId> {
     let candidates = module
         .globals
         .iter()
-        .filter(|g| g.ty == ValType::I32)
+        .filter(|g| g.ty == ValType::I32 || g.ty == ValType::I64)
         .filter(|g| g.mutable)
         // The stack pointer is guaranteed to not be initialized to 0, and it's
-        // guaranteed to have an i32 initializer, so find globals which are
-        // locally defined, are an i32, and have a nonzero initializer
+        // guaranteed to have an initializer of the pointer type (`i64` with
+        // 64-bit memories), so find globals which are locally defined, are an
+        // i32 or i64, and have a nonzero initializer
         .filter(|g| match g.kind {
             GlobalKind::Local(ConstExpr::Value(Value::I32(n))) => n != 0,
+            GlobalKind::Local(ConstExpr::Value(Value::I64(n))) => n != 0,
             _ => false,
         })
         .collect::<Vec<_>>();
@@ -113,7 +166,10 @@ pub fn get_function_table_entry(module: &Module, idx: u32) -> Result<FunctionTab
             } => *n as u32,
             _ => continue,
         };
-        let idx = (idx - offset) as usize;
+        let Some(idx) = idx.checked_sub(offset) else {
+            continue;
+        };
+        let idx = idx as usize;
 
         let slot = match &segment.items {
             ElementItems::Functions(items) => items.get(idx).map(Some),
@@ -192,7 +248,7 @@ pub fn target_feature(module: &Module, feature: &str) -> Result<bool> {
             .as_any()
             .downcast_ref()
             .context("failed to read section")?;
-        let mut reader = BinaryReader::new(&section.data, 0, WasmFeatures::default());
+        let mut reader = BinaryReader::new(&section.data, 0);
         // The first integer contains the target feature count.
         let count = reader.read_var_u32()?;
 
@@ -237,7 +293,7 @@ pub fn insert_target_feature(module: &mut Module, new_feature: &str) -> Result<(
             .as_any_mut()
             .downcast_mut()
             .context("failed to read section")?;
-        let mut reader = BinaryReader::new(&section.data, 0, WasmFeatures::default());
+        let mut reader = BinaryReader::new(&section.data, 0);
         // The first integer contains the target feature count.
         let count = reader.read_var_u32()?;
 
@@ -15,6 +15,9 @@
 //!
 //! The interpreter here is only geared towards this one exact use case, so it's
 //! quite small and likely not extra-efficient.
+//!
+//! Separately, [`snapshot`] runs a start function at build time with a more
+//! complete interpreter, storing its effects in the module.
 
 #![deny(missing_docs)]
 
@@ -23,6 +26,10 @@ use std::collections::{BTreeMap, BTreeSet, HashMap};
 use walrus::ir::Instr;
 use walrus::{ElementId, FunctionId, LocalId, Module, TableId};
 
+mod snapshot;
+
+pub use snapshot::snapshot;
+
 /// A ready-to-go interpreter of a Wasm module.
 ///
 /// An interpreter currently represents effectively cached state. It is reused
@@ -45,9 +52,12 @@ pub struct Interpreter {
 
     // The current stack pointer (global 0) and Wasm memory (the stack). Only
     // used in a limited capacity.
-    sp: i32,
+    //
+    // Values are held as `i64`s so that both 32 and 64-bit memories, whose
+    // pointers are `i64`s, can be interpreted.
+    sp: i64,
     mem: Vec<i32>,
-    scratch: Vec<i32>,
+    scratch: Vec<i64>,
 
     // The descriptor which we're assembling, a list of `u32` entries. This is
     // very specific to wasm-bindgen and is the purpose for the existence of
@@ -72,7 +82,7 @@ impl Interpreter {
         // Give ourselves some memory and set the stack pointer
         // (the LLVM call stack, now the Wasm stack, global 0) to the top.
         ret.mem = vec![0; 0x8000];
-        ret.sp = ret.mem.len() as i32;
+        ret.sp = ret.mem.len() as i64;
 
         // Figure out where the `__wbindgen_describe` imported function is, if
         // it exists. We'll special case calls to this function as our
@@ -114,7 +124,8 @@ impl Interpreter {
     /// when this `Interpreter` was constructed.
     ///
     /// The `func` must be a wasm-bindgen descriptor function meaning that it
-    /// doesn't do anything like use floats or i64. Instead all it should do is
+    /// doesn't do anything like use floats or i64s other than pointers. Instead
+    /// all it should do is
     /// call other functions, sometimes some stack pointer manipulation, and
     /// then call the one imported `__wbindgen_describe` function. Anything else
     /// will cause this interpreter to panic.
@@ -132,9 +143,9 @@ impl Interpreter {
 
         // We should have a blank Wasm and LLVM stack at both the start and end
         // of the call.
-        assert_eq!(self.sp, self.mem.len() as i32);
+        assert_eq!(self.sp, self.mem.len() as i64);
         self.call(id, module, &[]);
-        assert_eq!(self.sp, self.mem.len() as i32);
+        assert_eq!(self.sp, self.mem.len() as i64);
         Some(&self.descriptor)
     }
 
@@ -172,8 +183,10 @@ impl Interpreter {
         let func = module.funcs.get(id);
         let params = module.types.get(func.ty()).params();
         assert!(
-            params.iter().all(|p| *p == walrus::ValType::I32),
-            "closure descriptors should only have i32 params"
+            params
+                .iter()
+                .all(|p| *p == walrus::ValType::I32 || *p == walrus::ValType::I64),
+            "closure descriptors should only have i32 or i64 params"
         );
         let num_params = params.len();
         assert!(
@@ -215,7 +228,7 @@ impl Interpreter {
         self.functions
     }
 
-    fn call(&mut self, id: FunctionId, module: &Module, args: &[i32]) -> Option<i32> {
+    fn call(&mut self, id: FunctionId, module: &Module, args: &[i64]) -> Option<i64> {
         let func = module.funcs.get(id);
         log::debug!("starting a call of {:?} {:?}", id, func.name);
         log::debug!("arguments {:?}", args);
@@ -259,7 +272,7 @@ impl Interpreter {
 struct Frame<'a> {
     module: &'a Module,
     interp: &'a mut Interpreter,
-    locals: BTreeMap<LocalId, i32>,
+    locals: BTreeMap<LocalId, i64>,
     done: bool,
 }
 
@@ -271,8 +284,9 @@ impl Frame<'_> {
 
         match instr {
             Instr::Const(c) => match c.value {
-                Value::I32(n) => stack.push(n),
-                _ => bail!("non-i32 constant"),
+                Value::I32(n) => stack.push(n.into()),
+                Value::I64(n) => stack.push(n),
+                _ => bail!("non-integer constant"),
             },
             Instr::LocalGet(e) => stack.push(self.locals.get(&e.local).cloned().unwrap_or(0)),
             Instr::LocalSet(e) => {
@@ -297,8 +311,10 @@ impl Frame<'_> {
                 let rhs = stack.pop().unwrap();
                 let lhs = stack.pop().unwrap();
                 stack.push(match e.op {
-                    BinaryOp::I32Sub => lhs - rhs,
-                    BinaryOp::I32Add => lhs + rhs,
+                    BinaryOp::I32Sub => (lhs as i32).wrapping_sub(rhs as i32).into(),
+                    BinaryOp::I32Add => (lhs as i32).wrapping_add(rhs as i32).into(),
+                    BinaryOp::I64Sub => lhs.wrapping_sub(rhs),
+                    BinaryOp::I64Add => lhs.wrapping_add(rhs),
                     op => bail!("invalid binary op {:?}", op),
                 });
             }
@@ -312,9 +328,18 @@ impl Frame<'_> {
                     address > 0,
                     "Read a negative address value from the stack. Did we run out of memory?"
                 );
-                let address = address as u32 + e.arg.offset;
+                let address = address as u64 + u64::from(e.arg.offset);
                 ensure!(address % 4 == 0);
-                stack.push(self.interp.mem[address as usize / 4])
+                let slot = address as usize / 4;
+                stack.push(match e.kind {
+                    // Spilled pointers into 64-bit memories take up two slots.
+                    LoadKind::I64 { .. } => {
+                        let low = self.interp.mem[slot] as u32;
+                        let high = self.interp.mem[slot + 1];
+                        (i64::from(high) << 32) | i64::from(low)
+                    }
+                    _ => self.interp.mem[slot].into(),
+                })
             }
             Instr::Store(e) => {
                 let value = stack.pop().unwrap();
@@ -323,9 +348,13 @@ impl Frame<'_> {
                     address > 0,
                     "Read a negative address value from the stack. Did we run out of memory?"
                 );
-                let address = address as u32 + e.arg.offset;
+                let address = address as u64 + u64::from(e.arg.offset);
                 ensure!(address % 4 == 0);
-                self.interp.mem[address as usize / 4] = value;
+                let slot = address as usize / 4;
+                self.interp.mem[slot] = value as i32;
+                if let StoreKind::I64 { .. } = e.kind {
+                    self.interp.mem[slot + 1] = (value >> 32) as i32;
+                }
             }
 
             Instr::Return(_) => {
@@ -0,0 +1,809 @@
+//! Pre-initialization of a module by running its start function at build time.
+//!
+//! Unlike the descriptor interpreter in the rest of this crate, this is a
+//! general interpreter of the core Wasm instructions a start function usually
+//! consists of: control flow, integer and float arithmetic, memory accesses
+//! and bulk memory operations. It runs the function against the initial state
+//! of the module and writes the resulting memory and globals back into the
+//! module, so that the function doesn't have to run when the module is
+//! instantiated.
+//!
+//! There's no JS to run imported functions though, so calling one is an error
+//! naming the import, as is anything else which depends on the host, like
+//! reading an imported global or memory.
+
+use anyhow::{anyhow, bail, ensure, Context, Error};
+use std::collections::{HashMap, HashSet};
+use walrus::ir::*;
+use walrus::{
+    ConstExpr, DataId, DataKind, FunctionId, FunctionKind, GlobalId, GlobalKind, LocalFunction,
+    LocalId, MemoryId, Module, ValType,
+};
+
+const PAGE_SIZE: u64 = 1 << 16;
+const MAX_PAGES: u64 = 1 << 16;
+
+/// The maximum depth of calls, to report infinite recursion as an error
+/// rather than overflowing the stack of the interpreter itself.
+const MAX_CALL_DEPTH: usize = 512;
+
+/// Runs of zeros shorter than this are kept within a data segment, as
+/// starting a new segment would take about as much space.
+const MIN_GAP: usize = 64;
+
+/// Runs `func` at build time and stores its effects in `module`.
+///
+/// The function must take no arguments and return nothing, like the
+/// `#[wasm_bindgen(start)]` function. It's run against the initial contents of
+/// all memories defined by the module and the initial values of its globals.
+/// Afterwards, the active data segments of the memories are replaced by
+/// segments containing their final contents, the initial size of memories
+/// which grew is updated, and the initializers of mutable globals are set to
+/// their final values. It's up to the caller to make sure `func` isn't run
+/// again when the module is instantiated.
+///
+/// Nothing is changed if running the function fails, for example because it
+/// calls an imported function.
+pub fn snapshot(module: &mut Module, func: FunctionId) -> Result<(), Error> {
+    let ty = module.types.get(module.funcs.get(func).ty());
+    ensure!(
+        ty.params().is_empty() && ty.results().is_empty(),
+        "only functions without parameters and results can be snapshotted"
+    );
+
+    let mut machine = Machine::new(module)?;
+    machine.call(func, Vec::new())?;
+    let Machine {
+        memories, globals, ..
+    } = machine;
+
+    for (id, bytes) in memories {
+        let segments = module
+            .data
+            .iter()
+            .filter(|d| matches!(d.kind, DataKind::Active { memory, .. } if memory == id))
+            .map(|d| d.id())
+            .collect::<Vec<_>>();
+        for segment in segments {
+            module.data.delete(segment);
+            module.memories.get_mut(id).data_segments.remove(&segment);
+        }
+
+        module.memories.get_mut(id).initial = bytes.len() as u64 / PAGE_SIZE;
+        for (start, end) in data_ranges(&bytes) {
+            let kind = DataKind::Active {
+                memory: id,
+                offset: ConstExpr::Value(Value::I32(start as i32)),
+            };
+            let segment = module.data.add(kind, bytes[start..end].to_vec());
+            module.memories.get_mut(id).data_segments.insert(segment);
+        }
+    }
+
+    let ids = module.globals.iter().map(|g| g.id()).collect::<Vec<_>>();
+    for id in ids {
+        let global = module.globals.get_mut(id);
+        if !global.mutable || !matches!(global.kind, GlobalKind::Local(ConstExpr::Value(_))) {
+            continue;
+        }
+        if let Some(value) = globals
+            .get(&id)
+            .and_then(|bits| from_bits(global.ty, *bits))
+        {
+            global.kind = GlobalKind::Local(ConstExpr::Value(value));
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the ranges of `bytes` to store in data segments, skipping long runs
+/// of zeros, which is what memory is initialized with anyway.
+fn data_ranges(bytes: &[u8]) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut start = None;
+    let mut end = 0;
+    for (i, byte) in bytes.iter().enumerate() {
+        if *byte != 0 {
+            start.get_or_insert(i);
+            end = i + 1;
+        } else if let Some(s) = start {
+            if i + 1 - end >= MIN_GAP {
+                ranges.push((s, end));
+                start = None;
+            }
+        }
+    }
+    if let Some(s) = start {
+        ranges.push((s, end));
+    }
+    ranges
+}
+
+/// Values are held as their bits, zero-extended to 64 bits.
+fn to_bits(value: Value) -> Option<u64> {
+    Some(match value {
+        Value::I32(n) => u64::from(n as u32),
+        Value::I64(n) => n as u64,
+        Value::F32(n) => u64::from(n.to_bits()),
+        Value::F64(n) => n.to_bits(),
+        Value::V128(_) => return None,
+    })
+}
+
+fn from_bits(ty: ValType, bits: u64) -> Option<Value> {
+    Some(match ty {
+        ValType::I32 => Value::I32(bits as i32),
+        ValType::I64 => Value::I64(bits as i64),
+        ValType::F32 => Value::F32(f32::from_bits(bits as u32)),
+        ValType::F64 => Value::F64(f64::from_bits(bits)),
+        _ => return None,
+    })
+}
+
+/// The state of the instance the function runs in.
+struct Machine<'a> {
+    module: &'a Module,
+    memories: HashMap<MemoryId, Vec<u8>>,
+    globals: HashMap<GlobalId, u64>,
+    dropped: HashSet<DataId>,
+    stack: Vec<u64>,
+    depth: usize,
+    // Whether the current error already names the function it occurred in.
+    located: bool,
+}
+
+struct Frame<'a> {
+    func: &'a LocalFunction,
+    locals: HashMap<LocalId, u64>,
+}
+
+/// What to do after executing an instruction.
+enum Flow {
+    Next,
+    Branch(InstrSeqId),
+    Return,
+}
+
+impl<'a> Machine<'a> {
+    fn new(module: &'a Module) -> Result<Machine<'a>, Error> {
+        let mut memories = HashMap::new();
+        for memory in module.memories.iter() {
+            // Imported memories are left out, so accessing them is an error.
+            if memory.import.is_some() {
+                continue;
+            }
+            ensure!(
+                !memory.memory64,
+                "snapshotting 64-bit memories isn't supported"
+            );
+            memories.insert(memory.id(), vec![0; (memory.initial * PAGE_SIZE) as usize]);
+        }
+        for data in module.data.iter() {
+            let DataKind::Active { memory, offset } = &data.kind else {
+                continue;
+            };
+            let Some(bytes) = memories.get_mut(memory) else {
+                continue;
+            };
+            let offset = match offset {
+                ConstExpr::Value(Value::I32(n)) => *n as u32 as usize,
+                _ => bail!("data segments must have a constant offset"),
+            };
+            bytes
+                .get_mut(offset..offset + data.value.len())
+                .ok_or_else(|| anyhow!("data segment is out of bounds"))?
+                .copy_from_slice(&data.value);
+        }
+
+        let mut globals = HashMap::new();
+        for global in module.globals.iter() {
+            if let GlobalKind::Local(ConstExpr::Value(value)) = global.kind {
+                if let Some(bits) = to_bits(value) {
+                    globals.insert(global.id(), bits);
+                }
+            }
+        }
+
+        Ok(Machine {
+            module,
+            memories,
+            globals,
+            dropped: HashSet::new(),
+            stack: Vec::new(),
+            depth: 0,
+            located: false,
+        })
+    }
+
+    fn name(&self, id: FunctionId) -> String {
+        match &self.module.funcs.get(id).name {
+            Some(name) => name.clone(),
+            None => format!("function {}", id.index()),
+        }
+    }
+
+    fn call(&mut self, id: FunctionId, args: Vec<u64>) -> Result<Vec<u64>, Error> {
+        let module = self.module;
+        let func = module.funcs.get(id);
+        let local = match &func.kind {
+            FunctionKind::Local(local) => local,
+            FunctionKind::Import(import) => {
+                let import = module.imports.get(import.import);
+                bail!(
+                    "calls the import `{}` of module `{}`, which can't run at build time",
+                    import.name,
+                    import.module
+                )
+            }
+            FunctionKind::Uninitialized(_) => unreachable!(),
+        };
+        ensure!(self.depth < MAX_CALL_DEPTH, "call stack exhausted");
+
+        let mut frame = Frame {
+            func: local,
+            locals: local.args.iter().copied().zip(args).collect(),
+        };
+        let base = self.stack.len();
+        self.depth += 1;
+        let result = self.block(&mut frame, local.entry_block(), false);
+        self.depth -= 1;
+
+        if let Err(err) = result {
+            if self.located {
+                return Err(err);
+            }
+            self.located = true;
+            return Err(err.context(format!("failed to run `{}`", self.name(id))));
+        }
+
+        let results = module.types.get(func.ty()).results().len();
+        let values = self.stack.split_off(self.stack.len() - results);
+        self.stack.truncate(base);
+        Ok(values)
+    }
+
+    fn block(
+        &mut self,
+        frame: &mut Frame<'a>,
+        id: InstrSeqId,
+        is_loop: bool,
+    ) -> Result<Flow, Error> {
+        let func = frame.func;
+        let seq = func.block(id);
+        let (params, results) = match seq.ty {
+            InstrSeqType::Simple(result) => (0, usize::from(result.is_some())),
+            InstrSeqType::MultiValue(ty) => {
+                let ty = self.module.types.get(ty);
+                (ty.params().len(), ty.results().len())
+            }
+        };
+        // The entry block of a function takes the function's arguments as
+        // locals instead.
+        let params = if id == func.entry_block() { 0 } else { params };
+        let height = self.stack.len() - params;
+
+        'restart: loop {
+            for (instr, _) in seq.instrs.iter() {
+                match self.instr(frame, instr)? {
+                    Flow::Next => {}
+                    Flow::Branch(target) if target == id => {
+                        let arity = if is_loop { params } else { results };
+                        let values = self.stack.split_off(self.stack.len() - arity);
+                        self.stack.truncate(height);
+                        self.stack.extend(values);
+                        if is_loop {
+                            continue 'restart;
+                        }
+                        return Ok(Flow::Next);
+                    }
+                    flow => return Ok(flow),
+                }
+            }
+            return Ok(Flow::Next);
+        }
+    }
+
+    fn pop(&mut self) -> u64 {
+        self.stack.pop().expect("value stack is empty")
+    }
+
+    fn pop_n(&mut self, n: usize) -> Vec<u64> {
+        self.stack.split_off(self.stack.len() - n)
+    }
+
+    fn instr(&mut self, frame: &mut Frame<'a>, instr: &'a Instr) -> Result<Flow, Error> {
+        match instr {
+            Instr::Block(e) => return self.block(frame, e.seq, false),
+            Instr::Loop(e) => return self.block(frame, e.seq, true),
+            Instr::IfElse(e) => {
+                let seq = if self.pop() as u32 != 0 {
+                    e.consequent
+                } else {
+                    e.alternative
+                };
+                return self.block(frame, seq, false);
+            }
+            Instr::Br(e) => return Ok(Flow::Branch(e.block)),
+            Instr::BrIf(e) => {
+                if self.pop() as u32 != 0 {
+                    return Ok(Flow::Branch(e.block));
+                }
+            }
+            Instr::BrTable(e) => {
+                let idx = self.pop() as u32 as usize;
+                return Ok(Flow::Branch(
+                    e.blocks.get(idx).copied().unwrap_or(e.default),
+                ));
+            }
+            Instr::Return(_) => return Ok(Flow::Return),
+            Instr::Unreachable(_) => bail!("executed `unreachable`, the code probably panicked"),
+
+            Instr::Call(Call { func }) | Instr::ReturnCall(ReturnCall { func }) => {
+                self.call_func(*func)?;
+                if let Instr::ReturnCall(_) = instr {
+                    return Ok(Flow::Return);
+                }
+            }
+            Instr::CallIndirect(e) => {
+                let func = self.table_entry(e.ty)?;
+                self.call_func(func)?;
+            }
+            Instr::ReturnCallIndirect(e) => {
+                let func = self.table_entry(e.ty)?;
+                self.call_func(func)?;
+                return Ok(Flow::Return);
+            }
+
+            Instr::Const(e) => {
+                let value = to_bits(e.value).context("SIMD isn't supported")?;
+                self.stack.push(value);
+            }
+            Instr::Drop(_) => {
+                self.pop();
+            }
+            Instr::Select(_) => {
+                let condition = self.pop() as u32;
+                let b = self.pop();
+                let a = self.pop();
+                self.stack.push(if condition != 0 { a } else { b });
+            }
+            Instr::LocalGet(e) => {
+                let value = frame.locals.get(&e.local).copied().unwrap_or(0);
+                self.stack.push(value);
+            }
+            Instr::LocalSet(e) => {
+                let value = self.pop();
+                frame.locals.insert(e.local, value);
+            }
+            Instr::LocalTee(e) => {
+                let value = *self.stack.last().expect("value stack is empty");
+                frame.locals.insert(e.local, value);
+            }
+            Instr::GlobalGet(e) => {
+                let value = match self.globals.get(&e.global) {
+                    Some(value) => *value,
+                    None => bail!("reads a global which isn't known at build time"),
+                };
+                self.stack.push(value);
+            }
+            Instr::GlobalSet(e) => {
+                ensure!(
+                    matches!(self.module.globals.get(e.global).kind, GlobalKind::Local(_)),
+                    "writes an imported global"
+                );
+                let value = self.pop();
+                self.globals.insert(e.global, value);
+            }
+
+            Instr::Binop(e) => {
+                let b = self.pop();
+                let a = self.pop();
+                self.stack.push(binop(e.op, a, b)?);
+            }
+            Instr::Unop(e) => {
+                let a = self.pop();
+                self.stack.push(unop(e.op, a)?);
+            }
+
+            Instr::Load(e) => {
+                let (len, signed, wide) = match e.kind {
+                    LoadKind::I32 { .. } | LoadKind::F32 => (4, false, false),
+                    LoadKind::I64 { .. } | LoadKind::F64 => (8, false, true),
+                    LoadKind::I32_8 { kind } => {
+                        (1, matches!(kind, ExtendedLoad::SignExtend), false)
+                    }
+                    LoadKind::I32_16 { kind } => {
+                        (2, matches!(kind, ExtendedLoad::SignExtend), false)
+                    }
+                    LoadKind::I64_8 { kind } => (1, matches!(kind, ExtendedLoad::SignExtend), true),
+                    LoadKind::I64_16 { kind } => {
+                        (2, matches!(kind, ExtendedLoad::SignExtend), true)
+                    }
+                    LoadKind::I64_32 { kind } => {
+                        (4, matches!(kind, ExtendedLoad::SignExtend), true)
+                    }
+                    LoadKind::V128 => bail!("SIMD isn't supported"),
+                };
+                let address = self.pop();
+                let bytes = self.access(e.memory, address, u64::from(e.arg.offset), len)?;
+                let mut value = bytes
+                    .iter()
+                    .rev()
+                    .fold(0, |value, byte| (value << 8) | u64::from(*byte));
+                if signed {
+                    let shift = 64 - 8 * len as u32;
+                    value = (((value << shift) as i64) >> shift) as u64;
+                }
+                if !wide {
+                    value = u64::from(value as u32);
+                }
+                self.stack.push(value);
+            }
+            Instr::Store(e) => {
+                let len = match e.kind {
+                    StoreKind::I32_8 { .. } | StoreKind::I64_8 { .. } => 1,
+                    StoreKind::I32_16 { .. } | StoreKind::I64_16 { .. } => 2,
+                    StoreKind::I32 { .. } | StoreKind::F32 | StoreKind::I64_32 { .. } => 4,
+                    StoreKind::I64 { .. } | StoreKind::F64 => 8,
+                    StoreKind::V128 => bail!("SIMD isn't supported"),
+                };
+                let value = self.pop();
+                let address = self.pop();
+                let bytes = self.access(e.memory, address, u64::from(e.arg.offset), len)?;
+                bytes.copy_from_slice(&value.to_le_bytes()[..len]);
+            }
+            Instr::MemorySize(e) => {
+                let pages = self.memory(e.memory)?.len() as u64 / PAGE_SIZE;
+                self.stack.push(pages);
+            }
+            Instr::MemoryGrow(e) => {
+                let delta = u64::from(self.pop() as u32);
+                let maximum = self.module.memories.get(e.memory).maximum;
+                let maximum = maximum.map_or(MAX_PAGES, |m| m.min(MAX_PAGES));
+                let memory = self.memory(e.memory)?;
+                let pages = memory.len() as u64 / PAGE_SIZE;
+                if pages + delta <= maximum {
+                    memory.resize(((pages + delta) * PAGE_SIZE) as usize, 0);
+                    self.stack.push(pages);
+                } else {
+                    self.stack.push(u64::from(u32::MAX));
+                }
+            }
+            Instr::MemoryFill(e) => {
+                let len = self.pop() as u32 as usize;
+                let value = self.pop() as u8;
+                let address = self.pop();
+                self.access(e.memory, address, 0, len)?.fill(value);
+            }
+            Instr::MemoryCopy(e) => {
+                let len = self.pop() as u32 as usize;
+                let src = self.pop();
+                let dst = self.pop();
+                let bytes = self.access(e.src, src, 0, len)?.to_vec();
+                self.access(e.dst, dst, 0, len)?.copy_from_slice(&bytes);
+            }
+            Instr::MemoryInit(e) => {
+                let len = self.pop() as u32 as usize;
+                let src = self.pop() as u32 as usize;
+                let dst = self.pop();
+                let module = self.module;
+                let data = if self.dropped.contains(&e.data) {
+                    &[][..]
+                } else {
+                    &module.data.get(e.data).value[..]
+                };
+                let data = data
+                    .get(src..src + len)
+                    .ok_or_else(|| anyhow!("out of bounds data segment access"))?;
+                self.access(e.memory, dst, 0, len)?.copy_from_slice(data);
+            }
+            Instr::DataDrop(e) => {
+                self.dropped.insert(e.data);
+            }
+
+            instr => bail!("unsupported instruction {:?}", instr),
+        }
+
+        Ok(Flow::Next)
+    }
+
+    fn call_func(&mut self, func: FunctionId) -> Result<(), Error> {
+        let params = self
+            .module
+            .types
+            .get(self.module.funcs.get(func).ty())
+            .params()
+            .len();
+        let args = self.pop_n(params);
+        let results = self.call(func, args)?;
+        self.stack.extend(results);
+        Ok(())
+    }
+
+    /// Looks up the function called indirectly by the index on the stack.
+    fn table_entry(&mut self, ty: walrus::TypeId) -> Result<FunctionId, Error> {
+        let idx = self.pop() as u32;
+        let entry = wasm_bindgen_wasm_conventions::get_function_table_entry(self.module, idx)?;
+        let func = entry
+            .func
+            .ok_or_else(|| anyhow!("calls the null function reference at {}", idx))?;
+        let expected = self.module.types.get(ty);
+        let actual = self.module.types.get(self.module.funcs.get(func).ty());
+        ensure!(
+            expected.params() == actual.params() && expected.results() == actual.results(),
+            "indirect call type mismatch"
+        );
+        Ok(func)
+    }
+
+    fn memory(&mut self, id: MemoryId) -> Result<&mut Vec<u8>, Error> {
+        self.memories
+            .get_mut(&id)
+            .ok_or_else(|| anyhow!("accesses an imported memory"))
+    }
+
+    fn access(
+        &mut self,
+        memory: MemoryId,
+        address: u64,
+        offset: u64,
+        len: usize,
+    ) -> Result<&mut [u8], Error> {
+        let start = u64::from(address as u32) + offset;
+        let memory = self.memory(memory)?;
+        usize::try_from(start)
+            .ok()
+            .and_then(|start| memory.get_mut(start..start.checked_add(len)?))
+            .ok_or_else(|| anyhow!("out of bounds memory access at {:#x}", start))
+    }
+}
+
+fn binop(op: BinaryOp, a: u64, b: u64) -> Result<u64, Error> {
+    use BinaryOp::*;
+
+    let (a32, b32) = (a as u32, b as u32);
+    let (fa32, fb32) = (f32::from_bits(a32), f32::from_bits(b32));
+    let (fa64, fb64) = (f64::from_bits(a), f64::from_bits(b));
+
+    let flag = |b: bool| u64::from(b);
+    let int32 = |n: u32| u64::from(n);
+    let float32 = |n: f32| u64::from(n.to_bits());
+
+    Ok(match op {
+        I32Eq => flag(a32 == b32),
+        I32Ne => flag(a32 != b32),
+        I32LtS => flag((a32 as i32) < (b32 as i32)),
+        I32LtU => flag(a32 < b32),
+        I32GtS => flag((a32 as i32) > (b32 as i32)),
+        I32GtU => flag(a32 > b32),
+        I32LeS => flag((a32 as i32) <= (b32 as i32)),
+        I32LeU => flag(a32 <= b32),
+        I32GeS => flag((a32 as i32) >= (b32 as i32)),
+        I32GeU => flag(a32 >= b32),
+
+        I64Eq => flag(a == b),
+        I64Ne => flag(a != b),
+        I64LtS => flag((a as i64) < (b as i64)),
+        I64LtU => flag(a < b),
+        I64GtS => flag((a as i64) > (b as i64)),
+        I64GtU => flag(a > b),
+        I64LeS => flag((a as i64) <= (b as i64)),
+        I64LeU => flag(a <= b),
+        I64GeS => flag((a as i64) >= (b as i64)),
+        I64GeU => flag(a >= b),
+
+        F32Eq => flag(fa32 == fb32),
+        F32Ne => flag(fa32 != fb32),
+        F32Lt => flag(fa32 < fb32),
+        F32Gt => flag(fa32 > fb32),
+        F32Le => flag(fa32 <= fb32),
+        F32Ge => flag(fa32 >= fb32),
+
+        F64Eq => flag(fa64 == fb64),
+        F64Ne => flag(fa64 != fb64),
+        F64Lt => flag(fa64 < fb64),
+        F64Gt => flag(fa64 > fb64),
+        F64Le => flag(fa64 <= fb64),
+        F64Ge => flag(fa64 >= fb64),
+
+        I32Add => int32(a32.wrapping_add(b32)),
+        I32Sub => int32(a32.wrapping_sub(b32)),
+        I32Mul => int32(a32.wrapping_mul(b32)),
+        I32DivS => {
+            ensure!(b32 != 0, "integer divide by zero");
+            let (n, overflow) = (a32 as i32).overflowing_div(b32 as i32);
+            ensure!(!overflow, "integer overflow");
+            int32(n as u32)
+        }
+        I32DivU => int32(a32.checked_div(b32).context("integer divide by zero")?),
+        I32RemS => {
+            ensure!(b32 != 0, "integer divide by zero");
+            int32((a32 as i32).wrapping_rem(b32 as i32) as u32)
+        }
+        I32RemU => int32(a32.checked_rem(b32).context("integer divide by zero")?),
+        I32And => int32(a32 & b32),
+        I32Or => int32(a32 | b32),
+        I32Xor => int32(a32 ^ b32),
+        I32Shl => int32(a32.wrapping_shl(b32)),
+        I32ShrS => int32((a32 as i32).wrapping_shr(b32) as u32),
+        I32ShrU => int32(a32.wrapping_shr(b32)),
+        I32Rotl => int32(a32.rotate_left(b32 % 32)),
+        I32Rotr => int32(a32.rotate_right(b32 % 32)),
+
+        I64Add => a.wrapping_add(b),
+        I64Sub => a.wrapping_sub(b),
+        I64Mul => a.wrapping_mul(b),
+        I64DivS => {
+            ensure!(b != 0, "integer divide by zero");
+            let (n, overflow) = (a as i64).overflowing_div(b as i64);
+            ensure!(!overflow, "integer overflow");
+            n as u64
+        }
+        I64DivU => a.checked_div(b).context("integer divide by zero")?,
+        I64RemS => {
+            ensure!(b != 0, "integer divide by zero");
+            (a as i64).wrapping_rem(b as i64) as u64
+        }
+        I64RemU => a.checked_rem(b).context("integer divide by zero")?,
+        I64And => a & b,
+        I64Or => a | b,
+        I64Xor => a ^ b,
+        I64Shl => a.wrapping_shl(b as u32),
+        I64ShrS => (a as i64).wrapping_shr(b as u32) as u64,
+        I64ShrU => a.wrapping_shr(b as u32),
+        I64Rotl => a.rotate_left((b % 64) as u32),
+        I64Rotr => a.rotate_right((b % 64) as u32),
+
+        F32Add => float32(fa32 + fb32),
+        F32Sub => float32(fa32 - fb32),
+        F32Mul => float32(fa32 * fb32),
+        F32Div => float32(fa32 / fb32),
+        F32Min => float32(min(fa32.into(), fb32.into()) as f32),
+        F32Max => float32(max(fa32.into(), fb32.into()) as f32),
+        F32Copysign => float32(fa32.copysign(fb32)),
+
+        F64Add => (fa64 + fb64).to_bits(),
+        F64Sub => (fa64 - fb64).to_bits(),
+        F64Mul => (fa64 * fb64).to_bits(),
+        F64Div => (fa64 / fb64).to_bits(),
+        F64Min => min(fa64, fb64).to_bits(),
+        F64Max => max(fa64, fb64).to_bits(),
+        F64Copysign => fa64.copysign(fb64).to_bits(),
+
+        op => bail!("unsupported instruction {:?}", op),
+    })
+}
+
+fn unop(op: UnaryOp, a: u64) -> Result<u64, Error> {
+    use UnaryOp::*;
+
+    let a32 = a as u32;
+    let (fa32, fa64) = (f32::from_bits(a32), f64::from_bits(a));
+
+    let int32 = |n: u32| u64::from(n);
+    let float32 = |n: f32| u64::from(n.to_bits());
+
+    Ok(match op {
+        I32Eqz => u64::from(a32 == 0),
+        I32Clz => a32.leading_zeros().into(),
+        I32Ctz => a32.trailing_zeros().into(),
+        I32Popcnt => a32.count_ones().into(),
+        I64Eqz => u64::from(a == 0),
+        I64Clz => a.leading_zeros().into(),
+        I64Ctz => a.trailing_zeros().into(),
+        I64Popcnt => a.count_ones().into(),
+
+        // The sign of a float is its highest bit, even for NaNs.
+        F32Abs => int32(a32 & !(1 << 31)),
+        F32Neg => int32(a32 ^ (1 << 31)),
+        F32Ceil => float32(fa32.ceil()),
+        F32Floor => float32(fa32.floor()),
+        F32Trunc => float32(fa32.trunc()),
+        F32Nearest => float32(nearest(fa32.into()) as f32),
+        F32Sqrt => float32(fa32.sqrt()),
+        F64Abs => a & !(1 << 63),
+        F64Neg => a ^ (1 << 63),
+        F64Ceil => fa64.ceil().to_bits(),
+        F64Floor => fa64.floor().to_bits(),
+        F64Trunc => fa64.trunc().to_bits(),
+        F64Nearest => nearest(fa64).to_bits(),
+        F64Sqrt => fa64.sqrt().to_bits(),
+
+        I32WrapI64 => int32(a32),
+        I64ExtendSI32 => a32 as i32 as i64 as u64,
+        I64ExtendUI32 => a32.into(),
+        I32Extend8S => int32(a32 as i8 as i32 as u32),
+        I32Extend16S => int32(a32 as i16 as i32 as u32),
+        I64Extend8S => a as i8 as i64 as u64,
+        I64Extend16S => a as i16 as i64 as u64,
+        I64Extend32S => a as i32 as i64 as u64,
+
+        I32TruncSF32 => int32(trunc(fa32.into(), -2147483648.0, 2147483648.0)? as i32 as u32),
+        I32TruncUF32 => int32(trunc(fa32.into(), 0.0, 4294967296.0)? as u32),
+        I32TruncSF64 => int32(trunc(fa64, -2147483648.0, 2147483648.0)? as i32 as u32),
+        I32TruncUF64 => int32(trunc(fa64, 0.0, 4294967296.0)? as u32),
+        I64TruncSF32 => {
+            trunc(fa32.into(), -9223372036854775808.0, 9223372036854775808.0)? as i64 as u64
+        }
+        I64TruncUF32 => trunc(fa32.into(), 0.0, 18446744073709551616.0)? as u64,
+        I64TruncSF64 => trunc(fa64, -9223372036854775808.0, 9223372036854775808.0)? as i64 as u64,
+        I64TruncUF64 => trunc(fa64, 0.0, 18446744073709551616.0)? as u64,
+
+        // Casts between floats and integers in Rust saturate, like these
+        // instructions.
+        I32TruncSSatF32 => int32(fa32 as i32 as u32),
+        I32TruncUSatF32 => int32(fa32 as u32),
+        I32TruncSSatF64 => int32(fa64 as i32 as u32),
+        I32TruncUSatF64 => int32(fa64 as u32),
+        I64TruncSSatF32 => fa32 as i64 as u64,
+        I64TruncUSatF32 => fa32 as u64,
+        I64TruncSSatF64 => fa64 as i64 as u64,
+        I64TruncUSatF64 => fa64 as u64,
+
+        F32ConvertSI32 => float32(a32 as i32 as f32),
+        F32ConvertUI32 => float32(a32 as f32),
+        F32ConvertSI64 => float32(a as i64 as f32),
+        F32ConvertUI64 => float32(a as f32),
+        F32DemoteF64 => float32(fa64 as f32),
+        F64ConvertSI32 => (a32 as i32 as f64).to_bits(),
+        F64ConvertUI32 => (a32 as f64).to_bits(),
+        F64ConvertSI64 => (a as i64 as f64).to_bits(),
+        F64ConvertUI64 => (a as f64).to_bits(),
+        F64PromoteF32 => f64::from(fa32).to_bits(),
+
+        // Values are held as their bits already.
+        I32ReinterpretF32 | F32ReinterpretI32 => int32(a32),
+        I64ReinterpretF64 | F64ReinterpretI64 => a,
+
+        op => bail!("unsupported instruction {:?}", op),
+    })
+}
+
+/// Truncates `x`, which must be within `min..max` afterwards.
+fn trunc(x: f64, min: f64, max: f64) -> Result<f64, Error> {
+    ensure!(!x.is_nan(), "invalid conversion to integer");
+    let x = x.trunc();
+    ensure!(x >= min && x < max, "integer overflow");
+    Ok(x)
+}
+
+/// `f64::min`, except that NaNs propagate and -0 is less than 0.
+fn min(a: f64, b: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        f64::NAN
+    } else if a == b {
+        if a.is_sign_negative() {
+            a
+        } else {
+            b
+        }
+    } else {
+        a.min(b)
+    }
+}
+
+/// `f64::max`, except that NaNs propagate and 0 is greater than -0.
+fn max(a: f64, b: f64) -> f64 {
+    if a.is_nan() || b.is_nan() {
+        f64::NAN
+    } else if a == b {
+        if a.is_sign_positive() {
+            a
+        } else {
+            b
+        }
+    } else {
+        a.max(b)
+    }
+}
+
+/// Rounds to the nearest integer, with ties to even.
+fn nearest(x: f64) -> f64 {
+    let rounded = x.round();
+    if (x - x.trunc()).abs() == 0.5 {
+        2.0 * (x / 2.0).round()
+    } else {
+        rounded
+    }
+}
@@ -94,6 +94,50 @@ fn globals() {
     interpret(wat, "foo", Some(&[32768]));
 }
 
+#[test]
+fn memory64() {
+    let wat = r#"
+        (module
+            (import "__wbindgen_placeholder__" "__wbindgen_describe"
+              (func $__wbindgen_describe (param i32)))
+
+            (global (mut i64) (i64.const 32768))
+            (memory i64 1)
+
+            (func $foo
+                (local i64)
+                global.get 0
+                i64.const 16
+                i64.sub
+                local.tee 0
+                global.set 0
+
+                ;; spill the stack pointer
+                local.get 0
+                local.get 0
+                i64.store offset=8
+
+                local.get 0
+                i32.const 7
+                i32.store
+                local.get 0
+                i32.load
+                call $__wbindgen_describe
+
+                ;; restore the stack pointer from the spilled one
+                local.get 0
+                i64.load offset=8
+                i64.const 16
+                i64.add
+                global.set 0
+            )
+
+            (export "foo" (func $foo))
+        )
+    "#;
+    interpret(wat, "foo", Some(&[7]));
+}
+
 #[test]
 fn arithmetic() {
     let wat = r#"
@@ -0,0 +1,164 @@
+use walrus::ir::Value;
+use walrus::{ConstExpr, DataKind, GlobalKind, Module, ModuleConfig};
+
+fn parse(wat: &str) -> Module {
+    let wasm = wat::parse_str(wat).unwrap();
+    ModuleConfig::new()
+        .generate_producers_section(false)
+        .parse(&wasm)
+        .unwrap()
+}
+
+fn snapshot(module: &mut Module) -> anyhow::Result<()> {
+    let id = module.exports.get_func("start").unwrap();
+    wasm_bindgen_wasm_interpreter::snapshot(module, id)
+}
+
+fn segments(module: &Module) -> Vec<(i32, Vec<u8>)> {
+    let mut segments = module
+        .data
+        .iter()
+        .map(|d| match d.kind {
+            DataKind::Active {
+                offset: ConstExpr::Value(Value::I32(offset)),
+                ..
+            } => (offset, d.value.clone()),
+            _ => panic!("unexpected data segment"),
+        })
+        .collect::<Vec<_>>();
+    segments.sort();
+    segments
+}
+
+fn global(module: &Module, name: &str) -> Value {
+    let id = module
+        .exports
+        .iter()
+        .find(|e| e.name == name)
+        .and_then(|e| match e.item {
+            walrus::ExportItem::Global(g) => Some(g),
+            _ => None,
+        })
+        .unwrap();
+    match module.globals.get(id).kind {
+        GlobalKind::Local(ConstExpr::Value(value)) => value,
+        _ => panic!("unexpected global"),
+    }
+}
+
+#[test]
+fn memory_and_globals() {
+    let mut module = parse(
+        r#"
+        (module
+            (memory 1)
+            (global $counter (mut i32) (i32.const 0))
+            (data (i32.const 8) "\01\02")
+
+            (func $start
+                (local $i i32)
+                ;; Count to 5 in a loop.
+                (loop $continue
+                    local.get $i
+                    i32.const 1
+                    i32.add
+                    local.tee $i
+                    i32.const 5
+                    i32.lt_u
+                    br_if $continue)
+                local.get $i
+                global.set $counter
+
+                ;; Overwrite the first byte of the existing segment.
+                i32.const 8
+                i32.const 3
+                i32.store8
+
+                i32.const 1024
+                i64.const 0x0807060504030201
+                i64.store)
+
+            (export "start" (func $start))
+            (export "counter" (global $counter))
+        )
+    "#,
+    );
+    snapshot(&mut module).unwrap();
+
+    assert_eq!(
+        segments(&module),
+        [(8, vec![3, 2]), (1024, vec![1, 2, 3, 4, 5, 6, 7, 8])]
+    );
+    assert!(matches!(global(&module, "counter"), Value::I32(5)));
+}
+
+#[test]
+fn memory_grow() {
+    let mut module = parse(
+        r#"
+        (module
+            (memory 1)
+
+            (func $start
+                i32.const 1
+                memory.grow
+                drop
+                i32.const 0x10000
+                i32.const 0xff
+                i32.store8)
+
+            (export "start" (func $start))
+        )
+    "#,
+    );
+    snapshot(&mut module).unwrap();
+
+    let memory = module.memories.iter().next().unwrap();
+    assert_eq!(memory.initial, 2);
+    assert_eq!(segments(&module), [(0x10000, vec![0xff])]);
+}
+
+#[test]
+fn calls_import() {
+    let mut module = parse(
+        r#"
+        (module
+            (import "./snippet.js" "log" (func $log))
+            (memory 1)
+
+            (func $start
+                i32.const 0
+                i32.const 1
+                i32.store8
+                call $log)
+
+            (export "start" (func $start))
+        )
+    "#,
+    );
+    let err = snapshot(&mut module).unwrap_err();
+    let msg = format!("{:?}", err);
+    assert!(msg.contains("`log`"), "{}", msg);
+    assert!(msg.contains("`./snippet.js`"), "{}", msg);
+    // The module is left untouched.
+    assert!(segments(&module).is_empty());
+}
+
+#[test]
+fn traps() {
+    let mut module = parse(
+        r#"
+        (module
+            (memory 1)
+
+            (func $start
+                i32.const 0x10000
+                i32.load
+                drop)
+
+            (export "start" (func $start))
+        )
+    "#,
+    );
+    assert!(snapshot(&mut module).is_err());
+}
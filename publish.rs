@@ -29,6 +29,7 @@ const CRATES_TO_PUBLISH: &[&str] = &[
     "wasm-bindgen-threads-xform",
     "wasm-bindgen-multi-value-xform",
     "wasm-bindgen-externref-xform",
+    "wasm-bindgen-split-xform",
     "wasm-bindgen-cli-support",
     "wasm-bindgen-cli",
     "wasm-bindgen",
@@ -8,8 +8,12 @@
 
 use alloc::boxed::Box;
 use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::RefCell;
 use core::fmt;
+use core::marker::PhantomData;
 use core::mem::{self, ManuallyDrop};
+use core::ops::Deref;
 
 use crate::convert::*;
 use crate::describe::*;
@@ -31,8 +35,19 @@ use crate::UnwrapThrowExt;
 /// location in your program.
 ///
 /// The type parameter on `Closure` is the type of closure that this represents.
-/// Currently this can only be the `Fn` and `FnMut` traits with up to 7
-/// arguments (and an optional return value).
+/// Currently this can only be the `Fn` and `FnMut` traits with up to 16
+/// arguments (and an optional return value). Closures with up to 4 arguments
+/// may also take any of their arguments by reference, for example
+/// `dyn FnMut(&str, u32)`. Referenced arguments are only lent to the closure by
+/// JS for the duration of the call, just like arguments of exported functions.
+///
+/// A closure may return a `Result<T, E>` where `E: Into<JsValue>` (such as
+/// [`JsError`](crate::JsError)), in which case returning `Err` will throw the
+/// error in JS.
+///
+/// Closures returning a `Future` can be passed to JS as functions returning a
+/// `Promise` with `Closure::new_async`, which is provided by the
+/// `AsyncClosureExt` trait of the `wasm-bindgen-futures` crate.
 ///
 /// # Examples
 ///
@@ -264,7 +279,8 @@ where
     /// * It must be `'static`, aka no stack references (use the `move`
     ///   keyword).
     ///
-    /// * It can have at most 7 arguments.
+    /// * It can have at most 16 arguments, or at most 4 if any of them are
+    ///   taken by reference.
     ///
     /// * Its arguments and return values are all types that can be shared with
     ///   JS (i.e. have `#[wasm_bindgen]` annotations or are simple numbers,
@@ -340,7 +356,7 @@ where
         #[inline(never)]
         #[cfg_attr(wasm_bindgen_unstable_test_coverage, coverage(off))]
         unsafe fn breaks_if_inlined<T: WasmClosure + ?Sized>(a: usize, b: usize) -> u32 {
-            super::__wbindgen_describe_closure(a as u32, b as u32, describe::<T> as usize as u32)
+            super::__wbindgen_describe_closure(a, b, describe::<T> as usize as u32)
         }
 
         let idx = unsafe { breaks_if_inlined::<T>(a, b) };
@@ -372,6 +388,9 @@ where
     }
 
     /// Same as `into_js_value`, but doesn't return a value.
+    ///
+    /// As with `into_js_value`, the Rust memory is reclaimed once the JS
+    /// closure is GC'd if the platform supports weak references.
     pub fn forget(self) {
         drop(self.into_js_value());
     }
@@ -441,10 +460,208 @@ impl Closure<dyn FnOnce()> {
     {
         fn_once.into_js_function()
     }
+
+    /// Create closures which are allowed to borrow from the stack for the
+    /// duration of `f`.
+    ///
+    /// Unlike `Closure::new`, the closures created through the provided
+    /// [`ClosureScope`] don't need to be `'static`, which makes it convenient
+    /// to pass callbacks borrowing local state to synchronous JS APIs such as
+    /// `Array.prototype.forEach` or a sort comparator.
+    ///
+    /// All closures created through the scope are dropped when `f` returns, at
+    /// which point their JS functions are invalidated: JS can keep a reference
+    /// to them, but calling them afterwards throws an `Error` saying the
+    /// closure was invoked after the end of its scope, rather than accessing
+    /// freed memory. If one of them is still running at that point, for
+    /// example because its invocation was suspended, the program aborts as the
+    /// closure would outlive the data it borrows.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use wasm_bindgen::prelude::*;
+    ///
+    /// #[wasm_bindgen]
+    /// extern "C" {
+    ///     fn for_each_number(callback: &Closure<dyn FnMut(u32)>);
+    /// }
+    ///
+    /// let mut total = 0;
+    /// Closure::scoped(|scope| {
+    ///     let callback = scope.closure(|n: u32| total += n);
+    ///     for_each_number(&callback);
+    /// });
+    /// ```
+    pub fn scoped<'env, F, R>(f: F) -> R
+    where
+        F: for<'scope> FnOnce(&'scope ClosureScope<'scope, 'env>) -> R,
+    {
+        let scope = ClosureScope {
+            closures: ScopedClosures(RefCell::new(Vec::new())),
+            scope: PhantomData,
+            env: PhantomData,
+        };
+        f(&scope)
+    }
+}
+
+/// A scope to create closures borrowing from the stack in, see
+/// `Closure::scoped`.
+///
+/// Like `std::thread::Scope`, `'scope` is the lifetime of the scope itself and
+/// `'env` the lifetime of anything borrowed by the closures, which outlives
+/// the scope.
+pub struct ClosureScope<'scope, 'env: 'scope> {
+    closures: ScopedClosures,
+    // Make both lifetimes invariant, see `std::thread::Scope`.
+    scope: PhantomData<&'scope mut &'scope ()>,
+    env: PhantomData<&'env mut &'env ()>,
+}
+
+impl<'scope> ClosureScope<'scope, '_> {
+    /// Creates a new closure which lives until the end of this scope.
+    ///
+    /// This has the same requirements as `Closure::new`, except that the
+    /// closure only needs to outlive the scope instead of being `'static`.
+    pub fn closure<T, F>(&'scope self, t: F) -> ScopedClosure<'scope, T>
+    where
+        T: ?Sized + WasmClosure + 'static,
+        F: IntoScopedWasmClosure<'scope, T> + 'scope,
+    {
+        // SAFETY: the closure is owned by this scope which drops it, and with
+        // that invalidates the JS function, before `'scope` ends.
+        let closure = Box::new(Closure::wrap(unsafe { Box::new(t).unsize_scoped() }));
+        let ptr: *const Closure<T> = &*closure;
+        self.closures.0.borrow_mut().push(closure);
+        ScopedClosure {
+            // SAFETY: the closure is boxed, so its address doesn't change, and
+            // it isn't dropped before the scope itself.
+            closure: unsafe { &*ptr },
+        }
+    }
+}
+
+impl fmt::Debug for ClosureScope<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ClosureScope {{ ... }}")
+    }
+}
+
+// The closures are type erased to `'static`, which keeps `ClosureScope` free of
+// drop glue depending on `'scope` and lets `Closure::scoped` drop it after `f`
+// returns.
+struct ScopedClosures(RefCell<Vec<Box<dyn ScopedRelease>>>);
+
+impl Drop for ScopedClosures {
+    fn drop(&mut self) {
+        let closures = mem::take(self.0.get_mut());
+
+        // Invalidate all JS functions before dropping any of the Rust
+        // closures, as dropping one may call back into JS, which must not be
+        // able to call any of the others anymore.
+        let mut running = false;
+        let mut finished = Vec::with_capacity(closures.len());
+        for closure in closures {
+            if closure.invalidate() {
+                finished.push(closure);
+            } else {
+                // JS drops the Rust closure once it returns, which is too late
+                // as it may borrow from data which is about to go away.
+                running = true;
+                mem::forget(closure);
+            }
+        }
+        for closure in finished {
+            closure.free();
+        }
+
+        // Only abort once everything else is torn down, so that nothing can
+        // observe the scope half-dropped. Throwing isn't enough, as a `catch`
+        // import further up the stack could resume the closure.
+        if running {
+            scoped_closure_running();
+        }
+    }
+}
+
+#[cold]
+fn scoped_closure_running() -> ! {
+    cfg_if::cfg_if! {
+        if #[cfg(feature = "std")] {
+            std::process::abort();
+        } else if #[cfg(all(
+            target_arch = "wasm32",
+            any(target_os = "unknown", target_os = "none")
+        ))] {
+            core::arch::wasm32::unreachable();
+        } else {
+            unreachable!("scoped closure is still running at the end of its scope")
+        }
+    }
+}
+
+/// A handle to a closure created with `ClosureScope::closure`.
+///
+/// This dereferences to a `Closure`, so it can be passed to any imported JS
+/// function expecting a `&Closure`.
+pub struct ScopedClosure<'scope, T: ?Sized> {
+    closure: &'scope Closure<T>,
+}
+
+impl<T: ?Sized> Clone for ScopedClosure<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for ScopedClosure<'_, T> {}
+
+impl<T: ?Sized> Deref for ScopedClosure<'_, T> {
+    type Target = Closure<T>;
+
+    fn deref(&self) -> &Closure<T> {
+        self.closure
+    }
+}
+
+impl<T: ?Sized> AsRef<JsValue> for ScopedClosure<'_, T> {
+    fn as_ref(&self) -> &JsValue {
+        self.closure.as_ref()
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for ScopedClosure<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ScopedClosure {{ ... }}")
+    }
+}
+
+trait ScopedRelease {
+    /// Invalidates the JS function, returning `false` if the closure is still
+    /// running, in which case JS takes ownership of it.
+    fn invalidate(&self) -> bool;
+
+    /// Drops the Rust closure of an invalidated JS function.
+    fn free(self: Box<Self>);
+}
+
+impl<T: ?Sized> ScopedRelease for Closure<T> {
+    fn invalidate(&self) -> bool {
+        unsafe { super::__wbindgen_cb_drop_scoped(self.js.idx) != 0 }
+    }
+
+    fn free(self: Box<Self>) {
+        let mut closure = ManuallyDrop::new(*self);
+        unsafe { ManuallyDrop::drop(&mut closure.data) };
+    }
 }
 
 /// A trait for converting an `FnOnce(A...) -> R` into a `FnMut(A...) -> R` that
 /// will throw if ever called more than once.
+///
+/// `A` is a `fn` pointer type with the same arguments as the closure, which
+/// keeps the impls for owned and borrowed arguments apart.
 #[doc(hidden)]
 pub trait WasmClosureFnOnce<A, R>: 'static {
     type FnMut: ?Sized + 'static + WasmClosure;
@@ -543,6 +760,21 @@ pub trait IntoWasmClosure<T: ?Sized> {
     fn unsize(self: Box<Self>) -> Box<T>;
 }
 
+/// An internal trait for the `ClosureScope` type.
+///
+/// This trait is not stable and it's not recommended to use this in bounds or
+/// implement yourself.
+#[doc(hidden)]
+pub trait IntoScopedWasmClosure<'a, T: ?Sized> {
+    /// Unsizes a closure which may borrow data for `'a` into a `'static`
+    /// closure trait object.
+    ///
+    /// # Safety
+    ///
+    /// The returned closure must not be used after `'a` ends.
+    unsafe fn unsize_scoped(self: Box<Self>) -> Box<T>;
+}
+
 // The memory safety here in these implementations below is a bit tricky. We
 // want to be able to drop the `Closure` object from within the invocation of a
 // `Closure` for cases like promises. That means that while it's running we
@@ -558,26 +790,23 @@ pub trait IntoWasmClosure<T: ?Sized> {
 // deallocate data immediately.
 
 macro_rules! doit {
-    ($(
-        ($($var:ident $arg1:ident $arg2:ident $arg3:ident $arg4:ident)*)
-    )*) => ($(
+    ($( ($var:ident $kind:ident [$($bound:tt)*] $arg1:ident $arg2:ident $arg3:ident $arg4:ident) )*) => {
         #[allow(coherence_leak_check)]
-        unsafe impl<$($var,)* R> WasmClosure for dyn Fn($($var),*) -> R + 'static
-            where $($var: FromWasmAbi + 'static,)*
-                  R: ReturnWasmAbi + 'static,
+        unsafe impl<$($var: $($bound)* + 'static,)* R> WasmClosure for dyn Fn($(closure_arg_ty!($kind $var)),*) -> R + 'static
+            where R: ReturnWasmAbi + 'static,
         {
             #[cfg_attr(wasm_bindgen_unstable_test_coverage, coverage(off))]
             fn describe() {
                 #[allow(non_snake_case)]
                 #[cfg_attr(wasm_bindgen_unstable_test_coverage, coverage(off))]
-                unsafe extern "C" fn invoke<$($var: FromWasmAbi,)* R: ReturnWasmAbi>(
+                unsafe extern "C" fn invoke<$($var: $($bound)*,)* R: ReturnWasmAbi>(
                     a: usize,
                     b: usize,
                     $(
-                    $arg1: <$var::Abi as WasmAbi>::Prim1,
-                    $arg2: <$var::Abi as WasmAbi>::Prim2,
-                    $arg3: <$var::Abi as WasmAbi>::Prim3,
-                    $arg4: <$var::Abi as WasmAbi>::Prim4,
+                    $arg1: <<$var as WasmClosureArg<$kind>>::Abi as WasmAbi>::Prim1,
+                    $arg2: <<$var as WasmClosureArg<$kind>>::Abi as WasmAbi>::Prim2,
+                    $arg3: <<$var as WasmClosureArg<$kind>>::Abi as WasmAbi>::Prim3,
+                    $arg4: <<$var as WasmClosureArg<$kind>>::Abi as WasmAbi>::Prim4,
                     )*
                 ) -> WasmRet<R::Abi> {
                     if a == 0 {
@@ -587,19 +816,20 @@ macro_rules! doit {
                     // convert `ret` as it may throw (for `Result`, for
                     // example)
                     let ret = {
-                        let f: *const dyn Fn($($var),*) -> R =
+                        let f: *const dyn Fn($(closure_arg_ty!($kind $var)),*) -> R =
                             FatPtr { fields: (a, b) }.ptr;
                         $(
-                            let $var = <$var as FromWasmAbi>::from_abi($var::Abi::join($arg1, $arg2, $arg3, $arg4));
+                            let $var = <$var as WasmClosureArg<$kind>>::arg_from_abi(WasmAbi::join($arg1, $arg2, $arg3, $arg4));
                         )*
-                        (*f)($($var),*)
+                        (*f)($(closure_arg!($kind $var)),*)
                     };
                     ret.return_abi().into()
                 }
 
                 inform(invoke::<$($var,)* R> as usize as u32);
 
-                unsafe extern fn destroy<$($var: FromWasmAbi,)* R: ReturnWasmAbi>(
+                #[cfg_attr(wasm_bindgen_unstable_test_coverage, coverage(off))]
+                unsafe extern "C" fn destroy<$($var: $($bound)*,)* R: ReturnWasmAbi>(
                     a: usize,
                     b: usize,
                 ) {
@@ -611,7 +841,7 @@ macro_rules! doit {
                     if a == 0 {
                         return;
                     }
-                    drop(Box::from_raw(FatPtr::<dyn Fn($($var,)*) -> R> {
+                    drop(Box::from_raw(FatPtr::<dyn Fn($(closure_arg_ty!($kind $var)),*) -> R> {
                         fields: (a, b)
                     }.ptr));
                 }
@@ -622,22 +852,21 @@ macro_rules! doit {
         }
 
         #[allow(coherence_leak_check)]
-        unsafe impl<$($var,)* R> WasmClosure for dyn FnMut($($var),*) -> R + 'static
-            where $($var: FromWasmAbi + 'static,)*
-                  R: ReturnWasmAbi + 'static,
+        unsafe impl<$($var: $($bound)* + 'static,)* R> WasmClosure for dyn FnMut($(closure_arg_ty!($kind $var)),*) -> R + 'static
+            where R: ReturnWasmAbi + 'static,
         {
             #[cfg_attr(wasm_bindgen_unstable_test_coverage, coverage(off))]
             fn describe() {
                 #[allow(non_snake_case)]
                 #[cfg_attr(wasm_bindgen_unstable_test_coverage, coverage(off))]
-                unsafe extern "C" fn invoke<$($var: FromWasmAbi,)* R: ReturnWasmAbi>(
+                unsafe extern "C" fn invoke<$($var: $($bound)*,)* R: ReturnWasmAbi>(
                     a: usize,
                     b: usize,
                     $(
-                    $arg1: <$var::Abi as WasmAbi>::Prim1,
-                    $arg2: <$var::Abi as WasmAbi>::Prim2,
-                    $arg3: <$var::Abi as WasmAbi>::Prim3,
-                    $arg4: <$var::Abi as WasmAbi>::Prim4,
+                    $arg1: <<$var as WasmClosureArg<$kind>>::Abi as WasmAbi>::Prim1,
+                    $arg2: <<$var as WasmClosureArg<$kind>>::Abi as WasmAbi>::Prim2,
+                    $arg3: <<$var as WasmClosureArg<$kind>>::Abi as WasmAbi>::Prim3,
+                    $arg4: <<$var as WasmClosureArg<$kind>>::Abi as WasmAbi>::Prim4,
                     )*
                 ) -> WasmRet<R::Abi> {
                     if a == 0 {
@@ -647,20 +876,21 @@ macro_rules! doit {
                     // convert `ret` as it may throw (for `Result`, for
                     // example)
                     let ret = {
-                        let f: *const dyn FnMut($($var),*) -> R =
+                        let f: *const dyn FnMut($(closure_arg_ty!($kind $var)),*) -> R =
                             FatPtr { fields: (a, b) }.ptr;
-                        let f = f as *mut dyn FnMut($($var),*) -> R;
+                        let f = f as *mut dyn FnMut($(closure_arg_ty!($kind $var)),*) -> R;
                         $(
-                            let $var = <$var as FromWasmAbi>::from_abi($var::Abi::join($arg1, $arg2, $arg3, $arg4));
+                            let $var = <$var as WasmClosureArg<$kind>>::arg_from_abi(WasmAbi::join($arg1, $arg2, $arg3, $arg4));
                         )*
-                        (*f)($($var),*)
+                        (*f)($(closure_arg!($kind $var)),*)
                     };
                     ret.return_abi().into()
                 }
 
                 inform(invoke::<$($var,)* R> as usize as u32);
 
-                unsafe extern fn destroy<$($var: FromWasmAbi,)* R: ReturnWasmAbi>(
+                #[cfg_attr(wasm_bindgen_unstable_test_coverage, coverage(off))]
+                unsafe extern "C" fn destroy<$($var: $($bound)*,)* R: ReturnWasmAbi>(
                     a: usize,
                     b: usize,
                 ) {
@@ -668,7 +898,7 @@ macro_rules! doit {
                     if a == 0 {
                         return;
                     }
-                    drop(Box::from_raw(FatPtr::<dyn FnMut($($var,)*) -> R> {
+                    drop(Box::from_raw(FatPtr::<dyn FnMut($(closure_arg_ty!($kind $var)),*) -> R> {
                         fields: (a, b)
                     }.ptr));
                 }
@@ -678,17 +908,16 @@ macro_rules! doit {
             }
         }
 
-        #[allow(non_snake_case, unused_parens)]
-        impl<T, $($var,)* R> WasmClosureFnOnce<($($var),*), R> for T
-            where T: 'static + FnOnce($($var),*) -> R,
-                  $($var: FromWasmAbi + 'static,)*
+        #[allow(coherence_leak_check, non_snake_case)]
+        impl<T, $($var: $($bound)* + 'static,)* R> WasmClosureFnOnce<fn($(closure_arg_ty!($kind $var)),*), R> for T
+            where T: 'static + FnOnce($(closure_arg_ty!($kind $var)),*) -> R,
                   R: ReturnWasmAbi + 'static
         {
-            type FnMut = dyn FnMut($($var),*) -> R;
+            type FnMut = dyn FnMut($(closure_arg_ty!($kind $var)),*) -> R;
 
             fn into_fn_mut(self) -> Box<Self::FnMut> {
                 let mut me = Some(self);
-                Box::new(move |$($var),*| {
+                Box::new(move |$($var: closure_arg_ty!($kind $var)),*| {
                     let me = me.take().expect_throw("FnOnce called more than once");
                     me($($var),*)
                 })
@@ -703,7 +932,7 @@ macro_rules! doit {
                 let rc1 = Rc::new(WasmRefCell::new(None));
                 let rc2 = rc1.clone();
 
-                let closure = Closure::wrap(Box::new(move |$($var),*| {
+                let closure = Closure::wrap(Box::new(move |$($var: closure_arg_ty!($kind $var)),*| {
                     // Invoke ourself and get the result.
                     let me = me.take().expect_throw("FnOnce called more than once");
                     let result = me($($var),*);
@@ -716,7 +945,7 @@ macro_rules! doit {
                     drop(option_closure);
 
                     result
-                }) as Box<dyn FnMut($($var),*) -> R>);
+                }) as Box<dyn FnMut($(closure_arg_ty!($kind $var)),*) -> R>);
 
                 let js_val = closure.as_ref().clone();
 
@@ -728,188 +957,58 @@ macro_rules! doit {
             }
         }
 
-        impl<T, $($var,)* R> IntoWasmClosure<dyn FnMut($($var),*) -> R> for T
-            where T: 'static + FnMut($($var),*) -> R,
-                  $($var: FromWasmAbi + 'static,)*
+        #[allow(coherence_leak_check)]
+        impl<T, $($var: $($bound)* + 'static,)* R> IntoWasmClosure<dyn FnMut($(closure_arg_ty!($kind $var)),*) -> R> for T
+            where T: 'static + FnMut($(closure_arg_ty!($kind $var)),*) -> R,
                   R: ReturnWasmAbi + 'static,
         {
-            fn unsize(self: Box<Self>) -> Box<dyn FnMut($($var),*) -> R> { self }
+            fn unsize(self: Box<Self>) -> Box<dyn FnMut($(closure_arg_ty!($kind $var)),*) -> R> { self }
         }
 
-        impl<T, $($var,)* R> IntoWasmClosure<dyn Fn($($var),*) -> R> for T
-            where T: 'static + Fn($($var),*) -> R,
-                  $($var: FromWasmAbi + 'static,)*
+        #[allow(coherence_leak_check)]
+        impl<'a, T, $($var: $($bound)* + 'static,)* R> IntoScopedWasmClosure<'a, dyn FnMut($(closure_arg_ty!($kind $var)),*) -> R> for T
+            where T: 'a + FnMut($(closure_arg_ty!($kind $var)),*) -> R,
                   R: ReturnWasmAbi + 'static,
         {
-            fn unsize(self: Box<Self>) -> Box<dyn Fn($($var),*) -> R> { self }
-        }
-    )*)
-}
-
-doit! {
-    ()
-    (A a1 a2 a3 a4)
-    (A a1 a2 a3 a4 B b1 b2 b3 b4)
-    (A a1 a2 a3 a4 B b1 b2 b3 b4 C c1 c2 c3 c4)
-    (A a1 a2 a3 a4 B b1 b2 b3 b4 C c1 c2 c3 c4 D d1 d2 d3 d4)
-    (A a1 a2 a3 a4 B b1 b2 b3 b4 C c1 c2 c3 c4 D d1 d2 d3 d4 E e1 e2 e3 e4)
-    (A a1 a2 a3 a4 B b1 b2 b3 b4 C c1 c2 c3 c4 D d1 d2 d3 d4 E e1 e2 e3 e4 F f1 f2 f3 f4)
-    (A a1 a2 a3 a4 B b1 b2 b3 b4 C c1 c2 c3 c4 D d1 d2 d3 d4 E e1 e2 e3 e4 F f1 f2 f3 f4 G g1 g2 g3 g4)
-    (A a1 a2 a3 a4 B b1 b2 b3 b4 C c1 c2 c3 c4 D d1 d2 d3 d4 E e1 e2 e3 e4 F f1 f2 f3 f4 G g1 g2 g3 g4 H h1 h2 h3 h4)
-}
-
-// Copy the above impls down here for where there's only one argument and it's a
-// reference. We could add more impls for more kinds of references, but it
-// becomes a combinatorial explosion quickly. Let's see how far we can get with
-// just this one! Maybe someone else can figure out voodoo so we don't have to
-// duplicate.
-
-unsafe impl<A, R> WasmClosure for dyn Fn(&A) -> R
-where
-    A: RefFromWasmAbi,
-    R: ReturnWasmAbi + 'static,
-{
-    #[cfg_attr(wasm_bindgen_unstable_test_coverage, coverage(off))]
-    fn describe() {
-        #[allow(non_snake_case)]
-        #[cfg_attr(wasm_bindgen_unstable_test_coverage, coverage(off))]
-        unsafe extern "C" fn invoke<A: RefFromWasmAbi, R: ReturnWasmAbi>(
-            a: usize,
-            b: usize,
-            arg1: <A::Abi as WasmAbi>::Prim1,
-            arg2: <A::Abi as WasmAbi>::Prim2,
-            arg3: <A::Abi as WasmAbi>::Prim3,
-            arg4: <A::Abi as WasmAbi>::Prim4,
-        ) -> WasmRet<R::Abi> {
-            if a == 0 {
-                throw_str("closure invoked after being dropped");
-            }
-            // Make sure all stack variables are converted before we
-            // convert `ret` as it may throw (for `Result`, for
-            // example)
-            let ret = {
-                let f: *const dyn Fn(&A) -> R = FatPtr { fields: (a, b) }.ptr;
-                let arg = <A as RefFromWasmAbi>::ref_from_abi(A::Abi::join(arg1, arg2, arg3, arg4));
-                (*f)(&*arg)
-            };
-            ret.return_abi().into()
-        }
-
-        inform(invoke::<A, R> as usize as u32);
-
-        #[cfg_attr(wasm_bindgen_unstable_test_coverage, coverage(off))]
-        unsafe extern "C" fn destroy<A: RefFromWasmAbi, R: ReturnWasmAbi>(a: usize, b: usize) {
-            // See `Fn()` above for why we simply return
-            if a == 0 {
-                return;
+            unsafe fn unsize_scoped(self: Box<Self>) -> Box<dyn FnMut($(closure_arg_ty!($kind $var)),*) -> R> {
+                let closure: Box<dyn FnMut($(closure_arg_ty!($kind $var)),*) -> R + 'a> = self;
+                // SAFETY: this only erases `'a` from the trait object, which
+                // has the same layout either way. The caller guarantees that
+                // the closure isn't used after `'a` ends: `ScopedClosures`
+                // invalidates the JS function, after which calls throw
+                // instead of reaching Rust, before it frees the closure, and
+                // it does so before the scope ends. If the closure is still
+                // running at that point the program aborts.
+                mem::transmute(closure)
             }
-            drop(Box::from_raw(
-                FatPtr::<dyn Fn(&A) -> R> { fields: (a, b) }.ptr,
-            ));
         }
-        inform(destroy::<A, R> as usize as u32);
 
-        <&Self>::describe();
-    }
-}
-
-unsafe impl<A, R> WasmClosure for dyn FnMut(&A) -> R
-where
-    A: RefFromWasmAbi,
-    R: ReturnWasmAbi + 'static,
-{
-    #[cfg_attr(wasm_bindgen_unstable_test_coverage, coverage(off))]
-    fn describe() {
-        #[allow(non_snake_case)]
-        #[cfg_attr(wasm_bindgen_unstable_test_coverage, coverage(off))]
-        unsafe extern "C" fn invoke<A: RefFromWasmAbi, R: ReturnWasmAbi>(
-            a: usize,
-            b: usize,
-            arg1: <A::Abi as WasmAbi>::Prim1,
-            arg2: <A::Abi as WasmAbi>::Prim2,
-            arg3: <A::Abi as WasmAbi>::Prim3,
-            arg4: <A::Abi as WasmAbi>::Prim4,
-        ) -> WasmRet<R::Abi> {
-            if a == 0 {
-                throw_str("closure invoked recursively or after being dropped");
+        #[allow(coherence_leak_check)]
+        impl<'a, T, $($var: $($bound)* + 'static,)* R> IntoScopedWasmClosure<'a, dyn Fn($(closure_arg_ty!($kind $var)),*) -> R> for T
+            where T: 'a + Fn($(closure_arg_ty!($kind $var)),*) -> R,
+                  R: ReturnWasmAbi + 'static,
+        {
+            unsafe fn unsize_scoped(self: Box<Self>) -> Box<dyn Fn($(closure_arg_ty!($kind $var)),*) -> R> {
+                let closure: Box<dyn Fn($(closure_arg_ty!($kind $var)),*) -> R + 'a> = self;
+                // SAFETY: this only erases `'a` from the trait object, which
+                // has the same layout either way. The caller guarantees that
+                // the closure isn't used after `'a` ends: `ScopedClosures`
+                // invalidates the JS function, after which calls throw
+                // instead of reaching Rust, before it frees the closure, and
+                // it does so before the scope ends. If the closure is still
+                // running at that point the program aborts.
+                mem::transmute(closure)
             }
-            // Make sure all stack variables are converted before we
-            // convert `ret` as it may throw (for `Result`, for
-            // example)
-            let ret = {
-                let f: *const dyn FnMut(&A) -> R = FatPtr { fields: (a, b) }.ptr;
-                let f = f as *mut dyn FnMut(&A) -> R;
-                let arg = <A as RefFromWasmAbi>::ref_from_abi(A::Abi::join(arg1, arg2, arg3, arg4));
-                (*f)(&*arg)
-            };
-            ret.return_abi().into()
         }
 
-        inform(invoke::<A, R> as usize as u32);
-
-        #[cfg_attr(wasm_bindgen_unstable_test_coverage, coverage(off))]
-        unsafe extern "C" fn destroy<A: RefFromWasmAbi, R: ReturnWasmAbi>(a: usize, b: usize) {
-            // See `Fn()` above for why we simply return
-            if a == 0 {
-                return;
-            }
-            drop(Box::from_raw(
-                FatPtr::<dyn FnMut(&A) -> R> { fields: (a, b) }.ptr,
-            ));
+        #[allow(coherence_leak_check)]
+        impl<T, $($var: $($bound)* + 'static,)* R> IntoWasmClosure<dyn Fn($(closure_arg_ty!($kind $var)),*) -> R> for T
+            where T: 'static + Fn($(closure_arg_ty!($kind $var)),*) -> R,
+                  R: ReturnWasmAbi + 'static,
+        {
+            fn unsize(self: Box<Self>) -> Box<dyn Fn($(closure_arg_ty!($kind $var)),*) -> R> { self }
         }
-        inform(destroy::<A, R> as usize as u32);
-
-        <&mut Self>::describe();
-    }
+    };
 }
 
-#[allow(non_snake_case)]
-impl<T, A, R> WasmClosureFnOnce<(&A,), R> for T
-where
-    T: 'static + FnOnce(&A) -> R,
-    A: RefFromWasmAbi + 'static,
-    R: ReturnWasmAbi + 'static,
-{
-    type FnMut = dyn FnMut(&A) -> R;
-
-    fn into_fn_mut(self) -> Box<Self::FnMut> {
-        let mut me = Some(self);
-        Box::new(move |arg| {
-            let me = me.take().expect_throw("FnOnce called more than once");
-            me(arg)
-        })
-    }
-
-    fn into_js_function(self) -> JsValue {
-        use crate::__rt::WasmRefCell;
-        use alloc::rc::Rc;
-
-        let mut me = Some(self);
-
-        let rc1 = Rc::new(WasmRefCell::new(None));
-        let rc2 = rc1.clone();
-
-        let closure = Closure::wrap(Box::new(move |arg: &A| {
-            // Invoke ourself and get the result.
-            let me = me.take().expect_throw("FnOnce called more than once");
-            let result = me(arg);
-
-            // And then drop the `Rc` holding this function's `Closure`
-            // alive.
-            debug_assert_eq!(Rc::strong_count(&rc2), 1);
-            let option_closure = rc2.borrow_mut().take();
-            debug_assert!(option_closure.is_some());
-            drop(option_closure);
-
-            result
-        }) as Box<dyn FnMut(&A) -> R>);
-
-        let js_val = closure.as_ref().clone();
-
-        *rc1.borrow_mut() = Some(closure);
-        debug_assert_eq!(Rc::strong_count(&rc1), 2);
-        drop(rc1);
-
-        js_val
-    }
-}
+closure_signatures!(doit);
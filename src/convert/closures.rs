@@ -8,246 +8,286 @@ use crate::convert::{FromWasmAbi, IntoWasmAbi, ReturnWasmAbi, WasmAbi, WasmRet};
 use crate::describe::{inform, WasmDescribe, FUNCTION};
 use crate::throw_str;
 
+/// Marker for closure arguments which are passed by value.
+#[doc(hidden)]
+pub struct Owned;
+
+/// Marker for closure arguments which are passed by reference, where JS lends
+/// the value to Rust for the duration of the call just like it does for
+/// exported functions taking `&T`.
+#[doc(hidden)]
+pub struct Borrowed;
+
+/// An internal trait abstracting over how a closure argument is recovered
+/// from its ABI representation, either as an owned value or as an anchor which
+/// keeps a borrowed value alive while the closure runs.
+///
+/// This trait is not stable and it's not recommended to use this in bounds or
+/// implement yourself.
+#[doc(hidden)]
+pub trait WasmClosureArg<K> {
+    type Abi: WasmAbi;
+    type Anchor;
+
+    /// Recover the argument from its ABI representation.
+    ///
+    /// # Safety
+    ///
+    /// Same as `FromWasmAbi::from_abi` and `RefFromWasmAbi::ref_from_abi`.
+    unsafe fn arg_from_abi(js: Self::Abi) -> Self::Anchor;
+
+    fn describe_arg();
+}
+
+impl<T: FromWasmAbi> WasmClosureArg<Owned> for T {
+    type Abi = T::Abi;
+    type Anchor = T;
+
+    #[inline]
+    unsafe fn arg_from_abi(js: T::Abi) -> T {
+        T::from_abi(js)
+    }
+
+    #[cfg_attr(wasm_bindgen_unstable_test_coverage, coverage(off))]
+    fn describe_arg() {
+        T::describe();
+    }
+}
+
+impl<T: RefFromWasmAbi + ?Sized> WasmClosureArg<Borrowed> for T {
+    type Abi = T::Abi;
+    type Anchor = T::Anchor;
+
+    #[inline]
+    unsafe fn arg_from_abi(js: T::Abi) -> T::Anchor {
+        T::ref_from_abi(js)
+    }
+
+    #[cfg_attr(wasm_bindgen_unstable_test_coverage, coverage(off))]
+    fn describe_arg() {
+        <&T>::describe();
+    }
+}
+
+/// Expands to the type of a closure argument in a `dyn Fn` signature.
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __wbindgen_closure_arg_ty {
+    (Owned $var:ident) => {
+        $var
+    };
+    (Borrowed $var:ident) => {
+        &$var
+    };
+}
+
+/// Expands to the expression passing a recovered closure argument (see
+/// `WasmClosureArg`) to the Rust closure.
+macro_rules! closure_arg {
+    (Owned $var:ident) => {
+        $var
+    };
+    (Borrowed $var:ident) => {
+        &*$var
+    };
+}
+
+/// Invokes `$cb!` once for every closure signature that `Closure`, `&dyn Fn`
+/// and `&mut dyn FnMut` support.
+///
+/// Each argument is described as `(Type Kind [bounds] prim1 prim2 prim3
+/// prim4)` where `Kind` is either `Owned` or `Borrowed`. Every combination of
+/// owned and borrowed arguments is generated for closures of up to four
+/// arguments, which is where the number of impls starts to grow out of hand.
+/// Closures with more arguments, up to sixteen, take all arguments by value.
+///
+/// This is exported for `wasm-bindgen-futures`, so that the signatures of its
+/// async closures can't drift apart from the ones of `Closure`.
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __wbindgen_closure_signatures {
+    ($cb:ident) => {
+        $crate::__wbindgen_closure_signatures! {
+            @mixed $cb []
+            A a1 a2 a3 a4
+            B b1 b2 b3 b4
+            C c1 c2 c3 c4
+            D d1 d2 d3 d4
+        }
+        $crate::__wbindgen_closure_signatures! {
+            @owned $cb [
+                (A Owned [$crate::convert::FromWasmAbi] a1 a2 a3 a4)
+                (B Owned [$crate::convert::FromWasmAbi] b1 b2 b3 b4)
+                (C Owned [$crate::convert::FromWasmAbi] c1 c2 c3 c4)
+                (D Owned [$crate::convert::FromWasmAbi] d1 d2 d3 d4)
+            ]
+            E e1 e2 e3 e4
+            F f1 f2 f3 f4
+            G g1 g2 g3 g4
+            H h1 h2 h3 h4
+            I i1 i2 i3 i4
+            J j1 j2 j3 j4
+            K k1 k2 k3 k4
+            L l1 l2 l3 l4
+            M m1 m2 m3 m4
+            N n1 n2 n3 n4
+            O o1 o2 o3 o4
+            P p1 p2 p3 p4
+        }
+    };
+
+    (@mixed $cb:ident [$($done:tt)*]) => {
+        $cb! { $($done)* }
+    };
+    (@mixed $cb:ident [$($done:tt)*] $var:ident $a1:ident $a2:ident $a3:ident $a4:ident $($rest:tt)*) => {
+        $cb! { $($done)* }
+        $crate::__wbindgen_closure_signatures! { @mixed $cb [$($done)* ($var Owned [$crate::convert::FromWasmAbi] $a1 $a2 $a3 $a4)] $($rest)* }
+        $crate::__wbindgen_closure_signatures! { @next $cb [$($done)* ($var Borrowed [?Sized + $crate::convert::RefFromWasmAbi] $a1 $a2 $a3 $a4)] $($rest)* }
+    };
+    // Same as `@mixed`, but without emitting the signature we started with as
+    // that was already done by the `Owned` branch above.
+    (@next $cb:ident [$($done:tt)*]) => {
+        $cb! { $($done)* }
+    };
+    (@next $cb:ident [$($done:tt)*] $var:ident $a1:ident $a2:ident $a3:ident $a4:ident $($rest:tt)*) => {
+        $crate::__wbindgen_closure_signatures! { @mixed $cb [$($done)*] $var $a1 $a2 $a3 $a4 $($rest)* }
+    };
+
+    (@owned $cb:ident [$($done:tt)*]) => {};
+    (@owned $cb:ident [$($done:tt)*] $var:ident $a1:ident $a2:ident $a3:ident $a4:ident $($rest:tt)*) => {
+        $cb! { $($done)* ($var Owned [$crate::convert::FromWasmAbi] $a1 $a2 $a3 $a4) }
+        $crate::__wbindgen_closure_signatures! { @owned $cb [$($done)* ($var Owned [$crate::convert::FromWasmAbi] $a1 $a2 $a3 $a4)] $($rest)* }
+    };
+}
+
+/// Expands to the number of closure arguments passed to it.
+macro_rules! closure_arg_count {
+    () => (0);
+    ($head:tt $($rest:tt)*) => (1 + closure_arg_count!($($rest)*));
+}
+
+pub(crate) use crate::{
+    __wbindgen_closure_arg_ty as closure_arg_ty,
+    __wbindgen_closure_signatures as closure_signatures,
+};
+pub(crate) use closure_arg;
+
 macro_rules! stack_closures {
-    ($( ($cnt:tt $invoke:ident $invoke_mut:ident $($var:ident $arg1:ident $arg2:ident $arg3:ident $arg4:ident)*) )*) => ($(
+    ($( ($var:ident $kind:ident [$($bound:tt)*] $arg1:ident $arg2:ident $arg3:ident $arg4:ident) )*) => {
         #[allow(coherence_leak_check)]
-        impl<$($var,)* R> IntoWasmAbi for &'_ (dyn Fn($($var),*) -> R + '_)
-            where $($var: FromWasmAbi,)*
-                  R: ReturnWasmAbi
+        impl<$($var: $($bound)*,)* R> IntoWasmAbi for &'_ (dyn Fn($(closure_arg_ty!($kind $var)),*) -> R + '_)
+            where R: ReturnWasmAbi
         {
             type Abi = WasmSlice;
 
             fn into_abi(self) -> WasmSlice {
                 unsafe {
                     let (a, b): (usize, usize) = mem::transmute(self);
-                    WasmSlice { ptr: a as u32, len: b as u32 }
+                    WasmSlice { ptr: a, len: b }
                 }
             }
         }
 
-        #[allow(non_snake_case)]
-        unsafe extern "C" fn $invoke<$($var: FromWasmAbi,)* R: ReturnWasmAbi>(
-            a: usize,
-            b: usize,
-            $(
-            $arg1: <$var::Abi as WasmAbi>::Prim1,
-            $arg2: <$var::Abi as WasmAbi>::Prim2,
-            $arg3: <$var::Abi as WasmAbi>::Prim3,
-            $arg4: <$var::Abi as WasmAbi>::Prim4,
-            )*
-        ) -> WasmRet<R::Abi> {
-            if a == 0 {
-                throw_str("closure invoked after being dropped");
-            }
-            // Scope all local variables before we call `return_abi` to
-            // ensure they're all destroyed as `return_abi` may throw
-            let ret = {
-                let f: &dyn Fn($($var),*) -> R = mem::transmute((a, b));
-                $(
-                    let $var = <$var as FromWasmAbi>::from_abi($var::Abi::join($arg1, $arg2, $arg3, $arg4));
-                )*
-                f($($var),*)
-            };
-            ret.return_abi().into()
-        }
-
         #[allow(coherence_leak_check)]
-        impl<$($var,)* R> WasmDescribe for dyn Fn($($var),*) -> R + '_
-            where $($var: FromWasmAbi,)*
-                  R: ReturnWasmAbi
+        impl<$($var: $($bound)*,)* R> WasmDescribe for dyn Fn($(closure_arg_ty!($kind $var)),*) -> R + '_
+            where R: ReturnWasmAbi
         {
             #[cfg_attr(wasm_bindgen_unstable_test_coverage, coverage(off))]
             fn describe() {
+                #[allow(non_snake_case)]
+                unsafe extern "C" fn invoke<$($var: $($bound)*,)* R: ReturnWasmAbi>(
+                    a: usize,
+                    b: usize,
+                    $(
+                    $arg1: <<$var as WasmClosureArg<$kind>>::Abi as WasmAbi>::Prim1,
+                    $arg2: <<$var as WasmClosureArg<$kind>>::Abi as WasmAbi>::Prim2,
+                    $arg3: <<$var as WasmClosureArg<$kind>>::Abi as WasmAbi>::Prim3,
+                    $arg4: <<$var as WasmClosureArg<$kind>>::Abi as WasmAbi>::Prim4,
+                    )*
+                ) -> WasmRet<R::Abi> {
+                    if a == 0 {
+                        throw_str("closure invoked after being dropped");
+                    }
+                    // Scope all local variables before we call `return_abi` to
+                    // ensure they're all destroyed as `return_abi` may throw
+                    let ret = {
+                        let f: &dyn Fn($(closure_arg_ty!($kind $var)),*) -> R = mem::transmute((a, b));
+                        $(
+                            let $var = <$var as WasmClosureArg<$kind>>::arg_from_abi(WasmAbi::join($arg1, $arg2, $arg3, $arg4));
+                        )*
+                        f($(closure_arg!($kind $var)),*)
+                    };
+                    ret.return_abi().into()
+                }
+
+                const ARGS: u32 = closure_arg_count!($($var)*);
+
                 inform(FUNCTION);
-                inform($invoke::<$($var,)* R> as usize as u32);
-                inform($cnt);
-                $(<$var as WasmDescribe>::describe();)*
+                inform(invoke::<$($var,)* R> as usize as u32);
+                inform(ARGS);
+                $(<$var as WasmClosureArg<$kind>>::describe_arg();)*
                 <R as WasmDescribe>::describe();
                 <R as WasmDescribe>::describe();
             }
         }
 
         #[allow(coherence_leak_check)]
-        impl<$($var,)* R> IntoWasmAbi for &'_ mut (dyn FnMut($($var),*) -> R + '_)
-            where $($var: FromWasmAbi,)*
-                  R: ReturnWasmAbi
+        impl<$($var: $($bound)*,)* R> IntoWasmAbi for &'_ mut (dyn FnMut($(closure_arg_ty!($kind $var)),*) -> R + '_)
+            where R: ReturnWasmAbi
         {
             type Abi = WasmSlice;
 
             fn into_abi(self) -> WasmSlice {
                 unsafe {
                     let (a, b): (usize, usize) = mem::transmute(self);
-                    WasmSlice { ptr: a as u32, len: b as u32 }
+                    WasmSlice { ptr: a, len: b }
                 }
             }
         }
 
-        #[allow(non_snake_case)]
-        unsafe extern "C" fn $invoke_mut<$($var: FromWasmAbi,)* R: ReturnWasmAbi>(
-            a: usize,
-            b: usize,
-            $(
-            $arg1: <$var::Abi as WasmAbi>::Prim1,
-            $arg2: <$var::Abi as WasmAbi>::Prim2,
-            $arg3: <$var::Abi as WasmAbi>::Prim3,
-            $arg4: <$var::Abi as WasmAbi>::Prim4,
-            )*
-        ) -> WasmRet<R::Abi> {
-            if a == 0 {
-                throw_str("closure invoked recursively or after being dropped");
-            }
-            // Scope all local variables before we call `return_abi` to
-            // ensure they're all destroyed as `return_abi` may throw
-            let ret = {
-                let f: &mut dyn FnMut($($var),*) -> R = mem::transmute((a, b));
-                $(
-                    let $var = <$var as FromWasmAbi>::from_abi($var::Abi::join($arg1, $arg2, $arg3, $arg4));
-                )*
-                f($($var),*)
-            };
-            ret.return_abi().into()
-        }
-
         #[allow(coherence_leak_check)]
-        impl<$($var,)* R> WasmDescribe for dyn FnMut($($var),*) -> R + '_
-            where $($var: FromWasmAbi,)*
-                  R: ReturnWasmAbi
+        impl<$($var: $($bound)*,)* R> WasmDescribe for dyn FnMut($(closure_arg_ty!($kind $var)),*) -> R + '_
+            where R: ReturnWasmAbi
         {
             #[cfg_attr(wasm_bindgen_unstable_test_coverage, coverage(off))]
             fn describe() {
+                #[allow(non_snake_case)]
+                unsafe extern "C" fn invoke<$($var: $($bound)*,)* R: ReturnWasmAbi>(
+                    a: usize,
+                    b: usize,
+                    $(
+                    $arg1: <<$var as WasmClosureArg<$kind>>::Abi as WasmAbi>::Prim1,
+                    $arg2: <<$var as WasmClosureArg<$kind>>::Abi as WasmAbi>::Prim2,
+                    $arg3: <<$var as WasmClosureArg<$kind>>::Abi as WasmAbi>::Prim3,
+                    $arg4: <<$var as WasmClosureArg<$kind>>::Abi as WasmAbi>::Prim4,
+                    )*
+                ) -> WasmRet<R::Abi> {
+                    if a == 0 {
+                        throw_str("closure invoked recursively or after being dropped");
+                    }
+                    // Scope all local variables before we call `return_abi` to
+                    // ensure they're all destroyed as `return_abi` may throw
+                    let ret = {
+                        let f: &mut dyn FnMut($(closure_arg_ty!($kind $var)),*) -> R = mem::transmute((a, b));
+                        $(
+                            let $var = <$var as WasmClosureArg<$kind>>::arg_from_abi(WasmAbi::join($arg1, $arg2, $arg3, $arg4));
+                        )*
+                        f($(closure_arg!($kind $var)),*)
+                    };
+                    ret.return_abi().into()
+                }
+
+                const ARGS: u32 = closure_arg_count!($($var)*);
+
                 inform(FUNCTION);
-                inform($invoke_mut::<$($var,)* R> as usize as u32);
-                inform($cnt);
-                $(<$var as WasmDescribe>::describe();)*
+                inform(invoke::<$($var,)* R> as usize as u32);
+                inform(ARGS);
+                $(<$var as WasmClosureArg<$kind>>::describe_arg();)*
                 <R as WasmDescribe>::describe();
                 <R as WasmDescribe>::describe();
             }
         }
-    )*)
-}
-
-stack_closures! {
-    (0 invoke0 invoke0_mut)
-    (1 invoke1 invoke1_mut A a1 a2 a3 a4)
-    (2 invoke2 invoke2_mut A a1 a2 a3 a4 B b1 b2 b3 b4)
-    (3 invoke3 invoke3_mut A a1 a2 a3 a4 B b1 b2 b3 b4 C c1 c2 c3 c4)
-    (4 invoke4 invoke4_mut A a1 a2 a3 a4 B b1 b2 b3 b4 C c1 c2 c3 c4 D d1 d2 d3 d4)
-    (5 invoke5 invoke5_mut A a1 a2 a3 a4 B b1 b2 b3 b4 C c1 c2 c3 c4 D d1 d2 d3 d4 E e1 e2 e3 e4)
-    (6 invoke6 invoke6_mut A a1 a2 a3 a4 B b1 b2 b3 b4 C c1 c2 c3 c4 D d1 d2 d3 d4 E e1 e2 e3 e4 F f1 f2 f3 f4)
-    (7 invoke7 invoke7_mut A a1 a2 a3 a4 B b1 b2 b3 b4 C c1 c2 c3 c4 D d1 d2 d3 d4 E e1 e2 e3 e4 F f1 f2 f3 f4 G g1 g2 g3 g4)
-    (8 invoke8 invoke8_mut A a1 a2 a3 a4 B b1 b2 b3 b4 C c1 c2 c3 c4 D d1 d2 d3 d4 E e1 e2 e3 e4 F f1 f2 f3 f4 G g1 g2 g3 g4 H h1 h2 h3 h4)
-}
-
-impl<A, R> IntoWasmAbi for &(dyn Fn(&A) -> R + '_)
-where
-    A: RefFromWasmAbi,
-    R: ReturnWasmAbi,
-{
-    type Abi = WasmSlice;
-
-    fn into_abi(self) -> WasmSlice {
-        unsafe {
-            let (a, b): (usize, usize) = mem::transmute(self);
-            WasmSlice {
-                ptr: a as u32,
-                len: b as u32,
-            }
-        }
-    }
-}
-
-#[allow(non_snake_case)]
-#[cfg_attr(wasm_bindgen_unstable_test_coverage, coverage(off))]
-unsafe extern "C" fn invoke1_ref<A: RefFromWasmAbi, R: ReturnWasmAbi>(
-    a: usize,
-    b: usize,
-    arg1: <A::Abi as WasmAbi>::Prim1,
-    arg2: <A::Abi as WasmAbi>::Prim2,
-    arg3: <A::Abi as WasmAbi>::Prim3,
-    arg4: <A::Abi as WasmAbi>::Prim4,
-) -> WasmRet<R::Abi> {
-    if a == 0 {
-        throw_str("closure invoked after being dropped");
-    }
-    // Scope all local variables before we call `return_abi` to
-    // ensure they're all destroyed as `return_abi` may throw
-    let ret = {
-        let f: &dyn Fn(&A) -> R = mem::transmute((a, b));
-        let arg = <A as RefFromWasmAbi>::ref_from_abi(A::Abi::join(arg1, arg2, arg3, arg4));
-        f(&*arg)
-    };
-    ret.return_abi().into()
-}
-
-impl<A, R> WasmDescribe for dyn Fn(&A) -> R + '_
-where
-    A: RefFromWasmAbi,
-    R: ReturnWasmAbi,
-{
-    #[cfg_attr(wasm_bindgen_unstable_test_coverage, coverage(off))]
-    fn describe() {
-        inform(FUNCTION);
-        inform(invoke1_ref::<A, R> as usize as u32);
-        inform(1);
-        <&A as WasmDescribe>::describe();
-        <R as WasmDescribe>::describe();
-        <R as WasmDescribe>::describe();
-    }
-}
-
-impl<A, R> IntoWasmAbi for &mut (dyn FnMut(&A) -> R + '_)
-where
-    A: RefFromWasmAbi,
-    R: ReturnWasmAbi,
-{
-    type Abi = WasmSlice;
-
-    fn into_abi(self) -> WasmSlice {
-        unsafe {
-            let (a, b): (usize, usize) = mem::transmute(self);
-            WasmSlice {
-                ptr: a as u32,
-                len: b as u32,
-            }
-        }
-    }
-}
-
-#[allow(non_snake_case)]
-#[cfg_attr(wasm_bindgen_unstable_test_coverage, coverage(off))]
-unsafe extern "C" fn invoke1_mut_ref<A: RefFromWasmAbi, R: ReturnWasmAbi>(
-    a: usize,
-    b: usize,
-    arg1: <A::Abi as WasmAbi>::Prim1,
-    arg2: <A::Abi as WasmAbi>::Prim2,
-    arg3: <A::Abi as WasmAbi>::Prim3,
-    arg4: <A::Abi as WasmAbi>::Prim4,
-) -> WasmRet<R::Abi> {
-    if a == 0 {
-        throw_str("closure invoked recursively or after being dropped");
-    }
-    // Scope all local variables before we call `return_abi` to
-    // ensure they're all destroyed as `return_abi` may throw
-    let ret = {
-        let f: &mut dyn FnMut(&A) -> R = mem::transmute((a, b));
-        let arg = <A as RefFromWasmAbi>::ref_from_abi(A::Abi::join(arg1, arg2, arg3, arg4));
-        f(&*arg)
     };
-    ret.return_abi().into()
 }
 
-impl<A, R> WasmDescribe for dyn FnMut(&A) -> R + '_
-where
-    A: RefFromWasmAbi,
-    R: ReturnWasmAbi,
-{
-    #[cfg_attr(wasm_bindgen_unstable_test_coverage, coverage(off))]
-    fn describe() {
-        inform(FUNCTION);
-        inform(invoke1_mut_ref::<A, R> as usize as u32);
-        inform(1);
-        <&A as WasmDescribe>::describe();
-        <R as WasmDescribe>::describe();
-        <R as WasmDescribe>::describe();
-    }
-}
+closure_signatures!(stack_closures);
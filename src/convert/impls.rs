@@ -143,6 +143,12 @@ type_wasm_native!(
     f64 as f64
 );
 
+#[cfg(target_pointer_width = "64")]
+type_wasm_native!(
+    isize as i64
+    usize as u64
+);
+
 /// The sentinel value is 2^32 + 1 for 32-bit primitive types.
 ///
 /// 2^32 + 1 is used, because it's the smallest positive integer that cannot be
@@ -194,12 +200,16 @@ macro_rules! type_wasm_native_f64_option {
 
 type_wasm_native_f64_option!(
     i32 as i32
-    isize as i32
     u32 as u32
-    usize as u32
     f32 as f32
 );
 
+#[cfg(target_pointer_width = "32")]
+type_wasm_native_f64_option!(
+    isize as i32
+    usize as u32
+);
+
 /// The sentinel value is 0xFF_FFFF for primitives with less than 32 bits.
 ///
 /// This value is used, so all small primitive types (`bool`, `i8`, `u8`,
@@ -302,106 +312,98 @@ impl OptionFromWasmAbi for char {
     }
 }
 
-impl<T> IntoWasmAbi for *const T {
-    type Abi = u32;
-
-    #[inline]
-    fn into_abi(self) -> u32 {
-        self as u32
-    }
-}
-
-impl<T> FromWasmAbi for *const T {
-    type Abi = u32;
-
-    #[inline]
-    unsafe fn from_abi(js: u32) -> *const T {
-        js as *const T
-    }
-}
-
-impl<T> IntoWasmAbi for Option<*const T> {
-    type Abi = f64;
+macro_rules! type_wasm_pointer {
+    ($($t:ty)*) => ($(
+        impl<T> IntoWasmAbi for $t {
+            type Abi = usize;
 
-    #[inline]
-    fn into_abi(self) -> f64 {
-        self.map(|ptr| ptr as u32 as f64)
-            .unwrap_or(F64_ABI_OPTION_SENTINEL)
-    }
-}
+            #[inline]
+            fn into_abi(self) -> usize {
+                self as usize
+            }
+        }
 
-impl<T> FromWasmAbi for Option<*const T> {
-    type Abi = f64;
+        impl<T> FromWasmAbi for $t {
+            type Abi = usize;
 
-    #[inline]
-    unsafe fn from_abi(js: f64) -> Option<*const T> {
-        if js == F64_ABI_OPTION_SENTINEL {
-            None
-        } else {
-            Some(js as u32 as *const T)
+            #[inline]
+            unsafe fn from_abi(js: usize) -> $t {
+                js as $t
+            }
         }
-    }
-}
 
-impl<T> IntoWasmAbi for *mut T {
-    type Abi = u32;
+        #[cfg(target_pointer_width = "32")]
+        impl<T> IntoWasmAbi for Option<$t> {
+            type Abi = f64;
 
-    #[inline]
-    fn into_abi(self) -> u32 {
-        self as u32
-    }
-}
+            #[inline]
+            fn into_abi(self) -> f64 {
+                self.map(|ptr| ptr as u32 as f64)
+                    .unwrap_or(F64_ABI_OPTION_SENTINEL)
+            }
+        }
 
-impl<T> FromWasmAbi for *mut T {
-    type Abi = u32;
+        #[cfg(target_pointer_width = "32")]
+        impl<T> FromWasmAbi for Option<$t> {
+            type Abi = f64;
 
-    #[inline]
-    unsafe fn from_abi(js: u32) -> *mut T {
-        js as *mut T
-    }
-}
+            #[inline]
+            unsafe fn from_abi(js: f64) -> Option<$t> {
+                if js == F64_ABI_OPTION_SENTINEL {
+                    None
+                } else {
+                    Some(js as u32 as $t)
+                }
+            }
+        }
 
-impl<T> IntoWasmAbi for Option<*mut T> {
-    type Abi = f64;
+        // A sentinel can't be told apart from a valid 64-bit pointer, so
+        // optional pointers are passed like `Option<u64>` instead.
+        #[cfg(target_pointer_width = "64")]
+        impl<T> IntoWasmAbi for Option<$t> {
+            type Abi = Option<u64>;
 
-    #[inline]
-    fn into_abi(self) -> f64 {
-        self.map(|ptr| ptr as u32 as f64)
-            .unwrap_or(F64_ABI_OPTION_SENTINEL)
-    }
-}
+            #[inline]
+            fn into_abi(self) -> Option<u64> {
+                self.map(|ptr| ptr as u64)
+            }
+        }
 
-impl<T> FromWasmAbi for Option<*mut T> {
-    type Abi = f64;
+        #[cfg(target_pointer_width = "64")]
+        impl<T> FromWasmAbi for Option<$t> {
+            type Abi = Option<u64>;
 
-    #[inline]
-    unsafe fn from_abi(js: f64) -> Option<*mut T> {
-        if js == F64_ABI_OPTION_SENTINEL {
-            None
-        } else {
-            Some(js as u32 as *mut T)
+            #[inline]
+            unsafe fn from_abi(js: Option<u64>) -> Option<$t> {
+                js.map(|ptr| ptr as $t)
+            }
         }
-    }
+    )*)
 }
 
+type_wasm_pointer!(
+    *const T
+    *mut T
+);
+
 impl<T> IntoWasmAbi for NonNull<T> {
-    type Abi = u32;
+    type Abi = usize;
 
     #[inline]
-    fn into_abi(self) -> u32 {
-        self.as_ptr() as u32
+    fn into_abi(self) -> usize {
+        self.as_ptr() as usize
     }
 }
 
 impl<T> OptionIntoWasmAbi for NonNull<T> {
     #[inline]
-    fn none() -> u32 {
+    fn none() -> usize {
         0
     }
 }
 
 impl<T> FromWasmAbi for NonNull<T> {
-    type Abi = u32;
+    type Abi = usize;
 
     #[inline]
     unsafe fn from_abi(js: Self::Abi) -> Self {
@@ -412,7 +414,7 @@ impl<T> FromWasmAbi for NonNull<T> {
 
 impl<T> OptionFromWasmAbi for NonNull<T> {
     #[inline]
-    fn is_none(js: &u32) -> bool {
+    fn is_none(js: &usize) -> bool {
         *js == 0
     }
 }
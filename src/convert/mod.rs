@@ -10,6 +10,8 @@ mod impls;
 mod slices;
 mod traits;
 
+pub(crate) use self::closures::{closure_arg, closure_arg_ty, closure_signatures};
+pub use self::closures::{Borrowed, Owned, WasmClosureArg};
 pub use self::impls::*;
 pub use self::slices::WasmSlice;
 pub use self::traits::*;
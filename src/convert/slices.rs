@@ -27,25 +27,25 @@ use cfg_if::cfg_if;
 // functions in `lib.rs` rather than `WasmRet<WasmSlice>`.
 #[repr(C)]
 pub struct WasmSlice {
-    pub ptr: u32,
-    pub len: u32,
+    pub ptr: usize,
+    pub len: usize,
 }
 
 impl WasmAbi for WasmSlice {
     /// `self.ptr`
-    type Prim1 = u32;
+    type Prim1 = usize;
     /// `self.len`
-    type Prim2 = u32;
+    type Prim2 = usize;
     type Prim3 = ();
     type Prim4 = ();
 
     #[inline]
-    fn split(self) -> (u32, u32, (), ()) {
+    fn split(self) -> (usize, usize, (), ()) {
         (self.ptr, self.len, (), ())
     }
 
     #[inline]
-    fn join(ptr: u32, len: u32, _: (), _: ()) -> Self {
+    fn join(ptr: usize, len: usize, _: (), _: ()) -> Self {
         Self { ptr, len }
     }
 }
@@ -62,20 +62,20 @@ pub struct WasmMutSlice {
 
 impl WasmAbi for WasmMutSlice {
     /// `self.slice.ptr`
-    type Prim1 = u32;
+    type Prim1 = usize;
     /// `self.slice.len`
-    type Prim2 = u32;
+    type Prim2 = usize;
     /// `self.idx`
     type Prim3 = u32;
     type Prim4 = ();
 
     #[inline]
-    fn split(self) -> (u32, u32, u32, ()) {
+    fn split(self) -> (usize, usize, u32, ()) {
         (self.slice.ptr, self.slice.len, self.idx, ())
     }
 
     #[inline]
-    fn join(ptr: u32, len: u32, idx: u32, _: ()) -> Self {
+    fn join(ptr: usize, len: usize, idx: u32, _: ()) -> Self {
         Self {
             slice: WasmSlice { ptr, len },
             idx,
@@ -144,7 +144,7 @@ macro_rules! vectors_internal {
                 mem::forget(vector);
                 WasmSlice {
                     ptr: ptr.into_abi(),
-                    len: len as u32,
+                    len,
                 }
             }
         }
@@ -155,7 +155,7 @@ macro_rules! vectors_internal {
             #[inline]
             unsafe fn vector_from_abi(js: WasmSlice) -> Box<[$t]> {
                 let ptr = <*mut $t>::from_abi(js.ptr);
-                let len = js.len as usize;
+                let len = js.len;
                 Vec::from_raw_parts(ptr, len, len).into_boxed_slice()
             }
         }
@@ -167,7 +167,7 @@ macro_rules! vectors_internal {
             fn into_abi(self) -> WasmSlice {
                 WasmSlice {
                     ptr: self.as_ptr().into_abi(),
-                    len: self.len() as u32,
+                    len: self.len(),
                 }
             }
         }
@@ -270,7 +270,10 @@ cfg_if! {
         #[inline]
         fn unsafe_get_cached_str(x: &str) -> Option<WasmSlice> {
             // This uses 0 for the ptr as an indication that it is a JsValue and not a str.
-            crate::cache::intern::unsafe_get_str(x).map(|x| WasmSlice { ptr: 0, len: x })
+            crate::cache::intern::unsafe_get_str(x).map(|x| WasmSlice {
+                ptr: 0,
+                len: x as usize,
+            })
         }
 
     } else {
@@ -441,7 +444,7 @@ impl VectorIntoWasmAbi for JsValue {
         mem::forget(vector);
         WasmSlice {
             ptr: ptr.into_abi(),
-            len: len as u32,
+            len,
         }
     }
 }
@@ -452,7 +455,7 @@ impl VectorFromWasmAbi for JsValue {
     #[inline]
     unsafe fn vector_from_abi(js: WasmSlice) -> Box<[Self]> {
         let ptr = <*mut JsValue>::from_abi(js.ptr);
-        let len = js.len as usize;
+        let len = js.len;
         Vec::from_raw_parts(ptr, len, len).into_boxed_slice()
     }
 }
@@ -470,7 +473,7 @@ where
         mem::forget(vector);
         WasmSlice {
             ptr: ptr.into_abi(),
-            len: len as u32,
+            len,
         }
     }
 }
@@ -484,7 +487,7 @@ where
     #[inline]
     unsafe fn vector_from_abi(js: WasmSlice) -> Box<[T]> {
         let ptr = <*mut JsValue>::from_abi(js.ptr);
-        let len = js.len as usize;
+        let len = js.len;
         let vec: Vec<T> = Vec::from_raw_parts(ptr, len, len)
             .drain(..)
             .map(|js_value| T::unchecked_from_js(js_value))
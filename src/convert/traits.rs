@@ -182,6 +182,7 @@ unsafe impl WasmPrimitive for u32 {}
 unsafe impl WasmPrimitive for i32 {}
 unsafe impl WasmPrimitive for u64 {}
 unsafe impl WasmPrimitive for i64 {}
+unsafe impl WasmPrimitive for usize {}
 unsafe impl WasmPrimitive for f32 {}
 unsafe impl WasmPrimitive for f64 {}
 unsafe impl WasmPrimitive for () {}
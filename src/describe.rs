@@ -93,8 +93,6 @@ simple! {
     u64 => U64
     i128 => I128
     u128 => U128
-    isize => I32
-    usize => U32
     f32 => F32
     f64 => F64
     bool => BOOLEAN
@@ -115,17 +113,31 @@ cfg_if! {
     }
 }
 
+// Pointer-sized types are 64-bit on `wasm64` targets, where they're passed as
+// `i64`.
+#[cfg(target_pointer_width = "32")]
+simple! {
+    isize => I32
+    usize => U32
+}
+
+#[cfg(target_pointer_width = "64")]
+simple! {
+    isize => I64
+    usize => U64
+}
+
 impl<T> WasmDescribe for *const T {
     #[cfg_attr(wasm_bindgen_unstable_test_coverage, coverage(off))]
     fn describe() {
-        inform(U32)
+        usize::describe()
     }
 }
 
 impl<T> WasmDescribe for *mut T {
     #[cfg_attr(wasm_bindgen_unstable_test_coverage, coverage(off))]
     fn describe() {
-        inform(U32)
+        usize::describe()
     }
 }
 
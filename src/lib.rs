@@ -28,6 +28,12 @@
 //!
 //! Disable this feature to use this crate in `no_std` environments.
 //!
+//! ### `serde-native`
+//!
+//! Enables the [`serde`](crate::serde) module, a [`serde`](https://serde.rs)
+//! data format converting Rust values to and from JavaScript values directly,
+//! and the `#[wasm_bindgen(serde)]` attribute for structs.
+//!
 //! ### `strict-macro`
 //!
 //! All warnings the `#[wasm_bindgen]` macro emits are turned into hard errors.
@@ -48,7 +54,10 @@
 
 #![no_std]
 #![cfg_attr(wasm_bindgen_unstable_test_coverage, feature(coverage_attribute))]
-#![cfg_attr(target_feature = "atomics", feature(thread_local))]
+#![cfg_attr(
+    target_feature = "atomics",
+    feature(thread_local, stdarch_wasm_atomic_wait, simd_wasm64)
+)]
 #![cfg_attr(
     any(target_feature = "atomics", wasm_bindgen_unstable_test_coverage),
     feature(allow_internal_unstable),
@@ -71,21 +80,22 @@ use core::ops::{
 };
 use core::ptr::NonNull;
 
+use crate::closure::Closure;
 use crate::convert::{FromWasmAbi, TryFromJsValue, WasmRet, WasmSlice};
 
 macro_rules! externs {
     ($(#[$attr:meta])* extern "C" { $(fn $name:ident($($args:tt)*) -> $ret:ty;)* }) => (
-        #[cfg(all(target_arch = "wasm32", any(target_os = "unknown", target_os = "none")))]
+        #[cfg(all(target_family = "wasm", any(target_os = "unknown", target_os = "none")))]
         $(#[$attr])*
         extern "C" {
             $(fn $name($($args)*) -> $ret;)*
         }
 
         $(
-            #[cfg(not(all(target_arch = "wasm32", any(target_os = "unknown", target_os = "none"))))]
+            #[cfg(not(all(target_family = "wasm", any(target_os = "unknown", target_os = "none"))))]
             #[allow(unused_variables)]
             unsafe extern fn $name($($args)*) -> $ret {
-                panic!("function not implemented on non-wasm32 targets")
+                panic!("function not implemented on non-Wasm targets")
             }
         )*
     )
@@ -126,6 +136,12 @@ pub use cache::intern::{intern, unintern};
 #[path = "rt/mod.rs"]
 pub mod __rt;
 
+#[cfg(feature = "serde-native")]
+pub mod serde;
+
+#[cfg(all(target_family = "wasm", target_feature = "atomics"))]
+pub mod thread;
+
 /// Representation of an object owned by JS.
 ///
 /// A `JsValue` doesn't actually live in Rust right now but actually in a table
@@ -261,7 +277,7 @@ impl JsValue {
     #[deprecated = "causes dependency cycles, use `serde-wasm-bindgen` or `gloo_utils::format::JsValueSerdeExt` instead"]
     pub fn from_serde<T>(t: &T) -> serde_json::Result<JsValue>
     where
-        T: serde::ser::Serialize + ?Sized,
+        T: ::serde::ser::Serialize + ?Sized,
     {
         let s = serde_json::to_string(t)?;
         unsafe { Ok(JsValue::_new(__wbindgen_json_parse(s.as_ptr(), s.len()))) }
@@ -292,7 +308,7 @@ impl JsValue {
     #[deprecated = "causes dependency cycles, use `serde-wasm-bindgen` or `gloo_utils::format::JsValueSerdeExt` instead"]
     pub fn into_serde<T>(&self) -> serde_json::Result<T>
     where
-        T: for<'a> serde::de::Deserialize<'a>,
+        T: for<'a> ::serde::de::Deserialize<'a>,
     {
         unsafe {
             let ret = __wbindgen_json_serialize(self.idx);
@@ -442,6 +458,13 @@ impl JsValue {
         }
     }
 
+    /// Creates a [`WeakJsValue`] referring to this value, which doesn't keep
+    /// it alive.
+    #[inline]
+    pub fn downgrade(&self) -> WeakJsValue {
+        WeakJsValue::new(self)
+    }
+
     /// Compare two `JsValue`s for equality, using the `==` operator in JS.
     ///
     /// [MDN documentation](https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Operators/Equality)
@@ -1115,6 +1138,12 @@ externs! {
         fn __wbindgen_number_get(idx: u32) -> WasmRet<Option<f64>>;
         fn __wbindgen_boolean_get(idx: u32) -> u32;
         fn __wbindgen_string_get(idx: u32) -> WasmSlice;
+        fn __wbindgen_string_length(idx: u32) -> u32;
+        fn __wbindgen_string_char_code_at(idx: u32, index: u32) -> f64;
+        fn __wbindgen_string_concat(a: u32, b: u32) -> u32;
+        fn __wbindgen_string_substring(idx: u32, start: u32, end: u32) -> u32;
+        fn __wbindgen_string_equals(a: u32, b: u32) -> u32;
+        fn __wbindgen_string_from_char_code_array(ptr: *const u16, len: usize) -> u32;
         fn __wbindgen_bigint_get_as_i64(idx: u32) -> WasmRet<Option<i64>>;
 
         fn __wbindgen_debug_string(ret: *mut [usize; 2], idx: u32) -> ();
@@ -1122,11 +1151,13 @@ externs! {
         fn __wbindgen_throw(a: *const u8, b: usize) -> !;
         fn __wbindgen_rethrow(a: u32) -> !;
         fn __wbindgen_error_new(a: *const u8, b: usize) -> u32;
+        fn __wbindgen_panic_error_new(a: *const u8, b: usize, c: *const u8, d: usize) -> u32;
 
         fn __wbindgen_cb_drop(idx: u32) -> u32;
+        fn __wbindgen_cb_drop_scoped(idx: u32) -> u32;
 
         fn __wbindgen_describe(v: u32) -> ();
-        fn __wbindgen_describe_closure(a: u32, b: u32, c: u32) -> u32;
+        fn __wbindgen_describe_closure(a: usize, b: usize, c: u32) -> u32;
 
         fn __wbindgen_json_parse(ptr: *const u8, len: usize) -> u32;
         fn __wbindgen_json_serialize(idx: u32) -> WasmSlice;
@@ -1150,10 +1181,16 @@ externs! {
         fn __wbindgen_array_new() -> u32;
         fn __wbindgen_array_push(array: u32, value: u32) -> ();
 
+        fn __wbindgen_weak_ref_new(idx: u32) -> u32;
+        fn __wbindgen_weak_ref_deref(idx: u32) -> u32;
+        fn __wbindgen_weak_ref_register(target: u32, callback: u32, token: u32) -> ();
+        fn __wbindgen_weak_ref_unregister(token: u32) -> ();
+
         fn __wbindgen_not(idx: u32) -> u32;
 
         fn __wbindgen_exports() -> u32;
         fn __wbindgen_memory() -> u32;
+        fn __wbindgen_memory_by_name(ptr: *const u8, len: usize) -> u32;
         fn __wbindgen_module() -> u32;
         fn __wbindgen_function_table() -> u32;
     }
@@ -1198,6 +1235,86 @@ impl Default for JsValue {
     }
 }
 
+/// A weak reference to a JS value, backed by a JS [`WeakRef`].
+///
+/// Unlike a [`JsValue`], a `WeakJsValue` doesn't keep its target alive, so it
+/// can be used to cache JS objects such as DOM nodes in Rust without leaking
+/// them. Use [`WeakJsValue::upgrade`] to get the target back while it's still
+/// alive.
+///
+/// Primitive values like numbers and strings can't be garbage collected, so
+/// they are held strongly instead, as are all values in environments without
+/// `WeakRef` support.
+///
+/// [`WeakRef`]: https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/WeakRef
+pub struct WeakJsValue {
+    weak_ref: JsValue,
+    finalizer: Option<Closure<dyn FnMut()>>,
+}
+
+impl WeakJsValue {
+    /// Creates a new weak reference to `target`.
+    pub fn new(target: &JsValue) -> WeakJsValue {
+        WeakJsValue {
+            weak_ref: unsafe { JsValue::_new(__wbindgen_weak_ref_new(target.idx)) },
+            finalizer: None,
+        }
+    }
+
+    /// Creates a new weak reference to `target`, calling `finalizer` after
+    /// `target` has been garbage collected.
+    ///
+    /// The finalizer is registered in a JS [`FinalizationRegistry`], so it
+    /// runs asynchronously some time after the collection, if ever. It is
+    /// unregistered when the returned `WeakJsValue` is dropped. Finalizers are
+    /// never called for primitive values, or in environments without
+    /// `FinalizationRegistry` support.
+    ///
+    /// [`FinalizationRegistry`]: https://developer.mozilla.org/en-US/docs/Web/JavaScript/Reference/Global_Objects/FinalizationRegistry
+    pub fn with_finalizer<F>(target: &JsValue, finalizer: F) -> WeakJsValue
+    where
+        F: FnOnce() + 'static,
+    {
+        let mut weak = WeakJsValue::new(target);
+        let finalizer = Closure::once(finalizer);
+        unsafe {
+            __wbindgen_weak_ref_register(target.idx, finalizer.as_ref().idx, weak.weak_ref.idx);
+        }
+        weak.finalizer = Some(finalizer);
+        weak
+    }
+
+    /// Returns the target of this weak reference, or `None` if it has been
+    /// garbage collected.
+    ///
+    /// As `undefined` is also used to signal a collected target, this always
+    /// returns `None` for weak references to `undefined`.
+    pub fn upgrade(&self) -> Option<JsValue> {
+        let target = unsafe { JsValue::_new(__wbindgen_weak_ref_deref(self.weak_ref.idx)) };
+        if target.is_undefined() {
+            None
+        } else {
+            Some(target)
+        }
+    }
+}
+
+impl Drop for WeakJsValue {
+    fn drop(&mut self) {
+        if self.finalizer.is_some() {
+            unsafe { __wbindgen_weak_ref_unregister(self.weak_ref.idx) }
+        }
+    }
+}
+
+impl core::fmt::Debug for WeakJsValue {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("WeakJsValue")
+            .field("target", &self.upgrade())
+            .finish()
+    }
+}
+
 /// Wrapper type for imported statics.
 ///
 /// This type is used whenever a `static` is imported from a JS module, for
@@ -1320,6 +1437,20 @@ pub fn throw_val(s: JsValue) -> ! {
     }
 }
 
+/// Poisons the instance once an exported function panics, so that all further
+/// calls into it throw a `PanicError` right away instead of running on top of
+/// whatever state the panic left behind.
+///
+/// This only has an effect if panics unwind (`-Cpanic=unwind`), in which case
+/// panics in exported functions are thrown to JS as a `PanicError`. By default
+/// the instance stays usable after such a panic.
+pub fn poison_on_panic(poison: bool) {
+    #[cfg(all(feature = "std", panic = "unwind", target_family = "wasm"))]
+    __rt::unwind::POISON_ON_PANIC.store(poison, core::sync::atomic::Ordering::Relaxed);
+    #[cfg(not(all(feature = "std", panic = "unwind", target_family = "wasm")))]
+    let _ = poison;
+}
+
 /// Get the count of live `externref`s / `JsValue`s in `wasm-bindgen`'s heap.
 ///
 /// ## Usage
@@ -1380,7 +1511,7 @@ pub fn anyref_heap_live_count() -> u32 {
 /// `Option::unwrap` and `Option::expect` methods, but they are specific to
 /// working with Wasm and JS.
 ///
-/// On non-wasm32 targets, defaults to the normal unwrap/expect calls.
+/// On non-Wasm targets, defaults to the normal unwrap/expect calls.
 ///
 /// # Example
 ///
@@ -1408,7 +1539,7 @@ pub trait UnwrapThrowExt<T>: Sized {
     #[cfg_attr(
         any(
             debug_assertions,
-            not(all(target_arch = "wasm32", any(target_os = "unknown", target_os = "none")))
+            not(all(target_family = "wasm", any(target_os = "unknown", target_os = "none")))
         ),
         track_caller
     )]
@@ -1416,7 +1547,7 @@ pub trait UnwrapThrowExt<T>: Sized {
         if cfg!(all(
             debug_assertions,
             all(
-                target_arch = "wasm32",
+                target_family = "wasm",
                 any(target_os = "unknown", target_os = "none")
             )
         )) {
@@ -1440,7 +1571,7 @@ pub trait UnwrapThrowExt<T>: Sized {
     #[cfg_attr(
         any(
             debug_assertions,
-            not(all(target_arch = "wasm32", any(target_os = "unknown", target_os = "none")))
+            not(all(target_family = "wasm", any(target_os = "unknown", target_os = "none")))
         ),
         track_caller
     )]
@@ -1452,7 +1583,7 @@ impl<T> UnwrapThrowExt<T> for Option<T> {
         const MSG: &str = "called `Option::unwrap_throw()` on a `None` value";
 
         if cfg!(all(
-            target_arch = "wasm32",
+            target_family = "wasm",
             any(target_os = "unknown", target_os = "none")
         )) {
             if let Some(val) = self {
@@ -1473,7 +1604,7 @@ impl<T> UnwrapThrowExt<T> for Option<T> {
 
     fn expect_throw(self, message: &str) -> T {
         if cfg!(all(
-            target_arch = "wasm32",
+            target_family = "wasm",
             any(target_os = "unknown", target_os = "none")
         )) {
             if let Some(val) = self {
@@ -1506,7 +1637,7 @@ where
         const MSG: &str = "called `Result::unwrap_throw()` on an `Err` value";
 
         if cfg!(all(
-            target_arch = "wasm32",
+            target_family = "wasm",
             any(target_os = "unknown", target_os = "none")
         )) {
             match self {
@@ -1536,7 +1667,7 @@ where
 
     fn expect_throw(self, message: &str) -> T {
         if cfg!(all(
-            target_arch = "wasm32",
+            target_family = "wasm",
             any(target_os = "unknown", target_os = "none")
         )) {
             match self {
@@ -1582,6 +1713,22 @@ pub fn memory() -> JsValue {
     unsafe { JsValue::_new(__wbindgen_memory()) }
 }
 
+/// Returns a handle to the `WebAssembly.Memory` this Wasm instance exports as
+/// `name`, if any.
+///
+/// This gives access to memories besides the one used by `wasm-bindgen` in
+/// modules with multiple memories, which are exported under their name if
+/// they aren't exported already. Memories without a name are named after
+/// their index, like `memory1`.
+pub fn memory_by_name(name: &str) -> Option<JsValue> {
+    let memory = unsafe { JsValue::_new(__wbindgen_memory_by_name(name.as_ptr(), name.len())) };
+    if memory.is_undefined() {
+        None
+    } else {
+        Some(memory)
+    }
+}
+
 /// Returns a handle to this Wasm instance's `WebAssembly.Table` which is the
 /// indirect function table used by Rust
 pub fn function_table() -> JsValue {
@@ -177,6 +177,80 @@ macro_rules! __wbindgen_coverage {
     };
 }
 
+#[macro_export]
+#[doc(hidden)]
+#[cfg(feature = "serde-native")]
+macro_rules! __wbindgen_serde {
+    ($name:ty, $js_name_len:literal $(, $js_name_char:literal)*) => {
+        #[automatically_derived]
+        impl $crate::describe::WasmDescribe for $name {
+            fn describe() {
+                use $crate::describe::*;
+                inform(NAMED_EXTERNREF);
+                inform($js_name_len);
+                $(inform($js_name_char);)*
+            }
+        }
+
+        #[automatically_derived]
+        impl $crate::convert::IntoWasmAbi for $name {
+            type Abi = <$crate::JsValue as $crate::convert::IntoWasmAbi>::Abi;
+
+            fn into_abi(self) -> Self::Abi {
+                match $crate::serde::to_value(&self) {
+                    $crate::__rt::core::result::Result::Ok(js) => {
+                        $crate::convert::IntoWasmAbi::into_abi(js)
+                    }
+                    $crate::__rt::core::result::Result::Err(e) => $crate::throw_val(e.into()),
+                }
+            }
+        }
+
+        #[automatically_derived]
+        impl $crate::convert::OptionIntoWasmAbi for $name {
+            #[inline]
+            fn none() -> Self::Abi {
+                $crate::convert::IntoWasmAbi::into_abi($crate::JsValue::UNDEFINED)
+            }
+        }
+
+        #[automatically_derived]
+        impl $crate::convert::FromWasmAbi for $name {
+            type Abi = <$crate::JsValue as $crate::convert::FromWasmAbi>::Abi;
+
+            unsafe fn from_abi(js: Self::Abi) -> Self {
+                let js = <$crate::JsValue as $crate::convert::FromWasmAbi>::from_abi(js);
+                match $crate::serde::from_value(js) {
+                    $crate::__rt::core::result::Result::Ok(value) => value,
+                    $crate::__rt::core::result::Result::Err(e) => $crate::throw_val(e.into()),
+                }
+            }
+        }
+
+        #[automatically_derived]
+        impl $crate::convert::OptionFromWasmAbi for $name {
+            #[inline]
+            fn is_none(abi: &Self::Abi) -> bool {
+                // JS passes `null` and `undefined` as `0`, while `none` passes
+                // the index of `undefined` to JS.
+                *abi == 0
+                    || *abi == $crate::convert::IntoWasmAbi::into_abi($crate::JsValue::UNDEFINED)
+            }
+        }
+    };
+}
+
+#[macro_export]
+#[doc(hidden)]
+#[cfg(not(feature = "serde-native"))]
+macro_rules! __wbindgen_serde {
+    ($($t:tt)*) => {
+        compile_error!(
+            "`#[wasm_bindgen(serde)]` requires the `serde-native` feature of `wasm-bindgen`"
+        );
+    };
+}
+
 #[inline]
 pub fn assert_not_null<T>(s: *mut T) {
     if s.is_null() {
@@ -546,6 +620,152 @@ pub fn take_last_exception() -> Result<(), super::JsValue> {
     ret
 }
 
+/// Operations on JS strings for `js_sys::JsString`, which are lowered to the
+/// `wasm:js-string` builtins with `--js-string-builtins` where possible.
+///
+/// They behave like the methods of `String.prototype` they're named after,
+/// also for `String` objects.
+pub mod js_string {
+    use crate::JsValue;
+
+    #[inline]
+    pub fn length(s: &JsValue) -> u32 {
+        unsafe { crate::__wbindgen_string_length(s.idx) }
+    }
+
+    #[inline]
+    pub fn char_code_at(s: &JsValue, index: u32) -> f64 {
+        unsafe { crate::__wbindgen_string_char_code_at(s.idx, index) }
+    }
+
+    #[inline]
+    pub fn concat(a: &JsValue, b: &JsValue) -> JsValue {
+        unsafe { JsValue::_new(crate::__wbindgen_string_concat(a.idx, b.idx)) }
+    }
+
+    #[inline]
+    pub fn substring(s: &JsValue, start: u32, end: u32) -> JsValue {
+        unsafe { JsValue::_new(crate::__wbindgen_string_substring(s.idx, start, end)) }
+    }
+
+    #[inline]
+    pub fn equals(a: &JsValue, b: &JsValue) -> bool {
+        unsafe { crate::__wbindgen_string_equals(a.idx, b.idx) != 0 }
+    }
+
+    /// Like `String.fromCharCode(...char_codes)`.
+    #[inline]
+    pub fn from_char_code_array(char_codes: &[u16]) -> JsValue {
+        unsafe {
+            JsValue::_new(crate::__wbindgen_string_from_char_code_array(
+                char_codes.as_ptr(),
+                char_codes.len(),
+            ))
+        }
+    }
+}
+
+/// Runs the body of an exported function.
+///
+/// When panics unwind, a panic in `f` is caught once everything `f` borrowed,
+/// like the `WasmRefCell` of `self`, has been released again, and is thrown to
+/// JS as a `PanicError` carrying the message and location of the panic.
+#[inline]
+pub fn maybe_catch_unwind<R>(f: impl FnOnce() -> R) -> R {
+    #[cfg(all(feature = "std", panic = "unwind", target_family = "wasm"))]
+    {
+        unwind::catch_unwind(f)
+    }
+    #[cfg(not(all(feature = "std", panic = "unwind", target_family = "wasm")))]
+    {
+        f()
+    }
+}
+
+#[cfg(all(feature = "std", panic = "unwind", target_family = "wasm"))]
+pub(crate) mod unwind {
+    use crate::JsValue;
+    use alloc::boxed::Box;
+    use alloc::string::{String, ToString};
+    use core::cell::RefCell;
+    use core::sync::atomic::{AtomicBool, Ordering};
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::Once;
+
+    /// Whether a panic poisons the instance, see `crate::poison_on_panic`.
+    pub(crate) static POISON_ON_PANIC: AtomicBool = AtomicBool::new(false);
+    static POISONED: AtomicBool = AtomicBool::new(false);
+
+    std::thread_local! {
+        /// The location of the last panic, recorded by our panic hook.
+        static LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+    }
+
+    pub fn catch_unwind<R>(f: impl FnOnce() -> R) -> R {
+        if POISONED.load(Ordering::Relaxed) {
+            throw_panic_error(
+                String::from("the instance was poisoned by an earlier panic"),
+                String::new(),
+            );
+        }
+
+        static HOOK: Once = Once::new();
+        HOOK.call_once(install_hook);
+
+        match panic::catch_unwind(AssertUnwindSafe(f)) {
+            Ok(ret) => ret,
+            Err(payload) => {
+                if POISON_ON_PANIC.load(Ordering::Relaxed) {
+                    POISONED.store(true, Ordering::Relaxed);
+                }
+                let message = if let Some(s) = payload.downcast_ref::<&str>() {
+                    String::from(*s)
+                } else if let Some(s) = payload.downcast_ref::<String>() {
+                    s.clone()
+                } else {
+                    String::from("Box<dyn Any>")
+                };
+                drop(payload);
+                let location = LOCATION.with(|l| l.borrow_mut().take());
+                if location.is_none() {
+                    // Our hook didn't run, so it was replaced, for example by
+                    // `console_error_panic_hook`. Wrap the new hook so that
+                    // only this panic misses its location.
+                    install_hook();
+                }
+                throw_panic_error(message, location.unwrap_or_default())
+            }
+        }
+    }
+
+    /// The location of a panic isn't part of its payload, so we record it with
+    /// a panic hook which defers to the previous one.
+    fn install_hook() {
+        let prev = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let location = info.location().map(|l| l.to_string());
+            LOCATION.with(|l| *l.borrow_mut() = location);
+            prev(info);
+        }));
+    }
+
+    #[cold]
+    #[inline(never)]
+    fn throw_panic_error(message: String, location: String) -> ! {
+        let error = unsafe {
+            JsValue::_new(crate::__wbindgen_panic_error_new(
+                message.as_ptr(),
+                message.len(),
+                location.as_ptr(),
+                location.len(),
+            ))
+        };
+        // Nothing is dropped once we've thrown.
+        drop((message, location));
+        crate::throw_val(error)
+    }
+}
+
 /// An internal helper trait for usage in `#[wasm_bindgen]` on `async`
 /// functions to convert the return value of the function to
 /// `Result<JsValue, JsValue>` which is what we'll return to JS (where an
@@ -0,0 +1,918 @@
+//! A native [`serde`] data format for [`JsValue`].
+//!
+//! Unlike [`JsValue::from_serde`] and [`JsValue::into_serde`], which go
+//! through a JSON string, the [`Serializer`] and [`Deserializer`] in this
+//! module build and inspect JS values directly. This is faster for large
+//! payloads and supports JS types that have no JSON representation, such as
+//! `Map`, `BigInt`, `Uint8Array` and `undefined`.
+//!
+//! Values are represented as follows:
+//!
+//! | Rust                              | JS                                      |
+//! |-----------------------------------|-----------------------------------------|
+//! | `bool`                            | `boolean`                               |
+//! | integers up to 32 bits, floats    | `number`                                |
+//! | 64-bit integers                   | `number`, or `bigint` if enabled        |
+//! | 128-bit integers                  | `bigint`                                |
+//! | `char`, `String`                  | `string`                                |
+//! | `()`, `None`, unit structs        | `undefined`, or `null` if enabled       |
+//! | byte buffers                      | `Uint8Array`, or `Array` if enabled     |
+//! | sequences and tuples              | `Array`                                 |
+//! | maps                              | `Map`, or a plain object if enabled     |
+//! | structs                           | plain object                            |
+//! | enums                             | externally tagged, like `serde_json`    |
+//!
+//! This module requires the `serde-native` feature.
+//!
+//! ```rust,no_run
+//! use serde::{Deserialize, Serialize};
+//! use wasm_bindgen::prelude::*;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Point {
+//!     x: f64,
+//!     y: f64,
+//! }
+//!
+//! #[wasm_bindgen]
+//! pub fn mirror(point: JsValue) -> Result<JsValue, JsValue> {
+//!     let point: Point = wasm_bindgen::serde::from_value(point)?;
+//!     let mirrored = Point { x: point.y, y: point.x };
+//!     Ok(wasm_bindgen::serde::to_value(&mirrored)?)
+//! }
+//! ```
+//!
+//! Structs can also be annotated with `#[wasm_bindgen(serde)]`, in which case
+//! they are converted with the default [`Serializer`] whenever they are passed
+//! to or returned from an exported or imported function.
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
+
+use serde::de::{self, DeserializeOwned, IntoDeserializer};
+use serde::ser::{self, Serialize};
+
+use crate::convert::{FromWasmAbi, IntoWasmAbi, WasmSlice};
+use crate::{JsError, JsValue};
+
+externs! {
+    #[link(wasm_import_module = "__wbindgen_placeholder__")]
+    extern "C" {
+        fn __wbindgen_array_length(array: u32) -> u32;
+        fn __wbindgen_array_get(array: u32, index: u32) -> u32;
+        fn __wbindgen_object_new() -> u32;
+        fn __wbindgen_object_set(obj: u32, key: u32, value: u32) -> ();
+        fn __wbindgen_object_entries(obj: u32) -> u32;
+        fn __wbindgen_map_new() -> u32;
+        fn __wbindgen_map_set(map: u32, key: u32, value: u32) -> ();
+        fn __wbindgen_uint8_array_get(idx: u32) -> WasmSlice;
+    }
+}
+
+/// The largest integer that a JS `number` can represent exactly.
+const MAX_SAFE_INTEGER: i64 = (1 << 53) - 1;
+
+/// Converts a Rust value into a [`JsValue`] with the default [`Serializer`].
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<JsValue, Error> {
+    value.serialize(&Serializer::new())
+}
+
+/// Converts a [`JsValue`] into a Rust value.
+pub fn from_value<T: DeserializeOwned>(value: JsValue) -> Result<T, Error> {
+    T::deserialize(Deserializer::from(value))
+}
+
+/// An error raised while converting between Rust values and [`JsValue`]s.
+///
+/// This converts into a JS `Error` with the same message, so it can be
+/// returned from exported functions or thrown with [`throw_val`].
+///
+/// [`throw_val`]: crate::throw_val
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Error(String);
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl ser::StdError for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+impl From<Error> for JsValue {
+    fn from(error: Error) -> Self {
+        JsError::new(&error.0).into()
+    }
+}
+
+fn array_new() -> JsValue {
+    unsafe { JsValue::_new(crate::__wbindgen_array_new()) }
+}
+
+fn array_push(array: &JsValue, value: JsValue) {
+    unsafe { crate::__wbindgen_array_push(array.idx, value.into_abi()) }
+}
+
+fn array_length(array: &JsValue) -> u32 {
+    unsafe { __wbindgen_array_length(array.idx) }
+}
+
+fn array_get(array: &JsValue, index: u32) -> JsValue {
+    unsafe { JsValue::_new(__wbindgen_array_get(array.idx, index)) }
+}
+
+fn object_new() -> JsValue {
+    unsafe { JsValue::_new(__wbindgen_object_new()) }
+}
+
+fn object_set(obj: &JsValue, key: JsValue, value: JsValue) {
+    unsafe { __wbindgen_object_set(obj.idx, key.into_abi(), value.into_abi()) }
+}
+
+fn object_entries(obj: &JsValue) -> JsValue {
+    unsafe { JsValue::_new(__wbindgen_object_entries(obj.idx)) }
+}
+
+fn map_new() -> JsValue {
+    unsafe { JsValue::_new(__wbindgen_map_new()) }
+}
+
+fn map_set(map: &JsValue, key: JsValue, value: JsValue) {
+    unsafe { __wbindgen_map_set(map.idx, key.into_abi(), value.into_abi()) }
+}
+
+fn uint8_array_get(value: &JsValue) -> Option<Vec<u8>> {
+    unsafe { FromWasmAbi::from_abi(__wbindgen_uint8_array_get(value.idx)) }
+}
+
+/// Wraps `value` in an object with a single `variant` key, which is how
+/// non-unit enum variants are represented.
+fn tag_variant(variant: &'static str, value: JsValue) -> JsValue {
+    let obj = object_new();
+    object_set(&obj, JsValue::from_str(variant), value);
+    obj
+}
+
+/// A [`serde::Serializer`] producing [`JsValue`]s.
+///
+/// The default configuration, also used by [`to_value`], produces `Map`s for
+/// maps, `Uint8Array`s for byte buffers, `number`s for 64-bit integers and
+/// `undefined` for `()` and `None`. Each of these can be changed with the
+/// methods below.
+#[derive(Clone, Debug, Default)]
+pub struct Serializer {
+    maps_as_objects: bool,
+    bytes_as_arrays: bool,
+    large_number_types_as_bigints: bool,
+    missing_as_null: bool,
+}
+
+impl Serializer {
+    /// Creates a `Serializer` with the default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a `Serializer` producing values that survive a round-trip
+    /// through `JSON.stringify`: maps are serialized as plain objects and
+    /// `()` and `None` as `null`.
+    pub fn json_compatible() -> Self {
+        Self::new()
+            .serialize_maps_as_objects(true)
+            .serialize_missing_as_null(true)
+    }
+
+    /// Serialize maps as plain JS objects instead of `Map`s.
+    ///
+    /// Keys are converted to strings by JS, so this is only suitable for maps
+    /// with string or numeric keys.
+    pub fn serialize_maps_as_objects(mut self, value: bool) -> Self {
+        self.maps_as_objects = value;
+        self
+    }
+
+    /// Serialize byte buffers as arrays of numbers instead of `Uint8Array`s.
+    pub fn serialize_bytes_as_arrays(mut self, value: bool) -> Self {
+        self.bytes_as_arrays = value;
+        self
+    }
+
+    /// Serialize `i64` and `u64` as `bigint`s instead of `number`s.
+    ///
+    /// When this is disabled, 64-bit integers outside of the range that a
+    /// `number` can represent exactly produce an error.
+    pub fn serialize_large_number_types_as_bigints(mut self, value: bool) -> Self {
+        self.large_number_types_as_bigints = value;
+        self
+    }
+
+    /// Serialize `()`, `None` and unit structs as `null` instead of
+    /// `undefined`.
+    pub fn serialize_missing_as_null(mut self, value: bool) -> Self {
+        self.missing_as_null = value;
+        self
+    }
+
+    fn missing(&self) -> JsValue {
+        if self.missing_as_null {
+            JsValue::NULL
+        } else {
+            JsValue::UNDEFINED
+        }
+    }
+
+    fn serialize_large_number<T>(&self, v: T, as_f64: f64, in_range: bool) -> Result<JsValue, Error>
+    where
+        T: Into<JsValue> + fmt::Display,
+    {
+        if self.large_number_types_as_bigints {
+            Ok(v.into())
+        } else if in_range {
+            Ok(JsValue::from_f64(as_f64))
+        } else {
+            Err(Error(alloc::format!(
+                "{} can't be represented as a JS number, enable \
+                 `serialize_large_number_types_as_bigints` to serialize it as a bigint",
+                v
+            )))
+        }
+    }
+}
+
+impl<'s> ser::Serializer for &'s Serializer {
+    type Ok = JsValue;
+    type Error = Error;
+
+    type SerializeSeq = ArraySerializer<'s>;
+    type SerializeTuple = ArraySerializer<'s>;
+    type SerializeTupleStruct = ArraySerializer<'s>;
+    type SerializeTupleVariant = ArraySerializer<'s>;
+    type SerializeMap = MapSerializer<'s>;
+    type SerializeStruct = ObjectSerializer<'s>;
+    type SerializeStructVariant = ObjectSerializer<'s>;
+
+    fn serialize_bool(self, v: bool) -> Result<JsValue, Error> {
+        Ok(JsValue::from_bool(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<JsValue, Error> {
+        Ok(JsValue::from(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<JsValue, Error> {
+        Ok(JsValue::from(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<JsValue, Error> {
+        Ok(JsValue::from(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<JsValue, Error> {
+        let in_range = (-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&v);
+        self.serialize_large_number(v, v as f64, in_range)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<JsValue, Error> {
+        Ok(JsValue::from(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<JsValue, Error> {
+        Ok(JsValue::from(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<JsValue, Error> {
+        Ok(JsValue::from(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<JsValue, Error> {
+        Ok(JsValue::from(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<JsValue, Error> {
+        let in_range = v <= MAX_SAFE_INTEGER as u64;
+        self.serialize_large_number(v, v as f64, in_range)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<JsValue, Error> {
+        Ok(JsValue::from(v))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<JsValue, Error> {
+        Ok(JsValue::from(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<JsValue, Error> {
+        Ok(JsValue::from(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<JsValue, Error> {
+        Ok(JsValue::from_str(v.encode_utf8(&mut [0; 4])))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<JsValue, Error> {
+        Ok(JsValue::from_str(v))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<JsValue, Error> {
+        if self.bytes_as_arrays {
+            let array = array_new();
+            for byte in v {
+                array_push(&array, JsValue::from(*byte));
+            }
+            Ok(array)
+        } else {
+            Ok(JsValue::from(Box::<[u8]>::from(v)))
+        }
+    }
+
+    fn serialize_none(self) -> Result<JsValue, Error> {
+        Ok(self.missing())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<JsValue, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<JsValue, Error> {
+        Ok(self.missing())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<JsValue, Error> {
+        Ok(self.missing())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<JsValue, Error> {
+        Ok(JsValue::from_str(variant))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<JsValue, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<JsValue, Error> {
+        Ok(tag_variant(variant, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<ArraySerializer<'s>, Error> {
+        Ok(ArraySerializer::new(self, None))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<ArraySerializer<'s>, Error> {
+        Ok(ArraySerializer::new(self, None))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<ArraySerializer<'s>, Error> {
+        Ok(ArraySerializer::new(self, None))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<ArraySerializer<'s>, Error> {
+        Ok(ArraySerializer::new(self, Some(variant)))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<MapSerializer<'s>, Error> {
+        Ok(MapSerializer {
+            serializer: self,
+            map: if self.maps_as_objects {
+                object_new()
+            } else {
+                map_new()
+            },
+            key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<ObjectSerializer<'s>, Error> {
+        Ok(ObjectSerializer::new(self, None))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<ObjectSerializer<'s>, Error> {
+        Ok(ObjectSerializer::new(self, Some(variant)))
+    }
+}
+
+/// Serializes sequences, tuples and tuple variants into a JS `Array`.
+pub struct ArraySerializer<'s> {
+    serializer: &'s Serializer,
+    array: JsValue,
+    variant: Option<&'static str>,
+}
+
+impl<'s> ArraySerializer<'s> {
+    fn new(serializer: &'s Serializer, variant: Option<&'static str>) -> Self {
+        ArraySerializer {
+            serializer,
+            array: array_new(),
+            variant,
+        }
+    }
+
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        array_push(&self.array, value.serialize(self.serializer)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<JsValue, Error> {
+        Ok(match self.variant {
+            Some(variant) => tag_variant(variant, self.array),
+            None => self.array,
+        })
+    }
+}
+
+impl ser::SerializeSeq for ArraySerializer<'_> {
+    type Ok = JsValue;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<JsValue, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for ArraySerializer<'_> {
+    type Ok = JsValue;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<JsValue, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for ArraySerializer<'_> {
+    type Ok = JsValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<JsValue, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for ArraySerializer<'_> {
+    type Ok = JsValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<JsValue, Error> {
+        self.finish()
+    }
+}
+
+/// Serializes maps into a JS `Map` or a plain object.
+pub struct MapSerializer<'s> {
+    serializer: &'s Serializer,
+    map: JsValue,
+    key: Option<JsValue>,
+}
+
+impl ser::SerializeMap for MapSerializer<'_> {
+    type Ok = JsValue;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        self.key = Some(key.serialize(self.serializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error("serialize_value called before serialize_key".to_string()))?;
+        let value = value.serialize(self.serializer)?;
+        if self.serializer.maps_as_objects {
+            object_set(&self.map, key, value);
+        } else {
+            map_set(&self.map, key, value);
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<JsValue, Error> {
+        Ok(self.map)
+    }
+}
+
+/// Serializes structs and struct variants into a plain JS object.
+pub struct ObjectSerializer<'s> {
+    serializer: &'s Serializer,
+    object: JsValue,
+    variant: Option<&'static str>,
+}
+
+impl<'s> ObjectSerializer<'s> {
+    fn new(serializer: &'s Serializer, variant: Option<&'static str>) -> Self {
+        ObjectSerializer {
+            serializer,
+            object: object_new(),
+            variant,
+        }
+    }
+
+    fn set<T: Serialize + ?Sized>(&mut self, key: &'static str, value: &T) -> Result<(), Error> {
+        let value = value.serialize(self.serializer)?;
+        object_set(&self.object, JsValue::from_str(key), value);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<JsValue, Error> {
+        Ok(match self.variant {
+            Some(variant) => tag_variant(variant, self.object),
+            None => self.object,
+        })
+    }
+}
+
+impl ser::SerializeStruct for ObjectSerializer<'_> {
+    type Ok = JsValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.set(key, value)
+    }
+
+    fn end(self) -> Result<JsValue, Error> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for ObjectSerializer<'_> {
+    type Ok = JsValue;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.set(key, value)
+    }
+
+    fn end(self) -> Result<JsValue, Error> {
+        self.finish()
+    }
+}
+
+/// A [`serde::Deserializer`] reading from a [`JsValue`].
+///
+/// Both `Map`s and plain objects can be deserialized as maps and structs, and
+/// both `Uint8Array`s and arrays of numbers as byte buffers and sequences.
+/// `undefined` and `null` are both accepted for `()` and `None`.
+pub struct Deserializer {
+    value: JsValue,
+}
+
+impl From<JsValue> for Deserializer {
+    fn from(value: JsValue) -> Self {
+        Deserializer { value }
+    }
+}
+
+impl Deserializer {
+    fn is_nullish(&self) -> bool {
+        self.value.is_undefined() || self.value.is_null()
+    }
+
+    fn invalid_type(&self, expected: &dyn de::Expected) -> Error {
+        de::Error::custom(alloc::format!(
+            "invalid type: JS value {}, expected {}",
+            self.value.as_debug_string(),
+            expected
+        ))
+    }
+
+    fn deserialize_bigint<'de, V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let value = self.value;
+        if let Ok(v) = i64::try_from(value.clone()) {
+            return visitor.visit_i64(v);
+        }
+        if let Ok(v) = u64::try_from(value.clone()) {
+            return visitor.visit_u64(v);
+        }
+        if let Ok(v) = i128::try_from(value.clone()) {
+            return visitor.visit_i128(v);
+        }
+        match u128::try_from(value) {
+            Ok(v) => visitor.visit_u128(v),
+            Err(value) => Err(Error(alloc::format!(
+                "bigint {} is out of the range of 128-bit integers",
+                value.as_debug_string()
+            ))),
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer {
+    type Error = Error;
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.is_nullish() {
+            return visitor.visit_unit();
+        }
+        if let Some(v) = self.value.as_bool() {
+            return visitor.visit_bool(v);
+        }
+        if let Some(v) = self.value.as_f64() {
+            let safe = MAX_SAFE_INTEGER as f64;
+            return if v >= -safe && v <= safe && v as i64 as f64 == v {
+                if v < 0.0 {
+                    visitor.visit_i64(v as i64)
+                } else {
+                    visitor.visit_u64(v as u64)
+                }
+            } else {
+                visitor.visit_f64(v)
+            };
+        }
+        if self.value.is_bigint() {
+            return self.deserialize_bigint(visitor);
+        }
+        if let Some(v) = self.value.as_string() {
+            return visitor.visit_string(v);
+        }
+        if self.value.is_array() {
+            return visitor.visit_seq(SeqDeserializer::new(self.value));
+        }
+        if let Some(v) = uint8_array_get(&self.value) {
+            return visitor.visit_byte_buf(v);
+        }
+        if self.value.is_object() {
+            return visitor.visit_map(MapDeserializer::new(object_entries(&self.value)));
+        }
+        Err(self.invalid_type(&visitor))
+    }
+
+    fn deserialize_option<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        if self.is_nullish() {
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_bytes<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_byte_buf<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match uint8_array_get(&self.value) {
+            Some(v) => visitor.visit_byte_buf(v),
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_seq<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match uint8_array_get(&self.value) {
+            Some(v) => visitor.visit_seq(de::value::SeqDeserializer::new(v.into_iter())),
+            None => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_enum<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        if let Some(variant) = self.value.as_string() {
+            return visitor.visit_enum(variant.into_deserializer());
+        }
+        if self.value.is_object() && !self.value.is_array() {
+            let entries = object_entries(&self.value);
+            if array_length(&entries) == 1 {
+                let entry = array_get(&entries, 0);
+                if let Some(variant) = array_get(&entry, 0).as_string() {
+                    return visitor.visit_enum(EnumDeserializer {
+                        variant,
+                        value: array_get(&entry, 1),
+                    });
+                }
+            }
+        }
+        Err(self.invalid_type(&"a string or an object with a single key"))
+    }
+
+    fn deserialize_ignored_any<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        unit unit_struct tuple tuple_struct map struct identifier
+    }
+}
+
+impl<'de> IntoDeserializer<'de, Error> for Deserializer {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// Provides the elements of a JS array to a sequence visitor.
+struct SeqDeserializer {
+    array: JsValue,
+    index: u32,
+    len: u32,
+}
+
+impl SeqDeserializer {
+    fn new(array: JsValue) -> Self {
+        let len = array_length(&array);
+        SeqDeserializer {
+            array,
+            index: 0,
+            len,
+        }
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for SeqDeserializer {
+    type Error = Error;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        if self.index == self.len {
+            return Ok(None);
+        }
+        let value = array_get(&self.array, self.index);
+        self.index += 1;
+        seed.deserialize(Deserializer::from(value)).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some((self.len - self.index) as usize)
+    }
+}
+
+/// Provides the `[key, value]` entries of a `Map` or an object to a map
+/// visitor.
+struct MapDeserializer {
+    entries: SeqDeserializer,
+    value: Option<JsValue>,
+}
+
+impl MapDeserializer {
+    fn new(entries: JsValue) -> Self {
+        MapDeserializer {
+            entries: SeqDeserializer::new(entries),
+            value: None,
+        }
+    }
+}
+
+impl<'de> de::MapAccess<'de> for MapDeserializer {
+    type Error = Error;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        let entries = &mut self.entries;
+        if entries.index == entries.len {
+            return Ok(None);
+        }
+        let entry = array_get(&entries.array, entries.index);
+        entries.index += 1;
+        self.value = Some(array_get(&entry, 1));
+        seed.deserialize(Deserializer::from(array_get(&entry, 0)))
+            .map(Some)
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        let value = self
+            .value
+            .take()
+            .ok_or_else(|| Error("next_value called before next_key".to_string()))?;
+        seed.deserialize(Deserializer::from(value))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        de::SeqAccess::size_hint(&self.entries)
+    }
+}
+
+/// Provides the variant of an enum represented as `{ variant: value }`.
+struct EnumDeserializer {
+    variant: String,
+    value: JsValue,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumDeserializer {
+    type Error = Error;
+    type Variant = Deserializer;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Deserializer), Error> {
+        let variant = seed.deserialize(self.variant.into_deserializer())?;
+        Ok((variant, Deserializer::from(self.value)))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Deserializer {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        de::Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Error> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: de::Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: de::Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        de::Deserializer::deserialize_map(self, visitor)
+    }
+}
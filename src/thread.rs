@@ -0,0 +1,187 @@
+//! Spawning threads which share the memory of the current Wasm instance.
+//!
+//! Each thread runs in its own worker, a Web Worker on the web or a
+//! `worker_threads` worker in Node.js, which instantiates the module again with
+//! the same `WebAssembly.Module` and memory. The JS glue generated by
+//! `wasm-bindgen` contains the bootstrap script of these workers, so no extra
+//! JS has to be written. This is supported with `--target web`,
+//! `--target no-modules`, `--target nodejs` and
+//! `--target experimental-nodejs-module`.
+//!
+//! This module is only available when compiling with the `atomics` target
+//! feature, which requires a nightly compiler and rebuilding the standard
+//! library:
+//!
+//! ```sh
+//! RUSTFLAGS='-C target-feature=+atomics,+bulk-memory' \
+//!   cargo +nightly build --target wasm32-unknown-unknown -Z build-std=panic_abort,std
+//! ```
+
+use crate::JsValue;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::arch::wasm;
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicI32, Ordering};
+
+const RUNNING: i32 = 0;
+const FINISHED: i32 = 1;
+const FAILED: i32 = 2;
+
+externs! {
+    #[link(wasm_import_module = "__wbindgen_placeholder__")]
+    extern "C" {
+        fn __wbindgen_spawn_thread(work: usize, state: usize) -> u32;
+    }
+}
+
+/// The state shared between a thread and its [`JoinHandle`].
+#[repr(C)]
+struct Packet<T> {
+    /// Must be the first field, as the JS glue only passes a pointer to this
+    /// to `__wbindgen_thread_failed`, which doesn't know `T`.
+    state: AtomicI32,
+    result: UnsafeCell<Option<T>>,
+}
+
+// The result is written by the spawned thread before `state` is set to
+// `FINISHED`, and only read by the joining thread afterwards.
+unsafe impl<T: Send> Sync for Packet<T> {}
+
+/// An owned permission to join a thread spawned with [`spawn`].
+///
+/// Dropping the handle detaches the thread, which keeps running until its
+/// closure returns.
+pub struct JoinHandle<T> {
+    packet: Arc<Packet<T>>,
+}
+
+/// The closure passed to [`spawn`], along with the state it reports to.
+struct Work<F, T> {
+    f: F,
+    packet: Arc<Packet<T>>,
+}
+
+/// Type-erased [`Work`], which is passed to the new thread.
+trait Run: Send {
+    fn run(self: Box<Self>);
+}
+
+impl<F, T> Run for Work<F, T>
+where
+    F: FnOnce() -> T + Send,
+    T: Send,
+{
+    fn run(self: Box<Self>) {
+        // Moving the work out of its box frees the box before the closure
+        // runs, so it isn't leaked if the closure fails.
+        let Work { f, packet } = {
+            let work = self;
+            *work
+        };
+        let result = f();
+        unsafe { *packet.result.get() = Some(result) };
+        packet.state.store(FINISHED, Ordering::Release);
+        notify(&packet.state);
+    }
+}
+
+/// Spawns a new thread, returning a [`JoinHandle`] for it.
+///
+/// The closure runs in a new worker sharing the memory of the current
+/// instance. When it returns, the stack and thread-local storage of the thread
+/// are freed and the worker is closed, so work scheduled from it which
+/// outlives the closure, like futures spawned with
+/// `wasm_bindgen_futures::spawn_local`, doesn't run to completion. They are
+/// also freed if the closure fails, but values owned by the closure at that
+/// point are leaked.
+///
+/// Workers are started asynchronously: on the web, a thread can only start
+/// after the thread which spawned it yields to the event loop.
+///
+/// # Panics
+///
+/// Panics if the worker can't be created, after dropping the closure.
+pub fn spawn<F, T>(f: F) -> JoinHandle<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let packet = Arc::new(Packet {
+        state: AtomicI32::new(RUNNING),
+        result: UnsafeCell::new(None),
+    });
+    let state = &packet.state as *const AtomicI32 as usize;
+
+    let work: Box<dyn Run> = Box::new(Work {
+        f,
+        packet: packet.clone(),
+    });
+    let work = Box::into_raw(Box::new(work));
+
+    let err = JsValue::_new(unsafe { __wbindgen_spawn_thread(work as usize, state) });
+    if !err.is_undefined() {
+        drop(unsafe { Box::from_raw(work) });
+        panic!("failed to spawn thread: {:?}", err);
+    }
+    JoinHandle { packet }
+}
+
+impl<T> JoinHandle<T> {
+    /// Waits for the thread to finish, returning the value returned by its
+    /// closure, or `None` if the closure failed, e.g. because it panicked.
+    ///
+    /// This blocks the current thread, which isn't allowed on the main thread
+    /// of a web page, where it traps. Use [`JoinHandle::is_finished`] to poll
+    /// the thread instead.
+    pub fn join(self) -> Option<T> {
+        loop {
+            match self.packet.state.load(Ordering::Acquire) {
+                RUNNING => unsafe {
+                    wasm::memory_atomic_wait32(
+                        &self.packet.state as *const AtomicI32 as *mut i32,
+                        RUNNING,
+                        -1,
+                    );
+                },
+                FINISHED => return unsafe { (*self.packet.result.get()).take() },
+                _ => return None,
+            }
+        }
+    }
+
+    /// Checks if the thread has finished running its closure, whether or not
+    /// it succeeded.
+    pub fn is_finished(&self) -> bool {
+        self.packet.state.load(Ordering::Acquire) != RUNNING
+    }
+}
+
+fn notify(state: &AtomicI32) {
+    unsafe { wasm::memory_atomic_notify(state as *const AtomicI32 as *mut i32, u32::MAX) };
+}
+
+/// Runs the closure passed to [`spawn`] in the new thread.
+#[doc(hidden)]
+#[no_mangle]
+pub unsafe extern "C" fn __wbindgen_thread_entry(work: usize) {
+    let work = *Box::from_raw(work as *mut Box<dyn Run>);
+    work.run();
+}
+
+/// Marks a thread as failed after its closure threw, waking up threads
+/// waiting to join it.
+///
+/// The closure's own reference to the state is never dropped in this case,
+/// so the state is still valid, even if the thread was detached.
+#[doc(hidden)]
+#[no_mangle]
+pub unsafe extern "C" fn __wbindgen_thread_failed(state: usize) {
+    let state = &*(state as *const AtomicI32);
+    if state
+        .compare_exchange(RUNNING, FAILED, Ordering::Release, Ordering::Relaxed)
+        .is_ok()
+    {
+        notify(state);
+    }
+}
@@ -51,6 +51,7 @@ pub mod externref_heap_live_count;
 pub mod modules;
 pub mod snippets;
 pub mod strings;
+pub mod threads;
 
 #[wasm_bindgen_test]
 fn closures_work() {
@@ -0,0 +1,48 @@
+#![cfg(target_feature = "atomics")]
+
+use js_sys::Promise;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::thread;
+use wasm_bindgen_futures::JsFuture;
+use wasm_bindgen_test::*;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = setTimeout)]
+    fn set_timeout(f: &js_sys::Function, ms: i32);
+}
+
+async fn sleep() {
+    let promise = Promise::new(&mut |resolve, _| set_timeout(&resolve, 10));
+    JsFuture::from(promise).await.unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn spawn_and_join() {
+    let counter = Arc::new(AtomicU32::new(0));
+    let handles = (1..=4)
+        .map(|i| {
+            let counter = counter.clone();
+            thread::spawn(move || {
+                counter.fetch_add(i, Ordering::SeqCst);
+                i * 2
+            })
+        })
+        .collect::<Vec<_>>();
+
+    // Joining blocks, which isn't allowed on the main thread, so the threads
+    // are polled until they're finished first.
+    for handle in &handles {
+        while !handle.is_finished() {
+            sleep().await;
+        }
+    }
+    let results = handles
+        .into_iter()
+        .map(|handle| handle.join())
+        .collect::<Vec<_>>();
+    assert_eq!(results, [Some(2), Some(4), Some(6), Some(8)]);
+    assert_eq!(counter.load(Ordering::SeqCst), 10);
+}
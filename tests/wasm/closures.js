@@ -51,6 +51,10 @@ exports.many_arity_call9 = a => {
     a(1, 2, 3, 4, 5, 6, 7, 8);
 };
 
+exports.many_arity_call16 = a => {
+    a(1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16);
+};
+
 exports.option_call1 = a => {
     if (a) {
         a();
@@ -149,6 +153,17 @@ exports.pass_reference_first_arg_twice = (a, b, c) => {
   a.free();
 };
 
+exports.pass_borrowed_args = f => {
+  const arg = new wasm.RefFirstArgument(3);
+  try {
+    return f(1, 'hello', new Uint8Array([1, 2, 3]), arg);
+  } finally {
+    arg.free();
+  }
+};
+
+exports.call_result_closure = (f, arg) => f(arg);
+
 exports.call_destroyed = f => {
   assert.throws(f, /closure invoked.*after being dropped/);
 };
@@ -162,3 +177,30 @@ exports.js_store_forgotten_closure = f => {
 exports.js_call_forgotten_closure = () => {
   FORGOTTEN_CLOSURE();
 };
+
+let SCOPED_CLOSURE = null;
+
+exports.scoped_for_each = f => {
+  [1, 2, 3].forEach(x => f(x));
+};
+
+exports.scoped_store = f => {
+  SCOPED_CLOSURE = f;
+};
+
+exports.scoped_call_stored = () => {
+  SCOPED_CLOSURE(1);
+};
+
+exports.scoped_pin = f => {
+  f.original.cnt++;
+};
+
+exports.scoped_traps = f => {
+  try {
+    f();
+    return false;
+  } catch (e) {
+    return e instanceof WebAssembly.RuntimeError;
+  }
+};
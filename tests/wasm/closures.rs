@@ -119,6 +119,37 @@ extern "C" {
     fn js_store_forgotten_closure(closure: &Closure<dyn Fn()>);
     fn js_call_forgotten_closure();
 
+    fn many_arity_call16(
+        a: &Closure<
+            dyn Fn(u32, u32, u32, u32, u32, u32, u32, u32, u32, u32, u32, u32, u32, u32, u32, u32),
+        >,
+    );
+    #[wasm_bindgen(js_name = many_arity_call16)]
+    fn many_arity_stack16(
+        a: &dyn Fn(u32, u32, u32, u32, u32, u32, u32, u32, u32, u32, u32, u32, u32, u32, u32, u32),
+    );
+
+    fn pass_borrowed_args(
+        a: &Closure<dyn FnMut(u32, &str, &[u8], &RefFirstArgument) -> u32>,
+    ) -> u32;
+    #[wasm_bindgen(js_name = pass_borrowed_args)]
+    fn pass_borrowed_args_stack(
+        a: &mut dyn FnMut(u32, &str, &[u8], &RefFirstArgument) -> u32,
+    ) -> u32;
+
+    #[wasm_bindgen(catch)]
+    fn call_result_closure(
+        a: &Closure<dyn FnMut(u32) -> Result<u32, JsError>>,
+        arg: u32,
+    ) -> Result<u32, JsValue>;
+
+    fn scoped_for_each(a: &Closure<dyn FnMut(u32)>);
+    fn scoped_store(a: &Closure<dyn FnMut(u32)>);
+    #[wasm_bindgen(catch)]
+    fn scoped_call_stored() -> Result<(), JsValue>;
+    fn scoped_pin(a: &Closure<dyn FnMut(u32)>);
+    fn scoped_traps(f: &dyn Fn()) -> bool;
+
     #[wasm_bindgen(js_name = many_arity_call2)]
     fn externref_call(a: &Closure<dyn Fn(JsValue)>);
     #[wasm_bindgen(js_name = many_arity_call2)]
@@ -533,6 +564,14 @@ pub struct RefFirstArgument {
     contents: u32,
 }
 
+#[wasm_bindgen]
+impl RefFirstArgument {
+    #[wasm_bindgen(constructor)]
+    pub fn new(contents: u32) -> RefFirstArgument {
+        RefFirstArgument { contents }
+    }
+}
+
 #[wasm_bindgen_test]
 fn reference_as_first_argument_builds_at_all() {
     #[wasm_bindgen]
@@ -654,3 +693,114 @@ fn named_externref_no_duplicate_adapter() {
     externref_call(&Closure::new(|a| assert_eq!(a, 1)));
     named_externref_call(&Closure::new(|a| assert_eq!(a, 1)));
 }
+
+#[wasm_bindgen_test]
+fn sixteen_arguments() {
+    many_arity_call16(&Closure::new(
+        |a, b, c, d, e, f, g, h, i, j, k, l, m, n, o, p| {
+            assert_eq!(
+                [a, b, c, d, e, f, g, h, i, j, k, l, m, n, o, p],
+                [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16]
+            )
+        },
+    ));
+    many_arity_stack16(&|a, b, c, d, e, f, g, h, i, j, k, l, m, n, o, p| {
+        assert_eq!(
+            [a, b, c, d, e, f, g, h, i, j, k, l, m, n, o, p],
+            [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16]
+        )
+    });
+}
+
+#[wasm_bindgen_test]
+fn borrowed_arguments() {
+    fn check(n: u32, s: &str, bytes: &[u8], x: &RefFirstArgument) -> u32 {
+        assert_eq!(n, 1);
+        assert_eq!(s, "hello");
+        assert_eq!(bytes, [1, 2, 3]);
+        n + x.contents
+    }
+    assert_eq!(pass_borrowed_args(&Closure::new(check)), 4);
+    assert_eq!(pass_borrowed_args_stack(&mut check), 4);
+}
+
+#[wasm_bindgen_test]
+fn result_closure_throws() {
+    let closure = Closure::new(|x: u32| {
+        if x == 0 {
+            Err(JsError::new("zero is not allowed"))
+        } else {
+            Ok(x * 2)
+        }
+    });
+    assert_eq!(call_result_closure(&closure, 2).unwrap(), 4);
+    let err = call_result_closure(&closure, 0).unwrap_err();
+    let err = err.dyn_into::<js_sys::Error>().unwrap();
+    assert_eq!(String::from(err.message()), "zero is not allowed");
+}
+
+#[wasm_bindgen_test]
+fn scoped_closures_borrow_locals() {
+    let mut total = 0;
+    let ret = Closure::scoped(|scope| {
+        let closure = scope.closure(|x: u32| total += x);
+        scoped_for_each(&closure);
+        "done"
+    });
+    assert_eq!(ret, "done");
+    assert_eq!(total, 6);
+}
+
+#[wasm_bindgen_test]
+fn scoped_closures_invalidated_after_scope() {
+    let mut total = 0;
+    Closure::scoped(|scope| {
+        let closure = scope.closure(|x: u32| total += x);
+        scoped_store(&closure);
+        scoped_call_stored().unwrap();
+    });
+    assert_eq!(total, 1);
+    let err: js_sys::Error = scoped_call_stored().unwrap_err().unchecked_into();
+    assert_eq!(
+        String::from(err.message()),
+        "closure invoked after the end of its `Closure::scoped` scope"
+    );
+}
+
+#[wasm_bindgen_test]
+fn scoped_closures_invalidated_before_dropped() {
+    struct CallStored<'a>(&'a Cell<bool>);
+
+    impl Drop for CallStored<'_> {
+        fn drop(&mut self) {
+            self.0.set(scoped_call_stored().is_err());
+        }
+    }
+
+    let invalidated = Cell::new(false);
+    let mut total = 0;
+    Closure::scoped(|scope| {
+        let guard = CallStored(&invalidated);
+        scope.closure::<dyn FnMut(u32), _>(move |_: u32| {
+            let _guard = &guard;
+        });
+        let closure = scope.closure(|x: u32| total += x);
+        scoped_store(&closure);
+    });
+    // Dropping the first closure called the second one, which had already
+    // been invalidated along with it.
+    assert!(invalidated.get());
+    assert_eq!(total, 0);
+}
+
+#[wasm_bindgen_test]
+fn scoped_closure_running_at_end_of_scope() {
+    let trapped = scoped_traps(&|| {
+        Closure::scoped(|scope| {
+            let closure = scope.closure(|_: u32| {});
+            // Pretend an invocation is still in flight.
+            scoped_pin(&closure);
+        });
+    });
+    assert!(trapped);
+}
@@ -8,7 +8,7 @@ extern crate wasm_bindgen_test;
 extern crate wasm_bindgen_test_crate_a;
 extern crate wasm_bindgen_test_crate_b;
 
-#[cfg(feature = "serde-serialize")]
+#[cfg(any(feature = "serde-serialize", feature = "serde-native"))]
 #[macro_use]
 extern crate serde_derive;
 
@@ -52,6 +52,8 @@ pub mod optional_primitives;
 pub mod result;
 pub mod result_jserror;
 pub mod rethrow;
+#[cfg(feature = "serde-native")]
+pub mod serde_native;
 pub mod simple;
 pub mod slice;
 pub mod string_vecs;
@@ -62,6 +64,7 @@ pub mod usize;
 pub mod validate_prt;
 pub mod variadic;
 pub mod vendor_prefix;
+pub mod weak_ref;
 
 // should not be executed
 #[wasm_bindgen(start)]
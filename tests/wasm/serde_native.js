@@ -0,0 +1,77 @@
+const wasm = require('wasm-bindgen-test.js');
+const assert = require('assert');
+
+const foo = {
+  a: 0,
+  b: 'foo',
+  e: 'Unit',
+  f: [
+    { Newtype: 1.5 },
+    { Tuple: [true, 't'] },
+    { Struct: { x: -1 } },
+  ],
+  h: -(2n ** 100n),
+};
+
+exports.verify_default = function(value, bytes) {
+  assert.deepStrictEqual(value, {
+    ...foo,
+    c: undefined,
+    d: new Map([['one', 1]]),
+    g: 2 ** 40,
+  });
+  assert.deepStrictEqual(bytes, new Uint8Array([1, 2, 3]));
+};
+
+exports.verify_configured = function(value, bytes) {
+  assert.deepStrictEqual(value, {
+    ...foo,
+    c: null,
+    d: { one: 1 },
+    g: 2n ** 40n,
+  });
+  assert.deepStrictEqual(bytes, [1, 2, 3]);
+};
+
+exports.make_value = function() {
+  return [
+    { ...foo, c: null, d: { one: 1 }, g: 2 ** 40 },
+    new Uint8Array([4, 5, 6]),
+    2n ** 64n - 1n,
+  ];
+};
+
+exports.js_mirror_point = function() {
+  assert.deepStrictEqual(wasm.serde_native_mirror({ x: 1, y: 2 }), {
+    x: 2,
+    y: 1,
+    label: undefined,
+  });
+};
+
+exports.js_mirror_invalid_point = function() {
+  assert.throws(() => wasm.serde_native_mirror({ x: 'a', y: 2 }), /invalid type/);
+};
+
+exports.mirror_point_in_js = function(point) {
+  return { x: point.y, y: point.x, label: point.label };
+};
+
+exports.js_optional_point = function() {
+  assert.strictEqual(wasm.serde_native_optional(undefined), undefined);
+  assert.strictEqual(wasm.serde_native_optional(null), undefined);
+  assert.deepStrictEqual(wasm.serde_native_optional({ x: 1, y: 2 }), {
+    x: 1,
+    y: 2,
+    label: undefined,
+  });
+};
+
+exports.optional_point_in_js = function(point, is_none) {
+  if (is_none) {
+    assert.strictEqual(point, undefined);
+  } else {
+    assert.deepStrictEqual(point, { x: 1, y: 2, label: undefined });
+  }
+  return point;
+};
@@ -0,0 +1,154 @@
+use serde::ser::{Serialize, Serializer as _};
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::serde::{from_value, to_value, Serializer};
+use wasm_bindgen_test::*;
+
+#[wasm_bindgen(module = "tests/wasm/serde_native.js")]
+extern "C" {
+    fn verify_default(value: JsValue, bytes: JsValue);
+    fn verify_configured(value: JsValue, bytes: JsValue);
+    fn make_value() -> JsValue;
+    fn js_mirror_point();
+    fn js_mirror_invalid_point();
+    fn mirror_point_in_js(point: SerdePoint) -> SerdePoint;
+    fn js_optional_point();
+    fn optional_point_in_js(point: Option<SerdePoint>, is_none: bool) -> Option<SerdePoint>;
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct SerdeFoo {
+    a: u32,
+    b: String,
+    c: Option<u8>,
+    d: BTreeMap<String, i32>,
+    e: SerdeEnum,
+    f: Vec<SerdeEnum>,
+    g: u64,
+    h: i128,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub enum SerdeEnum {
+    Unit,
+    Newtype(f64),
+    Tuple(bool, String),
+    Struct { x: i8 },
+}
+
+fn foo() -> SerdeFoo {
+    let mut d = BTreeMap::new();
+    d.insert("one".to_string(), 1);
+    SerdeFoo {
+        a: 0,
+        b: "foo".to_string(),
+        c: None,
+        d,
+        e: SerdeEnum::Unit,
+        f: vec![
+            SerdeEnum::Newtype(1.5),
+            SerdeEnum::Tuple(true, "t".to_string()),
+            SerdeEnum::Struct { x: -1 },
+        ],
+        g: 1 << 40,
+        h: -(1 << 100),
+    }
+}
+
+#[wasm_bindgen_test]
+fn serialize_default() {
+    let bytes = Serializer::new().serialize_bytes(&[1, 2, 3]).unwrap();
+    verify_default(to_value(&foo()).unwrap(), bytes);
+}
+
+#[wasm_bindgen_test]
+fn serialize_configured() {
+    let serializer = Serializer::json_compatible()
+        .serialize_bytes_as_arrays(true)
+        .serialize_large_number_types_as_bigints(true);
+    let value = foo().serialize(&serializer).unwrap();
+    let bytes = serializer.serialize_bytes(&[1, 2, 3]).unwrap();
+    verify_configured(value, bytes);
+}
+
+#[wasm_bindgen_test]
+fn serialize_large_number_fails() {
+    let err = to_value(&u64::MAX).unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("can't be represented as a JS number"));
+}
+
+#[wasm_bindgen_test]
+fn deserialize() {
+    let value: (SerdeFoo, Vec<u8>, Option<u64>) = from_value(make_value()).unwrap();
+    assert_eq!(value.0, foo());
+    assert_eq!(value.1, [4, 5, 6]);
+    assert_eq!(value.2, Some(u64::MAX));
+
+    let err = from_value::<u32>(JsValue::from("nope")).unwrap_err();
+    let err: js_sys::Error = JsValue::from(err).dyn_into().unwrap();
+    assert!(String::from(err.message()).contains("expected u32"));
+}
+
+#[wasm_bindgen(serde)]
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct SerdePoint {
+    x: f64,
+    y: f64,
+    label: Option<String>,
+}
+
+#[wasm_bindgen]
+pub fn serde_native_mirror(point: SerdePoint) -> SerdePoint {
+    SerdePoint {
+        x: point.y,
+        y: point.x,
+        label: point.label,
+    }
+}
+
+#[wasm_bindgen_test]
+fn serde_attribute() {
+    js_mirror_point();
+    js_mirror_invalid_point();
+
+    let point = mirror_point_in_js(SerdePoint {
+        x: 1.0,
+        y: 2.0,
+        label: Some("p".to_string()),
+    });
+    assert_eq!(
+        point,
+        SerdePoint {
+            x: 2.0,
+            y: 1.0,
+            label: Some("p".to_string()),
+        }
+    );
+}
+
+#[wasm_bindgen]
+pub fn serde_native_optional(point: Option<SerdePoint>) -> Option<SerdePoint> {
+    point
+}
+
+#[wasm_bindgen_test]
+fn serde_attribute_option() {
+    js_optional_point();
+
+    assert_eq!(optional_point_in_js(None, true), None);
+    let point = SerdePoint {
+        x: 1.0,
+        y: 2.0,
+        label: None,
+    };
+    assert_eq!(
+        optional_point_in_js(Some(point), false),
+        Some(SerdePoint {
+            x: 1.0,
+            y: 2.0,
+            label: None,
+        })
+    );
+}
@@ -0,0 +1,3 @@
+exports.new_object = function() {
+  return { foo: 'bar' };
+};
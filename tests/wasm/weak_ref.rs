@@ -0,0 +1,46 @@
+use std::cell::Cell;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::WeakJsValue;
+use wasm_bindgen_test::*;
+
+#[wasm_bindgen(module = "tests/wasm/weak_ref.js")]
+extern "C" {
+    fn new_object() -> JsValue;
+}
+
+#[wasm_bindgen_test]
+fn upgrade_returns_live_target() {
+    let obj = new_object();
+    let weak = obj.downgrade();
+    assert_eq!(weak.upgrade(), Some(obj.clone()));
+    assert_eq!(weak.upgrade(), Some(obj));
+}
+
+#[wasm_bindgen_test]
+fn primitives_are_held_strongly() {
+    let weak = WeakJsValue::new(&JsValue::from("foo"));
+    assert_eq!(weak.upgrade(), Some(JsValue::from("foo")));
+
+    let weak = WeakJsValue::new(&JsValue::from(1));
+    assert_eq!(weak.upgrade(), Some(JsValue::from(1)));
+
+    assert_eq!(WeakJsValue::new(&JsValue::UNDEFINED).upgrade(), None);
+}
+
+#[wasm_bindgen_test]
+fn finalizer_is_unregistered_on_drop() {
+    let called = Rc::new(Cell::new(false));
+    let obj = new_object();
+    let weak = {
+        let called = called.clone();
+        WeakJsValue::with_finalizer(&obj, move || called.set(true))
+    };
+    assert_eq!(weak.upgrade(), Some(obj));
+    drop(weak);
+    assert!(!called.get());
+
+    // Registering a finalizer for a primitive is a no-op.
+    let weak = WeakJsValue::with_finalizer(&JsValue::from("foo"), || unreachable!());
+    assert_eq!(weak.upgrade(), Some(JsValue::from("foo")));
+}